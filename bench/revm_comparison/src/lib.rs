@@ -32,7 +32,7 @@ pub fn run_with_evm_mlir(program: &str, runs: usize, number_of_iterations: u32)
     let mut db = Db::default();
     let journal = Journal::new(&mut db);
     let mut context = SyscallContext::new(env, journal, Default::default(), gas_limit);
-    let executor = Executor::new(&module, &context, Default::default());
+    let executor = Executor::new(&module, Default::default());
     let initial_gas = 999_999_999;
 
     for _ in 0..runs - 1 {
@@ -0,0 +1,293 @@
+//! # Block execution
+//!
+//! A [`BlockExecutor`] runs the transactions of a block one after the other over the
+//! same database, committing the changes of each transaction before running the next
+//! one. Before them, the system contracts that keep the roots of the beacon blocks
+//! (EIP-4788) and the hashes of the recent blocks (EIP-2935) are updated, and once
+//! they're done, the withdrawals of the block (EIP-4895) are credited.
+//!
+//! Transactions that fail validation make the whole block invalid, so execution stops
+//! at the first of them.
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fmt,
+};
+
+use thiserror::Error;
+
+use crate::{
+    constants::{BEACON_ROOTS_ADDRESS, HISTORY_STORAGE_ADDRESS},
+    db::{AccountInfo, Database, DatabaseCommit, DatabaseError},
+    env::{BlockEnv, TxEnv},
+    primitives::{Address, Bloom, Bytes, B256, U256},
+    result::{logs_bloom, EVMError, InvalidTransaction, ResultAndState},
+    spec::SpecId,
+    state::{Account, AccountStatus},
+    syscall::Log,
+    Evm,
+};
+
+/// Amount of wei in a gwei, the unit of withdrawal amounts
+const GWEI_TO_WEI: u64 = 1_000_000_000;
+
+/// Withdrawal from the beacon chain to the execution layer (EIP-4895)
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Withdrawal {
+    pub index: u64,
+    pub validator_index: u64,
+    /// Account credited with the withdrawn amount
+    pub address: Address,
+    /// Withdrawn amount, in gwei
+    pub amount: u64,
+}
+
+impl Withdrawal {
+    pub fn amount_in_wei(&self) -> U256 {
+        U256::from(self.amount) * GWEI_TO_WEI
+    }
+}
+
+/// Outcome of a transaction included in a block
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Receipt {
+    /// Whether the transaction was successful (EIP-658)
+    pub success: bool,
+    /// Gas used by the block up to and including this transaction, after refunds
+    pub cumulative_gas_used: u64,
+    pub logs: Vec<Log>,
+    /// Bloom filter of the addresses and topics of the logs
+    pub logs_bloom: Bloom,
+}
+
+impl Receipt {
+    pub fn new(success: bool, cumulative_gas_used: u64, logs: Vec<Log>) -> Self {
+        let logs_bloom = logs_bloom(&logs);
+        Self {
+            success,
+            cumulative_gas_used,
+            logs,
+            logs_bloom,
+        }
+    }
+}
+
+/// Outcome of the execution of a block
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BlockResult {
+    /// Receipts of the transactions, in the order they were executed
+    pub receipts: Vec<Receipt>,
+    /// Gas used by all the transactions of the block, after refunds
+    pub gas_used: u64,
+    /// Blob gas used by all the transactions of the block (EIP-4844)
+    pub blob_gas_used: u64,
+}
+
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum BlockExecutionError {
+    #[error("transaction {index} of the block is invalid: {error}")]
+    Transaction { index: usize, error: EVMError },
+    #[error("system call to {contract:?} failed: {error}")]
+    SystemCall { contract: Address, error: EVMError },
+    #[error(transparent)]
+    Database(#[from] DatabaseError),
+}
+
+/// Executes whole blocks over the database of an [`Evm`], following its configuration.
+///
+/// The [executor cache](Evm::executor_cache), precompiles and inspector of the [`Evm`]
+/// are used for every transaction, so it can be set up with an
+/// [`EvmBuilder`](crate::builder::EvmBuilder).
+#[derive(Debug)]
+pub struct BlockExecutor<DB: Database> {
+    evm: Evm<DB>,
+}
+
+impl<DB: Database> BlockExecutor<DB> {
+    pub fn new(evm: Evm<DB>) -> Self {
+        Self { evm }
+    }
+
+    pub fn db(&self) -> &DB {
+        &self.evm.db
+    }
+
+    pub fn db_mut(&mut self) -> &mut DB {
+        &mut self.evm.db
+    }
+
+    pub fn into_db(self) -> DB {
+        self.evm.db
+    }
+}
+
+impl<DB> BlockExecutor<DB>
+where
+    DB: Database + DatabaseCommit,
+    DB::Error: fmt::Display,
+{
+    /// Executes `transactions` in the given block and then processes its `withdrawals`,
+    /// committing every change to the database. The system calls of the block are made
+    /// before the transactions.
+    ///
+    /// Returns an error if a transaction is invalid, either by itself or because it
+    /// doesn't fit in the gas left in the block. The changes of the transactions before
+    /// it are kept in the database.
+    pub fn execute_block(
+        &mut self,
+        block: BlockEnv,
+        transactions: Vec<TxEnv>,
+        withdrawals: &[Withdrawal],
+    ) -> Result<BlockResult, BlockExecutionError> {
+        self.evm.env.block = block;
+        self.apply_pre_block_system_calls()?;
+        let mut block_result = BlockResult::default();
+
+        for (index, tx) in transactions.into_iter().enumerate() {
+            let available_gas = self
+                .evm
+                .env
+                .block
+                .gas_limit
+                .saturating_sub(U256::from(block_result.gas_used));
+            if U256::from(tx.gas_limit) > available_gas {
+                return Err(BlockExecutionError::Transaction {
+                    index,
+                    error: EVMError::Transaction(InvalidTransaction::CallerGasLimitMoreThanBlock),
+                });
+            }
+            let blob_gas = tx.get_total_blob_gas();
+
+            self.evm.env.tx = tx;
+            let result = self
+                .evm
+                .transact_commit()
+                .map_err(|error| BlockExecutionError::Transaction { index, error })?;
+
+            block_result.gas_used += result.gas_used() - result.gas_refunded();
+            block_result.blob_gas_used += blob_gas;
+            let success = result.is_success();
+            block_result.receipts.push(Receipt::new(
+                success,
+                block_result.gas_used,
+                result.into_logs(),
+            ));
+        }
+
+        self.process_withdrawals(withdrawals)?;
+        Ok(block_result)
+    }
+
+    /// Stores the parent beacon block root (from Cancun) and the parent block hash (from
+    /// Prague) in their system contracts. The genesis block has no parent, so it skips them.
+    fn apply_pre_block_system_calls(&mut self) -> Result<(), BlockExecutionError> {
+        let block = &self.evm.env.block;
+        if block.number.is_zero() {
+            return Ok(());
+        }
+        let spec_id = self.evm.env.cfg.spec_id;
+        let mut calls = Vec::new();
+        if let Some(root) = block
+            .parent_beacon_block_root
+            .filter(|_| spec_id.is_enabled_in(SpecId::Cancun))
+        {
+            calls.push((Address::from(BEACON_ROOTS_ADDRESS), root));
+        }
+        if let Some(hash) = block
+            .parent_hash
+            .filter(|_| spec_id.is_enabled_in(SpecId::Prague))
+        {
+            calls.push((Address::from(HISTORY_STORAGE_ADDRESS), hash));
+        }
+
+        for (contract, input) in calls {
+            self.system_call_commit(contract, input)?;
+        }
+        Ok(())
+    }
+
+    fn system_call_commit(
+        &mut self,
+        contract: Address,
+        input: B256,
+    ) -> Result<(), BlockExecutionError> {
+        let data = Bytes::copy_from_slice(input.as_bytes());
+        let ResultAndState { state, .. } = self
+            .evm
+            .system_call(contract, data)
+            .map_err(|error| BlockExecutionError::SystemCall { contract, error })?;
+        self.evm.db.commit(state);
+        Ok(())
+    }
+
+    /// Credits the withdrawn amounts to their accounts. Withdrawals don't run any code,
+    /// and can't fail.
+    fn process_withdrawals(&mut self, withdrawals: &[Withdrawal]) -> Result<(), DatabaseError> {
+        let mut changes: HashMap<Address, Account> = HashMap::new();
+        // Empty withdrawals don't touch the account
+        for withdrawal in withdrawals
+            .iter()
+            .filter(|withdrawal| withdrawal.amount > 0)
+        {
+            let account = match changes.entry(withdrawal.address) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    let info = self
+                        .evm
+                        .db
+                        .basic(withdrawal.address)
+                        .map_err(DatabaseError::new)?
+                        .unwrap_or_else(AccountInfo::empty);
+                    entry.insert(Account {
+                        info,
+                        storage: HashMap::new(),
+                        status: AccountStatus::Touched,
+                    })
+                }
+            };
+            account.info.balance += withdrawal.amount_in_wei();
+        }
+        self.evm.db.commit(changes);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syscall::{LogData, U256 as SyscallU256};
+    use ethereum_types::BloomInput;
+
+    #[test]
+    fn withdrawal_amounts_are_in_gwei() {
+        let withdrawal = Withdrawal {
+            amount: 3,
+            ..Default::default()
+        };
+        assert_eq!(withdrawal.amount_in_wei(), U256::from(3_000_000_000_u64));
+    }
+
+    #[test]
+    fn receipt_bloom_contains_log_addresses_and_topics() {
+        let address = Address::from_low_u64_be(40);
+        let topic = SyscallU256 { hi: 0, lo: 1 };
+        let log = Log {
+            address,
+            data: LogData {
+                topics: vec![topic],
+                data: vec![],
+            },
+        };
+
+        let receipt = Receipt::new(true, 21_000, vec![log]);
+
+        assert!(receipt
+            .logs_bloom
+            .contains_input(BloomInput::Raw(address.as_bytes())));
+        assert!(receipt
+            .logs_bloom
+            .contains_input(BloomInput::Raw(&topic.to_fixed_be_bytes())));
+        assert!(!receipt
+            .logs_bloom
+            .contains_input(BloomInput::Raw(Address::from_low_u64_be(41).as_bytes())));
+    }
+}
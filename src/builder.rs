@@ -1,18 +1,71 @@
-use crate::{db::Database, env::Env, Evm};
+use std::sync::Arc;
+
+use crate::{
+    cache::{CacheConfig, ExecutorCache, SharedExecutorCache},
+    db::Database,
+    env::Env,
+    inspector::{BoxedInspector, Inspector},
+    precompiles::Precompiles,
+    Evm,
+};
 
 #[derive(Default)]
 pub struct EvmBuilder<DB: Database> {
     db: DB,
     env: Env,
+    executor_cache: Option<SharedExecutorCache>,
+    precompiles: Option<Arc<Precompiles>>,
+    inspector: Option<BoxedInspector>,
 }
 
 impl<DB: Database + Default> EvmBuilder<DB> {
     /// Sets the [`Database`] that will be used by [`Evm`].
     pub fn with_db(self, db: DB) -> EvmBuilder<DB> {
-        EvmBuilder { db, env: self.env }
+        EvmBuilder { db, ..self }
+    }
+
+    /// Sets the configuration of the compiled executors cache of the [`Evm`].
+    pub fn with_cache_config(self, config: CacheConfig) -> EvmBuilder<DB> {
+        EvmBuilder {
+            executor_cache: Some(ExecutorCache::shared(config)),
+            ..self
+        }
+    }
+
+    /// Makes the [`Evm`] use an existing executor cache, which allows sharing
+    /// compiled code between different [`Evm`] instances.
+    pub fn with_executor_cache(self, executor_cache: SharedExecutorCache) -> EvmBuilder<DB> {
+        EvmBuilder {
+            executor_cache: Some(executor_cache),
+            ..self
+        }
+    }
+
+    /// Sets the precompiles that the executed code can call, instead of the mainnet
+    /// ones of the configured spec. Use it to add custom precompiles or disable some.
+    pub fn with_precompiles(self, precompiles: Precompiles) -> EvmBuilder<DB> {
+        EvmBuilder {
+            precompiles: Some(Arc::new(precompiles)),
+            ..self
+        }
+    }
+
+    /// Attaches an [`Inspector`] notified of the events of every transaction run by the
+    /// [`Evm`]. To read its results afterwards, pass a shared `Arc<Mutex<_>>` handle to it.
+    pub fn with_inspector(self, inspector: impl Inspector + Send + 'static) -> EvmBuilder<DB> {
+        EvmBuilder {
+            inspector: Some(Box::new(inspector)),
+            ..self
+        }
     }
 
     pub fn build(self) -> Evm<DB> {
-        Evm::new(self.env, self.db)
+        let mut evm = Evm::new(self.env, self.db);
+        if let Some(executor_cache) = self.executor_cache {
+            evm.executor_cache = executor_cache;
+        }
+        evm.precompiles = self.precompiles;
+        evm.inspector = self.inspector;
+        evm
     }
 }
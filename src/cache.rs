@@ -0,0 +1,454 @@
+//! # Cache of compiled programs
+//!
+//! Compiling a program through MLIR and LLVM is by far the most expensive part of
+//! running it, so executors are cached by the keccak256 hash of the bytecode they
+//! were compiled from and reused by [`Evm`](crate::Evm) and by the `CALL`/`CREATE`
+//! syscalls.
+//!
+//! Compiled code keeps its stack, memory and gas counter in module globals, saving
+//! them on entry and restoring them on return, so an executor can be entered again
+//! while it's running (e.g. `A -> B -> A`) but not run by two threads at once. Because
+//! of this, executors are handed out by [`ExecutorCache::checkout`] to a single thread
+//! at a time, shared by all the frames it runs them in, and given back with
+//! [`ExecutorCache::release`] once each of those frames finishes. Programs are compiled
+//! without holding the lock of a [`SharedExecutorCache`], so other executions can go on
+//! meanwhile.
+//!
+//! When an [AOT directory](CacheConfig::aot_dir) is configured, programs are compiled
+//! into shared libraries stored there, named after their code hash and optimization
+//! level, so they survive restarts and are loaded instead of compiled on later misses.
+//!
+//! Programs instrumented for [inspecting steps](crate::inspector::Inspector::step) are
+//! cached separately from the regular ones, and always compiled in memory. The same
+//! goes for programs compiled for different [hardforks](crate::spec::SpecId), which
+//! are also stored in separate shared libraries.
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread::{self, ThreadId},
+};
+
+use sha3::{Digest, Keccak256};
+
+use crate::{
+    codegen::{compile_aot_shared_lib, get_platform_library_ext},
+    context::{Context, Session},
+    errors::CodegenError,
+    executor::{Executor, OptLevel},
+    primitives::B256,
+    program::Program,
+    spec::SpecId,
+};
+
+/// Executor cache that can be shared between [`Evm`](crate::Evm) instances and
+/// the nested contexts created by calls.
+pub type SharedExecutorCache = Arc<Mutex<ExecutorCache>>;
+
+pub const DEFAULT_CACHE_CAPACITY: usize = 256;
+
+/// Which entry is removed when a new code hash is inserted into a full cache
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum EvictionPolicy {
+    /// Evict the least recently used entry
+    #[default]
+    Lru,
+    /// Evict the least frequently used entry
+    Lfu,
+    /// Evict the oldest entry
+    Fifo,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CacheConfig {
+    /// Maximum amount of distinct code hashes kept in the cache. A capacity of
+    /// zero disables caching.
+    pub capacity: usize,
+    pub eviction_policy: EvictionPolicy,
+    /// Optimization level used when compiling programs on a cache miss
+    pub opt_level: OptLevel,
+    /// Directory where programs are stored as shared libraries. If `None`, programs
+    /// are compiled in memory and lost when the cache is dropped.
+    pub aot_dir: Option<PathBuf>,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            capacity: DEFAULT_CACHE_CAPACITY,
+            eviction_policy: EvictionPolicy::default(),
+            opt_level: OptLevel::Aggressive,
+            aot_dir: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct CacheStats {
+    /// Checkouts that reused an already compiled executor
+    pub hits: u64,
+    /// Checkouts that had to compile the program
+    pub misses: u64,
+    /// Entries removed to make room for new code hashes
+    pub evictions: u64,
+    /// Misses served by loading an already compiled library from the AOT directory
+    pub aot_loads: u64,
+}
+
+impl CacheStats {
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / total as f64
+    }
+}
+
+struct CacheEntry {
+    /// Compiled executors, each of them idle or running on a single thread
+    executors: Vec<CachedExecutor>,
+    inserted_at: u64,
+    last_used: u64,
+    uses: u64,
+}
+
+struct CachedExecutor {
+    executor: Arc<Executor>,
+    /// Thread running the executor, along with the amount of frames running it
+    running_on: Option<(ThreadId, usize)>,
+}
+
+#[derive(Default)]
+pub struct ExecutorCache {
+    config: CacheConfig,
+    /// Entries keyed by code hash, spec and whether the code is instrumented
+    entries: HashMap<(B256, SpecId, bool), CacheEntry>,
+    stats: CacheStats,
+    /// Logical clock used to order insertions and accesses
+    tick: u64,
+    /// Programs with frames running an executor loaded from a shared library, along
+    /// with the thread running them and the amount of frames. Loading the same library
+    /// twice returns the same handle, sharing its globals, so other threads compile
+    /// these in memory instead.
+    aot_in_use: HashMap<(B256, SpecId), (ThreadId, usize)>,
+}
+
+impl fmt::Debug for ExecutorCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExecutorCache")
+            .field("config", &self.config)
+            .field("entries", &self.entries.len())
+            .field("stats", &self.stats)
+            .finish()
+    }
+}
+
+impl ExecutorCache {
+    pub fn new(config: CacheConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    pub fn shared(config: CacheConfig) -> SharedExecutorCache {
+        Arc::new(Mutex::new(Self::new(config)))
+    }
+
+    pub fn config(&self) -> &CacheConfig {
+        &self.config
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains(&self, code_hash: &B256) -> bool {
+        self.entries.keys().any(|(hash, _, _)| hash == code_hash)
+    }
+
+    /// Removes every cached executor. Statistics are kept.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Returns an executor for the given bytecode, compiling the program if there's
+    /// none that can run on this thread. The executor should be given back with
+    /// [`ExecutorCache::release`] after running it.
+    ///
+    /// The program follows the rules of `spec_id`. If `instrumented` is true, it calls
+    /// the inspector before each operation.
+    pub fn checkout(
+        cache: &SharedExecutorCache,
+        code_hash: B256,
+        bytecode: &[u8],
+        spec_id: SpecId,
+        instrumented: bool,
+    ) -> Result<Arc<Executor>, CodegenError> {
+        let key = (code_hash, spec_id, instrumented);
+        let (aot_dir, opt_level) = {
+            let mut cache = cache.lock().unwrap();
+            if let Some(executor) = cache.take_compiled(key) {
+                cache.stats.hits += 1;
+                return Ok(executor);
+            }
+            cache.stats.misses += 1;
+
+            let aot_dir = match cache.config.aot_dir.clone() {
+                Some(aot_dir) if !instrumented && cache.enter_aot(code_hash, spec_id) => {
+                    Some(aot_dir)
+                }
+                _ => None,
+            };
+            (aot_dir, cache.config.opt_level)
+        };
+
+        let compiled = match &aot_dir {
+            Some(aot_dir) => load_or_compile_aot(aot_dir, code_hash, bytecode, spec_id, opt_level),
+            None => compile(bytecode, spec_id, instrumented, opt_level).map(|e| (e, false)),
+        };
+
+        let mut cache = cache.lock().unwrap();
+        let (executor, loaded) = match compiled {
+            Ok(compiled) => compiled,
+            Err(error) => {
+                if aot_dir.is_some() {
+                    cache.exit_aot(code_hash, spec_id);
+                }
+                return Err(error);
+            }
+        };
+        if loaded {
+            cache.stats.aot_loads += 1;
+        }
+        let executor = Arc::new(executor);
+        cache.insert(key, executor.clone());
+        Ok(executor)
+    }
+
+    /// Gives back an executor obtained from [`ExecutorCache::checkout`], once the frame
+    /// running it finishes.
+    pub fn release(&mut self, code_hash: B256, executor: &Arc<Executor>) {
+        if executor.is_aot() {
+            self.exit_aot(code_hash, executor.spec_id());
+        }
+        let key = (code_hash, executor.spec_id(), executor.is_instrumented());
+        let Some(entry) = self.entries.get_mut(&key) else {
+            return;
+        };
+        let cached = entry
+            .executors
+            .iter_mut()
+            .find(|cached| Arc::ptr_eq(&cached.executor, executor));
+        if let Some(cached) = cached {
+            if let Some((_, frames)) = cached.running_on.as_mut() {
+                *frames -= 1;
+                if *frames == 0 {
+                    cached.running_on = None;
+                }
+            }
+        }
+    }
+
+    /// Takes an executor already running on this thread, or an idle one
+    fn take_compiled(&mut self, key: (B256, SpecId, bool)) -> Option<Arc<Executor>> {
+        let current_thread = thread::current().id();
+        self.tick += 1;
+        let tick = self.tick;
+        let entry = self.entries.get_mut(&key)?;
+        entry.last_used = tick;
+        entry.uses += 1;
+
+        let position = entry
+            .executors
+            .iter()
+            .position(
+                |cached| matches!(cached.running_on, Some((thread, _)) if thread == current_thread),
+            )
+            .or_else(|| {
+                entry
+                    .executors
+                    .iter()
+                    .position(|cached| cached.running_on.is_none())
+            })?;
+        let executor = entry.executors[position].executor.clone();
+        if executor.is_aot() && !self.enter_aot(key.0, key.1) {
+            return None;
+        }
+
+        let cached = &mut self.entries.get_mut(&key)?.executors[position];
+        let (_, frames) = cached.running_on.get_or_insert((current_thread, 0));
+        *frames += 1;
+        Some(executor)
+    }
+
+    /// Stores a just compiled executor, running on this thread
+    fn insert(&mut self, key: (B256, SpecId, bool), executor: Arc<Executor>) {
+        if self.config.capacity == 0 {
+            return;
+        }
+        if !self.entries.contains_key(&key) && self.entries.len() >= self.config.capacity {
+            self.evict();
+        }
+        let tick = self.tick;
+        self.entries
+            .entry(key)
+            .or_insert_with(|| CacheEntry {
+                executors: Vec::new(),
+                inserted_at: tick,
+                last_used: tick,
+                uses: 1,
+            })
+            .executors
+            .push(CachedExecutor {
+                executor,
+                running_on: Some((thread::current().id(), 1)),
+            });
+    }
+
+    /// Registers a frame running the shared library of a program on this thread.
+    /// Returns false if the library is being run by another thread.
+    fn enter_aot(&mut self, code_hash: B256, spec_id: SpecId) -> bool {
+        let current_thread = thread::current().id();
+        let (thread, frames) = self
+            .aot_in_use
+            .entry((code_hash, spec_id))
+            .or_insert((current_thread, 0));
+        if *thread != current_thread {
+            return false;
+        }
+        *frames += 1;
+        true
+    }
+
+    fn exit_aot(&mut self, code_hash: B256, spec_id: SpecId) {
+        if let Some((_, frames)) = self.aot_in_use.get_mut(&(code_hash, spec_id)) {
+            *frames -= 1;
+            if *frames == 0 {
+                self.aot_in_use.remove(&(code_hash, spec_id));
+            }
+        }
+    }
+
+    fn evict(&mut self) {
+        let policy = self.config.eviction_policy;
+        let victim = self
+            .entries
+            .iter()
+            .min_by_key(|(_, entry)| match policy {
+                EvictionPolicy::Lru => (entry.last_used, entry.inserted_at),
+                EvictionPolicy::Lfu => (entry.uses, entry.last_used),
+                EvictionPolicy::Fifo => (entry.inserted_at, 0),
+            })
+            .map(|(key, _)| *key);
+
+        if let Some(key) = victim {
+            self.entries.remove(&key);
+            self.stats.evictions += 1;
+        }
+    }
+}
+
+fn compile(
+    bytecode: &[u8],
+    spec_id: SpecId,
+    instrumented: bool,
+    opt_level: OptLevel,
+) -> Result<Executor, CodegenError> {
+    let program = Program::for_spec(bytecode, spec_id);
+    let context = Context::new();
+    let session = Session {
+        spec_id,
+        instrument_steps: instrumented,
+        ..Default::default()
+    };
+    let module = context.compile(&program, session)?;
+    let mut executor = Executor::new(&module, opt_level);
+    executor.instrumented = instrumented;
+    executor.spec_id = spec_id;
+    Ok(executor)
+}
+
+/// Loads the shared library of the program, compiling it first if it isn't stored yet.
+/// Returns whether it was already stored.
+fn load_or_compile_aot(
+    aot_dir: &Path,
+    code_hash: B256,
+    bytecode: &[u8],
+    spec_id: SpecId,
+    opt_level: OptLevel,
+) -> Result<(Executor, bool), CodegenError> {
+    let library_path = aot_dir.join(aot_library_name(code_hash, spec_id, opt_level));
+    let loaded = library_path.exists();
+    if !loaded {
+        fs::create_dir_all(aot_dir)?;
+        // Compile to a temporary file first so other processes sharing the
+        // directory never load a partially written library
+        let temp_path = library_path.with_extension(format!(
+            "{}.{}",
+            std::process::id(),
+            get_platform_library_ext()
+        ));
+        let program = Program::for_spec(bytecode, spec_id);
+        compile_aot_shared_lib(&program, spec_id, opt_level, &temp_path)?;
+        fs::rename(&temp_path, &library_path)?;
+    }
+    // SAFETY: the library was built by `compile_aot_shared_lib` into the AOT directory
+    let mut executor = unsafe { Executor::load(library_path) }?;
+    executor.spec_id = spec_id;
+    Ok((executor, loaded))
+}
+
+/// Returns the file name of the shared library holding the given program
+pub fn aot_library_name(code_hash: B256, spec_id: SpecId, opt_level: OptLevel) -> String {
+    format!(
+        "{code_hash:x}-{spec_id}-O{}.{}",
+        opt_level as usize,
+        get_platform_library_ext()
+    )
+}
+
+/// Returns the key under which the executor for `bytecode` is cached
+pub fn code_hash(bytecode: &[u8]) -> B256 {
+    let mut hasher = Keccak256::new();
+    hasher.update(bytecode);
+    B256::from_slice(&hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::constants::EMPTY_CODE_HASH_STR;
+
+    #[test]
+    fn hit_rate_without_lookups_is_zero() {
+        assert_eq!(CacheStats::default().hit_rate(), 0.0);
+    }
+
+    #[test]
+    fn hit_rate_is_hits_over_lookups() {
+        let stats = CacheStats {
+            hits: 3,
+            misses: 1,
+            ..Default::default()
+        };
+        assert_eq!(stats.hit_rate(), 0.75);
+    }
+
+    #[test]
+    fn code_hash_of_empty_bytecode() {
+        assert_eq!(code_hash(&[]), B256::from_str(EMPTY_CODE_HASH_STR).unwrap());
+    }
+}
@@ -0,0 +1,414 @@
+//! # Cache database
+//!
+//! A [`CacheDB`] sits in front of another database, keeping in memory everything read
+//! from it and every change committed to it. The wrapped database is only read, through
+//! [`DatabaseRef`], so it can be a slow or shared store: a fork of a remote node, the
+//! state before a bundle of transactions or the one a simulation runs over.
+use std::collections::{hash_map::Entry, HashMap};
+
+use crate::{
+    db::{AccountInfo, Bytecode, Database, DatabaseCommit, DatabaseRef},
+    primitives::{Address, B256, U256},
+    state::Account,
+};
+
+/// Account as kept by a [`CacheDB`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CachedAccount {
+    /// Information of the account, `None` if it doesn't exist
+    pub info: Option<AccountInfo>,
+    /// Whether the slots that aren't cached are read from the wrapped database
+    pub account_state: AccountState,
+    /// Slots read or written so far. The rest are read from the wrapped database, unless
+    /// the [`account_state`](Self::account_state) says its storage there is stale.
+    pub storage: HashMap<U256, U256>,
+}
+
+impl CachedAccount {
+    /// Value of a slot that isn't cached, if it can be known without reading the wrapped
+    /// database
+    fn uncached_slot(&self) -> Option<U256> {
+        match self.account_state {
+            AccountState::NotExisting | AccountState::StorageCleared => Some(U256::zero()),
+            AccountState::Touched | AccountState::None => None,
+        }
+    }
+}
+
+/// State of a [`CachedAccount`] with respect to the wrapped database
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum AccountState {
+    /// The account doesn't exist, because it was never created or it was self-destructed.
+    /// It has no storage.
+    NotExisting,
+    /// The account was created after the wrapped database was read, so its storage is
+    /// only what is cached
+    StorageCleared,
+    /// The account was changed, keeping the storage of the wrapped database
+    Touched,
+    /// The account was only read
+    #[default]
+    None,
+}
+
+impl AccountState {
+    /// Whether the storage of the wrapped database was discarded
+    pub fn is_storage_cleared(&self) -> bool {
+        matches!(
+            self,
+            AccountState::NotExisting | AccountState::StorageCleared
+        )
+    }
+}
+
+/// Write cache in front of a read-only database. Changes are committed to the cache,
+/// leaving the wrapped database untouched.
+#[derive(Clone, Debug, Default)]
+pub struct CacheDB<ExtDB> {
+    accounts: HashMap<Address, CachedAccount>,
+    contracts: HashMap<B256, Bytecode>,
+    block_hashes: HashMap<U256, B256>,
+    db: ExtDB,
+}
+
+impl<ExtDB> CacheDB<ExtDB> {
+    pub fn new(db: ExtDB) -> Self {
+        Self {
+            accounts: HashMap::new(),
+            contracts: HashMap::new(),
+            block_hashes: HashMap::new(),
+            db,
+        }
+    }
+
+    /// The wrapped database
+    pub fn db(&self) -> &ExtDB {
+        &self.db
+    }
+
+    pub fn into_db(self) -> ExtDB {
+        self.db
+    }
+
+    /// Accounts read or written so far
+    pub fn accounts(&self) -> &HashMap<Address, CachedAccount> {
+        &self.accounts
+    }
+
+    /// Replaces the information of an account, keeping its storage
+    pub fn insert_account_info(&mut self, address: Address, mut info: AccountInfo) {
+        store_contract(&mut self.contracts, &mut info);
+        self.accounts.entry(address).or_default().info = Some(info);
+    }
+
+    pub fn insert_block_hash(&mut self, number: U256, hash: B256) {
+        self.block_hashes.insert(number, hash);
+    }
+}
+
+impl<ExtDB: DatabaseRef> CacheDB<ExtDB> {
+    /// Sets a storage slot of an account, loading the account if it isn't cached
+    pub fn insert_account_storage(
+        &mut self,
+        address: Address,
+        index: U256,
+        value: U256,
+    ) -> Result<(), ExtDB::Error> {
+        self.load_account(address)?.storage.insert(index, value);
+        Ok(())
+    }
+
+    fn load_account(&mut self, address: Address) -> Result<&mut CachedAccount, ExtDB::Error> {
+        match self.accounts.entry(address) {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => {
+                let mut info = self.db.basic_ref(address)?;
+                let account_state = match info.as_mut() {
+                    Some(info) => {
+                        store_contract(&mut self.contracts, info);
+                        AccountState::None
+                    }
+                    None => AccountState::NotExisting,
+                };
+                Ok(entry.insert(CachedAccount {
+                    info,
+                    account_state,
+                    storage: HashMap::new(),
+                }))
+            }
+        }
+    }
+}
+
+/// Moves the code of the account to the contracts, so it's kept once
+fn store_contract(contracts: &mut HashMap<B256, Bytecode>, info: &mut AccountInfo) {
+    if let Some(code) = info.code.take() {
+        if info.has_code() {
+            contracts.entry(info.code_hash).or_insert(code);
+        }
+    }
+}
+
+impl<ExtDB: DatabaseRef> Database for CacheDB<ExtDB> {
+    type Error = ExtDB::Error;
+
+    fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        Ok(self.load_account(address)?.info.clone())
+    }
+
+    fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        match self.contracts.entry(code_hash) {
+            Entry::Occupied(entry) => Ok(entry.get().clone()),
+            Entry::Vacant(entry) => Ok(entry.insert(self.db.code_by_hash_ref(code_hash)?).clone()),
+        }
+    }
+
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        let account = self.load_account(address)?;
+        if let Some(value) = account.storage.get(&index) {
+            return Ok(*value);
+        }
+        let value = match account.uncached_slot() {
+            Some(value) => value,
+            None => self.db.storage_ref(address, index)?,
+        };
+        self.accounts
+            .entry(address)
+            .or_default()
+            .storage
+            .insert(index, value);
+        Ok(value)
+    }
+
+    fn block_hash(&mut self, number: U256) -> Result<B256, Self::Error> {
+        match self.block_hashes.entry(number) {
+            Entry::Occupied(entry) => Ok(*entry.get()),
+            Entry::Vacant(entry) => Ok(*entry.insert(self.db.block_hash_ref(number)?)),
+        }
+    }
+}
+
+/// Reads the cache, falling back to the wrapped database without caching what is read
+impl<ExtDB: DatabaseRef> DatabaseRef for CacheDB<ExtDB> {
+    type Error = ExtDB::Error;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        match self.accounts.get(&address) {
+            Some(account) => Ok(account.info.clone()),
+            None => self.db.basic_ref(address),
+        }
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        match self.contracts.get(&code_hash) {
+            Some(code) => Ok(code.clone()),
+            None => self.db.code_by_hash_ref(code_hash),
+        }
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        match self.accounts.get(&address) {
+            Some(account) => match account
+                .storage
+                .get(&index)
+                .or(account.uncached_slot().as_ref())
+            {
+                Some(value) => Ok(*value),
+                None => self.db.storage_ref(address, index),
+            },
+            None => self.db.storage_ref(address, index),
+        }
+    }
+
+    fn block_hash_ref(&self, number: U256) -> Result<B256, Self::Error> {
+        match self.block_hashes.get(&number) {
+            Some(hash) => Ok(*hash),
+            None => self.db.block_hash_ref(number),
+        }
+    }
+}
+
+/// Commits the changes like [`Db`](crate::db::Db) does, to the cache only. Accounts
+/// self-destructed or created stop reading their storage from the wrapped database.
+impl<ExtDB> DatabaseCommit for CacheDB<ExtDB> {
+    fn commit(&mut self, changes: HashMap<Address, Account>) {
+        for (address, mut account) in changes {
+            let not_modified =
+                !account.is_touched() && !account.is_created() && !account.is_selfdestructed();
+            if not_modified {
+                continue;
+            }
+
+            let cached_account = self.accounts.entry(address).or_default();
+            if account.is_selfdestructed() {
+                cached_account.info = None;
+                cached_account.account_state = AccountState::NotExisting;
+                cached_account.storage.clear();
+                continue;
+            }
+
+            let is_created = account.is_created();
+            store_contract(&mut self.contracts, &mut account.info);
+            cached_account.info = Some(account.info);
+            cached_account.account_state = if is_created {
+                cached_account.storage.clear();
+                AccountState::StorageCleared
+            } else if cached_account.account_state.is_storage_cleared() {
+                AccountState::StorageCleared
+            } else {
+                AccountState::Touched
+            };
+            cached_account.storage.extend(
+                account
+                    .storage
+                    .into_iter()
+                    .map(|(key, value)| (key, value.present_value)),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::Db,
+        state::{AccountStatus, EvmStorageSlot},
+        test_utils::CountingReads,
+    };
+
+    #[test]
+    fn reads_are_cached() {
+        let address = Address::from_low_u64_be(1);
+        let mut db = Db::new().with_contract(address, Bytecode::from_static(&[0x00]));
+        db.write_storage(address, U256::one(), U256::from(2));
+        let mut cache_db = CacheDB::new(CountingReads::new(db));
+
+        for _ in 0..2 {
+            let info = cache_db.basic(address).unwrap().unwrap();
+            assert_eq!(
+                cache_db.code_by_hash(info.code_hash).unwrap(),
+                Bytecode::from_static(&[0x00])
+            );
+            assert_eq!(
+                cache_db.storage(address, U256::one()).unwrap(),
+                U256::from(2)
+            );
+        }
+
+        assert_eq!(cache_db.db().reads(), 3);
+    }
+
+    #[test]
+    fn missing_accounts_have_no_storage() {
+        let mut cache_db = CacheDB::new(CountingReads::new(Db::new()));
+
+        let address = Address::from_low_u64_be(1);
+        assert_eq!(cache_db.basic(address).unwrap(), None);
+        assert_eq!(
+            cache_db.storage(address, U256::one()).unwrap(),
+            U256::zero()
+        );
+
+        // Only the account was read
+        assert_eq!(cache_db.db().reads(), 1);
+    }
+
+    #[test]
+    fn commits_stay_in_the_cache() {
+        let address = Address::from_low_u64_be(1);
+        let mut db = Db::new();
+        db.set_account(
+            address,
+            1,
+            U256::from(10),
+            HashMap::from([(U256::one(), U256::from(2)), (U256::from(2), U256::from(3))]),
+        );
+        let mut cache_db = CacheDB::new(&db);
+        let mut info = cache_db.basic(address).unwrap().unwrap();
+        info.balance = U256::from(20);
+        let changes = HashMap::from([(
+            address,
+            Account {
+                info,
+                storage: HashMap::from([(
+                    U256::one(),
+                    EvmStorageSlot {
+                        original_value: U256::from(2),
+                        present_value: U256::from(4),
+                        is_cold: false,
+                    },
+                )]),
+                status: AccountStatus::Touched,
+            },
+        )]);
+
+        cache_db.commit(changes);
+
+        let info = cache_db.basic_ref(address).unwrap().unwrap();
+        assert_eq!(info.balance, U256::from(20));
+        assert_eq!(
+            cache_db.storage_ref(address, U256::one()).unwrap(),
+            U256::from(4)
+        );
+        // Slots that weren't changed are still read from the wrapped database
+        assert_eq!(
+            cache_db.storage(address, U256::from(2)).unwrap(),
+            U256::from(3)
+        );
+        assert_eq!(db.get_balance(address), Some(U256::from(10)));
+        assert_eq!(db.read_storage(address, U256::one()), U256::from(2));
+    }
+
+    #[test]
+    fn destroyed_and_recreated_accounts_ignore_the_wrapped_storage() {
+        let address = Address::from_low_u64_be(1);
+        let mut db = Db::new();
+        db.set_account(
+            address,
+            1,
+            U256::zero(),
+            HashMap::from([(U256::one(), U256::from(2)), (U256::from(2), U256::from(3))]),
+        );
+        let mut cache_db = CacheDB::new(&db);
+        let info = cache_db.basic(address).unwrap().unwrap();
+        let changes = |status, storage| {
+            HashMap::from([(
+                address,
+                Account {
+                    info: info.clone(),
+                    storage,
+                    status,
+                },
+            )])
+        };
+
+        cache_db.commit(changes(
+            AccountStatus::Touched | AccountStatus::SelfDestructed,
+            HashMap::new(),
+        ));
+        assert_eq!(cache_db.basic(address).unwrap(), None);
+        assert_eq!(
+            cache_db.storage(address, U256::one()).unwrap(),
+            U256::zero()
+        );
+
+        cache_db.commit(changes(
+            AccountStatus::Created | AccountStatus::Touched,
+            HashMap::from([(U256::from(2), EvmStorageSlot::from(U256::from(4)))]),
+        ));
+        assert_eq!(cache_db.basic(address).unwrap(), Some(info));
+        assert_eq!(
+            cache_db.storage_ref(address, U256::one()).unwrap(),
+            U256::zero()
+        );
+        assert_eq!(
+            cache_db.storage(address, U256::from(2)).unwrap(),
+            U256::from(4)
+        );
+        assert_eq!(
+            cache_db.accounts()[&address].account_state,
+            AccountState::StorageCleared
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 use melior::{
     dialect::{
@@ -8,18 +8,20 @@ use melior::{
     ir::{
         attribute::{IntegerAttribute, TypeAttribute},
         r#type::IntegerType,
-        Block, BlockRef, Location, Module, Region, Value,
+        Block, BlockRef, Location, Module, Region, Type, Value,
     },
     Context as MeliorContext,
 };
 
 use crate::{
     constants::{
-        CallType, CALLDATA_PTR_GLOBAL, CALLDATA_SIZE_GLOBAL, GAS_COUNTER_GLOBAL, MAX_STACK_SIZE,
-        MEMORY_PTR_GLOBAL, MEMORY_SIZE_GLOBAL, STACK_BASEPTR_GLOBAL, STACK_PTR_GLOBAL,
+        return_codes::SUCCESS_RETURN_CODE, CallType, CALLDATA_PTR_GLOBAL, CALLDATA_SIZE_GLOBAL,
+        GAS_COUNTER_GLOBAL, MAX_STACK_SIZE, MEMORY_PTR_GLOBAL, MEMORY_SIZE_GLOBAL,
+        STACK_BASEPTR_GLOBAL, STACK_PTR_GLOBAL,
     },
     errors::CodegenError,
     program::{Operation, Program},
+    spec::SpecId,
     syscall::{self, ExitStatusCode},
     utils::{
         allocate_and_store_value, constant_value_from_i64, consume_gas_as_value, get_remaining_gas,
@@ -33,6 +35,8 @@ pub(crate) struct OperationCtx<'c> {
     pub mlir_context: &'c MeliorContext,
     /// The program IR.
     pub program: &'c Program,
+    /// The hardfork whose rules the program follows.
+    pub spec_id: SpecId,
     /// The syscall context to be passed to syscalls.
     pub syscall_ctx: Value<'c, 'c>,
     /// Reference to the revert block.
@@ -44,6 +48,15 @@ pub(crate) struct OperationCtx<'c> {
     pub jumptable_block: BlockRef<'c, 'c>,
     /// Blocks to jump to. These are registered dynamically as JUMPDESTs are processed.
     pub jumpdest_blocks: BTreeMap<usize, BlockRef<'c, 'c>>,
+    /// Blocks landing on the targets of the static jumps and calls of EOF code, by PC.
+    /// They're created as the jumps are processed, and linked to the operation at
+    /// their PC once all of them are generated.
+    pub eof_landing_blocks: BTreeMap<usize, BlockRef<'c, 'c>>,
+    /// Block receiving the PC a `RETF` returns to, and dispatching to its landing block.
+    pub retf_block: Option<BlockRef<'c, 'c>>,
+    /// Values the frame globals had when the program was entered, restored before
+    /// returning so the frame we were called from (if any) can keep running.
+    pub saved_globals: Vec<(&'static str, Value<'c, 'c>)>,
 }
 
 impl<'c> OperationCtx<'c> {
@@ -53,6 +66,7 @@ impl<'c> OperationCtx<'c> {
         region: &'c Region,
         setup_block: &'c Block<'c>,
         program: &'c Program,
+        spec_id: SpecId,
     ) -> Result<Self, CodegenError> {
         let location = Location::unknown(context);
         let ptr_type = pointer(context, 0);
@@ -61,6 +75,9 @@ impl<'c> OperationCtx<'c> {
         let syscall_ctx = setup_block.add_argument(ptr_type, location);
         let initial_gas = setup_block.add_argument(uint64, location);
 
+        // The same program may be running in an outer frame (e.g. `A -> B -> A`)
+        let saved_globals = generate_globals_save_code(context, setup_block)?;
+
         // Append setup code to be run at the start
         generate_stack_setup_code(context, module, setup_block)?;
         generate_memory_setup_code(context, module, setup_block)?;
@@ -70,20 +87,31 @@ impl<'c> OperationCtx<'c> {
         syscall::mlir::declare_symbols(context, module);
 
         // Generate helper blocks
-        let revert_block = region.append_block(generate_revert_block(context, syscall_ctx)?);
+        let revert_block =
+            region.append_block(generate_revert_block(context, syscall_ctx, &saved_globals)?);
         let jumptable_block = region.append_block(create_jumptable_landing_block(context));
 
         let op_ctx = OperationCtx {
             mlir_context: context,
             program,
+            spec_id,
             syscall_ctx,
             revert_block,
             jumptable_block,
             jumpdest_blocks: Default::default(),
+            eof_landing_blocks: Default::default(),
+            retf_block: None,
+            saved_globals,
         };
         Ok(op_ctx)
     }
 
+    /// Restores the frame globals to the values they had when the program was entered.
+    /// Must be called right before returning from the program.
+    pub(crate) fn restore_globals(&self, block: &Block<'c>) -> Result<(), CodegenError> {
+        generate_globals_restore_code(self.mlir_context, block, &self.saved_globals)
+    }
+
     /// Populate the jumptable block with a dynamic dispatch according to the
     /// received PC.
     pub(crate) fn populate_jumptable(&self) -> Result<(), CodegenError> {
@@ -137,6 +165,77 @@ impl<'c> OperationCtx<'c> {
         self.jumpdest_blocks.insert(pc, block);
     }
 
+    /// Returns the block landing on the operation at `pc`, for EOF static jumps and calls
+    pub(crate) fn eof_landing_block(
+        &mut self,
+        region: &'c Region<'c>,
+        pc: usize,
+    ) -> BlockRef<'c, 'c> {
+        *self
+            .eof_landing_blocks
+            .entry(pc)
+            .or_insert_with(|| region.append_block(Block::new(&[])))
+    }
+
+    /// Returns the block `RETF` branches to with the PC to return to
+    pub(crate) fn retf_block(&mut self, region: &'c Region<'c>) -> BlockRef<'c, 'c> {
+        let context = self.mlir_context;
+        let location = Location::unknown(context);
+        let uint32 = IntegerType::new(context, 32).into();
+        *self
+            .retf_block
+            .get_or_insert_with(|| region.append_block(Block::new(&[(uint32, location)])))
+    }
+
+    /// Links the EOF landing blocks to the first block of the operation at their PC, and
+    /// populates the `RETF` block with a dispatch to the PCs after each `CALLF`.
+    pub(crate) fn populate_eof_blocks(
+        &self,
+        op_blocks: &BTreeMap<usize, BlockRef<'c, 'c>>,
+    ) -> Result<(), CodegenError> {
+        let context = self.mlir_context;
+        let location = Location::unknown(context);
+
+        for (pc, landing_block) in &self.eof_landing_blocks {
+            // Validation ensures every target is an operation
+            let target = op_blocks.get(pc).unwrap_or(&self.revert_block);
+            landing_block.append_operation(cf::br(target, &[], location));
+        }
+
+        let Some(retf_block) = self.retf_block else {
+            return Ok(());
+        };
+        let uint32 = IntegerType::new(context, 32);
+        let (return_pcs, return_blocks): (Vec<i64>, Vec<_>) = self
+            .program
+            .operations
+            .iter()
+            .filter_map(|op| match op {
+                Operation::Callf { pc, .. } => Some(pc + 3),
+                _ => None,
+            })
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|pc| {
+                let block: (&Block, &[Value]) = (&self.eof_landing_blocks[&pc], &[]);
+                (pc as i64, block)
+            })
+            .unzip();
+
+        let op = retf_block.append_operation(cf::switch(
+            context,
+            &return_pcs,
+            retf_block.argument(0)?.into(),
+            uint32.into(),
+            (&self.revert_block, &[]),
+            &return_blocks,
+            location,
+        )?);
+        assert!(op.verify());
+
+        Ok(())
+    }
+
     /// Registers a block as a valid jump destination.
     // TODO: move into jumptable module
     #[allow(dead_code)]
@@ -151,6 +250,72 @@ impl<'c> OperationCtx<'c> {
     }
 }
 
+/// Globals holding the state of the running frame, along with their types
+fn frame_globals(context: &MeliorContext) -> [(&'static str, Type); 7] {
+    let ptr_type = pointer(context, 0);
+    let uint32 = IntegerType::new(context, 32).into();
+    let uint64 = IntegerType::new(context, 64).into();
+    [
+        (GAS_COUNTER_GLOBAL, uint64),
+        (STACK_BASEPTR_GLOBAL, ptr_type),
+        (STACK_PTR_GLOBAL, ptr_type),
+        (MEMORY_PTR_GLOBAL, ptr_type),
+        (MEMORY_SIZE_GLOBAL, uint32),
+        (CALLDATA_PTR_GLOBAL, ptr_type),
+        (CALLDATA_SIZE_GLOBAL, uint32),
+    ]
+}
+
+fn generate_globals_save_code<'c>(
+    context: &'c MeliorContext,
+    block: &'c Block<'c>,
+) -> Result<Vec<(&'static str, Value<'c, 'c>)>, CodegenError> {
+    let location = Location::unknown(context);
+    let ptr_type = pointer(context, 0);
+
+    let mut saved_globals = Vec::new();
+    for (name, global_type) in frame_globals(context) {
+        let global_ptr = block
+            .append_operation(llvm_mlir::addressof(context, name, ptr_type, location))
+            .result(0)?;
+        let value = block
+            .append_operation(llvm::load(
+                context,
+                global_ptr.into(),
+                global_type,
+                location,
+                LoadStoreOptions::default(),
+            ))
+            .result(0)?;
+        saved_globals.push((name, value.into()));
+    }
+    Ok(saved_globals)
+}
+
+fn generate_globals_restore_code<'c>(
+    context: &'c MeliorContext,
+    block: &Block<'c>,
+    saved_globals: &[(&'static str, Value<'c, 'c>)],
+) -> Result<(), CodegenError> {
+    let location = Location::unknown(context);
+    let ptr_type = pointer(context, 0);
+
+    for (name, value) in saved_globals {
+        let global_ptr = block
+            .append_operation(llvm_mlir::addressof(context, name, ptr_type, location))
+            .result(0)?;
+        let res = block.append_operation(llvm::store(
+            context,
+            *value,
+            global_ptr.into(),
+            location,
+            LoadStoreOptions::default(),
+        ));
+        assert!(res.verify());
+    }
+    Ok(())
+}
+
 fn generate_gas_counter_setup_code<'c>(
     context: &'c MeliorContext,
     module: &'c Module,
@@ -424,6 +589,7 @@ fn create_jumptable_landing_block(context: &MeliorContext) -> Block {
 pub fn generate_revert_block<'c>(
     context: &'c MeliorContext,
     syscall_ctx: Value<'c, 'c>,
+    saved_globals: &[(&'static str, Value<'c, 'c>)],
 ) -> Result<Block<'c>, CodegenError> {
     let location = Location::unknown(context);
     let uint32 = IntegerType::new(context, 32).into();
@@ -472,6 +638,7 @@ pub fn generate_revert_block<'c>(
         location,
     );
 
+    generate_globals_restore_code(context, &revert_block, saved_globals)?;
     revert_block.append_operation(func::r#return(&[reason], location));
 
     Ok(revert_block)
@@ -1189,6 +1356,227 @@ impl<'c> OperationCtx<'c> {
         )
     }
 
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn eof_create_syscall(
+        &'c self,
+        block: &'c Block,
+        initcontainer_index: Value<'c, 'c>,
+        input_offset: Value<'c, 'c>,
+        input_size: Value<'c, 'c>,
+        value: Value<'c, 'c>,
+        remaining_gas: Value<'c, 'c>,
+        salt: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value, CodegenError> {
+        syscall::mlir::eof_create_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            initcontainer_index,
+            input_offset,
+            input_size,
+            value,
+            remaining_gas,
+            salt,
+            location,
+        )
+    }
+
+    pub(crate) fn eof_return_contract_syscall(
+        &'c self,
+        block: &'c Block,
+        container_index: Value<'c, 'c>,
+        aux_data_offset: Value<'c, 'c>,
+        aux_data_size: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value, CodegenError> {
+        syscall::mlir::eof_return_contract_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            container_index,
+            aux_data_offset,
+            aux_data_size,
+            location,
+        )
+    }
+
+    pub(crate) fn eof_push_return_syscall(
+        &'c self,
+        block: &'c Block,
+        return_pc: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value, CodegenError> {
+        syscall::mlir::eof_push_return_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            return_pc,
+            location,
+        )
+    }
+
+    pub(crate) fn eof_pop_return_syscall(
+        &'c self,
+        block: &'c Block,
+        location: Location<'c>,
+    ) -> Result<Value, CodegenError> {
+        syscall::mlir::eof_pop_return_syscall(self.mlir_context, self.syscall_ctx, block, location)
+    }
+
+    pub(crate) fn eof_data_load_syscall(
+        &'c self,
+        block: &'c Block,
+        offset: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        syscall::mlir::eof_data_load_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            offset,
+            location,
+        )
+    }
+
+    pub(crate) fn copy_eof_data_to_memory_syscall(
+        &'c self,
+        block: &'c Block,
+        data_offset: Value<'c, 'c>,
+        size: Value<'c, 'c>,
+        dest_offset: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        syscall::mlir::copy_eof_data_to_memory_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            data_offset,
+            size,
+            dest_offset,
+            location,
+        )
+    }
+
+    /// Calls the `EXTCALL` family syscall and charges its gas, returning the status
+    /// to push to the stack in `finish_block`
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn ext_call_syscall(
+        &'c self,
+        start_block: &'c Block,
+        finish_block: &'c Block,
+        location: Location<'c>,
+        address: Value<'c, 'c>,
+        value: Value<'c, 'c>,
+        args_offset: Value<'c, 'c>,
+        args_size: Value<'c, 'c>,
+        call_type: CallType,
+    ) -> Result<Value, CodegenError> {
+        let context = self.mlir_context;
+        let uint8 = IntegerType::new(context, 8);
+        let uint64 = IntegerType::new(context, 64);
+        let uint256 = IntegerType::new(context, 256);
+        let ptr_type = pointer(context, 0);
+
+        let available_gas = get_remaining_gas(context, start_block)?;
+        let value_ptr = allocate_and_store_value(self, start_block, value, location)?;
+        // The syscall replaces the address with the status of the call
+        let address_ptr = allocate_and_store_value(self, start_block, address, location)?;
+
+        let gas_pointer_size = constant_value_from_i64(context, start_block, 1_i64)?;
+        let consumed_gas_ptr = start_block
+            .append_operation(llvm::alloca(
+                context,
+                gas_pointer_size,
+                ptr_type,
+                location,
+                AllocaOptions::new().elem_type(Some(TypeAttribute::new(uint64.into()))),
+            ))
+            .result(0)?
+            .into();
+
+        let call_type_value = start_block
+            .append_operation(arith::constant(
+                context,
+                IntegerAttribute::new(uint8.into(), call_type as u8 as i64).into(),
+                location,
+            ))
+            .result(0)?
+            .into();
+
+        let return_code = syscall::mlir::ext_call_syscall(
+            context,
+            self.syscall_ctx,
+            start_block,
+            location,
+            address_ptr,
+            value_ptr,
+            args_offset,
+            args_size,
+            available_gas,
+            consumed_gas_ptr,
+            call_type_value,
+        )?;
+
+        let success_return_code = start_block
+            .append_operation(arith::constant(
+                context,
+                integer_constant_from_u8(context, SUCCESS_RETURN_CODE).into(),
+                location,
+            ))
+            .result(0)?
+            .into();
+        let success_flag = start_block
+            .append_operation(arith::cmpi(
+                context,
+                arith::CmpiPredicate::Eq,
+                return_code,
+                success_return_code,
+                location,
+            ))
+            .result(0)?
+            .into();
+
+        let consumed_gas = start_block
+            .append_operation(llvm::load(
+                context,
+                consumed_gas_ptr,
+                uint64.into(),
+                location,
+                LoadStoreOptions::default(),
+            ))
+            .result(0)?
+            .into();
+        let gas_flag = consume_gas_as_value(context, start_block, consumed_gas)?;
+        let ok_flag = start_block
+            .append_operation(arith::andi(success_flag, gas_flag, location))
+            .result(0)?
+            .into();
+
+        start_block.append_operation(cf::cond_br(
+            context,
+            ok_flag,
+            finish_block,
+            &self.revert_block,
+            &[],
+            &[],
+            location,
+        ));
+
+        let status = finish_block
+            .append_operation(llvm::load(
+                context,
+                address_ptr,
+                uint256.into(),
+                location,
+                LoadStoreOptions::default(),
+            ))
+            .result(0)?
+            .into();
+
+        Ok(status)
+    }
+
     pub(crate) fn selfdestruct_syscall(
         &'c self,
         block: &'c Block,
@@ -1204,6 +1592,10 @@ impl<'c> OperationCtx<'c> {
         )
     }
 
+    pub(crate) fn not_activated_syscall(&'c self, block: &'c Block, location: Location<'c>) {
+        syscall::mlir::not_activated_syscall(self.mlir_context, self.syscall_ctx, block, location)
+    }
+
     pub(crate) fn get_return_data_size(
         &'c self,
         block: &'c Block,
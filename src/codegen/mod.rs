@@ -7,13 +7,22 @@ use std::{
 
 use crate::module::MLIRModule;
 use crate::program::Program;
-use crate::{context::Session, errors::CodegenError};
+use crate::{
+    constants::AOT_SYSCALL_SLOT_SUFFIX, context::Session, errors::CodegenError, executor::OptLevel,
+    spec::SpecId,
+};
 use llvm_sys::{
     core::{
-        LLVMContextCreate, LLVMContextDispose, LLVMDisposeMessage, LLVMDisposeModule,
-        LLVMPrintModuleToFile,
+        LLVMAddGlobal, LLVMAppendBasicBlockInContext, LLVMBuildCall2, LLVMBuildLoad2, LLVMBuildRet,
+        LLVMBuildRetVoid, LLVMConstPointerNull, LLVMContextCreate, LLVMContextDispose,
+        LLVMCountParams, LLVMCreateBuilderInContext, LLVMDisposeBuilder, LLVMDisposeMessage,
+        LLVMDisposeModule, LLVMGetFirstFunction, LLVMGetModuleContext, LLVMGetNextFunction,
+        LLVMGetParams, LLVMGetReturnType, LLVMGetTypeKind, LLVMGetValueName2,
+        LLVMGlobalGetValueType, LLVMIsDeclaration, LLVMPointerTypeInContext,
+        LLVMPositionBuilderAtEnd, LLVMPrintModuleToFile, LLVMSetInitializer, LLVMSetLinkage,
     },
     error::LLVMGetErrorMessage,
+    prelude::LLVMModuleRef,
     target_machine::{
         LLVMCodeGenFileType, LLVMCodeGenOptLevel, LLVMCodeModel, LLVMCreateTargetMachine,
         LLVMDisposeTargetMachine, LLVMGetDefaultTargetTriple, LLVMGetHostCPUFeatures,
@@ -23,6 +32,7 @@ use llvm_sys::{
     transforms::pass_builder::{
         LLVMCreatePassBuilderOptions, LLVMDisposePassBuilderOptions, LLVMRunPasses,
     },
+    LLVMLinkage, LLVMTypeKind,
 };
 use mlir_sys::mlirTranslateModuleToLLVMIR;
 
@@ -47,10 +57,35 @@ pub fn compile(program: &Program, output_file: impl AsRef<Path>) -> Result<PathB
 /// The object will be written to the specified target path.
 ///
 /// Returns the path to the object.
-// TODO: pass options to the function
 pub fn compile_to_object(
     module: &MLIRModule<'_>,
     output_file: impl AsRef<Path>,
+) -> Result<PathBuf, CodegenError> {
+    emit_object(module, output_file, &Default::default())
+}
+
+struct ObjectOptions {
+    opt_level: OptLevel,
+    /// Route syscalls through globals patched at load time, see [`add_syscall_slots`]
+    syscall_slots: bool,
+    /// Also write the LLVM IR and the assembly next to the object
+    emit_ir_and_asm: bool,
+}
+
+impl Default for ObjectOptions {
+    fn default() -> Self {
+        Self {
+            opt_level: OptLevel::None,
+            syscall_slots: false,
+            emit_ir_and_asm: true,
+        }
+    }
+}
+
+fn emit_object(
+    module: &MLIRModule<'_>,
+    output_file: impl AsRef<Path>,
+    options: &ObjectOptions,
 ) -> Result<PathBuf, CodegenError> {
     let target_file = output_file.as_ref().with_extension("o");
 
@@ -63,6 +98,10 @@ pub fn compile_to_object(
 
         let llvm_module = mlirTranslateModuleToLLVMIR(op, llvm_context as *mut _) as *mut _;
 
+        if options.syscall_slots {
+            add_syscall_slots(llvm_module);
+        }
+
         let mut null = null_mut();
         let mut error_buffer = addr_of_mut!(null);
 
@@ -91,13 +130,18 @@ pub fn compile_to_object(
             target_triple.cast(),
             target_cpu.cast(),
             target_cpu_features.cast(),
-            LLVMCodeGenOptLevel::LLVMCodeGenLevelNone,
+            match options.opt_level {
+                OptLevel::None => LLVMCodeGenOptLevel::LLVMCodeGenLevelNone,
+                OptLevel::Less => LLVMCodeGenOptLevel::LLVMCodeGenLevelLess,
+                OptLevel::Default => LLVMCodeGenOptLevel::LLVMCodeGenLevelDefault,
+                OptLevel::Aggressive => LLVMCodeGenOptLevel::LLVMCodeGenLevelAggressive,
+            },
             LLVMRelocMode::LLVMRelocPIC,
             LLVMCodeModel::LLVMCodeModelDefault,
         );
 
         let opts = LLVMCreatePassBuilderOptions();
-        let opt = 0;
+        let opt = options.opt_level as usize;
         let passes = CString::new(format!("default<O{opt}>")).unwrap();
         let error = LLVMRunPasses(llvm_module as *mut _, passes.as_ptr(), machine, opts);
         if !error.is_null() {
@@ -111,22 +155,24 @@ pub fn compile_to_object(
         LLVMDisposePassBuilderOptions(opts);
 
         // Output the LLVM IR
-        let filename = CString::new(
-            target_file
-                .with_extension("ll")
-                .as_os_str()
-                .to_string_lossy()
-                .as_bytes(),
-        )
-        .unwrap();
-        if LLVMPrintModuleToFile(llvm_module, filename.as_ptr(), error_buffer) != 0 {
-            let error = CStr::from_ptr(*error_buffer);
-            let err = error.to_string_lossy().to_string();
-            LLVMDisposeMessage(*error_buffer);
-            return Err(CodegenError::LLVMCompileError(err));
-        } else if !(*error_buffer).is_null() {
-            LLVMDisposeMessage(*error_buffer);
-            error_buffer = addr_of_mut!(null);
+        if options.emit_ir_and_asm {
+            let filename = CString::new(
+                target_file
+                    .with_extension("ll")
+                    .as_os_str()
+                    .to_string_lossy()
+                    .as_bytes(),
+            )
+            .unwrap();
+            if LLVMPrintModuleToFile(llvm_module, filename.as_ptr(), error_buffer) != 0 {
+                let error = CStr::from_ptr(*error_buffer);
+                let err = error.to_string_lossy().to_string();
+                LLVMDisposeMessage(*error_buffer);
+                return Err(CodegenError::LLVMCompileError(err));
+            } else if !(*error_buffer).is_null() {
+                LLVMDisposeMessage(*error_buffer);
+                error_buffer = addr_of_mut!(null);
+            }
         }
 
         // Output the object file
@@ -149,29 +195,31 @@ pub fn compile_to_object(
         }
 
         // Output the assembly
-        let filename = CString::new(
-            target_file
-                .with_extension("asm")
-                .as_os_str()
-                .to_string_lossy()
-                .as_bytes(),
-        )
-        .unwrap();
-        let ok = LLVMTargetMachineEmitToFile(
-            machine,
-            llvm_module,
-            filename.as_ptr().cast_mut(),
-            LLVMCodeGenFileType::LLVMAssemblyFile,
-            error_buffer,
-        );
-
-        if ok != 0 {
-            let error = CStr::from_ptr(*error_buffer);
-            let err = error.to_string_lossy().to_string();
-            LLVMDisposeMessage(*error_buffer);
-            return Err(CodegenError::LLVMCompileError(err));
-        } else if !(*error_buffer).is_null() {
-            LLVMDisposeMessage(*error_buffer);
+        if options.emit_ir_and_asm {
+            let filename = CString::new(
+                target_file
+                    .with_extension("asm")
+                    .as_os_str()
+                    .to_string_lossy()
+                    .as_bytes(),
+            )
+            .unwrap();
+            let ok = LLVMTargetMachineEmitToFile(
+                machine,
+                llvm_module,
+                filename.as_ptr().cast_mut(),
+                LLVMCodeGenFileType::LLVMAssemblyFile,
+                error_buffer,
+            );
+
+            if ok != 0 {
+                let error = CStr::from_ptr(*error_buffer);
+                let err = error.to_string_lossy().to_string();
+                LLVMDisposeMessage(*error_buffer);
+                return Err(CodegenError::LLVMCompileError(err));
+            } else if !(*error_buffer).is_null() {
+                LLVMDisposeMessage(*error_buffer);
+            }
         }
 
         LLVMDisposeTargetMachine(machine);
@@ -323,7 +371,14 @@ pub fn link_shared_lib(
 
     let mut linker = std::process::Command::new("ld");
     let proc = linker.args(args.iter()).spawn()?;
-    proc.wait_with_output()?;
+    let output = proc.wait_with_output()?;
+
+    if !output.status.success() {
+        return Err(std::io::Error::other(format!(
+            "linker exited with {}",
+            output.status
+        )));
+    }
     Ok(())
 }
 
@@ -345,3 +400,86 @@ pub fn compile_shared_lib(
     link_shared_lib(&[object_file], output_file)?;
     Ok(())
 }
+
+/// Compiles the program into a shared library that can be loaded with
+/// [`Executor::load`](crate::executor::Executor::load).
+///
+/// The syscalls are left as undefined symbols by the regular compilation, which
+/// the dynamic loader can't resolve against our process. Instead, each of them is
+/// routed through a global slot that the executor fills in after loading.
+pub fn compile_aot_shared_lib(
+    program: &Program,
+    spec_id: SpecId,
+    opt_level: OptLevel,
+    output_file: impl AsRef<Path>,
+) -> Result<(), CodegenError> {
+    let context = Context::new();
+    let session = Session {
+        spec_id,
+        ..Default::default()
+    };
+    let mlir_module = context.compile(program, session)?;
+    let options = ObjectOptions {
+        opt_level,
+        syscall_slots: true,
+        emit_ir_and_asm: false,
+    };
+    let object_file = emit_object(&mlir_module, &output_file, &options)?;
+    let result = link_shared_lib(&[&object_file], output_file);
+    std::fs::remove_file(object_file)?;
+    result?;
+    Ok(())
+}
+
+/// Gives a body to every syscall declaration in the module, which loads the
+/// address of the syscall from an external global named `<syscall>__slot` and
+/// forwards the call to it.
+unsafe fn add_syscall_slots(llvm_module: LLVMModuleRef) {
+    let llvm_context = LLVMGetModuleContext(llvm_module);
+    let ptr_type = LLVMPointerTypeInContext(llvm_context, 0);
+    let builder = LLVMCreateBuilderInContext(llvm_context);
+
+    let mut syscalls = vec![];
+    let mut function = LLVMGetFirstFunction(llvm_module);
+    while !function.is_null() {
+        let mut name_len = 0;
+        let name = LLVMGetValueName2(function, &mut name_len);
+        let name = std::slice::from_raw_parts(name.cast::<u8>(), name_len);
+        if LLVMIsDeclaration(function) != 0 && name.starts_with(b"evm_mlir__") {
+            syscalls.push((function, String::from_utf8_lossy(name).into_owned()));
+        }
+        function = LLVMGetNextFunction(function);
+    }
+
+    for (function, name) in syscalls {
+        let slot_name = CString::new(format!("{name}{AOT_SYSCALL_SLOT_SUFFIX}")).unwrap();
+        let slot = LLVMAddGlobal(llvm_module, ptr_type, slot_name.as_ptr());
+        LLVMSetInitializer(slot, LLVMConstPointerNull(ptr_type));
+
+        let function_type = LLVMGlobalGetValueType(function);
+        let entry = LLVMAppendBasicBlockInContext(llvm_context, function, c"entry".as_ptr());
+        LLVMPositionBuilderAtEnd(builder, entry);
+        LLVMSetLinkage(function, LLVMLinkage::LLVMInternalLinkage);
+
+        let syscall_ptr = LLVMBuildLoad2(builder, ptr_type, slot, c"syscall_ptr".as_ptr());
+        let mut args = vec![null_mut(); LLVMCountParams(function) as usize];
+        LLVMGetParams(function, args.as_mut_ptr());
+        let result = LLVMBuildCall2(
+            builder,
+            function_type,
+            syscall_ptr,
+            args.as_mut_ptr(),
+            args.len() as u32,
+            c"".as_ptr(),
+        );
+
+        let return_type = LLVMGetReturnType(function_type);
+        if LLVMGetTypeKind(return_type) == LLVMTypeKind::LLVMVoidTypeKind {
+            LLVMBuildRetVoid(builder);
+        } else {
+            LLVMBuildRet(builder, result);
+        }
+    }
+
+    LLVMDisposeBuilder(builder);
+}
@@ -9,7 +9,7 @@ use melior::{
     ir::{
         attribute::{IntegerAttribute, TypeAttribute},
         r#type::IntegerType,
-        Attribute, Block, BlockRef, Location, Region,
+        Attribute, Block, BlockRef, Location, Region, Value,
     },
 };
 
@@ -21,6 +21,7 @@ use crate::{
     },
     errors::CodegenError,
     program::Operation,
+    spec::SpecId,
     syscall::ExitStatusCode,
     utils::{
         allocate_and_store_value, allocate_gas_counter_ptr, check_context_is_not_static,
@@ -29,8 +30,9 @@ use crate::{
         consume_gas_as_value, context_is_static, extend_memory, get_basefee,
         get_blob_hash_at_index, get_block_number, get_calldata_ptr, get_calldata_size,
         get_memory_pointer, get_nth_from_stack, get_prevrandao, get_remaining_gas,
-        get_stack_pointer, inc_stack_pointer, integer_constant_from_i64, llvm_mlir,
-        return_empty_result, return_result_from_stack, stack_pop, stack_push, swap_stack_elements,
+        get_stack_pointer, inc_stack_pointer, integer_constant_from_i64, integer_constant_from_u8,
+        llvm_mlir, return_empty_result, return_result_from_stack, stack_pop, stack_push,
+        swap_stack_elements,
     },
 };
 
@@ -113,8 +115,8 @@ pub fn generate_code_for_op<'c>(
         Operation::Tload => codegen_tload(op_ctx, region),
         Operation::Tstore => codegen_tstore(op_ctx, region),
         Operation::Mcopy => codegen_mcopy(op_ctx, region),
-        Operation::Push0 => codegen_push(op_ctx, region, BigUint::ZERO, true),
-        Operation::Push((_, x)) => codegen_push(op_ctx, region, x, false),
+        Operation::Push0 => codegen_push(op_ctx, region, BigUint::ZERO, gas_cost::PUSH0),
+        Operation::Push((_, x)) => codegen_push(op_ctx, region, x, gas_cost::PUSHN),
         Operation::Dup(x) => codegen_dup(op_ctx, region, x),
         Operation::Swap(x) => codegen_swap(op_ctx, region, x),
         Operation::Log(x) => codegen_log(op_ctx, region, x),
@@ -128,6 +130,21 @@ pub fn generate_code_for_op<'c>(
         Operation::Revert => codegen_revert(op_ctx, region),
         Operation::Invalid => codegen_invalid(op_ctx, region),
         Operation::SelfDestruct => codegen_selfdestruct(op_ctx, region),
+        Operation::Rjump { pc, offset } => codegen_rjump(op_ctx, region, pc, offset),
+        Operation::Rjumpi { pc, offset } => codegen_rjumpi(op_ctx, region, pc, offset),
+        Operation::Rjumpv { pc, offsets } => codegen_rjumpv(op_ctx, region, pc, offsets),
+        Operation::Callf { pc, index } => codegen_callf(op_ctx, region, pc, index),
+        Operation::Retf => codegen_retf(op_ctx, region),
+        Operation::Jumpf(index) => codegen_jumpf(op_ctx, region, index),
+        Operation::DataLoad => codegen_dataload(op_ctx, region),
+        Operation::DataLoadN(offset) => codegen_dataloadn(op_ctx, region, offset),
+        Operation::DataSize => codegen_datasize(op_ctx, region),
+        Operation::DataCopy => codegen_datacopy(op_ctx, region),
+        Operation::EofCreate(index) => codegen_eofcreate(op_ctx, region, index),
+        Operation::ReturnContract(index) => codegen_returncontract(op_ctx, region, index),
+        Operation::ExtCall => codegen_extcall(op_ctx, region, CallType::Call),
+        Operation::ExtDelegateCall => codegen_extcall(op_ctx, region, CallType::DelegateCall),
+        Operation::ExtStaticCall => codegen_extcall(op_ctx, region, CallType::StaticCall),
     }
 }
 
@@ -724,7 +741,7 @@ fn codegen_exp<'c, 'r>(
     let dynamic_gas_cost = ok_block
         .append_operation(arith::muli(
             number_of_bytes,
-            constant_value_from_i64(context, &ok_block, 50)?,
+            constant_value_from_i64(context, &ok_block, op_ctx.spec_id.gas_schedule().exp_byte)?,
             location,
         ))
         .result(0)?
@@ -1117,7 +1134,7 @@ fn codegen_push<'c, 'r>(
     op_ctx: &mut OperationCtx<'c>,
     region: &'r Region<'c>,
     value_to_push: BigUint,
-    is_zero: bool,
+    gas_cost: i64,
 ) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
     let start_block = region.append_block(Block::new(&[]));
     let context = &op_ctx.mlir_context;
@@ -1125,11 +1142,6 @@ fn codegen_push<'c, 'r>(
 
     // Check there's enough space in stack
     let flag = check_stack_has_space_for(context, &start_block, 1)?;
-    let gas_cost = if is_zero {
-        gas_cost::PUSH0
-    } else {
-        gas_cost::PUSHN
-    };
     let gas_flag = consume_gas(context, &start_block, gas_cost)?;
     let condition = start_block
         .append_operation(arith::andi(gas_flag, flag, location))
@@ -2369,10 +2381,16 @@ fn codegen_sstore<'c, 'r>(
     // Write storage and get the gas cost
     let gas_cost = op_ctx.storage_write_syscall(&ok_block, key_ptr, value_ptr, location)?;
 
+    // The call stipend can't be spent on storage writes since Istanbul (EIP-2200)
+    let min_remaining_gas = if op_ctx.spec_id.is_enabled_in(SpecId::Istanbul) {
+        gas_cost::SSTORE_MIN_REMAINING_GAS
+    } else {
+        0
+    };
     let min_remaining_gas = ok_block
         .append_operation(arith::constant(
             context,
-            IntegerAttribute::new(uint64, gas_cost::SSTORE_MIN_REMAINING_GAS).into(),
+            IntegerAttribute::new(uint64, min_remaining_gas).into(),
             location,
         ))
         .result(0)?
@@ -4600,6 +4618,22 @@ fn codegen_invalid<'c, 'r>(
     Ok((start_block, empty_block))
 }
 
+/// Generates the code for an operation that the program's hardfork doesn't have yet
+pub(crate) fn codegen_not_activated<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let context = op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let start_block = region.append_block(Block::new(&[]));
+    let empty_block = region.append_block(Block::new(&[]));
+
+    op_ctx.not_activated_syscall(&start_block, location);
+    start_block.append_operation(cf::br(&op_ctx.revert_block, &[], location));
+
+    Ok((start_block, empty_block))
+}
+
 fn codegen_selfbalance<'c, 'r>(
     op_ctx: &mut OperationCtx<'c>,
     region: &'r Region<'c>,
@@ -5459,7 +5493,11 @@ fn codegen_selfdestruct<'c, 'r>(
     let context = &op_ctx.mlir_context;
     let location = Location::unknown(context);
 
-    let gas_flag = consume_gas(context, &start_block, gas_cost::SELFDESTRUCT)?;
+    let gas_flag = consume_gas(
+        context,
+        &start_block,
+        op_ctx.spec_id.gas_schedule().selfdestruct,
+    )?;
     let stack_flag = check_stack_has_at_least(context, &start_block, 1)?;
     let gas_stack_flag = start_block
         .append_operation(arith::andi(gas_flag, stack_flag, location))
@@ -5661,3 +5699,836 @@ fn codegen_tstore<'c, 'r>(
 
     Ok((start_block, ok_block))
 }
+
+/// Target of a relative jump, which is relative to the end of the jump's immediates
+fn relative_jump_target(pc_after_immediates: usize, offset: i16) -> usize {
+    (pc_after_immediates as isize + offset as isize) as usize
+}
+
+fn codegen_rjump<'c>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'c Region<'c>,
+    pc: usize,
+    offset: i16,
+) -> Result<(BlockRef<'c, 'c>, BlockRef<'c, 'c>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = op_ctx.mlir_context;
+    let location = Location::unknown(context);
+
+    let gas_flag = consume_gas(context, &start_block, gas_cost::RJUMP)?;
+
+    let target_block = op_ctx.eof_landing_block(region, relative_jump_target(pc + 3, offset));
+    start_block.append_operation(cf::cond_br(
+        context,
+        gas_flag,
+        &target_block,
+        &op_ctx.revert_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let empty_block = region.append_block(Block::new(&[]));
+
+    Ok((start_block, empty_block))
+}
+
+fn codegen_rjumpi<'c>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'c Region<'c>,
+    pc: usize,
+    offset: i16,
+) -> Result<(BlockRef<'c, 'c>, BlockRef<'c, 'c>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = op_ctx.mlir_context;
+    let location = Location::unknown(context);
+
+    let flag = check_stack_has_at_least(context, &start_block, 1)?;
+    let gas_flag = consume_gas(context, &start_block, gas_cost::RJUMPI)?;
+    let condition = start_block
+        .append_operation(arith::andi(gas_flag, flag, location))
+        .result(0)?
+        .into();
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        condition,
+        &ok_block,
+        &op_ctx.revert_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let condition = stack_pop(context, &ok_block)?;
+    let zero = constant_value_from_i64(context, &ok_block, 0)?;
+    let should_jump = ok_block
+        .append_operation(arith::cmpi(
+            context,
+            CmpiPredicate::Ne,
+            condition,
+            zero,
+            location,
+        ))
+        .result(0)?
+        .into();
+
+    let target_block = op_ctx.eof_landing_block(region, relative_jump_target(pc + 3, offset));
+    let false_block = region.append_block(Block::new(&[]));
+
+    ok_block.append_operation(cf::cond_br(
+        context,
+        should_jump,
+        &target_block,
+        &false_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    Ok((start_block, false_block))
+}
+
+fn codegen_rjumpv<'c>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'c Region<'c>,
+    pc: usize,
+    offsets: Vec<i16>,
+) -> Result<(BlockRef<'c, 'c>, BlockRef<'c, 'c>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint256 = IntegerType::new(context, 256);
+
+    let flag = check_stack_has_at_least(context, &start_block, 1)?;
+    let gas_flag = consume_gas(context, &start_block, gas_cost::RJUMPV)?;
+    let condition = start_block
+        .append_operation(arith::andi(gas_flag, flag, location))
+        .result(0)?
+        .into();
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        condition,
+        &ok_block,
+        &op_ctx.revert_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let index = stack_pop(context, &ok_block)?;
+
+    // Offsets are relative to the end of the jump table
+    let pc_after_immediates = pc + 2 + 2 * offsets.len();
+    let target_blocks: Vec<_> = offsets
+        .iter()
+        .map(|offset| {
+            op_ctx.eof_landing_block(region, relative_jump_target(pc_after_immediates, *offset))
+        })
+        .collect();
+    let case_values: Vec<i64> = (0..offsets.len() as i64).collect();
+    let case_destinations: Vec<_> = target_blocks
+        .iter()
+        .map(|block| {
+            let destination: (&Block, &[Value]) = (block, &[]);
+            destination
+        })
+        .collect();
+
+    // An index out of the table falls through
+    let fallthrough_block = region.append_block(Block::new(&[]));
+
+    ok_block.append_operation(cf::switch(
+        context,
+        &case_values,
+        index,
+        uint256.into(),
+        (&fallthrough_block, &[]),
+        &case_destinations,
+        location,
+    )?);
+
+    Ok((start_block, fallthrough_block))
+}
+
+fn codegen_callf<'c>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'c Region<'c>,
+    pc: usize,
+    index: u16,
+) -> Result<(BlockRef<'c, 'c>, BlockRef<'c, 'c>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint32 = IntegerType::new(context, 32);
+
+    let eof = op_ctx
+        .program
+        .eof
+        .as_ref()
+        .expect("CALLF outside an EOF container");
+    let code_type = eof.types[index as usize];
+    let section_offset = eof.code_section_offset(index as usize);
+
+    // The function can't grow the stack past its limit
+    let stack_growth = code_type.max_stack_height - code_type.inputs as u16;
+    let flag = check_stack_has_space_for(context, &start_block, stack_growth.into())?;
+    let gas_flag = consume_gas(context, &start_block, gas_cost::CALLF)?;
+    let condition = start_block
+        .append_operation(arith::andi(gas_flag, flag, location))
+        .result(0)?
+        .into();
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        condition,
+        &ok_block,
+        &op_ctx.revert_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    // Execution resumes after the CALLF once the function returns
+    let return_pc = pc + 3;
+    op_ctx.eof_landing_block(region, return_pc);
+    let function_block = op_ctx.eof_landing_block(region, section_offset);
+
+    let return_pc_value = ok_block
+        .append_operation(arith::constant(
+            context,
+            IntegerAttribute::new(uint32.into(), return_pc as i64).into(),
+            location,
+        ))
+        .result(0)?
+        .into();
+    let result = op_ctx.eof_push_return_syscall(&ok_block, return_pc_value, location)?;
+    let success_return_code = ok_block
+        .append_operation(arith::constant(
+            context,
+            integer_constant_from_u8(context, SUCCESS_RETURN_CODE).into(),
+            location,
+        ))
+        .result(0)?
+        .into();
+    let flag = ok_block
+        .append_operation(arith::cmpi(
+            context,
+            CmpiPredicate::Eq,
+            result,
+            success_return_code,
+            location,
+        ))
+        .result(0)?
+        .into();
+
+    ok_block.append_operation(cf::cond_br(
+        context,
+        flag,
+        &function_block,
+        &op_ctx.revert_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let empty_block = region.append_block(Block::new(&[]));
+
+    Ok((start_block, empty_block))
+}
+
+fn codegen_retf<'c>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'c Region<'c>,
+) -> Result<(BlockRef<'c, 'c>, BlockRef<'c, 'c>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = op_ctx.mlir_context;
+    let location = Location::unknown(context);
+
+    let gas_flag = consume_gas(context, &start_block, gas_cost::RETF)?;
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        gas_flag,
+        &ok_block,
+        &op_ctx.revert_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let retf_block = op_ctx.retf_block(region);
+    let return_pc = op_ctx.eof_pop_return_syscall(&ok_block, location)?;
+    ok_block.append_operation(cf::br(&retf_block, &[return_pc], location));
+
+    let empty_block = region.append_block(Block::new(&[]));
+
+    Ok((start_block, empty_block))
+}
+
+fn codegen_jumpf<'c>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'c Region<'c>,
+    index: u16,
+) -> Result<(BlockRef<'c, 'c>, BlockRef<'c, 'c>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = op_ctx.mlir_context;
+    let location = Location::unknown(context);
+
+    let eof = op_ctx
+        .program
+        .eof
+        .as_ref()
+        .expect("JUMPF outside an EOF container");
+    let code_type = eof.types[index as usize];
+    let section_offset = eof.code_section_offset(index as usize);
+
+    let stack_growth = code_type.max_stack_height - code_type.inputs as u16;
+    let flag = check_stack_has_space_for(context, &start_block, stack_growth.into())?;
+    let gas_flag = consume_gas(context, &start_block, gas_cost::JUMPF)?;
+    let condition = start_block
+        .append_operation(arith::andi(gas_flag, flag, location))
+        .result(0)?
+        .into();
+
+    // The function returns to our caller, so nothing is pushed to the return stack
+    let function_block = op_ctx.eof_landing_block(region, section_offset);
+    start_block.append_operation(cf::cond_br(
+        context,
+        condition,
+        &function_block,
+        &op_ctx.revert_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let empty_block = region.append_block(Block::new(&[]));
+
+    Ok((start_block, empty_block))
+}
+
+fn codegen_dataload<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint256 = IntegerType::new(context, 256);
+
+    let flag = check_stack_has_at_least(context, &start_block, 1)?;
+    let gas_flag = consume_gas(context, &start_block, gas_cost::DATALOAD)?;
+    let condition = start_block
+        .append_operation(arith::andi(gas_flag, flag, location))
+        .result(0)?
+        .into();
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        condition,
+        &ok_block,
+        &op_ctx.revert_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let offset = stack_pop(context, &ok_block)?;
+    // The syscall replaces the offset with the loaded word
+    let offset_ptr = allocate_and_store_value(op_ctx, &ok_block, offset, location)?;
+    op_ctx.eof_data_load_syscall(&ok_block, offset_ptr, location);
+
+    let value = ok_block
+        .append_operation(llvm::load(
+            context,
+            offset_ptr,
+            uint256.into(),
+            location,
+            LoadStoreOptions::default(),
+        ))
+        .result(0)?
+        .into();
+
+    stack_push(context, &ok_block, value)?;
+
+    Ok((start_block, ok_block))
+}
+
+fn codegen_dataloadn<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+    offset: u16,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let data = &op_ctx
+        .program
+        .eof
+        .as_ref()
+        .expect("DATALOADN outside an EOF container")
+        .data;
+    // Validation ensures the word is within the declared data, which is only missing
+    // the auxiliary data while running initcode
+    let mut word = [0u8; 32];
+    let start = (offset as usize).min(data.len());
+    let end = data.len().min(start + 32);
+    word[..end - start].copy_from_slice(&data[start..end]);
+
+    codegen_push(
+        op_ctx,
+        region,
+        BigUint::from_bytes_be(&word),
+        gas_cost::DATALOADN,
+    )
+}
+
+fn codegen_datasize<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let data_size = op_ctx
+        .program
+        .eof
+        .as_ref()
+        .expect("DATASIZE outside an EOF container")
+        .data
+        .len();
+
+    codegen_push(op_ctx, region, BigUint::from(data_size), gas_cost::DATASIZE)
+}
+
+fn codegen_datacopy<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint32 = IntegerType::new(context, 32);
+
+    let flag = check_stack_has_at_least(context, &start_block, 3)?;
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        flag,
+        &ok_block,
+        &op_ctx.revert_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let dest_offset = stack_pop(context, &ok_block)?;
+    let offset = stack_pop(context, &ok_block)?;
+    let size = stack_pop(context, &ok_block)?;
+
+    let dest_offset = ok_block
+        .append_operation(arith::trunci(dest_offset, uint32.into(), location))
+        .result(0)?
+        .into();
+    let offset = ok_block
+        .append_operation(arith::trunci(offset, uint32.into(), location))
+        .result(0)?
+        .into();
+    let size = ok_block
+        .append_operation(arith::trunci(size, uint32.into(), location))
+        .result(0)?
+        .into();
+
+    let required_size = ok_block
+        .append_operation(arith::addi(dest_offset, size, location))
+        .result(0)?
+        .into();
+
+    let dynamic_gas_cost = compute_copy_cost(op_ctx, &ok_block, size)?;
+    let flag = consume_gas_as_value(context, &ok_block, dynamic_gas_cost)?;
+
+    let memory_extension_block = region.append_block(Block::new(&[]));
+    let copy_block = region.append_block(Block::new(&[]));
+
+    ok_block.append_operation(cf::cond_br(
+        context,
+        flag,
+        &memory_extension_block,
+        &op_ctx.revert_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    extend_memory(
+        op_ctx,
+        &memory_extension_block,
+        &copy_block,
+        region,
+        required_size,
+        gas_cost::DATACOPY,
+    )?;
+
+    op_ctx.copy_eof_data_to_memory_syscall(&copy_block, offset, size, dest_offset, location);
+
+    Ok((start_block, copy_block))
+}
+
+fn codegen_eofcreate<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+    initcontainer_index: u8,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint8 = IntegerType::new(context, 8);
+    let uint32 = IntegerType::new(context, 32);
+    let uint64 = IntegerType::new(context, 64);
+    let uint256 = IntegerType::new(context, 256);
+
+    let stack_flag = check_stack_has_at_least(context, &start_block, 4)?;
+    let context_flag = check_context_is_not_static(op_ctx, &start_block)?;
+    let ok_flag = start_block
+        .append_operation(arith::andi(context_flag, stack_flag, location))
+        .result(0)?
+        .into();
+    let ok_block = region.append_block(Block::new(&[]));
+    start_block.append_operation(cf::cond_br(
+        context,
+        ok_flag,
+        &ok_block,
+        &op_ctx.revert_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let value = stack_pop(context, &ok_block)?;
+    let salt = stack_pop(context, &ok_block)?;
+    let input_offset = stack_pop(context, &ok_block)?;
+    let input_size = stack_pop(context, &ok_block)?;
+
+    let input_offset = ok_block
+        .append_operation(arith::trunci(input_offset, uint32.into(), location))
+        .result(0)?
+        .into();
+    let input_size = ok_block
+        .append_operation(arith::trunci(input_size, uint32.into(), location))
+        .result(0)?
+        .into();
+    let req_mem_size = ok_block
+        .append_operation(arith::addi(input_offset, input_size, location))
+        .result(0)?
+        .into();
+
+    let create_block = region.append_block(Block::new(&[]));
+
+    extend_memory(
+        op_ctx,
+        &ok_block,
+        &create_block,
+        region,
+        req_mem_size,
+        gas_cost::EOFCREATE,
+    )?;
+
+    let value_ptr = allocate_and_store_value(op_ctx, &create_block, value, location)?;
+    let salt_ptr = allocate_and_store_value(op_ctx, &create_block, salt, location)?;
+    let gas_ptr = allocate_gas_counter_ptr(context, &create_block, location)?;
+    let initcontainer_index = create_block
+        .append_operation(arith::constant(
+            context,
+            IntegerAttribute::new(uint8.into(), initcontainer_index.into()).into(),
+            location,
+        ))
+        .result(0)?
+        .into();
+
+    let result = op_ctx.eof_create_syscall(
+        &create_block,
+        initcontainer_index,
+        input_offset,
+        input_size,
+        value_ptr,
+        gas_ptr,
+        salt_ptr,
+        location,
+    )?;
+
+    let success_return_code = create_block
+        .append_operation(arith::constant(
+            context,
+            IntegerAttribute::new(uint8.into(), SUCCESS_RETURN_CODE as i64).into(),
+            location,
+        ))
+        .result(0)?
+        .into();
+    let flag = create_block
+        .append_operation(arith::cmpi(
+            context,
+            CmpiPredicate::Eq,
+            success_return_code,
+            result,
+            location,
+        ))
+        .result(0)?
+        .into();
+
+    // Consume gas after creation
+    let dynamic_gas = create_block
+        .append_operation(llvm::load(
+            context,
+            gas_ptr,
+            uint64.into(),
+            location,
+            LoadStoreOptions::default(),
+        ))
+        .result(0)?
+        .into();
+    let gas_flag = consume_gas_as_value(context, &create_block, dynamic_gas)?;
+    let ok_flag = create_block
+        .append_operation(arith::andi(flag, gas_flag, location))
+        .result(0)?
+        .into();
+
+    let finish_block = region.append_block(Block::new(&[]));
+    create_block.append_operation(cf::cond_br(
+        context,
+        ok_flag,
+        &finish_block,
+        &op_ctx.revert_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    // The syscall replaces the value with the address of the new contract
+    let address = finish_block
+        .append_operation(llvm::load(
+            context,
+            value_ptr,
+            uint256.into(),
+            location,
+            LoadStoreOptions::default(),
+        ))
+        .result(0)?
+        .into();
+    stack_push(context, &finish_block, address)?;
+
+    Ok((start_block, finish_block))
+}
+
+fn codegen_returncontract<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+    container_index: u8,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint8 = IntegerType::new(context, 8);
+    let uint32 = IntegerType::new(context, 32);
+
+    let flag = check_stack_has_at_least(context, &start_block, 2)?;
+    let ok_block = region.append_block(Block::new(&[]));
+    start_block.append_operation(cf::cond_br(
+        context,
+        flag,
+        &ok_block,
+        &op_ctx.revert_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let aux_data_offset = stack_pop(context, &ok_block)?;
+    let aux_data_size = stack_pop(context, &ok_block)?;
+
+    let aux_data_offset = ok_block
+        .append_operation(arith::trunci(aux_data_offset, uint32.into(), location))
+        .result(0)?
+        .into();
+    let aux_data_size = ok_block
+        .append_operation(arith::trunci(aux_data_size, uint32.into(), location))
+        .result(0)?
+        .into();
+    let required_size = ok_block
+        .append_operation(arith::addi(aux_data_offset, aux_data_size, location))
+        .result(0)?
+        .into();
+
+    let return_block = region.append_block(Block::new(&[]));
+
+    extend_memory(
+        op_ctx,
+        &ok_block,
+        &return_block,
+        region,
+        required_size,
+        gas_cost::RETURNCONTRACT,
+    )?;
+
+    let container_index = return_block
+        .append_operation(arith::constant(
+            context,
+            IntegerAttribute::new(uint8.into(), container_index.into()).into(),
+            location,
+        ))
+        .result(0)?
+        .into();
+    let result = op_ctx.eof_return_contract_syscall(
+        &return_block,
+        container_index,
+        aux_data_offset,
+        aux_data_size,
+        location,
+    )?;
+    let success_return_code = return_block
+        .append_operation(arith::constant(
+            context,
+            IntegerAttribute::new(uint8.into(), SUCCESS_RETURN_CODE as i64).into(),
+            location,
+        ))
+        .result(0)?
+        .into();
+    let flag = return_block
+        .append_operation(arith::cmpi(
+            context,
+            CmpiPredicate::Eq,
+            result,
+            success_return_code,
+            location,
+        ))
+        .result(0)?
+        .into();
+
+    let deploy_block = region.append_block(Block::new(&[]));
+    return_block.append_operation(cf::cond_br(
+        context,
+        flag,
+        &deploy_block,
+        &op_ctx.revert_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    // The deployed container is kept by the syscall, so nothing is returned from memory
+    return_empty_result(
+        op_ctx,
+        &deploy_block,
+        ExitStatusCode::ReturnContract,
+        location,
+    )?;
+
+    let empty_block = region.append_block(Block::new(&[]));
+
+    Ok((start_block, empty_block))
+}
+
+fn codegen_extcall<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+    call_type: CallType,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint32 = IntegerType::new(context, 32);
+
+    let nargs = match call_type {
+        CallType::Call => 4,
+        _ => 3,
+    };
+
+    let flag = check_stack_has_at_least(context, &start_block, nargs)?;
+    let stack_ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        flag,
+        &stack_ok_block,
+        &op_ctx.revert_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let address = stack_pop(context, &stack_ok_block)?;
+    let args_offset = stack_pop(context, &stack_ok_block)?;
+    let args_size = stack_pop(context, &stack_ok_block)?;
+    let value = match call_type {
+        CallType::Call => stack_pop(context, &stack_ok_block)?,
+        _ => constant_value_from_i64(context, &stack_ok_block, 0)?,
+    };
+
+    // If the current context is static, value must be zero
+    let ok_block = region.append_block(Block::new(&[]));
+    let ctx_is_static = context_is_static(op_ctx, &stack_ok_block)?;
+    let zero_value = constant_value_from_i64(context, &stack_ok_block, 0)?;
+    let value_is_not_zero = stack_ok_block
+        .append_operation(arith::cmpi(
+            context,
+            CmpiPredicate::Ne,
+            value,
+            zero_value,
+            location,
+        ))
+        .result(0)?
+        .into();
+    let revert_flag = stack_ok_block
+        .append_operation(arith::andi(ctx_is_static, value_is_not_zero, location))
+        .result(0)?
+        .into();
+    stack_ok_block.append_operation(cf::cond_br(
+        context,
+        revert_flag,
+        &op_ctx.revert_block,
+        &ok_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let args_offset = ok_block
+        .append_operation(arith::trunci(args_offset, uint32.into(), location))
+        .result(0)?
+        .into();
+    let args_size = ok_block
+        .append_operation(arith::trunci(args_size, uint32.into(), location))
+        .result(0)?
+        .into();
+    let req_mem_size = ok_block
+        .append_operation(arith::addi(args_offset, args_size, location))
+        .result(0)?
+        .into();
+
+    // 0 cost, the access and call costs are consumed with the ext_call_syscall
+    let mem_ext_block = region.append_block(Block::new(&[]));
+    extend_memory(op_ctx, &ok_block, &mem_ext_block, region, req_mem_size, 0)?;
+
+    let finish_block = region.append_block(Block::new(&[]));
+    let status = op_ctx.ext_call_syscall(
+        &mem_ext_block,
+        &finish_block,
+        location,
+        address,
+        value,
+        args_offset,
+        args_size,
+        call_type,
+    )?;
+
+    stack_push(context, &finish_block, status)?;
+
+    Ok((start_block, finish_block))
+}
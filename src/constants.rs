@@ -1,6 +1,8 @@
 use thiserror::Error;
 
 pub const MAX_STACK_SIZE: usize = 1024;
+/// Maximum depth of nested calls and creates, where the transaction's frame has depth 0
+pub const CALL_STACK_LIMIT: usize = 1024;
 pub const GAS_COUNTER_GLOBAL: &str = "evm_mlir__gas_counter";
 pub const STACK_BASEPTR_GLOBAL: &str = "evm_mlir__stack_baseptr";
 pub const CODE_PTR_GLOBAL: &str = "evm_mlir__code_ptr";
@@ -10,13 +12,74 @@ pub const MEMORY_SIZE_GLOBAL: &str = "evm_mlir__memory_size";
 pub const CALLDATA_PTR_GLOBAL: &str = "evm_mlir__calldata_ptr";
 pub const CALLDATA_SIZE_GLOBAL: &str = "evm_mlir__calldata_size";
 pub const MAIN_ENTRYPOINT: &str = "main";
+// Suffix of the globals holding the address of each syscall in AOT compiled code
+pub const AOT_SYSCALL_SLOT_SUFFIX: &str = "__slot";
 
 // An empty bytecode has the following Keccak256 hash
 pub const EMPTY_CODE_HASH_STR: &str =
     "0xc5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470";
+// A trie without entries has the following root, the Keccak256 hash of an empty RLP string
+pub const EMPTY_TRIE_ROOT_STR: &str =
+    "0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421";
 
 pub const VERSIONED_HASH_VERSION_KZG: u8 = 0x01;
 pub const MAX_BLOB_NUMBER_PER_BLOCK: u8 = 0x01;
+/// Blob gas used by each blob of a transaction (EIP-4844)
+pub const GAS_PER_BLOB: u64 = 1 << 17;
+
+/// Sender of the calls the protocol makes to system contracts at the start of a block
+pub const SYSTEM_ADDRESS: [u8; 20] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xfe,
+];
+/// Contract storing the roots of the parent beacon blocks (EIP-4788)
+pub const BEACON_ROOTS_ADDRESS: [u8; 20] = [
+    0x00, 0x0f, 0x3d, 0xf6, 0xd7, 0x32, 0x80, 0x7e, 0xf1, 0x31, 0x9f, 0xb7, 0xb8, 0xbb, 0x85, 0x22,
+    0xd0, 0xbe, 0xac, 0x02,
+];
+/// Contract storing the hashes of the recent blocks (EIP-2935)
+pub const HISTORY_STORAGE_ADDRESS: [u8; 20] = [
+    0x00, 0x00, 0xf9, 0x08, 0x27, 0xf1, 0xc5, 0x3a, 0x10, 0xcb, 0x7a, 0x02, 0x33, 0x5b, 0x17, 0x53,
+    0x20, 0x00, 0x29, 0x35,
+];
+/// Gas available to system calls, which isn't accounted in the block
+pub const SYSTEM_CALL_GAS_LIMIT: u64 = 30_000_000;
+
+/// Code of an account delegating to another one is this prefix followed by the address (EIP-7702)
+pub const DELEGATION_DESIGNATOR_PREFIX: [u8; 3] = [0xef, 0x01, 0x00];
+pub const DELEGATION_DESIGNATOR_SIZE: usize = 23;
+/// Prefix of the RLP encoded authorizations when hashing them to be signed (EIP-7702)
+pub const SET_CODE_AUTHORIZATION_MAGIC: u8 = 0x05;
+/// Half the order of the secp256k1 curve, the highest `s` of a valid signature (EIP-2)
+pub const SECP256K1N_HALF: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
+/// Layout and limits of EVM Object Format containers (EIP-3540 and EIP-7692)
+pub mod eof {
+    /// Every container starts with this magic, which legacy code can't start with (EIP-3541)
+    pub const MAGIC: [u8; 2] = [0xef, 0x00];
+    pub const VERSION: u8 = 0x01;
+
+    // Kinds of the sections listed in the header
+    pub const KIND_TERMINATOR: u8 = 0x00;
+    pub const KIND_TYPES: u8 = 0x01;
+    pub const KIND_CODE: u8 = 0x02;
+    pub const KIND_CONTAINER: u8 = 0x03;
+    pub const KIND_DATA: u8 = 0x04;
+
+    /// Size of each entry of the type section
+    pub const TYPE_SIZE: usize = 4;
+    pub const MAX_CODE_SECTIONS: usize = 1024;
+    pub const MAX_CONTAINER_SECTIONS: usize = 256;
+    /// Value of the outputs of a code section that never returns to its caller
+    pub const NON_RETURNING: u8 = 0x80;
+    pub const MAX_INPUTS_OUTPUTS: u8 = 0x7f;
+    pub const MAX_STACK_HEIGHT: u16 = 0x03ff;
+    /// Maximum amount of nested `CALLF`s
+    pub const MAX_RETURN_STACK_HEIGHT: usize = 1024;
+}
 
 //TODO: Add missing opcodes gas consumption costs
 //  -> This implies refactoring codegen/operations.rs
@@ -104,6 +167,20 @@ pub mod gas_cost {
     pub const TSTORE: i64 = 100;
     pub const SELFDESTRUCT: i64 = 5_000;
     pub const SELFDESTRUCT_DYNAMIC_GAS: i64 = 25_000;
+    pub const RJUMP: i64 = 2;
+    pub const RJUMPI: i64 = 4;
+    pub const RJUMPV: i64 = 4;
+    pub const CALLF: i64 = 5;
+    pub const RETF: i64 = 3;
+    pub const JUMPF: i64 = 5;
+    pub const DATALOAD: i64 = 4;
+    pub const DATALOADN: i64 = 3;
+    pub const DATASIZE: i64 = 2;
+    pub const DATACOPY: i64 = 3;
+    pub const EOFCREATE: i64 = 32_000;
+    pub const RETURNCONTRACT: i64 = 0;
+    pub const EXTCALL_WARM: i64 = 100;
+    pub const EXTCALL_COLD: i64 = 2600;
 
     pub const MIN_BLOB_GASPRICE: u64 = 1;
     pub const BLOB_GASPRICE_UPDATE_FRACTION: u64 = 3338477;
@@ -119,6 +196,10 @@ pub mod gas_cost {
     pub const TX_CREATE_COST: u64 = 32000;
     pub const TX_ACCESS_LIST_ADDRESS_COST: u64 = 2400;
     pub const TX_ACCESS_LIST_STORAGE_KEY_COST: u64 = 1900;
+    /// Charged for each authorization of a set code transaction (EIP-7702)
+    pub const PER_EMPTY_ACCOUNT_COST: u64 = 25000;
+    /// Part of [`PER_EMPTY_ACCOUNT_COST`] that isn't refunded when the authority already exists
+    pub const PER_AUTH_BASE_COST: u64 = 12500;
     pub const MAX_CODE_SIZE: usize = 0x6000;
 
     /// calculates the init_code_cost of create transactions as specified by the eip 3860
@@ -164,6 +245,14 @@ pub mod call_opcode {
     pub const EMPTY_CALLEE_COST: u64 = 25000;
     pub const STIPEND_GAS_ADDITION: u64 = 2300;
     pub const GAS_CAP_DIVISION_FACTOR: u64 = 64;
+    /// The `EXTCALL` family fails if the caller would be left with less gas (EIP-7069)
+    pub const EXT_MIN_RETAINED_GAS: u64 = 5000;
+    /// The `EXTCALL` family fails if the callee would get less gas (EIP-7069)
+    pub const EXT_MIN_CALLEE_GAS: u64 = 2300;
+    // Statuses pushed by the `EXTCALL` family
+    pub const EXT_CALL_SUCCESS: u128 = 0;
+    pub const EXT_CALL_REVERT: u128 = 1;
+    pub const EXT_CALL_FAILURE: u128 = 2;
 }
 
 pub mod return_codes {
@@ -188,6 +277,21 @@ pub mod precompiles {
     pub fn blake2_gas_cost(rounds: u32) -> u64 {
         rounds as u64
     }
+    /// Cost of a BLS12-381 multi-scalar multiplication of `pairs` points, discounted per EIP-2537.
+    pub fn bls12_381_msm_cost(pairs: u64, multiplication_cost: u64, discounts: &[u64]) -> u64 {
+        if pairs == 0 {
+            return 0;
+        }
+        let discount = discounts
+            .get(pairs as usize - 1)
+            .or(discounts.last())
+            .copied()
+            .unwrap_or(BLS12_381_MSM_MULTIPLIER);
+        pairs * multiplication_cost * discount / BLS12_381_MSM_MULTIPLIER
+    }
+    pub fn bls12_381_pairing_cost(pairs: u64) -> u64 {
+        BLS12_381_PAIRING_STATIC_COST + BLS12_381_PAIRING_PAIR_COST * pairs
+    }
 
     pub const fn ecpairing_g2_point1_start(pos: usize) -> usize {
         pos + ECP_FIELD_SIZE
@@ -294,9 +398,79 @@ pub mod precompiles {
     /// (196; 212) contains the Offset counters vector, which contains 2 BF2_VEC_ELEM_SIZE.
     pub const BF2_OFFSET_COUNT_INIT: usize = 196;
     pub const BLAKE2F_ADDRESS: u64 = 0x09;
+
+    // point evaluation
+    /// (0; 32) contains the versioned hash of the commitment.
+    pub const PE_VERSIONED_HASH_END: usize = 32;
+    /// (32; 64) contains the point z.
+    pub const PE_Z_END: usize = 64;
+    /// (64; 96) contains the claimed evaluation y.
+    pub const PE_Y_END: usize = 96;
+    /// (96; 144) contains the commitment, followed by the proof up to the end.
+    pub const PE_COMMITMENT_END: usize = 144;
+    pub const POINT_EVALUATION_CALLDATA_LEN: usize = 192;
+    pub const POINT_EVALUATION_COST: u64 = 50000;
+    pub const POINT_EVALUATION_ADDRESS: u64 = 0x0a;
+    pub const FIELD_ELEMENTS_PER_BLOB: u64 = 4096;
+    /// Modulus of the BLS12-381 scalar field, big endian.
+    pub const BLS_MODULUS: [u8; 32] = [
+        0x73, 0xed, 0xa7, 0x53, 0x29, 0x9d, 0x7d, 0x48, 0x33, 0x39, 0xd8, 0x08, 0x09, 0xa1, 0xd8,
+        0x05, 0x53, 0xbd, 0xa4, 0x02, 0xff, 0xfe, 0x5b, 0xfe, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00,
+        0x00, 0x01,
+    ];
+
+    // BLS12-381 (EIP-2537)
+    /// Field elements are encoded in 64 bytes, big endian.
+    pub const BLS12_381_FP_SIZE: usize = 64;
+    /// The top 16 bytes of an encoded field element must be zero.
+    pub const BLS12_381_FP_PADDING: usize = 16;
+    /// (0; 64) contains x and (64; 128) contains y.
+    pub const BLS12_381_G1_POINT_SIZE: usize = 128;
+    /// (0; 128) contains x and (128; 256) contains y, each as c0 followed by c1.
+    pub const BLS12_381_G2_POINT_SIZE: usize = 256;
+    pub const BLS12_381_SCALAR_SIZE: usize = 32;
+    pub const BLS12_381_G1ADD_ADDRESS: u64 = 0x0b;
+    pub const BLS12_381_G1ADD_COST: u64 = 375;
+    pub const BLS12_381_G1MSM_ADDRESS: u64 = 0x0c;
+    pub const BLS12_381_G1MSM_MUL_COST: u64 = 12000;
+    pub const BLS12_381_G2ADD_ADDRESS: u64 = 0x0d;
+    pub const BLS12_381_G2ADD_COST: u64 = 600;
+    pub const BLS12_381_G2MSM_ADDRESS: u64 = 0x0e;
+    pub const BLS12_381_G2MSM_MUL_COST: u64 = 22500;
+    pub const BLS12_381_PAIRING_ADDRESS: u64 = 0x0f;
+    pub const BLS12_381_PAIRING_STATIC_COST: u64 = 37700;
+    pub const BLS12_381_PAIRING_PAIR_COST: u64 = 32600;
+    pub const BLS12_381_MAP_FP_TO_G1_ADDRESS: u64 = 0x10;
+    pub const BLS12_381_MAP_FP_TO_G1_COST: u64 = 5500;
+    pub const BLS12_381_MAP_FP2_TO_G2_ADDRESS: u64 = 0x11;
+    pub const BLS12_381_MAP_FP2_TO_G2_COST: u64 = 23800;
+    /// The MSM discounts are given in thousandths.
+    pub const BLS12_381_MSM_MULTIPLIER: u64 = 1000;
+    /// Discount of a G1 MSM by amount of pairs, the last one applies to bigger amounts.
+    pub const BLS12_381_G1MSM_DISCOUNTS: [u64; 128] = [
+        1000, 949, 848, 797, 764, 750, 738, 728, 719, 712, 705, 698, 692, 687, 682, 677, 673, 669,
+        665, 661, 658, 654, 651, 648, 645, 642, 640, 637, 635, 632, 630, 627, 625, 623, 621, 619,
+        617, 615, 613, 611, 609, 608, 606, 604, 603, 601, 599, 598, 596, 595, 593, 592, 591, 589,
+        588, 586, 585, 584, 582, 581, 580, 579, 577, 576, 575, 574, 573, 572, 570, 569, 568, 567,
+        566, 565, 564, 563, 562, 561, 560, 559, 558, 557, 556, 555, 554, 553, 552, 551, 550, 549,
+        548, 547, 547, 546, 545, 544, 543, 542, 541, 540, 540, 539, 538, 537, 536, 536, 535, 534,
+        533, 532, 532, 531, 530, 529, 528, 528, 527, 526, 525, 525, 524, 523, 522, 522, 521, 520,
+        520, 519,
+    ];
+    /// Discount of a G2 MSM by amount of pairs, the last one applies to bigger amounts.
+    pub const BLS12_381_G2MSM_DISCOUNTS: [u64; 128] = [
+        1000, 1000, 923, 884, 855, 832, 812, 796, 782, 770, 759, 749, 740, 732, 724, 717, 711, 704,
+        699, 693, 688, 683, 679, 674, 670, 666, 663, 659, 655, 652, 649, 646, 643, 640, 637, 634,
+        632, 629, 627, 624, 622, 620, 618, 615, 613, 611, 609, 607, 606, 604, 602, 600, 598, 597,
+        595, 593, 592, 590, 589, 587, 586, 584, 583, 582, 580, 579, 578, 576, 575, 574, 573, 571,
+        570, 569, 568, 567, 566, 565, 563, 562, 561, 560, 559, 558, 557, 556, 555, 554, 553, 552,
+        552, 551, 550, 549, 548, 547, 546, 545, 545, 544, 543, 542, 541, 541, 540, 539, 538, 537,
+        537, 536, 535, 535, 534, 533, 532, 532, 531, 530, 530, 529, 528, 528, 527, 526, 526, 525,
+        524, 524,
+    ];
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum CallType {
     Call,
     StaticCall,
@@ -22,6 +22,7 @@ use melior::{
     Context as MeliorContext,
 };
 use std::{
+    collections::BTreeMap,
     ffi::CStr,
     mem::MaybeUninit,
     path::PathBuf,
@@ -30,13 +31,18 @@ use std::{
 };
 
 use crate::{
-    codegen::{context::OperationCtx, operations::generate_code_for_op, run_pass_manager},
+    codegen::{
+        context::OperationCtx,
+        operations::{codegen_not_activated, generate_code_for_op},
+        run_pass_manager,
+    },
     constants::MAIN_ENTRYPOINT,
     errors::CodegenError,
     module::MLIRModule,
-    program::Program,
+    program::{Operation, Program},
+    spec::SpecId,
     syscall::ExitStatusCode,
-    utils::return_empty_result,
+    utils::{inspect_step, return_empty_result},
 };
 
 #[derive(Debug, Eq, PartialEq)]
@@ -60,6 +66,10 @@ pub struct Session {
     pub raw_mlir_path: Option<PathBuf>,
     /// The path for the after-pass mlir file.
     pub after_pass_mlir_path: Option<PathBuf>,
+    /// Whether to report each operation to the inspector before running it.
+    pub instrument_steps: bool,
+    /// The hardfork whose rules the program follows.
+    pub spec_id: SpecId,
 }
 
 impl Context {
@@ -107,7 +117,7 @@ impl Context {
 
         let mut melior_module = MeliorModule::from_operation(op).expect("module failed to create");
 
-        compile_program(context, &melior_module, program)?;
+        compile_program(context, &melior_module, program, &config)?;
         assert!(melior_module.as_operation().verify());
 
         if let Some(path) = &config.raw_mlir_path {
@@ -204,6 +214,7 @@ fn compile_program(
     context: &MeliorContext,
     module: &MeliorModule,
     program: &Program,
+    config: &Session,
 ) -> Result<(), CodegenError> {
     let location = Location::unknown(context);
     let ptr_type = pointer(context, 0);
@@ -235,19 +246,52 @@ fn compile_program(
     // PERF: avoid generating unneeded setup blocks
     let setup_block = main_region.append_block(Block::new(&[]));
 
-    let mut op_ctx = OperationCtx::new(context, module, &main_region, &setup_block, program)?;
+    let mut op_ctx = OperationCtx::new(
+        context,
+        module,
+        &main_region,
+        &setup_block,
+        program,
+        config.spec_id,
+    )?;
 
     let mut last_block = setup_block;
+    let mut pc = 0;
+    // First block of each operation, for the static jumps of EOF code
+    let mut op_blocks = BTreeMap::new();
 
     // Generate code for the program
     for op in &op_ctx.program.operations {
-        let (block_start, block_end) = generate_code_for_op(&mut op_ctx, &main_region, op.clone())?;
+        // Operations from later hardforks halt as if they were undefined
+        let (mut block_start, block_end) = if op.is_activated_in(config.spec_id) {
+            generate_code_for_op(&mut op_ctx, &main_region, op.clone())?
+        } else {
+            codegen_not_activated(&mut op_ctx, &main_region)?
+        };
+
+        let opcode = op.to_bytecode();
+        if config.instrument_steps {
+            let step_block = main_region.append_block(Block::new(&[]));
+            inspect_step(&op_ctx, &step_block, pc, opcode[0], location)?;
+            step_block.append_operation(cf::br(&block_start, &[], location));
+
+            // Jumps should also report the JUMPDEST they land on
+            if let Operation::Jumpdest { pc } = op {
+                op_ctx.register_jump_destination(*pc, step_block);
+            }
+            block_start = step_block;
+        }
+        if op_ctx.program.is_eof() {
+            op_blocks.insert(pc, block_start);
+        }
+        pc += opcode.len();
 
         last_block.append_operation(cf::br(&block_start, &[], location));
         last_block = block_end;
     }
 
     op_ctx.populate_jumptable()?;
+    op_ctx.populate_eof_blocks(&op_blocks)?;
 
     let return_block = main_region.append_block(Block::new(&[]));
     last_block.append_operation(cf::br(&return_block, &[], location));
@@ -3,6 +3,7 @@ use crate::{
     constants::EMPTY_CODE_HASH_STR,
     primitives::{Address, Bytes, B256, U256},
     state::{Account, AccountStatus, EvmStorageSlot},
+    trie,
 };
 use core::fmt;
 use sha3::{Digest, Keccak256};
@@ -103,6 +104,53 @@ impl Db {
         self.contracts.insert(hash, bytecode);
     }
 
+    /// Sets the nonce, balance and code of an account, keeping its storage
+    pub fn insert_account_info(&mut self, address: Address, info: AccountInfo) {
+        self.store_contract(&info);
+        let account = self
+            .accounts
+            .entry(address)
+            .or_insert_with(DbAccount::empty);
+        account.nonce = info.nonce;
+        account.balance = info.balance;
+        account.bytecode_hash = info.code_hash;
+        account.status = AccountStatus::Cold;
+    }
+
+    /// Stores code that accounts can use, returning its hash
+    pub fn insert_code(&mut self, bytecode: Bytecode) -> B256 {
+        let mut hasher = Keccak256::new();
+        hasher.update(&bytecode);
+        let hash = B256::from_slice(&hasher.finalize());
+        self.contracts.insert(hash, bytecode);
+        hash
+    }
+
+    /// Removes an account along with its storage
+    pub fn remove_account(&mut self, address: Address) {
+        self.accounts.remove(&address);
+    }
+
+    /// Removes the storage of an account, keeping the rest of it
+    pub fn clear_storage(&mut self, address: Address) {
+        if let Some(account) = self.accounts.get_mut(&address) {
+            account.storage.clear();
+        }
+    }
+
+    pub fn accounts(&self) -> &HashMap<Address, DbAccount> {
+        &self.accounts
+    }
+
+    /// Code of the contracts, by hash
+    pub fn contracts(&self) -> &HashMap<B256, Bytecode> {
+        &self.contracts
+    }
+
+    pub fn block_hashes(&self) -> &HashMap<U256, B256> {
+        &self.block_hashes
+    }
+
     pub fn write_storage(&mut self, address: Address, key: U256, value: U256) {
         let account = self.accounts.entry(address).or_insert(DbAccount::empty());
         account.storage.insert(key, value);
@@ -116,6 +164,34 @@ impl Db {
             .unwrap_or(U256::zero())
     }
 
+    /// Root of the world state trie holding the accounts of the database
+    pub fn state_root(&self) -> B256 {
+        let empty_code_hash = B256::from_str(EMPTY_CODE_HASH_STR).unwrap();
+        trie::secure_trie_root(self.accounts.iter().map(|(address, db_account)| {
+            let storage_root =
+                trie::storage_root(db_account.storage.iter().map(|(key, value)| (*key, *value)));
+            let info = AccountInfo {
+                balance: db_account.balance,
+                nonce: db_account.nonce,
+                // Accounts without code may have been inserted without its hash
+                code_hash: match db_account.bytecode_hash {
+                    hash if hash.is_zero() => empty_code_hash,
+                    hash => hash,
+                },
+                code: None,
+            };
+            (*address, trie::encode_account(&info, storage_root))
+        }))
+    }
+
+    /// Root of the world state trie once `changes` are committed to the database,
+    /// which is left untouched.
+    pub fn state_root_with(&self, changes: HashMap<Address, Account>) -> B256 {
+        let mut db = self.clone();
+        db.commit(changes);
+        db.state_root()
+    }
+
     pub fn into_state(self) -> HashMap<Address, Account> {
         self.accounts
             .iter()
@@ -136,13 +212,17 @@ impl Db {
             .collect()
     }
 
+    /// Applies the state changes of an execution. Self-destructed accounts are removed
+    /// along with their storage, and created ones replace the storage they had.
     pub fn commit(&mut self, changes: HashMap<Address, Account>) {
         for (address, mut account) in changes {
             let not_modified =
                 !account.is_touched() && !account.is_created() && !account.is_selfdestructed();
-            let created_and_destroyed = account.is_created() && account.is_selfdestructed();
-
-            if created_and_destroyed || not_modified {
+            if not_modified {
+                continue;
+            }
+            if account.is_selfdestructed() {
+                self.accounts.remove(&address);
                 continue;
             }
 
@@ -154,6 +234,9 @@ impl Db {
                 .accounts
                 .entry(address)
                 .or_insert_with(DbAccount::empty);
+            if account.is_created() {
+                db_account.storage.clear();
+            }
             db_account.nonce = account.info.nonce;
             db_account.balance = account.info.balance;
             db_account.status = AccountStatus::Cold;
@@ -240,17 +323,70 @@ pub trait Database {
 
     /// Get account code by its address.
     fn code_by_address(&mut self, address: Address) -> Result<Bytecode, Self::Error> {
-        let code = self
-            .basic(address)?
-            .and_then(|acc| acc.code.or_else(|| self.code_by_hash(acc.code_hash).ok()))
-            .unwrap_or_default();
-        Ok(code)
+        let Some(account) = self.basic(address)? else {
+            return Ok(Bytecode::default());
+        };
+        match account.code {
+            Some(code) => Ok(code),
+            None => self.code_by_hash(account.code_hash),
+        }
     }
 }
 
+/// Read-only access to the state, for databases that can be shared between executions.
+/// Wrap them in a [`CacheDB`](crate::cache_db::CacheDB) to execute over them.
+pub trait DatabaseRef {
+    /// The database error type.
+    type Error;
+
+    /// Get basic account information.
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error>;
+
+    /// Get account code by its hash.
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error>;
+
+    /// Get storage value of address at index.
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error>;
+
+    /// Get block hash by block number.
+    fn block_hash_ref(&self, number: U256) -> Result<B256, Self::Error>;
+}
+
+impl<T: DatabaseRef + ?Sized> DatabaseRef for &T {
+    type Error = T::Error;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        (**self).basic_ref(address)
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        (**self).code_by_hash_ref(code_hash)
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        (**self).storage_ref(address, index)
+    }
+
+    fn block_hash_ref(&self, number: U256) -> Result<B256, Self::Error> {
+        (**self).block_hash_ref(number)
+    }
+}
+
+/// Applies the state changes of an execution.
+pub trait DatabaseCommit {
+    /// Commit changes to the database.
+    fn commit(&mut self, changes: HashMap<Address, Account>);
+}
+
 #[derive(Error, Debug, Clone, Hash, PartialEq, Eq)]
-#[error("error on database access")]
-pub struct DatabaseError;
+#[error("error on database access: {0}")]
+pub struct DatabaseError(pub String);
+
+impl DatabaseError {
+    pub fn new(error: impl fmt::Display) -> Self {
+        Self(error.to_string())
+    }
+}
 
 impl Database for Db {
     type Error = Infallible;
@@ -275,6 +411,32 @@ impl Database for Db {
     }
 }
 
+impl DatabaseRef for Db {
+    type Error = Infallible;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        Ok(self.accounts.get(&address).cloned().map(AccountInfo::from))
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        Ok(self.contracts.get(&code_hash).cloned().unwrap_or_default())
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        Ok(self.read_storage(address, index))
+    }
+
+    fn block_hash_ref(&self, number: U256) -> Result<B256, Self::Error> {
+        Ok(self.block_hashes.get(&number).cloned().unwrap_or_default())
+    }
+}
+
+impl DatabaseCommit for Db {
+    fn commit(&mut self, changes: HashMap<Address, Account>) {
+        Db::commit(self, changes)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use melior::ir::block;
@@ -357,4 +519,98 @@ mod tests {
 
         assert_eq!(hash, expected_hash);
     }
+
+    #[test]
+    fn state_root_ignores_empty_storage_slots() {
+        let address = Address::from_low_u64_be(1);
+        let mut db = Db::new();
+        assert_eq!(db.state_root(), trie::empty_trie_root());
+
+        db.set_account(address, 1, U256::from(10), HashMap::new());
+        let root = db.state_root();
+        assert_ne!(root, trie::empty_trie_root());
+
+        db.write_storage(address, U256::from(1), U256::zero());
+        assert_eq!(db.state_root(), root);
+        db.write_storage(address, U256::from(1), U256::from(2));
+        assert_ne!(db.state_root(), root);
+    }
+
+    #[test]
+    fn state_root_with_changes_leaves_the_db_untouched() {
+        let address = Address::from_low_u64_be(1);
+        let mut db = Db::new();
+        db.set_account(address, 1, U256::from(10), HashMap::new());
+        let root = db.state_root();
+        let mut info = db.basic(address).unwrap().unwrap();
+        info.balance = U256::from(20);
+        let changes = HashMap::from([(
+            address,
+            Account {
+                info,
+                storage: HashMap::new(),
+                status: AccountStatus::Touched,
+            },
+        )]);
+
+        let new_root = db.state_root_with(changes.clone());
+
+        assert_ne!(new_root, root);
+        assert_eq!(db.state_root(), root);
+        db.commit(changes);
+        assert_eq!(db.state_root(), new_root);
+    }
+
+    #[test]
+    fn self_destructed_accounts_are_removed_from_the_state_root() {
+        let (kept, destroyed) = (Address::from_low_u64_be(1), Address::from_low_u64_be(2));
+        let mut db = Db::new();
+        db.set_account(kept, 1, U256::from(10), HashMap::new());
+        let expected_root = db.state_root();
+        db.set_account(
+            destroyed,
+            1,
+            U256::zero(),
+            HashMap::from([(U256::from(1), U256::from(2))]),
+        );
+        let changes = HashMap::from([(
+            destroyed,
+            Account {
+                info: db.basic(destroyed).unwrap().unwrap(),
+                storage: HashMap::new(),
+                status: AccountStatus::Touched | AccountStatus::SelfDestructed,
+            },
+        )]);
+
+        db.commit(changes);
+
+        assert_eq!(db.state_root(), expected_root);
+        assert_eq!(db.basic(destroyed).unwrap(), None);
+        assert_eq!(db.read_storage(destroyed, U256::from(1)), U256::zero());
+    }
+
+    #[test]
+    fn created_accounts_replace_the_storage() {
+        let address = Address::from_low_u64_be(1);
+        let mut db = Db::new();
+        db.set_account(
+            address,
+            1,
+            U256::zero(),
+            HashMap::from([(U256::from(1), U256::from(2))]),
+        );
+        let changes = HashMap::from([(
+            address,
+            Account {
+                info: db.basic(address).unwrap().unwrap(),
+                storage: HashMap::from([(U256::from(2), EvmStorageSlot::from(U256::from(3)))]),
+                status: AccountStatus::Created | AccountStatus::Touched,
+            },
+        )]);
+
+        db.commit(changes);
+
+        assert_eq!(db.read_storage(address, U256::from(1)), U256::zero());
+        assert_eq!(db.read_storage(address, U256::from(2)), U256::from(3));
+    }
 }
@@ -0,0 +1,702 @@
+//! # Disk database
+//!
+//! A [`DiskDb`] keeps the state in memory, in a [`Db`], and persists it to a directory
+//! so it survives restarts. The directory holds two files of checksummed records, each
+//! record being a batch of writes (accounts, code by hash, storage slots and block
+//! hashes, along with the removal of accounts and of their storage):
+//!
+//! - `state.snapshot`: a single record with the whole state at some point.
+//! - `state.log`: the records written after that snapshot, one per block.
+//!
+//! Changes committed to a [`DiskDb`] are applied in memory right away, but they're
+//! only written once their block is committed with [`DiskDb::commit_block`], in a
+//! single record. A crash while writing it leaves an incomplete record at the end of
+//! the log, which is dropped when the database is opened again, so blocks are
+//! persisted either whole or not at all.
+use std::{
+    collections::{HashMap, HashSet},
+    convert::Infallible,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use sha3::{Digest, Keccak256};
+use thiserror::Error;
+
+use crate::{
+    db::{AccountInfo, Bytecode, Database, DatabaseCommit, DatabaseRef, Db},
+    primitives::{Address, B256, U256},
+    state::Account,
+};
+
+const SNAPSHOT_FILE: &str = "state.snapshot";
+const LOG_FILE: &str = "state.log";
+
+/// Records start with the length of their payload and its hash
+const LENGTH_SIZE: usize = 8;
+const CHECKSUM_SIZE: usize = 32;
+const HEADER_SIZE: usize = LENGTH_SIZE + CHECKSUM_SIZE;
+
+const ACCOUNT_TAG: u8 = 0;
+const STORAGE_TAG: u8 = 1;
+const CODE_TAG: u8 = 2;
+const BLOCK_HASH_TAG: u8 = 3;
+const DELETED_ACCOUNT_TAG: u8 = 4;
+const CLEARED_STORAGE_TAG: u8 = 5;
+
+#[derive(Debug, Error)]
+pub enum DiskDbError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("corrupted snapshot: {0}")]
+    CorruptedSnapshot(String),
+    #[error("the changes of the current block aren't committed")]
+    UncommittedChanges,
+}
+
+/// Write of a single key
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Entry {
+    Account {
+        address: Address,
+        nonce: u64,
+        balance: U256,
+        code_hash: B256,
+    },
+    Storage {
+        address: Address,
+        index: U256,
+        value: U256,
+    },
+    Code {
+        hash: B256,
+        code: Bytecode,
+    },
+    BlockHash {
+        number: U256,
+        hash: B256,
+    },
+    /// Removal of an account along with its storage
+    DeletedAccount {
+        address: Address,
+    },
+    /// Removal of the storage of an account, before the slots it's created with
+    ClearedStorage {
+        address: Address,
+    },
+}
+
+impl Encodable for Entry {
+    fn rlp_append(&self, stream: &mut RlpStream) {
+        match self {
+            Entry::Account {
+                address,
+                nonce,
+                balance,
+                code_hash,
+            } => {
+                stream.begin_list(5);
+                stream.append(&ACCOUNT_TAG);
+                stream.append(address);
+                stream.append(nonce);
+                stream.append(balance);
+                stream.append(code_hash);
+            }
+            Entry::Storage {
+                address,
+                index,
+                value,
+            } => {
+                stream.begin_list(4);
+                stream.append(&STORAGE_TAG);
+                stream.append(address);
+                stream.append(index);
+                stream.append(value);
+            }
+            Entry::Code { hash, code } => {
+                stream.begin_list(3);
+                stream.append(&CODE_TAG);
+                stream.append(hash);
+                stream.append(&code.as_ref());
+            }
+            Entry::BlockHash { number, hash } => {
+                stream.begin_list(3);
+                stream.append(&BLOCK_HASH_TAG);
+                stream.append(number);
+                stream.append(hash);
+            }
+            Entry::DeletedAccount { address } => {
+                stream.begin_list(2);
+                stream.append(&DELETED_ACCOUNT_TAG);
+                stream.append(address);
+            }
+            Entry::ClearedStorage { address } => {
+                stream.begin_list(2);
+                stream.append(&CLEARED_STORAGE_TAG);
+                stream.append(address);
+            }
+        }
+    }
+}
+
+impl Decodable for Entry {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        match rlp.val_at(0)? {
+            ACCOUNT_TAG => Ok(Entry::Account {
+                address: rlp.val_at(1)?,
+                nonce: rlp.val_at(2)?,
+                balance: rlp.val_at(3)?,
+                code_hash: rlp.val_at(4)?,
+            }),
+            STORAGE_TAG => Ok(Entry::Storage {
+                address: rlp.val_at(1)?,
+                index: rlp.val_at(2)?,
+                value: rlp.val_at(3)?,
+            }),
+            CODE_TAG => Ok(Entry::Code {
+                hash: rlp.val_at(1)?,
+                code: rlp.val_at::<Vec<u8>>(2)?.into(),
+            }),
+            BLOCK_HASH_TAG => Ok(Entry::BlockHash {
+                number: rlp.val_at(1)?,
+                hash: rlp.val_at(2)?,
+            }),
+            DELETED_ACCOUNT_TAG => Ok(Entry::DeletedAccount {
+                address: rlp.val_at(1)?,
+            }),
+            CLEARED_STORAGE_TAG => Ok(Entry::ClearedStorage {
+                address: rlp.val_at(1)?,
+            }),
+            _ => Err(DecoderError::Custom("unknown entry tag")),
+        }
+    }
+}
+
+/// Database persisted to a directory, with the commit semantics of [`Db`]
+#[derive(Debug)]
+pub struct DiskDb {
+    db: Db,
+    dir: PathBuf,
+    log: File,
+    /// Writes of the block being executed
+    pending: Vec<Entry>,
+    /// Hashes of the code already written
+    stored_code: HashSet<B256>,
+}
+
+impl DiskDb {
+    /// Opens the database in `dir`, creating it if it doesn't exist. An incomplete
+    /// record at the end of the log, left by a crash, is dropped.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, DiskDbError> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        let mut db = Db::new();
+
+        let snapshot_path = dir.join(SNAPSHOT_FILE);
+        if snapshot_path.exists() {
+            let data = fs::read(&snapshot_path)?;
+            let (entries, len) = read_record(&data)
+                .ok_or_else(|| DiskDbError::CorruptedSnapshot("invalid record".to_string()))?;
+            if len != data.len() {
+                return Err(DiskDbError::CorruptedSnapshot(
+                    "trailing data after the record".to_string(),
+                ));
+            }
+            apply_entries(&mut db, entries);
+        }
+
+        let mut log = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(dir.join(LOG_FILE))?;
+        let mut data = Vec::new();
+        log.read_to_end(&mut data)?;
+        let mut offset = 0;
+        while let Some((entries, len)) = read_record(&data[offset..]) {
+            apply_entries(&mut db, entries);
+            offset += len;
+        }
+        if offset < data.len() {
+            log.set_len(offset as u64)?;
+            log.sync_all()?;
+        }
+
+        let stored_code = db.contracts().keys().copied().collect();
+        Ok(Self {
+            db,
+            dir,
+            log,
+            pending: Vec::new(),
+            stored_code,
+        })
+    }
+
+    /// State of the database, including the changes of the block being executed
+    pub fn db(&self) -> &Db {
+        &self.db
+    }
+
+    /// Whether there are changes committed since the last block was
+    pub fn has_uncommitted_changes(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Writes the changes committed since the last block, along with the hash of
+    /// this one, in a single record. They're persisted once this returns, and the
+    /// hash is only readable from then on.
+    pub fn commit_block(&mut self, number: U256, hash: B256) -> Result<(), DiskDbError> {
+        self.pending.push(Entry::BlockHash { number, hash });
+        let record = encode_record(&self.pending);
+        self.pending.pop();
+        let log_len = self.log.metadata()?.len();
+        if let Err(error) = self
+            .log
+            .write_all(&record)
+            .and_then(|()| self.log.sync_data())
+        {
+            // Drops the part of the record written, so the records after it can be read
+            self.log.set_len(log_len)?;
+            return Err(error.into());
+        }
+        self.db.insert_block_hash(number, hash);
+        self.pending.clear();
+        Ok(())
+    }
+
+    /// Replaces the snapshot with the current state and empties the log. The snapshot
+    /// is written to a temporary file first, and renamed over the previous one.
+    pub fn compact(&mut self) -> Result<(), DiskDbError> {
+        if self.has_uncommitted_changes() {
+            return Err(DiskDbError::UncommittedChanges);
+        }
+        let snapshot_path = self.dir.join(SNAPSHOT_FILE);
+        let tmp_path = snapshot_path.with_extension("tmp");
+        let mut tmp = File::create(&tmp_path)?;
+        tmp.write_all(&encode_record(&snapshot_entries(&self.db)))?;
+        tmp.sync_all()?;
+        fs::rename(&tmp_path, &snapshot_path)?;
+        File::open(&self.dir)?.sync_all()?;
+
+        // A crash before this replays the log over the new snapshot, which is harmless
+        // as the snapshot already holds the last value of every key
+        self.log.set_len(0)?;
+        self.log.sync_all()?;
+        Ok(())
+    }
+}
+
+impl Database for DiskDb {
+    type Error = Infallible;
+
+    fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        self.db.basic(address)
+    }
+
+    fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        self.db.code_by_hash(code_hash)
+    }
+
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        self.db.storage(address, index)
+    }
+
+    fn block_hash(&mut self, number: U256) -> Result<B256, Self::Error> {
+        self.db.block_hash(number)
+    }
+}
+
+impl DatabaseRef for DiskDb {
+    type Error = Infallible;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        self.db.basic_ref(address)
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        self.db.code_by_hash_ref(code_hash)
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        self.db.storage_ref(address, index)
+    }
+
+    fn block_hash_ref(&self, number: U256) -> Result<B256, Self::Error> {
+        self.db.block_hash_ref(number)
+    }
+}
+
+/// Commits the changes to the [`Db`] in memory, and records the accounts and slots they
+/// touch as they end up, to write them with the block. Self-destructed accounts are
+/// recorded as deleted, and created ones as having their storage cleared first.
+impl DatabaseCommit for DiskDb {
+    fn commit(&mut self, changes: HashMap<Address, Account>) {
+        let touched: Vec<TouchedAccount> = changes
+            .iter()
+            .map(|(address, account)| TouchedAccount {
+                address: *address,
+                slots: account.storage.keys().copied().collect(),
+                is_selfdestructed: account.is_selfdestructed(),
+                is_created: account.is_created(),
+            })
+            .collect();
+        self.db.commit(changes);
+
+        for TouchedAccount {
+            address,
+            slots,
+            is_selfdestructed,
+            is_created,
+        } in touched
+        {
+            if is_selfdestructed {
+                self.pending.push(Entry::DeletedAccount { address });
+                continue;
+            }
+            // Accounts skipped by the commit may not exist
+            let Some(db_account) = self.db.accounts().get(&address) else {
+                continue;
+            };
+            if is_created {
+                self.pending.push(Entry::ClearedStorage { address });
+            }
+            let code_hash = db_account.bytecode_hash;
+            if let Some(code) = self.db.contracts().get(&code_hash) {
+                if self.stored_code.insert(code_hash) {
+                    self.pending.push(Entry::Code {
+                        hash: code_hash,
+                        code: code.clone(),
+                    });
+                }
+            }
+            self.pending.push(Entry::Account {
+                address,
+                nonce: db_account.nonce,
+                balance: db_account.balance,
+                code_hash,
+            });
+            self.pending
+                .extend(slots.into_iter().map(|index| Entry::Storage {
+                    address,
+                    index,
+                    value: db_account.storage.get(&index).copied().unwrap_or_default(),
+                }));
+        }
+    }
+}
+
+/// Account changed by a commit, with what has to be written for it
+struct TouchedAccount {
+    address: Address,
+    slots: Vec<U256>,
+    is_selfdestructed: bool,
+    is_created: bool,
+}
+
+/// Writes to rebuild the whole state of `db`
+fn snapshot_entries(db: &Db) -> Vec<Entry> {
+    let code = db.contracts().iter().map(|(hash, code)| Entry::Code {
+        hash: *hash,
+        code: code.clone(),
+    });
+    let accounts = db.accounts().iter().flat_map(|(address, db_account)| {
+        let account = Entry::Account {
+            address: *address,
+            nonce: db_account.nonce,
+            balance: db_account.balance,
+            code_hash: db_account.bytecode_hash,
+        };
+        let storage = db_account
+            .storage
+            .iter()
+            .map(|(index, value)| Entry::Storage {
+                address: *address,
+                index: *index,
+                value: *value,
+            });
+        std::iter::once(account).chain(storage)
+    });
+    let block_hashes = db
+        .block_hashes()
+        .iter()
+        .map(|(number, hash)| Entry::BlockHash {
+            number: *number,
+            hash: *hash,
+        });
+    code.chain(accounts).chain(block_hashes).collect()
+}
+
+fn apply_entries(db: &mut Db, entries: Vec<Entry>) {
+    for entry in entries {
+        match entry {
+            Entry::Account {
+                address,
+                nonce,
+                balance,
+                code_hash,
+            } => db.insert_account_info(
+                address,
+                AccountInfo {
+                    balance,
+                    nonce,
+                    code_hash,
+                    code: None,
+                },
+            ),
+            Entry::Storage {
+                address,
+                index,
+                value,
+            } => db.write_storage(address, index, value),
+            Entry::Code { code, .. } => {
+                db.insert_code(code);
+            }
+            Entry::BlockHash { number, hash } => db.insert_block_hash(number, hash),
+            Entry::DeletedAccount { address } => db.remove_account(address),
+            Entry::ClearedStorage { address } => db.clear_storage(address),
+        }
+    }
+}
+
+fn encode_record(entries: &[Entry]) -> Vec<u8> {
+    let payload = rlp::encode_list::<Entry, _>(entries);
+    let mut record = Vec::with_capacity(HEADER_SIZE + payload.len());
+    record.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+    record.extend_from_slice(&Keccak256::digest(&payload));
+    record.extend_from_slice(&payload);
+    record
+}
+
+/// Reads the record at the start of `data`, returning its entries and its length.
+/// Returns `None` if the record is incomplete or corrupted.
+fn read_record(data: &[u8]) -> Option<(Vec<Entry>, usize)> {
+    let header = data.get(..HEADER_SIZE)?;
+    let (length, checksum) = header.split_at(LENGTH_SIZE);
+    let payload_len = usize::try_from(u64::from_le_bytes(length.try_into().ok()?)).ok()?;
+    let payload = data.get(HEADER_SIZE..HEADER_SIZE.checked_add(payload_len)?)?;
+    if Keccak256::digest(payload).as_slice() != checksum {
+        return None;
+    }
+    let entries = Rlp::new(payload).as_list().ok()?;
+    Some((entries, HEADER_SIZE + payload_len))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{
+        constants::EMPTY_CODE_HASH_STR,
+        state::{AccountStatus, EvmStorageSlot},
+    };
+
+    fn changes(address: Address, balance: u64, slot: (u64, u64)) -> HashMap<Address, Account> {
+        let code = Bytecode::from_static(&[0x5f, 0x54]);
+        let info = AccountInfo {
+            balance: U256::from(balance),
+            nonce: 1,
+            code_hash: B256::from_slice(&Keccak256::digest(&code)),
+            code: Some(code),
+        };
+        let storage = HashMap::from([(
+            U256::from(slot.0),
+            EvmStorageSlot {
+                present_value: U256::from(slot.1),
+                ..Default::default()
+            },
+        )]);
+        HashMap::from([(
+            address,
+            Account {
+                info,
+                storage,
+                status: AccountStatus::Created | AccountStatus::Touched,
+            },
+        )])
+    }
+
+    #[test]
+    fn committed_blocks_survive_reopening() {
+        let dir = tempfile::tempdir().unwrap();
+        let address = Address::from_low_u64_be(40);
+        let mut disk_db = DiskDb::open(dir.path()).unwrap();
+        disk_db.commit(changes(address, 10, (1, 2)));
+        disk_db
+            .commit_block(U256::one(), B256::from_low_u64_be(1))
+            .unwrap();
+        drop(disk_db);
+
+        let mut disk_db = DiskDb::open(dir.path()).unwrap();
+
+        let info = disk_db.basic(address).unwrap().unwrap();
+        assert_eq!(info.balance, U256::from(10));
+        assert_eq!(
+            disk_db.code_by_hash(info.code_hash).unwrap(),
+            Bytecode::from_static(&[0x5f, 0x54])
+        );
+        assert_eq!(
+            disk_db.storage(address, U256::one()).unwrap(),
+            U256::from(2)
+        );
+        assert_eq!(
+            disk_db.block_hash(U256::one()).unwrap(),
+            B256::from_low_u64_be(1)
+        );
+    }
+
+    #[test]
+    fn deleted_and_recreated_accounts_survive_reopening() {
+        let dir = tempfile::tempdir().unwrap();
+        let deleted = Address::from_low_u64_be(40);
+        let recreated = Address::from_low_u64_be(41);
+        let mut disk_db = DiskDb::open(dir.path()).unwrap();
+        disk_db.commit(changes(deleted, 10, (1, 2)));
+        disk_db.commit(changes(recreated, 10, (1, 2)));
+        disk_db
+            .commit_block(U256::one(), B256::from_low_u64_be(1))
+            .unwrap();
+        let mut selfdestructed = changes(deleted, 0, (1, 0));
+        selfdestructed.get_mut(&deleted).unwrap().status =
+            AccountStatus::SelfDestructed | AccountStatus::Touched;
+        disk_db.commit(selfdestructed);
+        disk_db.commit(changes(recreated, 20, (2, 4)));
+        disk_db
+            .commit_block(U256::from(2), B256::from_low_u64_be(2))
+            .unwrap();
+        let state_root = disk_db.db().state_root();
+        drop(disk_db);
+
+        let mut disk_db = DiskDb::open(dir.path()).unwrap();
+
+        assert_eq!(disk_db.db().state_root(), state_root);
+        assert_eq!(disk_db.basic(deleted).unwrap(), None);
+        assert_eq!(disk_db.storage(deleted, U256::one()).unwrap(), U256::zero());
+        assert_eq!(
+            disk_db.storage(recreated, U256::one()).unwrap(),
+            U256::zero()
+        );
+        assert_eq!(
+            disk_db.storage(recreated, U256::from(2)).unwrap(),
+            U256::from(4)
+        );
+    }
+
+    #[test]
+    fn uncommitted_blocks_are_lost() {
+        let dir = tempfile::tempdir().unwrap();
+        let address = Address::from_low_u64_be(40);
+        let mut disk_db = DiskDb::open(dir.path()).unwrap();
+        disk_db.commit(changes(address, 10, (1, 2)));
+        disk_db
+            .commit_block(U256::one(), B256::from_low_u64_be(1))
+            .unwrap();
+        disk_db.commit(changes(address, 20, (1, 3)));
+        assert!(disk_db.has_uncommitted_changes());
+        assert_eq!(
+            disk_db.storage(address, U256::one()).unwrap(),
+            U256::from(3)
+        );
+        drop(disk_db);
+
+        let mut disk_db = DiskDb::open(dir.path()).unwrap();
+
+        assert_eq!(
+            disk_db.basic(address).unwrap().unwrap().balance,
+            U256::from(10)
+        );
+        assert_eq!(
+            disk_db.storage(address, U256::one()).unwrap(),
+            U256::from(2)
+        );
+    }
+
+    #[test]
+    fn incomplete_records_are_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let address = Address::from_low_u64_be(40);
+        let mut disk_db = DiskDb::open(dir.path()).unwrap();
+        disk_db.commit(changes(address, 10, (1, 2)));
+        disk_db
+            .commit_block(U256::one(), B256::from_low_u64_be(1))
+            .unwrap();
+        drop(disk_db);
+        // A crash while writing the second block
+        let record = encode_record(&[Entry::BlockHash {
+            number: U256::from(2),
+            hash: B256::from_low_u64_be(2),
+        }]);
+        let mut log = OpenOptions::new()
+            .append(true)
+            .open(dir.path().join(LOG_FILE))
+            .unwrap();
+        log.write_all(&record[..record.len() - 1]).unwrap();
+        drop(log);
+
+        let mut disk_db = DiskDb::open(dir.path()).unwrap();
+        assert_eq!(disk_db.block_hash(U256::from(2)).unwrap(), B256::zero());
+        let mut second_block = changes(address, 30, (2, 4));
+        second_block.get_mut(&address).unwrap().status = AccountStatus::Touched;
+        disk_db.commit(second_block);
+        disk_db
+            .commit_block(U256::from(2), B256::from_low_u64_be(3))
+            .unwrap();
+        drop(disk_db);
+
+        // Blocks committed after the recovery are read back
+        let mut disk_db = DiskDb::open(dir.path()).unwrap();
+        assert_eq!(
+            disk_db.block_hash(U256::from(2)).unwrap(),
+            B256::from_low_u64_be(3)
+        );
+        assert_eq!(
+            disk_db.storage(address, U256::one()).unwrap(),
+            U256::from(2)
+        );
+        assert_eq!(
+            disk_db.storage(address, U256::from(2)).unwrap(),
+            U256::from(4)
+        );
+    }
+
+    #[test]
+    fn compaction_keeps_the_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let address = Address::from_low_u64_be(40);
+        let empty_account = Address::from_low_u64_be(41);
+        let mut disk_db = DiskDb::open(dir.path()).unwrap();
+        disk_db.commit(changes(address, 10, (1, 2)));
+        disk_db.commit(HashMap::from([(
+            empty_account,
+            Account {
+                info: AccountInfo {
+                    code_hash: B256::from_str(EMPTY_CODE_HASH_STR).unwrap(),
+                    ..Default::default()
+                },
+                storage: HashMap::new(),
+                status: AccountStatus::Touched,
+            },
+        )]));
+        assert!(matches!(
+            disk_db.compact(),
+            Err(DiskDbError::UncommittedChanges)
+        ));
+        disk_db
+            .commit_block(U256::one(), B256::from_low_u64_be(1))
+            .unwrap();
+        let state_root = disk_db.db().state_root();
+
+        disk_db.compact().unwrap();
+        drop(disk_db);
+
+        assert_eq!(fs::metadata(dir.path().join(LOG_FILE)).unwrap().len(), 0);
+        let disk_db = DiskDb::open(dir.path()).unwrap();
+        assert_eq!(disk_db.db().state_root(), state_root);
+        assert_eq!(
+            disk_db.db().contracts().values().collect::<Vec<_>>(),
+            [&Bytecode::from_static(&[0x5f, 0x54])]
+        );
+    }
+}
@@ -1,18 +1,74 @@
 use crate::{
     constants::{
         gas_cost::{
-            init_code_cost, MAX_CODE_SIZE, TX_BASE_COST, TX_CREATE_COST, TX_DATA_COST_PER_NON_ZERO,
+            init_code_cost, MAX_CODE_SIZE, PER_EMPTY_ACCOUNT_COST, TX_BASE_COST, TX_CREATE_COST,
             TX_DATA_COST_PER_ZERO,
         },
-        MAX_BLOB_NUMBER_PER_BLOCK, VERSIONED_HASH_VERSION_KZG,
+        GAS_PER_BLOB, MAX_BLOB_NUMBER_PER_BLOCK, SECP256K1N_HALF, SET_CODE_AUTHORIZATION_MAGIC,
+        VERSIONED_HASH_VERSION_KZG,
     },
+    db::AccountInfo,
+    precompiles::recover_address,
     primitives::{Address, Bytes, B256, U256},
     result::InvalidTransaction,
-    utils::{access_list_cost, calc_blob_gasprice},
+    spec::SpecId,
+    utils::{access_list_cost, calc_blob_gasprice, delegated_address},
 };
+use rlp::RlpStream;
+use sha3::{Digest, Keccak256};
+use std::cmp::Ordering;
 
 pub type AccessList = Vec<(Address, Vec<U256>)>;
 
+pub type AuthorizationList = Vec<SignedAuthorization>;
+
+/// Authorization of a set code transaction, allowing the signer to delegate the
+/// execution of its account to the code at `address` (EIP-7702).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SignedAuthorization {
+    /// Chain where the authorization is valid, zero meaning any chain
+    pub chain_id: U256,
+    /// Account whose code is executed by the authority
+    pub address: Address,
+    /// Nonce the authority must have when the authorization is applied
+    pub nonce: u64,
+    pub y_parity: u8,
+    pub r: U256,
+    pub s: U256,
+}
+
+impl SignedAuthorization {
+    /// Hash signed by the authority: `keccak256(MAGIC || rlp([chain_id, address, nonce]))`
+    pub fn signature_hash(&self) -> B256 {
+        let mut stream = RlpStream::new_list(3);
+        stream.append(&self.chain_id);
+        stream.append(&self.address);
+        stream.append(&self.nonce);
+
+        let mut hasher = Keccak256::new();
+        hasher.update([SET_CODE_AUTHORIZATION_MAGIC]);
+        hasher.update(stream.out());
+        B256::from_slice(&hasher.finalize())
+    }
+
+    /// Recovers the address that signed the authorization. Returns `None` if the
+    /// signature is invalid or malleable, i.e. its `s` is in the upper half of the curve order.
+    pub fn recover_authority(&self) -> Option<Address> {
+        if self.y_parity > 1 || self.s > U256::from_big_endian(&SECP256K1N_HALF) {
+            return None;
+        }
+        let mut signature = [0u8; 64];
+        self.r.to_big_endian(&mut signature[..32]);
+        self.s.to_big_endian(&mut signature[32..]);
+        recover_address(
+            self.signature_hash().as_bytes(),
+            self.y_parity as i32,
+            &signature,
+        )
+        .ok()
+    }
+}
+
 //This Env struct contains configuration information about the EVM, the block containing the transaction, and the transaction itself.
 //Structs inspired by the REVM primitives
 //-> https://github.com/bluealloy/revm/blob/main/crates/primitives/src/env.rs
@@ -40,8 +96,12 @@ impl Env {
     /// Reference: https://github.com/ethereum/execution-specs/blob/c854868f4abf2ab0c3e8790d4c40607e0d251147/src/ethereum/cancun/fork.py#L332
     pub fn validate_transaction(&mut self) -> Result<(), InvalidTransaction> {
         let is_create = matches!(self.tx.transact_to, TransactTo::Create);
+        let spec_id = self.cfg.spec_id;
 
-        if is_create && self.tx.data.len() > 2 * MAX_CODE_SIZE {
+        if is_create
+            && spec_id.is_enabled_in(SpecId::Shanghai)
+            && self.tx.data.len() > 2 * MAX_CODE_SIZE
+        {
             return Err(InvalidTransaction::CreateInitCodeSizeLimit);
         }
         if let Some(max) = self.tx.max_fee_per_blob_gas {
@@ -69,25 +129,147 @@ impl Env {
                 });
             }
         }
+        if !self.cfg.disable_block_gas_limit && U256::from(self.tx.gas_limit) > self.block.gas_limit
+        {
+            return Err(InvalidTransaction::CallerGasLimitMoreThanBlock);
+        }
+        if let Some(priority_fee) = self.tx.gas_priority_fee {
+            if priority_fee > self.tx.gas_price {
+                return Err(InvalidTransaction::PriorityFeeGreaterThanMaxFee);
+            }
+        }
+        if !self.cfg.disable_base_fee
+            && spec_id.is_enabled_in(SpecId::London)
+            && self.effective_gas_price() < self.block.basefee
+        {
+            return Err(InvalidTransaction::GasPriceLessThanBasefee);
+        }
+        if self
+            .tx
+            .chain_id
+            .is_some_and(|chain_id| chain_id != self.cfg.chain_id)
+        {
+            return Err(InvalidTransaction::InvalidChainId);
+        }
+        if let Some(authorization_list) = &self.tx.authorization_list {
+            if !spec_id.is_enabled_in(SpecId::Prague) {
+                return Err(InvalidTransaction::AuthorizationListNotSupported);
+            }
+            if authorization_list.is_empty() {
+                return Err(InvalidTransaction::EmptyAuthorizationList);
+            }
+            if is_create {
+                return Err(InvalidTransaction::SetCodeCreateTransaction);
+            }
+        }
         // TODO: check if more validations are needed
         Ok(())
     }
 
+    /// Checks the transaction against the account of its caller, whose code is
+    /// `caller_code`. Must be called before the intrinsic cost is consumed, since the
+    /// caller has to be able to pay for the whole gas limit.
+    pub fn validate_tx_against_state(
+        &self,
+        caller: &AccountInfo,
+        caller_code: &[u8],
+    ) -> Result<(), InvalidTransaction> {
+        // EIP-3607: accounts with code can't send transactions, unless the code is
+        // a delegation designator (EIP-7702)
+        if !caller_code.is_empty() && delegated_address(caller_code).is_none() {
+            return Err(InvalidTransaction::RejectCallerWithCode);
+        }
+        // EIP-2681: the nonce can't be increased past its maximum
+        if caller.nonce == u64::MAX {
+            return Err(InvalidTransaction::NonceOverflowInTransaction);
+        }
+        if let Some(tx) = self.tx.nonce.filter(|_| !self.cfg.disable_nonce_check) {
+            let state = caller.nonce;
+            match tx.cmp(&state) {
+                Ordering::Greater => return Err(InvalidTransaction::NonceTooHigh { tx, state }),
+                Ordering::Less => return Err(InvalidTransaction::NonceTooLow { tx, state }),
+                Ordering::Equal => {}
+            }
+        }
+
+        // Blob transactions must afford their blob gas at the max fee (EIP-4844)
+        let max_blob_fee = self
+            .tx
+            .max_fee_per_blob_gas
+            .unwrap_or_default()
+            .checked_mul(U256::from(self.tx.get_total_blob_gas()));
+        let fee = U256::from(self.tx.gas_limit)
+            .checked_mul(self.tx.gas_price)
+            .zip(max_blob_fee)
+            .and_then(|(gas_cost, blob_fee)| gas_cost.checked_add(blob_fee))
+            .and_then(|gas_cost| gas_cost.checked_add(self.tx.value))
+            .ok_or(InvalidTransaction::OverflowPaymentInTransaction)?;
+        if !self.cfg.disable_balance_check && fee > caller.balance {
+            return Err(InvalidTransaction::LackOfFundForMaxFee {
+                fee: Box::new(fee),
+                balance: Box::new(caller.balance),
+            });
+        }
+        Ok(())
+    }
+
+    /// Price paid for each unit of gas. EIP-1559 transactions pay the block's base fee
+    /// plus their priority fee, up to their max fee.
+    pub fn effective_gas_price(&self) -> U256 {
+        match self.tx.gas_priority_fee {
+            Some(priority_fee) => self
+                .tx
+                .gas_price
+                .min(self.block.basefee.saturating_add(priority_fee)),
+            None => self.tx.gas_price,
+        }
+    }
+
+    /// Part of the [effective gas price](Self::effective_gas_price) paid to the block's
+    /// coinbase. The rest is the base fee, which is burned from London on.
+    pub fn priority_fee_per_gas(&self) -> U256 {
+        let gas_price = self.effective_gas_price();
+        if self.cfg.spec_id.is_enabled_in(SpecId::London) {
+            gas_price.saturating_sub(self.block.basefee)
+        } else {
+            gas_price
+        }
+    }
+
+    /// Fee paid for the blob gas of the transaction at the block's blob gas price. It's
+    /// burned, like the base fee (EIP-4844).
+    pub fn blob_fee(&self) -> U256 {
+        U256::from(self.block.blob_gasprice.unwrap_or_default())
+            * U256::from(self.tx.get_total_blob_gas())
+    }
+
     ///  Calculates the gas that is charged before execution is started.
     pub fn calculate_intrinsic_cost(&self) -> u64 {
+        let spec_id = self.cfg.spec_id;
+        let data_cost_per_non_zero = spec_id.gas_schedule().tx_data_non_zero;
         let data_cost = self.tx.data.iter().fold(0, |acc, byte| {
             acc + if *byte == 0 {
                 TX_DATA_COST_PER_ZERO
             } else {
-                TX_DATA_COST_PER_NON_ZERO
+                data_cost_per_non_zero
             }
         });
         let create_cost = match self.tx.transact_to {
             TransactTo::Call(_) => 0,
+            // Contract creation transactions cost the same as calls before Homestead,
+            // and the init code is charged from Shanghai on (EIP-3860)
+            TransactTo::Create if !spec_id.is_enabled_in(SpecId::Homestead) => 0,
+            TransactTo::Create if !spec_id.is_enabled_in(SpecId::Shanghai) => TX_CREATE_COST,
             TransactTo::Create => TX_CREATE_COST + init_code_cost(self.tx.data.len() as u64),
         };
         let access_list_cost = access_list_cost(&self.tx.access_list);
-        TX_BASE_COST + data_cost + create_cost + access_list_cost
+        // Authorities are charged as if they were new accounts, and refunded if they exist
+        let authorization_list_cost = self
+            .tx
+            .authorization_list
+            .as_ref()
+            .map_or(0, |list| PER_EMPTY_ACCOUNT_COST * list.len() as u64);
+        TX_BASE_COST + data_cost + create_cost + access_list_cost + authorization_list_cost
     }
 }
 
@@ -97,6 +279,17 @@ pub struct CfgEnv {
     // Chain ID of the EVM, it will be compared to the transaction's Chain ID.
     // Chain ID is introduced EIP-155
     pub chain_id: u64,
+    /// Hardfork whose rules are followed. Defaults to Cancun.
+    pub spec_id: SpecId,
+    /// Skips checking that the caller can pay for the gas limit and value of the
+    /// transaction. Useful to simulate calls.
+    pub disable_balance_check: bool,
+    /// Skips checking the nonce of the transaction against the caller's.
+    pub disable_nonce_check: bool,
+    /// Skips checking that the gas price covers the block's base fee (EIP-1559).
+    pub disable_base_fee: bool,
+    /// Skips checking that the gas limit of the transaction fits in the block's.
+    pub disable_block_gas_limit: bool,
     // Bytecode that is created with CREATE/CREATE2 is by default analysed and jumptable is created.
     // This is very beneficial for testing and speeds up execution of that bytecode if called multiple times.
     //
@@ -107,7 +300,7 @@ pub struct CfgEnv {
     //pub limit_contract_code_size: Option<usize>,
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug)]
 pub struct BlockEnv {
     /// The number of ancestor blocks of this block (block height).
     pub number: U256,
@@ -117,9 +310,8 @@ pub struct BlockEnv {
     pub coinbase: Address,
     /// The timestamp of the block in seconds since the UNIX epoch.
     pub timestamp: U256,
-    // The gas limit of the block.
-    //pub gas_limit: U256,
-    //
+    /// The gas limit of the block. Transactions can't have a higher gas limit.
+    pub gas_limit: U256,
     // The base fee per gas, added in the London upgrade with [EIP-1559].
     //
     // [EIP-1559]: https://eips.ethereum.org/EIPS/eip-1559
@@ -145,6 +337,33 @@ pub struct BlockEnv {
     // [EIP-4844]: https://eips.ethereum.org/EIPS/eip-4844
     pub excess_blob_gas: Option<u64>,
     pub blob_gasprice: Option<u128>,
+    /// Root of the parent beacon block, stored in the beacon roots contract before
+    /// running the transactions of the block ([EIP-4788]).
+    ///
+    /// [EIP-4788]: https://eips.ethereum.org/EIPS/eip-4788
+    pub parent_beacon_block_root: Option<B256>,
+    /// Hash of the parent block, stored in the history storage contract before running
+    /// the transactions of the block ([EIP-2935]).
+    ///
+    /// [EIP-2935]: https://eips.ethereum.org/EIPS/eip-2935
+    pub parent_hash: Option<B256>,
+}
+
+impl Default for BlockEnv {
+    fn default() -> Self {
+        Self {
+            number: U256::zero(),
+            coinbase: Address::zero(),
+            timestamp: U256::zero(),
+            gas_limit: U256::MAX,
+            basefee: U256::zero(),
+            prevrandao: None,
+            excess_blob_gas: None,
+            blob_gasprice: None,
+            parent_beacon_block_root: None,
+            parent_hash: None,
+        }
+    }
 }
 
 impl BlockEnv {
@@ -161,7 +380,8 @@ pub struct TxEnv {
     pub caller: Address,
     /// The gas limit of the transaction.
     pub gas_limit: u64,
-    /// The gas price of the transaction.
+    /// The gas price of the transaction. For EIP-1559 transactions, the ones with a
+    /// [priority fee](Self::gas_priority_fee), this is the max fee per gas.
     pub gas_price: U256,
     /// The destination of the transaction.
     pub transact_to: TransactTo,
@@ -169,17 +389,17 @@ pub struct TxEnv {
     pub value: U256,
     // The data of the transaction.
     pub data: Bytes,
-    // The nonce of the transaction.
-    //
-    // Caution: If set to `None`, then nonce validation against the account's nonce is skipped: [InvalidTransaction::NonceTooHigh] and [InvalidTransaction::NonceTooLow]
-    // pub nonce: Option<u64>,
+    /// The nonce of the transaction.
+    ///
+    /// Caution: If set to `None`, then nonce validation against the account's nonce is skipped: [InvalidTransaction::NonceTooHigh] and [InvalidTransaction::NonceTooLow]
+    pub nonce: Option<u64>,
 
-    // The chain ID of the transaction. If set to `None`, no checks are performed.
-    //
-    // Incorporated as part of the Spurious Dragon upgrade via [EIP-155].
-    //
-    // [EIP-155]: https://eips.ethereum.org/EIPS/eip-155
-    // pub chain_id: Option<u64>,
+    /// The chain ID of the transaction. If set to `None`, no checks are performed.
+    ///
+    /// Incorporated as part of the Spurious Dragon upgrade via [EIP-155].
+    ///
+    /// [EIP-155]: https://eips.ethereum.org/EIPS/eip-155
+    pub chain_id: Option<u64>,
 
     // A list of addresses and storage keys that the transaction plans to access.
     //
@@ -188,12 +408,12 @@ pub struct TxEnv {
     // [EIP-2930]: https://eips.ethereum.org/EIPS/eip-2930
     pub access_list: AccessList,
 
-    // The priority fee per gas.
-    //
-    // Incorporated as part of the London upgrade via [EIP-1559].
-    //
-    // [EIP-1559]: https://eips.ethereum.org/EIPS/eip-1559
-    // pub gas_priority_fee: Option<U256>,
+    /// The max priority fee per gas, paid to the block's coinbase on top of the base fee.
+    ///
+    /// Incorporated as part of the London upgrade via [EIP-1559].
+    ///
+    /// [EIP-1559]: https://eips.ethereum.org/EIPS/eip-1559
+    pub gas_priority_fee: Option<U256>,
 
     // The list of blob versioned hashes. Per EIP there should be at least
     // one blob present if [`Self::max_fee_per_blob_gas`] is `Some`.
@@ -208,6 +428,13 @@ pub struct TxEnv {
     //
     // [EIP-4844]: https://eips.ethereum.org/EIPS/eip-4844
     pub max_fee_per_blob_gas: Option<U256>,
+    /// Authorizations applied before the transaction is executed. `Some` makes the
+    /// transaction a set code transaction.
+    ///
+    /// Incorporated as part of the Prague upgrade via [EIP-7702].
+    ///
+    /// [EIP-7702]: https://eips.ethereum.org/EIPS/eip-7702
+    pub authorization_list: Option<AuthorizationList>,
 }
 
 impl Default for TxEnv {
@@ -217,15 +444,16 @@ impl Default for TxEnv {
             // TODO: we are using signed comparison for the gas counter
             gas_limit: i64::MAX as _,
             gas_price: U256::zero(),
-            // gas_priority_fee: None,
+            gas_priority_fee: None,
             transact_to: TransactTo::Call(Address::zero()),
             value: U256::zero(),
             data: Bytes::new(),
-            // chain_id: None,
-            // nonce: None,
+            chain_id: None,
+            nonce: None,
             access_list: Default::default(),
             blob_hashes: Vec::new(),
             max_fee_per_blob_gas: None,
+            authorization_list: None,
         }
     }
 }
@@ -246,4 +474,233 @@ impl TxEnv {
             TransactTo::Create => self.caller,
         }
     }
+
+    /// Blob gas used by the transaction (EIP-4844)
+    pub fn get_total_blob_gas(&self) -> u64 {
+        GAS_PER_BLOB * self.blob_hashes.len() as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::delegation_designator;
+
+    fn set_code_env(authorization_list: AuthorizationList) -> Env {
+        let mut env = Env::default();
+        env.cfg.spec_id = SpecId::Prague;
+        env.tx.transact_to = TransactTo::Call(Address::from_low_u64_be(1));
+        env.tx.authorization_list = Some(authorization_list);
+        env
+    }
+
+    #[test]
+    fn authorizations_are_charged_as_new_accounts() {
+        let env = set_code_env(vec![SignedAuthorization::default(); 2]);
+        assert_eq!(
+            env.calculate_intrinsic_cost(),
+            TX_BASE_COST + 2 * PER_EMPTY_ACCOUNT_COST
+        );
+    }
+
+    #[test]
+    fn set_code_transactions_are_validated() {
+        let mut env = set_code_env(vec![]);
+        assert_eq!(
+            env.validate_transaction(),
+            Err(InvalidTransaction::EmptyAuthorizationList)
+        );
+
+        let mut env = set_code_env(vec![SignedAuthorization::default()]);
+        env.tx.transact_to = TransactTo::Create;
+        assert_eq!(
+            env.validate_transaction(),
+            Err(InvalidTransaction::SetCodeCreateTransaction)
+        );
+
+        let mut env = set_code_env(vec![SignedAuthorization::default()]);
+        env.cfg.spec_id = SpecId::Cancun;
+        assert_eq!(
+            env.validate_transaction(),
+            Err(InvalidTransaction::AuthorizationListNotSupported)
+        );
+    }
+
+    #[test]
+    fn malleable_authorization_signatures_are_rejected() {
+        let authorization = SignedAuthorization {
+            r: U256::one(),
+            s: U256::from_big_endian(&SECP256K1N_HALF) + 1,
+            ..Default::default()
+        };
+        assert_eq!(authorization.recover_authority(), None);
+    }
+
+    #[test]
+    fn transactions_for_another_chain_are_rejected() {
+        let mut env = Env::default();
+        env.cfg.chain_id = 1;
+        env.tx.chain_id = Some(5);
+        assert_eq!(
+            env.validate_transaction(),
+            Err(InvalidTransaction::InvalidChainId)
+        );
+
+        env.tx.chain_id = Some(1);
+        assert_eq!(env.validate_transaction(), Ok(()));
+    }
+
+    #[test]
+    fn nonce_must_match_the_caller_account() {
+        let mut env = Env::default();
+        let caller = AccountInfo {
+            nonce: 3,
+            ..Default::default()
+        };
+        env.tx.nonce = Some(2);
+        assert_eq!(
+            env.validate_tx_against_state(&caller, &[]),
+            Err(InvalidTransaction::NonceTooLow { tx: 2, state: 3 })
+        );
+        env.tx.nonce = Some(4);
+        assert_eq!(
+            env.validate_tx_against_state(&caller, &[]),
+            Err(InvalidTransaction::NonceTooHigh { tx: 4, state: 3 })
+        );
+        env.tx.nonce = None;
+        assert_eq!(env.validate_tx_against_state(&caller, &[]), Ok(()));
+    }
+
+    #[test]
+    fn caller_must_afford_the_gas_limit_and_value() {
+        let mut env = Env::default();
+        env.tx.gas_limit = 21_000;
+        env.tx.gas_price = U256::from(10);
+        env.tx.value = U256::from(5);
+        let caller = AccountInfo {
+            balance: U256::from(210_004),
+            ..Default::default()
+        };
+        assert_eq!(
+            env.validate_tx_against_state(&caller, &[]),
+            Err(InvalidTransaction::LackOfFundForMaxFee {
+                fee: Box::new(U256::from(210_005)),
+                balance: Box::new(U256::from(210_004)),
+            })
+        );
+
+        env.tx.gas_price = U256::MAX;
+        assert_eq!(
+            env.validate_tx_against_state(&caller, &[]),
+            Err(InvalidTransaction::OverflowPaymentInTransaction)
+        );
+    }
+
+    #[test]
+    fn blob_callers_must_afford_the_blob_gas_at_the_max_fee() {
+        let mut env = Env::default();
+        env.tx.gas_limit = 21_000;
+        env.tx.gas_price = U256::from(10);
+        env.tx.blob_hashes = vec![B256::zero(); 2];
+        env.tx.max_fee_per_blob_gas = Some(U256::from(3));
+        let fee = 210_000 + 3 * 2 * GAS_PER_BLOB;
+        let caller = AccountInfo {
+            balance: U256::from(fee - 1),
+            ..Default::default()
+        };
+        assert_eq!(
+            env.validate_tx_against_state(&caller, &[]),
+            Err(InvalidTransaction::LackOfFundForMaxFee {
+                fee: Box::new(U256::from(fee)),
+                balance: Box::new(U256::from(fee - 1)),
+            })
+        );
+
+        let caller = AccountInfo {
+            balance: U256::from(fee),
+            ..Default::default()
+        };
+        assert_eq!(env.validate_tx_against_state(&caller, &[]), Ok(()));
+    }
+
+    #[test]
+    fn callers_with_code_are_rejected_unless_delegated() {
+        let env = Env::default();
+        let caller = AccountInfo::default();
+        assert_eq!(
+            env.validate_tx_against_state(&caller, &[0x60, 0x00]),
+            Err(InvalidTransaction::RejectCallerWithCode)
+        );
+        let designator = delegation_designator(Address::from_low_u64_be(1));
+        assert_eq!(env.validate_tx_against_state(&caller, &designator), Ok(()));
+    }
+
+    #[test]
+    fn effective_gas_price_is_capped_by_the_max_fee() {
+        let mut env = Env::default();
+        env.block.basefee = U256::from(7);
+        env.tx.gas_price = U256::from(10);
+        env.tx.gas_priority_fee = Some(U256::from(2));
+        assert_eq!(env.effective_gas_price(), U256::from(9));
+        assert_eq!(env.priority_fee_per_gas(), U256::from(2));
+
+        env.tx.gas_priority_fee = Some(U256::from(5));
+        assert_eq!(env.effective_gas_price(), U256::from(10));
+        assert_eq!(env.priority_fee_per_gas(), U256::from(3));
+
+        // Legacy transactions pay their gas price
+        env.tx.gas_priority_fee = None;
+        assert_eq!(env.effective_gas_price(), U256::from(10));
+    }
+
+    #[test]
+    fn base_fee_is_not_burned_before_london() {
+        let mut env = Env::default();
+        env.cfg.spec_id = SpecId::Berlin;
+        env.block.basefee = U256::from(7);
+        env.tx.gas_price = U256::from(10);
+        assert_eq!(env.priority_fee_per_gas(), U256::from(10));
+    }
+
+    #[test]
+    fn fee_market_fields_are_validated() {
+        let mut env = Env::default();
+        env.block.basefee = U256::from(7);
+        env.tx.gas_price = U256::from(10);
+        env.tx.gas_priority_fee = Some(U256::from(11));
+        assert_eq!(
+            env.validate_transaction(),
+            Err(InvalidTransaction::PriorityFeeGreaterThanMaxFee)
+        );
+
+        env.tx.gas_priority_fee = None;
+        env.tx.gas_price = U256::from(6);
+        assert_eq!(
+            env.validate_transaction(),
+            Err(InvalidTransaction::GasPriceLessThanBasefee)
+        );
+
+        env.cfg.spec_id = SpecId::Berlin;
+        assert_eq!(env.validate_transaction(), Ok(()));
+    }
+
+    #[test]
+    fn checks_can_be_disabled_to_simulate_calls() {
+        let mut env = Env::default();
+        env.block.basefee = U256::from(7);
+        env.block.gas_limit = U256::from(30_000);
+        env.tx.gas_limit = 50_000;
+        env.tx.gas_price = U256::from(1);
+        env.tx.nonce = Some(5);
+        env.cfg.disable_base_fee = true;
+        env.cfg.disable_block_gas_limit = true;
+        env.cfg.disable_nonce_check = true;
+        env.cfg.disable_balance_check = true;
+
+        assert_eq!(env.validate_transaction(), Ok(()));
+        assert_eq!(
+            env.validate_tx_against_state(&AccountInfo::default(), &[]),
+            Ok(())
+        );
+    }
 }
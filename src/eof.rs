@@ -0,0 +1,1115 @@
+//! # EVM Object Format
+//!
+//! From Prague, code starting with [`MAGIC`] is an EOF container (EIP-3540): a header
+//! listing the sizes of its sections, followed by the type, code, container and data
+//! sections. Containers are validated before being deployed or run (EIP-3670, EIP-5450,
+//! EIP-7620), so the compiled code can trust that relative jumps land on instructions,
+//! that calls between code sections keep the stack within the declared bounds, and that
+//! immediates refer to existing sections and data.
+use thiserror::Error;
+
+use crate::{
+    constants::{
+        eof::{
+            KIND_CODE, KIND_CONTAINER, KIND_DATA, KIND_TERMINATOR, KIND_TYPES, MAGIC,
+            MAX_CODE_SECTIONS, MAX_CONTAINER_SECTIONS, MAX_INPUTS_OUTPUTS, MAX_STACK_HEIGHT,
+            NON_RETURNING, TYPE_SIZE, VERSION,
+        },
+        MAX_STACK_SIZE,
+    },
+    primitives::Bytes,
+    program::Opcode,
+    result::HaltReason,
+};
+
+/// Returns whether `code` is an EOF container, rather than legacy code
+pub fn is_eof(code: &[u8]) -> bool {
+    code.starts_with(&MAGIC)
+}
+
+/// Returns the data section of the container `code` without decoding the other sections,
+/// or an empty one if `code` isn't a container.
+pub fn data_section(code: &[u8]) -> &[u8] {
+    let Ok(header) = Header::decode(code) else {
+        return &[];
+    };
+    let data_start = header.size + header.body_size() - header.data_size as usize;
+    let data = code.get(data_start..).unwrap_or_default();
+    &data[..data.len().min(header.data_size as usize)]
+}
+
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum EofError {
+    #[error("the code doesn't start with the EOF magic")]
+    InvalidMagic,
+    #[error("unsupported EOF version {0}")]
+    UnsupportedVersion(u8),
+    #[error("the header is incomplete")]
+    TruncatedHeader,
+    #[error("expected a section of kind {expected:#04x}, found {found:#04x}")]
+    UnexpectedSectionKind { expected: u8, found: u8 },
+    #[error("invalid amount of code sections: {0}")]
+    InvalidCodeSectionCount(usize),
+    #[error("invalid amount of container sections: {0}")]
+    InvalidContainerSectionCount(usize),
+    #[error("sections can't be empty")]
+    EmptySection,
+    #[error("the type section doesn't have one entry per code section")]
+    TypeSectionSizeMismatch,
+    #[error("the container ends before its sections")]
+    TruncatedBody,
+    #[error("the container ends before its data section")]
+    TruncatedData,
+    #[error("there are bytes after the end of the container")]
+    TrailingBytes,
+    #[error("the first code section must take no inputs and not return")]
+    InvalidFirstSectionType,
+    #[error("invalid type of code section {0}")]
+    InvalidSectionType(usize),
+    #[error("undefined instruction {opcode:#04x} at offset {offset}")]
+    UndefinedInstruction { opcode: u8, offset: usize },
+    #[error("the immediates of the instruction at offset {offset} are cut short")]
+    TruncatedImmediate { offset: usize },
+    #[error("the jump at offset {offset} doesn't land on an instruction")]
+    InvalidJumpTarget { offset: usize },
+    #[error("the instruction at offset {offset} refers to a missing code section")]
+    InvalidCodeSectionIndex { offset: usize },
+    #[error("the instruction at offset {offset} refers to a missing container section")]
+    InvalidContainerIndex { offset: usize },
+    #[error("the instruction at offset {offset} reads past the data section")]
+    InvalidDataOffset { offset: usize },
+    #[error("CALLF at offset {offset} calls a code section that doesn't return")]
+    CallfToNonReturning { offset: usize },
+    #[error("JUMPF at offset {offset} jumps to a code section returning more outputs")]
+    InvalidJumpfTarget { offset: usize },
+    #[error("RETF at offset {offset} is in a code section that doesn't return")]
+    RetfInNonReturning { offset: usize },
+    #[error("code section {0} is declared as returning but never returns")]
+    MissingReturn(usize),
+    #[error("the code section doesn't end with a terminating instruction")]
+    MissingTerminatingInstruction,
+    #[error("the instruction at offset {offset} is unreachable")]
+    UnreachableCode { offset: usize },
+    #[error("code section {0} is unreachable")]
+    UnreachableCodeSection(usize),
+    #[error("the stack can underflow at offset {offset}")]
+    StackUnderflow { offset: usize },
+    #[error("the stack can overflow at offset {offset}")]
+    StackOverflow { offset: usize },
+    #[error("invalid stack height at offset {offset}")]
+    InvalidStackHeight { offset: usize },
+    #[error("declared a max stack height of {declared}, but the code reaches {computed}")]
+    MaxStackHeightMismatch { declared: u16, computed: u16 },
+    #[error("runtime code can't use RETURNCONTRACT")]
+    ReturnContractInRuntime,
+    #[error("initcode can't use RETURN or STOP")]
+    ReturnInInitcode,
+    #[error("container section {0} is used both as initcode and as runtime code")]
+    AmbiguousContainerKind(usize),
+    #[error("container section {0} isn't used")]
+    UnreferencedContainer(usize),
+    #[error("invalid container section {index}: {error}")]
+    InvalidContainer { index: usize, error: Box<EofError> },
+}
+
+/// Inputs, outputs and maximum stack height of a code section
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CodeType {
+    pub inputs: u8,
+    /// [`NON_RETURNING`] if the section never returns to its caller
+    pub outputs: u8,
+    pub max_stack_height: u16,
+}
+
+impl CodeType {
+    pub fn is_returning(&self) -> bool {
+        self.outputs != NON_RETURNING
+    }
+}
+
+/// What a container is used for, which restricts the instructions ending its execution
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerKind {
+    /// Code of an account, which can't use `RETURNCONTRACT`
+    Runtime,
+    /// Code creating an account, which ends with `RETURNCONTRACT` instead of `RETURN`
+    /// or `STOP`
+    Initcode,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EofContainer {
+    pub types: Vec<CodeType>,
+    pub code_sections: Vec<Bytes>,
+    pub container_sections: Vec<Bytes>,
+    pub data: Bytes,
+    /// Size of the data section declared in the header. Containers yet to be deployed
+    /// can have less data, which `RETURNCONTRACT` completes.
+    pub data_size: u16,
+}
+
+impl EofContainer {
+    /// Decodes a container taking exactly `code`
+    pub fn decode(code: &[u8]) -> Result<Self, EofError> {
+        let (container, size) = Self::decode_prefix(code)?;
+        if container.data.len() < container.data_size as usize {
+            return Err(EofError::TruncatedData);
+        }
+        if code.len() > size {
+            return Err(EofError::TrailingBytes);
+        }
+        Ok(container)
+    }
+
+    /// Decodes the container at the start of `code`, returning it along with the bytes
+    /// after it. Creation transactions pass their calldata this way.
+    pub fn decode_with_trailing(code: &[u8]) -> Result<(Self, &[u8]), EofError> {
+        let (container, size) = Self::decode_prefix(code)?;
+        if container.data.len() < container.data_size as usize {
+            return Err(EofError::TruncatedData);
+        }
+        Ok((container, &code[size..]))
+    }
+
+    /// Decodes a container section, whose data can be cut short if it's yet to be deployed
+    pub fn decode_subcontainer(code: &[u8]) -> Result<Self, EofError> {
+        let (container, size) = Self::decode_prefix(code)?;
+        if code.len() > size {
+            return Err(EofError::TrailingBytes);
+        }
+        Ok(container)
+    }
+
+    /// Decodes the container at the start of `code`, returning it along with its size.
+    /// The data section is cut short if `code` ends before it does.
+    fn decode_prefix(code: &[u8]) -> Result<(Self, usize), EofError> {
+        let header = Header::decode(code)?;
+        let data_start = header.size + header.body_size() - header.data_size as usize;
+        if code.len() < data_start {
+            return Err(EofError::TruncatedBody);
+        }
+
+        let mut offset = header.size;
+        let types = code[offset..offset + header.types_size as usize]
+            .chunks(TYPE_SIZE)
+            .map(|entry| CodeType {
+                inputs: entry[0],
+                outputs: entry[1],
+                max_stack_height: u16::from_be_bytes([entry[2], entry[3]]),
+            })
+            .collect();
+        offset += header.types_size as usize;
+
+        let mut code_sections = Vec::with_capacity(header.code_sizes.len());
+        for size in &header.code_sizes {
+            let size = *size as usize;
+            code_sections.push(Bytes::copy_from_slice(&code[offset..offset + size]));
+            offset += size;
+        }
+        let mut container_sections = Vec::with_capacity(header.container_sizes.len());
+        for size in &header.container_sizes {
+            let size = *size as usize;
+            container_sections.push(Bytes::copy_from_slice(&code[offset..offset + size]));
+            offset += size;
+        }
+
+        let data_end = code.len().min(data_start + header.data_size as usize);
+        let container = EofContainer {
+            types,
+            code_sections,
+            container_sections,
+            data: Bytes::copy_from_slice(&code[data_start..data_end]),
+            data_size: header.data_size,
+        };
+        Ok((container, data_start + header.data_size as usize))
+    }
+
+    /// Encodes the container, declaring [`Self::data_size`] as the size of its data
+    pub fn encode(&self) -> Bytes {
+        let mut code = Vec::new();
+        code.extend_from_slice(&MAGIC);
+        code.push(VERSION);
+
+        code.push(KIND_TYPES);
+        code.extend_from_slice(&((self.types.len() * TYPE_SIZE) as u16).to_be_bytes());
+        code.push(KIND_CODE);
+        code.extend_from_slice(&(self.code_sections.len() as u16).to_be_bytes());
+        for section in &self.code_sections {
+            code.extend_from_slice(&(section.len() as u16).to_be_bytes());
+        }
+        if !self.container_sections.is_empty() {
+            code.push(KIND_CONTAINER);
+            code.extend_from_slice(&(self.container_sections.len() as u16).to_be_bytes());
+            for section in &self.container_sections {
+                code.extend_from_slice(&(section.len() as u32).to_be_bytes());
+            }
+        }
+        code.push(KIND_DATA);
+        code.extend_from_slice(&self.data_size.to_be_bytes());
+        code.push(KIND_TERMINATOR);
+
+        for code_type in &self.types {
+            code.push(code_type.inputs);
+            code.push(code_type.outputs);
+            code.extend_from_slice(&code_type.max_stack_height.to_be_bytes());
+        }
+        for section in self.code_sections.iter().chain(&self.container_sections) {
+            code.extend_from_slice(section);
+        }
+        code.extend_from_slice(&self.data);
+        code.into()
+    }
+
+    /// Offset of code section `index` when laying out the code sections one after
+    /// another, which is how the operations of a [`Program`](crate::program::Program)
+    /// are numbered.
+    pub fn code_section_offset(&self, index: usize) -> usize {
+        self.code_sections[..index].iter().map(Bytes::len).sum()
+    }
+
+    /// Appends the auxiliary data given to `RETURNCONTRACT`, completing the data section
+    /// of a container being deployed.
+    pub fn append_aux_data(&mut self, aux_data: &[u8]) -> Result<(), HaltReason> {
+        let data_size = self.data.len() + aux_data.len();
+        let data_size = u16::try_from(data_size).map_err(|_| HaltReason::EofAuxDataOverflow)?;
+        if data_size < self.data_size {
+            return Err(HaltReason::EofAuxDataTooSmall);
+        }
+        self.data = [self.data.as_ref(), aux_data].concat().into();
+        self.data_size = data_size;
+        Ok(())
+    }
+
+    /// Validates the container and its container sections. Without a `kind`, the
+    /// container is accepted as either initcode or runtime code.
+    pub fn validate(&self, kind: Option<ContainerKind>) -> Result<(), EofError> {
+        self.validate_types()?;
+
+        let mut uses = vec![ContainerUse::default(); self.container_sections.len()];
+        let mut ends = SectionEnds::default();
+        let mut callees = Vec::with_capacity(self.code_sections.len());
+        for index in 0..self.code_sections.len() {
+            callees.push(self.validate_code_section(index, &mut uses, &mut ends)?);
+            self.validate_stack(index)?;
+        }
+
+        match kind {
+            Some(ContainerKind::Runtime) if ends.return_contract => {
+                return Err(EofError::ReturnContractInRuntime)
+            }
+            Some(ContainerKind::Initcode) if ends.return_or_stop => {
+                return Err(EofError::ReturnInInitcode)
+            }
+            None if ends.return_contract && ends.return_or_stop => {
+                return Err(EofError::ReturnInInitcode)
+            }
+            _ => {}
+        }
+
+        // Every code section must be reachable from the first one
+        let mut reachable = vec![false; self.code_sections.len()];
+        reachable[0] = true;
+        let mut pending = vec![0];
+        while let Some(index) = pending.pop() {
+            for &callee in &callees[index] {
+                if !reachable[callee] {
+                    reachable[callee] = true;
+                    pending.push(callee);
+                }
+            }
+        }
+        if let Some(index) = reachable.iter().position(|reachable| !reachable) {
+            return Err(EofError::UnreachableCodeSection(index));
+        }
+
+        for (index, (section, usage)) in self.container_sections.iter().zip(uses).enumerate() {
+            let kind = match (usage.eofcreate, usage.returncontract) {
+                (true, false) => ContainerKind::Initcode,
+                (false, true) => ContainerKind::Runtime,
+                (true, true) => return Err(EofError::AmbiguousContainerKind(index)),
+                (false, false) => return Err(EofError::UnreferencedContainer(index)),
+            };
+            Self::decode_subcontainer(section)
+                .and_then(|container| {
+                    // Only containers yet to be deployed can lack part of their data
+                    if kind == ContainerKind::Initcode
+                        && container.data.len() < container.data_size as usize
+                    {
+                        return Err(EofError::TruncatedData);
+                    }
+                    container.validate(Some(kind))
+                })
+                .map_err(|error| EofError::InvalidContainer {
+                    index,
+                    error: Box::new(error),
+                })?;
+        }
+        Ok(())
+    }
+
+    fn validate_types(&self) -> Result<(), EofError> {
+        let first = self.types[0];
+        if first.inputs != 0 || first.is_returning() {
+            return Err(EofError::InvalidFirstSectionType);
+        }
+        for (index, code_type) in self.types.iter().enumerate() {
+            let invalid = code_type.inputs > MAX_INPUTS_OUTPUTS
+                || (code_type.is_returning() && code_type.outputs > MAX_INPUTS_OUTPUTS)
+                || code_type.max_stack_height > MAX_STACK_HEIGHT
+                || u16::from(code_type.inputs) > code_type.max_stack_height;
+            if invalid {
+                return Err(EofError::InvalidSectionType(index));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the instructions of a code section and their immediates, recording how
+    /// it uses the container sections and how it ends. Returns the code sections it
+    /// calls or jumps to.
+    fn validate_code_section(
+        &self,
+        index: usize,
+        uses: &mut [ContainerUse],
+        ends: &mut SectionEnds,
+    ) -> Result<Vec<usize>, EofError> {
+        let code = &self.code_sections[index];
+        let section_type = self.types[index];
+        let mut instruction_starts = vec![false; code.len()];
+        let mut jump_targets = Vec::new();
+        let mut callees = Vec::new();
+        let mut returns = false;
+
+        let mut offset = 0;
+        while offset < code.len() {
+            let instruction = decode_instruction(code, offset)?;
+            instruction_starts[offset] = true;
+            jump_targets.extend(
+                instruction
+                    .jump_targets()
+                    .into_iter()
+                    .map(|target| (offset, target)),
+            );
+
+            match instruction.opcode {
+                Opcode::CALLF | Opcode::JUMPF => {
+                    let callee = instruction.immediate_u16() as usize;
+                    let callee_type = self
+                        .types
+                        .get(callee)
+                        .ok_or(EofError::InvalidCodeSectionIndex { offset })?;
+                    if instruction.opcode == Opcode::CALLF && !callee_type.is_returning() {
+                        return Err(EofError::CallfToNonReturning { offset });
+                    }
+                    // Jumping to a returning section returns its outputs to our caller
+                    if instruction.opcode == Opcode::JUMPF && callee_type.is_returning() {
+                        if !section_type.is_returning()
+                            || callee_type.outputs > section_type.outputs
+                        {
+                            return Err(EofError::InvalidJumpfTarget { offset });
+                        }
+                        returns = true;
+                    }
+                    callees.push(callee);
+                }
+                Opcode::RETF => {
+                    if !section_type.is_returning() {
+                        return Err(EofError::RetfInNonReturning { offset });
+                    }
+                    returns = true;
+                }
+                Opcode::DATALOADN => {
+                    let data_offset = instruction.immediate_u16() as usize;
+                    if data_offset + 32 > self.data_size as usize {
+                        return Err(EofError::InvalidDataOffset { offset });
+                    }
+                }
+                Opcode::EOFCREATE | Opcode::RETURNCONTRACT => {
+                    let usage = uses
+                        .get_mut(instruction.immediate[0] as usize)
+                        .ok_or(EofError::InvalidContainerIndex { offset })?;
+                    if instruction.opcode == Opcode::EOFCREATE {
+                        usage.eofcreate = true;
+                    } else {
+                        usage.returncontract = true;
+                        ends.return_contract = true;
+                    }
+                }
+                Opcode::RETURN | Opcode::STOP => ends.return_or_stop = true,
+                _ => {}
+            }
+            offset = instruction.end();
+        }
+
+        for (offset, target) in jump_targets {
+            let lands_on_instruction = usize::try_from(target)
+                .ok()
+                .and_then(|target| instruction_starts.get(target))
+                .is_some_and(|is_start| *is_start);
+            if !lands_on_instruction {
+                return Err(EofError::InvalidJumpTarget { offset });
+            }
+        }
+        if section_type.is_returning() && !returns {
+            return Err(EofError::MissingReturn(index));
+        }
+        Ok(callees)
+    }
+
+    /// Computes the stack heights each instruction of a code section can start with
+    /// (EIP-5450), checking that no instruction underflows the stack, that the section
+    /// returns exactly its outputs and that the maximum height is the declared one.
+    ///
+    /// Instructions are visited in order, so they must all be reachable through the
+    /// instructions before them or forward jumps. Backward jumps must land with the
+    /// same heights the instruction was reached with before.
+    fn validate_stack(&self, index: usize) -> Result<(), EofError> {
+        let code = &self.code_sections[index];
+        let section_type = self.types[index];
+        let inputs = u16::from(section_type.inputs);
+
+        let mut heights: Vec<Option<(u16, u16)>> = vec![None; code.len()];
+        heights[0] = Some((inputs, inputs));
+        let mut max_stack_height = inputs;
+
+        let mut offset = 0;
+        while offset < code.len() {
+            let instruction = decode_instruction(code, offset)?;
+            let (min, max) = heights[offset].ok_or(EofError::UnreachableCode { offset })?;
+
+            let (pops, pushes) = match instruction.opcode {
+                Opcode::CALLF | Opcode::JUMPF => {
+                    let callee_type = self.types[instruction.immediate_u16() as usize];
+                    let callee_growth =
+                        callee_type.max_stack_height - u16::from(callee_type.inputs);
+                    if (max + callee_growth) as usize > MAX_STACK_SIZE {
+                        return Err(EofError::StackOverflow { offset });
+                    }
+                    if instruction.opcode == Opcode::JUMPF && callee_type.is_returning() {
+                        let expected = u16::from(section_type.outputs)
+                            + u16::from(callee_type.inputs)
+                            - u16::from(callee_type.outputs);
+                        if min != max || max != expected {
+                            return Err(EofError::InvalidStackHeight { offset });
+                        }
+                    }
+                    let pushes = match instruction.opcode {
+                        Opcode::CALLF => callee_type.outputs,
+                        _ => 0,
+                    };
+                    (callee_type.inputs, pushes)
+                }
+                Opcode::RETF => {
+                    if min != max || max != u16::from(section_type.outputs) {
+                        return Err(EofError::InvalidStackHeight { offset });
+                    }
+                    (section_type.outputs, 0)
+                }
+                opcode => stack_io(opcode).unwrap_or_default(),
+            };
+            let (pops, pushes) = (u16::from(pops), u16::from(pushes));
+            if min < pops {
+                return Err(EofError::StackUnderflow { offset });
+            }
+            let next = (min - pops + pushes, max - pops + pushes);
+            max_stack_height = max_stack_height.max(next.1);
+
+            let mut successors = instruction.jump_targets();
+            if !instruction.is_terminating() {
+                if instruction.end() >= code.len() {
+                    return Err(EofError::MissingTerminatingInstruction);
+                }
+                successors.push(instruction.end() as isize);
+            }
+            for target in successors {
+                // Targets were checked to land on instructions
+                let target = target as usize;
+                if target > offset {
+                    heights[target] = Some(match heights[target] {
+                        Some((target_min, target_max)) => {
+                            (target_min.min(next.0), target_max.max(next.1))
+                        }
+                        None => next,
+                    });
+                } else if heights[target] != Some(next) {
+                    return Err(EofError::InvalidStackHeight { offset });
+                }
+            }
+            offset = instruction.end();
+        }
+
+        if max_stack_height != section_type.max_stack_height {
+            return Err(EofError::MaxStackHeightMismatch {
+                declared: section_type.max_stack_height,
+                computed: max_stack_height,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Section sizes listed in the header of a container
+struct Header {
+    types_size: u16,
+    code_sizes: Vec<u16>,
+    container_sizes: Vec<u32>,
+    data_size: u16,
+    /// Size of the header itself
+    size: usize,
+}
+
+impl Header {
+    fn decode(code: &[u8]) -> Result<Self, EofError> {
+        let mut reader = HeaderReader { code, offset: 0 };
+        if !is_eof(code) {
+            return Err(EofError::InvalidMagic);
+        }
+        reader.offset = MAGIC.len();
+        let version = reader.u8()?;
+        if version != VERSION {
+            return Err(EofError::UnsupportedVersion(version));
+        }
+
+        reader.expect_kind(KIND_TYPES)?;
+        let types_size = reader.u16()?;
+
+        reader.expect_kind(KIND_CODE)?;
+        let code_sections = reader.u16()? as usize;
+        if code_sections == 0 || code_sections > MAX_CODE_SECTIONS {
+            return Err(EofError::InvalidCodeSectionCount(code_sections));
+        }
+        let code_sizes = (0..code_sections)
+            .map(|_| reader.u16())
+            .collect::<Result<Vec<_>, _>>()?;
+        if types_size as usize != code_sections * TYPE_SIZE {
+            return Err(EofError::TypeSectionSizeMismatch);
+        }
+
+        let mut container_sizes = Vec::new();
+        if reader.peek()? == KIND_CONTAINER {
+            reader.offset += 1;
+            let container_sections = reader.u16()? as usize;
+            if container_sections == 0 || container_sections > MAX_CONTAINER_SECTIONS {
+                return Err(EofError::InvalidContainerSectionCount(container_sections));
+            }
+            container_sizes = (0..container_sections)
+                .map(|_| reader.u32())
+                .collect::<Result<Vec<_>, _>>()?;
+        }
+
+        reader.expect_kind(KIND_DATA)?;
+        let data_size = reader.u16()?;
+        reader.expect_kind(KIND_TERMINATOR)?;
+
+        let has_empty_section = code_sizes.iter().any(|size| *size == 0)
+            || container_sizes.iter().any(|size| *size == 0);
+        if has_empty_section {
+            return Err(EofError::EmptySection);
+        }
+
+        Ok(Header {
+            types_size,
+            code_sizes,
+            container_sizes,
+            data_size,
+            size: reader.offset,
+        })
+    }
+
+    /// Size of the sections following the header, including the full data section
+    fn body_size(&self) -> usize {
+        self.types_size as usize
+            + self
+                .code_sizes
+                .iter()
+                .map(|size| *size as usize)
+                .sum::<usize>()
+            + self
+                .container_sizes
+                .iter()
+                .map(|size| *size as usize)
+                .sum::<usize>()
+            + self.data_size as usize
+    }
+}
+
+struct HeaderReader<'a> {
+    code: &'a [u8],
+    offset: usize,
+}
+
+impl HeaderReader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], EofError> {
+        let bytes = self
+            .code
+            .get(self.offset..self.offset + N)
+            .ok_or(EofError::TruncatedHeader)?;
+        self.offset += N;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn peek(&self) -> Result<u8, EofError> {
+        self.code
+            .get(self.offset)
+            .copied()
+            .ok_or(EofError::TruncatedHeader)
+    }
+
+    fn u8(&mut self) -> Result<u8, EofError> {
+        self.take::<1>().map(|[byte]| byte)
+    }
+
+    fn u16(&mut self) -> Result<u16, EofError> {
+        self.take().map(u16::from_be_bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, EofError> {
+        self.take().map(u32::from_be_bytes)
+    }
+
+    fn expect_kind(&mut self, expected: u8) -> Result<(), EofError> {
+        let found = self.u8()?;
+        if found != expected {
+            return Err(EofError::UnexpectedSectionKind { expected, found });
+        }
+        Ok(())
+    }
+}
+
+/// Instructions ending the code sections of a container
+#[derive(Default)]
+struct SectionEnds {
+    return_or_stop: bool,
+    return_contract: bool,
+}
+
+/// Instructions using a container section
+#[derive(Clone, Default)]
+struct ContainerUse {
+    eofcreate: bool,
+    returncontract: bool,
+}
+
+struct Instruction<'a> {
+    opcode: Opcode,
+    offset: usize,
+    immediate: &'a [u8],
+}
+
+impl Instruction<'_> {
+    fn end(&self) -> usize {
+        self.offset + 1 + self.immediate.len()
+    }
+
+    fn immediate_u16(&self) -> u16 {
+        u16::from_be_bytes([self.immediate[0], self.immediate[1]])
+    }
+
+    /// Offsets the instruction can jump to, which might be out of the code section
+    fn jump_targets(&self) -> Vec<isize> {
+        let end = self.end() as isize;
+        let relative_offsets = match self.opcode {
+            Opcode::RJUMP | Opcode::RJUMPI => self.immediate,
+            Opcode::RJUMPV => &self.immediate[1..],
+            _ => return Vec::new(),
+        };
+        relative_offsets
+            .chunks(2)
+            .map(|offset| end + i16::from_be_bytes([offset[0], offset[1]]) as isize)
+            .collect()
+    }
+
+    /// Whether the execution never continues with the next instruction
+    fn is_terminating(&self) -> bool {
+        matches!(
+            self.opcode,
+            Opcode::STOP
+                | Opcode::RETURN
+                | Opcode::REVERT
+                | Opcode::INVALID
+                | Opcode::RETF
+                | Opcode::JUMPF
+                | Opcode::RETURNCONTRACT
+                | Opcode::RJUMP
+        )
+    }
+}
+
+/// Decodes the instruction at `offset`, which must be valid in EOF code
+fn decode_instruction(code: &[u8], offset: usize) -> Result<Instruction<'_>, EofError> {
+    let opcode = Opcode::try_from(code[offset])
+        .ok()
+        .filter(|opcode| stack_io(*opcode).is_some())
+        .ok_or(EofError::UndefinedInstruction {
+            opcode: code[offset],
+            offset,
+        })?;
+    let immediate_size = match opcode {
+        Opcode::RJUMP | Opcode::RJUMPI | Opcode::CALLF | Opcode::JUMPF | Opcode::DATALOADN => 2,
+        Opcode::EOFCREATE | Opcode::RETURNCONTRACT => 1,
+        // The table of offsets is preceded by its highest index
+        Opcode::RJUMPV => code
+            .get(offset + 1)
+            .map_or(1, |max_index| 1 + 2 * (*max_index as usize + 1)),
+        _ if (Opcode::PUSH1 as u8..=Opcode::PUSH32 as u8).contains(&(opcode as u8)) => {
+            (opcode as u8 - Opcode::PUSH0 as u8) as usize
+        }
+        _ => 0,
+    };
+    let immediate = code
+        .get(offset + 1..offset + 1 + immediate_size)
+        .ok_or(EofError::TruncatedImmediate { offset })?;
+    Ok(Instruction {
+        opcode,
+        offset,
+        immediate,
+    })
+}
+
+/// Amount of stack items an instruction pops and pushes, or `None` if it isn't valid
+/// in EOF code. The effect of `CALLF`, `RETF` and `JUMPF` depends on the code sections
+/// involved, so it isn't included.
+fn stack_io(opcode: Opcode) -> Option<(u8, u8)> {
+    let io = match opcode {
+        Opcode::STOP | Opcode::JUMPDEST | Opcode::INVALID | Opcode::RJUMP => (0, 0),
+        Opcode::CALLF | Opcode::RETF | Opcode::JUMPF => (0, 0),
+        Opcode::ADD
+        | Opcode::MUL
+        | Opcode::SUB
+        | Opcode::DIV
+        | Opcode::SDIV
+        | Opcode::MOD
+        | Opcode::SMOD
+        | Opcode::EXP
+        | Opcode::SIGNEXTEND
+        | Opcode::LT
+        | Opcode::GT
+        | Opcode::SLT
+        | Opcode::SGT
+        | Opcode::EQ
+        | Opcode::AND
+        | Opcode::OR
+        | Opcode::XOR
+        | Opcode::BYTE
+        | Opcode::SHL
+        | Opcode::SHR
+        | Opcode::SAR
+        | Opcode::KECCAK256 => (2, 1),
+        Opcode::ADDMOD | Opcode::MULMOD => (3, 1),
+        Opcode::ISZERO
+        | Opcode::NOT
+        | Opcode::BALANCE
+        | Opcode::CALLDATALOAD
+        | Opcode::BLOCKHASH
+        | Opcode::BLOBHASH
+        | Opcode::MLOAD
+        | Opcode::SLOAD
+        | Opcode::TLOAD
+        | Opcode::DATALOAD => (1, 1),
+        Opcode::ADDRESS
+        | Opcode::ORIGIN
+        | Opcode::CALLER
+        | Opcode::CALLVALUE
+        | Opcode::CALLDATASIZE
+        | Opcode::GASPRICE
+        | Opcode::RETURNDATASIZE
+        | Opcode::COINBASE
+        | Opcode::TIMESTAMP
+        | Opcode::NUMBER
+        | Opcode::PREVRANDAO
+        | Opcode::GASLIMIT
+        | Opcode::CHAINID
+        | Opcode::SELFBALANCE
+        | Opcode::BASEFEE
+        | Opcode::BLOBBASEFEE
+        | Opcode::MSIZE
+        | Opcode::PUSH0
+        | Opcode::DATALOADN
+        | Opcode::DATASIZE => (0, 1),
+        Opcode::CALLDATACOPY | Opcode::RETURNDATACOPY | Opcode::MCOPY | Opcode::DATACOPY => (3, 0),
+        Opcode::POP | Opcode::RJUMPI | Opcode::RJUMPV => (1, 0),
+        Opcode::MSTORE
+        | Opcode::MSTORE8
+        | Opcode::SSTORE
+        | Opcode::TSTORE
+        | Opcode::RETURN
+        | Opcode::REVERT
+        | Opcode::RETURNCONTRACT => (2, 0),
+        Opcode::EOFCREATE | Opcode::EXTCALL => (4, 1),
+        Opcode::EXTDELEGATECALL | Opcode::EXTSTATICCALL => (3, 1),
+        // Operations observing the code or the gas, and the legacy calls and creates
+        // are removed from EOF (EIP-3540, EIP-3670 and EIP-7069)
+        Opcode::CODESIZE
+        | Opcode::CODECOPY
+        | Opcode::EXTCODESIZE
+        | Opcode::EXTCODECOPY
+        | Opcode::EXTCODEHASH
+        | Opcode::JUMP
+        | Opcode::JUMPI
+        | Opcode::PC
+        | Opcode::GAS
+        | Opcode::CREATE
+        | Opcode::CALL
+        | Opcode::CALLCODE
+        | Opcode::DELEGATECALL
+        | Opcode::CREATE2
+        | Opcode::STATICCALL
+        | Opcode::SELFDESTRUCT => return None,
+        _ => {
+            let value = opcode as u8;
+            match value {
+                _ if (Opcode::PUSH1 as u8..=Opcode::PUSH32 as u8).contains(&value) => (0, 1),
+                _ if (Opcode::DUP1 as u8..=Opcode::DUP16 as u8).contains(&value) => {
+                    let n = value - Opcode::DUP1 as u8 + 1;
+                    (n, n + 1)
+                }
+                _ if (Opcode::SWAP1 as u8..=Opcode::SWAP16 as u8).contains(&value) => {
+                    let n = value - Opcode::SWAP1 as u8 + 1;
+                    (n + 1, n + 1)
+                }
+                _ if (Opcode::LOG0 as u8..=Opcode::LOG4 as u8).contains(&value) => {
+                    (value - Opcode::LOG0 as u8 + 2, 0)
+                }
+                _ => return None,
+            }
+        }
+    };
+    Some(io)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn container(code_sections: Vec<(CodeType, Vec<u8>)>, data: &[u8]) -> EofContainer {
+        let (types, code_sections) = code_sections
+            .into_iter()
+            .map(|(code_type, code)| (code_type, Bytes::from(code)))
+            .unzip();
+        EofContainer {
+            types,
+            code_sections,
+            container_sections: vec![],
+            data: Bytes::copy_from_slice(data),
+            data_size: data.len() as u16,
+        }
+    }
+
+    fn main_type(max_stack_height: u16) -> CodeType {
+        CodeType {
+            inputs: 0,
+            outputs: NON_RETURNING,
+            max_stack_height,
+        }
+    }
+
+    #[test]
+    fn encoding_roundtrips() {
+        // PUSH1 0, PUSH1 0, RETURN
+        let container = container(vec![(main_type(2), vec![0x60, 0, 0x60, 0, 0xf3])], &[1, 2]);
+        let code = container.encode();
+        assert_eq!(
+            &code[..],
+            &[
+                0xef, 0x00, 0x01, 0x01, 0x00, 0x04, 0x02, 0x00, 0x01, 0x00, 0x05, 0x04, 0x00, 0x02,
+                0x00, 0x00, 0x80, 0x00, 0x02, 0x60, 0x00, 0x60, 0x00, 0xf3, 0x01, 0x02
+            ]
+        );
+        assert_eq!(EofContainer::decode(&code), Ok(container));
+        assert_eq!(data_section(&code), &[1, 2]);
+    }
+
+    #[test]
+    fn decoding_checks_the_sizes() {
+        let code = container(vec![(main_type(0), vec![0x00])], &[1, 2]).encode();
+        assert_eq!(
+            EofContainer::decode(&code[..code.len() - 1]),
+            Err(EofError::TruncatedData)
+        );
+        assert_eq!(
+            EofContainer::decode(&[&code[..], &[0]].concat()),
+            Err(EofError::TrailingBytes)
+        );
+        assert_eq!(
+            EofContainer::decode(&code[..10]),
+            Err(EofError::TruncatedHeader)
+        );
+
+        let creation_data = [&code[..], &[7, 8]].concat();
+        let (_, calldata) =
+            EofContainer::decode_with_trailing(&creation_data).expect("valid container");
+        assert_eq!(calldata, &[7, 8]);
+    }
+
+    #[test]
+    fn decoding_rejects_other_versions() {
+        let mut code = container(vec![(main_type(0), vec![0x00])], &[])
+            .encode()
+            .to_vec();
+        code[2] = 2;
+        assert_eq!(
+            EofContainer::decode(&code),
+            Err(EofError::UnsupportedVersion(2))
+        );
+    }
+
+    #[test]
+    fn validates_calls_between_code_sections() {
+        let function_type = CodeType {
+            inputs: 2,
+            outputs: 1,
+            max_stack_height: 2,
+        };
+        let container = container(
+            vec![
+                // PUSH1 1, PUSH1 2, CALLF 1, POP, STOP
+                (main_type(2), vec![0x60, 1, 0x60, 2, 0xe3, 0, 1, 0x50, 0x00]),
+                // ADD, RETF
+                (function_type, vec![0x01, 0xe4]),
+            ],
+            &[],
+        );
+        assert_eq!(container.validate(Some(ContainerKind::Runtime)), Ok(()));
+        assert_eq!(
+            container.validate(Some(ContainerKind::Initcode)),
+            Err(EofError::ReturnInInitcode)
+        );
+    }
+
+    #[test]
+    fn rejects_legacy_only_instructions() {
+        // PC, STOP
+        let container = container(vec![(main_type(1), vec![0x58, 0x00])], &[]);
+        assert_eq!(
+            container.validate(None),
+            Err(EofError::UndefinedInstruction {
+                opcode: 0x58,
+                offset: 0
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_jumps_into_immediates() {
+        // RJUMP 1, PUSH1 0, STOP
+        let container = container(vec![(main_type(1), vec![0xe0, 0, 1, 0x60, 0, 0x00])], &[]);
+        assert_eq!(
+            container.validate(None),
+            Err(EofError::InvalidJumpTarget { offset: 0 })
+        );
+    }
+
+    #[test]
+    fn rejects_stack_underflows_and_wrong_max_heights() {
+        // POP, STOP
+        let underflow = container(vec![(main_type(0), vec![0x50, 0x00])], &[]);
+        assert_eq!(
+            underflow.validate(None),
+            Err(EofError::StackUnderflow { offset: 0 })
+        );
+
+        // PUSH0, STOP
+        let wrong_height = container(vec![(main_type(2), vec![0x5f, 0x00])], &[]);
+        assert_eq!(
+            wrong_height.validate(None),
+            Err(EofError::MaxStackHeightMismatch {
+                declared: 2,
+                computed: 1
+            })
+        );
+    }
+
+    #[test]
+    fn loops_must_keep_the_stack_height() {
+        // PUSH0, RJUMP -4: every iteration pushes another item
+        let growing = container(vec![(main_type(1), vec![0x5f, 0xe0, 0xff, 0xfc])], &[]);
+        assert_eq!(
+            growing.validate(None),
+            Err(EofError::InvalidStackHeight { offset: 1 })
+        );
+
+        // PUSH0, POP, RJUMP -5
+        let balanced = container(
+            vec![(main_type(1), vec![0x5f, 0x50, 0xe0, 0xff, 0xfb])],
+            &[],
+        );
+        assert_eq!(balanced.validate(None), Ok(()));
+    }
+
+    #[test]
+    fn rejects_unreachable_code() {
+        let function_type = CodeType {
+            inputs: 0,
+            outputs: 0,
+            max_stack_height: 0,
+        };
+        let unreachable_section = container(
+            vec![(main_type(0), vec![0x00]), (function_type, vec![0xe4])],
+            &[],
+        );
+        assert_eq!(
+            unreachable_section.validate(None),
+            Err(EofError::UnreachableCodeSection(1))
+        );
+
+        // STOP, STOP
+        let unreachable_instruction = container(vec![(main_type(0), vec![0x00, 0x00])], &[]);
+        assert_eq!(
+            unreachable_instruction.validate(None),
+            Err(EofError::UnreachableCode { offset: 1 })
+        );
+    }
+
+    #[test]
+    fn data_loads_must_be_in_bounds() {
+        // DATALOADN 1, POP, STOP
+        let container = container(vec![(main_type(1), vec![0xd1, 0, 1, 0x50, 0x00])], &[0; 32]);
+        assert_eq!(
+            container.validate(None),
+            Err(EofError::InvalidDataOffset { offset: 0 })
+        );
+    }
+
+    #[test]
+    fn validates_the_kind_of_container_sections() {
+        // PUSH0, PUSH0, RETURNCONTRACT 0
+        let initcode = |deployed: &EofContainer| EofContainer {
+            container_sections: vec![deployed.encode()],
+            ..container(vec![(main_type(2), vec![0x5f, 0x5f, 0xee, 0])], &[])
+        };
+
+        let runtime = container(vec![(main_type(0), vec![0x00])], &[]);
+        assert_eq!(
+            initcode(&runtime).validate(Some(ContainerKind::Initcode)),
+            Ok(())
+        );
+        assert_eq!(
+            initcode(&runtime).validate(Some(ContainerKind::Runtime)),
+            Err(EofError::ReturnContractInRuntime)
+        );
+
+        // A container deploying itself would return a container using RETURNCONTRACT
+        let nested = initcode(&runtime);
+        assert_eq!(
+            initcode(&nested).validate(Some(ContainerKind::Initcode)),
+            Err(EofError::InvalidContainer {
+                index: 0,
+                error: Box::new(EofError::ReturnContractInRuntime)
+            })
+        );
+    }
+
+    #[test]
+    fn aux_data_completes_the_data_section() {
+        let mut container = container(vec![(main_type(0), vec![0x00])], &[1]);
+        container.data_size = 3;
+        assert_eq!(
+            container.clone().append_aux_data(&[2]),
+            Err(HaltReason::EofAuxDataTooSmall)
+        );
+
+        container.append_aux_data(&[2, 3, 4]).unwrap();
+        assert_eq!(&container.data[..], &[1, 2, 3, 4]);
+        assert_eq!(container.data_size, 4);
+    }
+}
@@ -6,6 +6,8 @@ pub enum CodegenError {
     LinkError(#[from] std::io::Error),
     #[error("llvm compile error: {0}")]
     LLVMCompileError(String),
+    #[error("error loading library: {0}")]
+    LibraryLoadError(#[from] libloading::Error),
     #[error("melior error: {0}")]
     MeliorError(#[from] melior::Error),
     #[error("not yet implemented: {0}")]
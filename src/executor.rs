@@ -1,8 +1,13 @@
+use std::path::Path;
+
+use libloading::Library;
 use melior::ExecutionEngine;
 
 use crate::{
-    constants::MAIN_ENTRYPOINT,
+    constants::{AOT_SYSCALL_SLOT_SUFFIX, MAIN_ENTRYPOINT},
+    errors::CodegenError,
     module::MLIRModule,
+    spec::SpecId,
     syscall::{MainFunc, SyscallContext},
 };
 
@@ -16,14 +21,86 @@ pub enum OptLevel {
 }
 
 pub struct Executor {
-    engine: ExecutionEngine,
+    backend: Backend,
+    /// Whether the code reports each operation to the inspector
+    pub(crate) instrumented: bool,
+    /// Hardfork the code was compiled for
+    pub(crate) spec_id: SpecId,
+}
+
+enum Backend {
+    /// Code compiled in memory by the MLIR execution engine
+    Jit(ExecutionEngine),
+    /// Code loaded from a shared library built by [`crate::codegen::compile_aot_shared_lib`]
+    Aot {
+        _library: Library,
+        main_fn: MainFunc,
+    },
 }
 
+// Neither the execution engine nor the loaded library hold references to the MLIR
+// context, so it's safe to move them between threads.
+unsafe impl Send for Executor {}
+// Executors are shared through the [`ExecutorCache`](crate::cache::ExecutorCache),
+// which never hands the same one to two threads at once since the compiled code keeps
+// the state of the running frame in module globals.
+unsafe impl Sync for Executor {}
+
 impl Executor {
-    pub fn new(module: &MLIRModule, syscall_ctx: &SyscallContext, opt_level: OptLevel) -> Self {
+    pub fn new(module: &MLIRModule, opt_level: OptLevel) -> Self {
         let engine = ExecutionEngine::new(module.module(), opt_level as usize, &[], false);
-        syscall_ctx.register_symbols(&engine);
-        Self { engine }
+        SyscallContext::register_symbols(&engine);
+        Self {
+            backend: Backend::Jit(engine),
+            instrumented: false,
+            spec_id: SpecId::default(),
+        }
+    }
+
+    /// Loads a shared library built by [`crate::codegen::compile_aot_shared_lib`],
+    /// pointing its syscall slots to the syscalls of this process.
+    ///
+    /// # Safety
+    ///
+    /// Loading a library runs its initialization code, and the library is assumed to
+    /// have been produced by this crate's AOT compilation, so it should only be
+    /// loaded from trusted locations.
+    pub unsafe fn load(path: impl AsRef<Path>) -> Result<Self, CodegenError> {
+        let library = Library::new(path.as_ref())?;
+
+        SyscallContext::for_each_symbol(|name, address| {
+            let slot_name = format!("{name}{AOT_SYSCALL_SLOT_SUFFIX}\0");
+            // A missing slot means the program doesn't use the syscall
+            if let Ok(slot) = library.get::<*mut *mut ()>(slot_name.as_bytes()) {
+                **slot = address;
+            }
+        });
+
+        let function_name = format!("_mlir_ciface_{MAIN_ENTRYPOINT}\0");
+        let main_fn = *library.get::<MainFunc>(function_name.as_bytes())?;
+
+        Ok(Self {
+            backend: Backend::Aot {
+                _library: library,
+                main_fn,
+            },
+            instrumented: false,
+            spec_id: SpecId::default(),
+        })
+    }
+
+    /// Returns whether the code was loaded from a shared library
+    pub fn is_aot(&self) -> bool {
+        matches!(self.backend, Backend::Aot { .. })
+    }
+
+    /// Returns whether the code was compiled with calls to [`Inspector::step`](crate::inspector::Inspector::step)
+    pub fn is_instrumented(&self) -> bool {
+        self.instrumented
+    }
+
+    pub fn spec_id(&self) -> SpecId {
+        self.spec_id
     }
 
     pub fn execute(&self, context: &mut SyscallContext, initial_gas: u64) -> u8 {
@@ -33,8 +110,13 @@ impl Executor {
     }
 
     fn get_main_entrypoint(&self) -> MainFunc {
-        let function_name = format!("_mlir_ciface_{MAIN_ENTRYPOINT}");
-        let fptr = self.engine.lookup(&function_name);
-        unsafe { std::mem::transmute(fptr) }
+        match &self.backend {
+            Backend::Jit(engine) => {
+                let function_name = format!("_mlir_ciface_{MAIN_ENTRYPOINT}");
+                let fptr = engine.lookup(&function_name);
+                unsafe { std::mem::transmute::<*mut (), MainFunc>(fptr) }
+            }
+            Backend::Aot { main_fn, .. } => *main_fn,
+        }
     }
 }
@@ -0,0 +1,622 @@
+//! # Fork mode
+//!
+//! Runs over the state of a live chain at a pinned block. A [`ForkDb`] fetches
+//! accounts, code, storage and block hashes the first time they're read, from a
+//! [`StateProvider`], and keeps them in a [`ForkState`].
+//!
+//! The state fetched can be saved to a file and given to a [`FixtureProvider`] later,
+//! so the same executions can run offline. Fetching it from a node with the
+//! `JsonRpcProvider` needs the `json-rpc` feature:
+//!
+//! ```no_run
+//! # #[cfg(feature = "json-rpc")]
+//! # fn main() {
+//! use evm_mlir::{cache_db::CacheDB, fork::{ForkDb, JsonRpcProvider}, Env, Evm};
+//!
+//! let provider = JsonRpcProvider::new("http://localhost:8545");
+//! let fork_db = ForkDb::new(provider, 20_000_000);
+//! let mut evm = Evm::new(Env::default(), CacheDB::new(&fork_db));
+//! let _ = evm.transact();
+//! fork_db.save("fork.json").unwrap();
+//!
+//! // Later, without a node
+//! let fork_db = ForkDb::from_fixture("fork.json").unwrap();
+//! # }
+//! # #[cfg(not(feature = "json-rpc"))]
+//! # fn main() {}
+//! ```
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::Path,
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    constants::EMPTY_CODE_HASH_STR,
+    db::{AccountInfo, Bytecode, Database, DatabaseRef},
+    primitives::{Address, B256, U256},
+};
+
+#[cfg(feature = "json-rpc")]
+pub use json_rpc::JsonRpcProvider;
+
+#[derive(Debug, Error)]
+pub enum ProviderError {
+    #[error("request to the node failed: {0}")]
+    Transport(String),
+    #[error("node returned error {code}: {message}")]
+    Rpc { code: i64, message: String },
+    #[error("invalid response from the node: {0}")]
+    InvalidResponse(String),
+    #[error("{0} is missing from the fixture")]
+    MissingFromFixture(String),
+    #[error("code with hash {0:?} wasn't fetched")]
+    MissingCode(B256),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+/// Source of the state of a chain at a given block
+pub trait StateProvider {
+    /// Information of an account, with its code. `None` if the account doesn't exist.
+    ///
+    /// Providers may not tell empty accounts from missing ones, returning `None` for
+    /// both: the JSON-RPC API of the nodes doesn't, so the `JsonRpcProvider` can't. Both
+    /// are treated the same from Spurious Dragon on (EIP-161), but forks of earlier
+    /// blocks may charge calls to empty accounts as if they created them.
+    fn account(
+        &self,
+        address: Address,
+        block_number: u64,
+    ) -> Result<Option<AccountInfo>, ProviderError>;
+
+    fn storage(
+        &self,
+        address: Address,
+        index: U256,
+        block_number: u64,
+    ) -> Result<U256, ProviderError>;
+
+    fn block_hash(&self, number: u64) -> Result<B256, ProviderError>;
+}
+
+/// Account as kept by a [`ForkState`]. Its code is kept by hash.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForkAccount {
+    pub balance: U256,
+    pub nonce: u64,
+    pub code_hash: B256,
+}
+
+impl From<ForkAccount> for AccountInfo {
+    fn from(account: ForkAccount) -> Self {
+        Self {
+            balance: account.balance,
+            nonce: account.nonce,
+            code_hash: account.code_hash,
+            code: None,
+        }
+    }
+}
+
+/// State fetched from a chain at a block, serialized as JSON
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForkState {
+    pub block_number: u64,
+    /// Accounts by address, `None` for the ones that don't exist
+    pub accounts: HashMap<Address, Option<ForkAccount>>,
+    pub storage: HashMap<Address, HashMap<U256, U256>>,
+    #[serde(with = "hex_contracts")]
+    pub contracts: HashMap<B256, Bytecode>,
+    pub block_hashes: HashMap<u64, B256>,
+}
+
+impl ForkState {
+    pub fn new(block_number: u64) -> Self {
+        Self {
+            block_number,
+            ..Default::default()
+        }
+    }
+
+    /// Keeps an account fetched at the block, with its code
+    pub fn insert_account(&mut self, address: Address, info: Option<AccountInfo>) {
+        let account = info.map(|info| {
+            let has_code = info.has_code();
+            if let Some(code) = info.code.filter(|_| has_code) {
+                self.contracts.insert(info.code_hash, code);
+            }
+            ForkAccount {
+                balance: info.balance,
+                nonce: info.nonce,
+                code_hash: info.code_hash,
+            }
+        });
+        self.accounts.insert(address, account);
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ProviderError> {
+        let reader = BufReader::new(File::open(path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ProviderError> {
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+}
+
+/// Database forking a chain at a pinned block. What is read is fetched from the
+/// provider once and kept, and commits aren't accepted: wrap it in a
+/// [`CacheDB`](crate::cache_db::CacheDB) to execute transactions over it.
+#[derive(Debug)]
+pub struct ForkDb<P> {
+    provider: P,
+    state: RefCell<ForkState>,
+}
+
+impl<P: StateProvider> ForkDb<P> {
+    pub fn new(provider: P, block_number: u64) -> Self {
+        Self {
+            provider,
+            state: RefCell::new(ForkState::new(block_number)),
+        }
+    }
+
+    /// Block the state is read at
+    pub fn block_number(&self) -> u64 {
+        self.state.borrow().block_number
+    }
+
+    /// State fetched so far
+    pub fn state(&self) -> ForkState {
+        self.state.borrow().clone()
+    }
+
+    /// Saves the state fetched so far, to fork offline from it with
+    /// [`from_fixture`](ForkDb::from_fixture)
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ProviderError> {
+        self.state.borrow().save(path)
+    }
+}
+
+impl ForkDb<FixtureProvider> {
+    /// Forks from a state saved with [`save`](ForkDb::save), at the block it was saved at
+    pub fn from_fixture(path: impl AsRef<Path>) -> Result<Self, ProviderError> {
+        let provider = FixtureProvider::load(path)?;
+        let block_number = provider.state.block_number;
+        Ok(Self::new(provider, block_number))
+    }
+}
+
+impl<P: StateProvider> DatabaseRef for ForkDb<P> {
+    type Error = ProviderError;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        if let Some(account) = self.state.borrow().accounts.get(&address) {
+            return Ok(account.clone().map(AccountInfo::from));
+        }
+        let info = self.provider.account(address, self.block_number())?;
+        self.state
+            .borrow_mut()
+            .insert_account(address, info.clone());
+        Ok(info)
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        if code_hash.is_zero() || code_hash == B256::from_str(EMPTY_CODE_HASH_STR).unwrap() {
+            return Ok(Bytecode::new());
+        }
+        // The code is fetched along with its account
+        self.state
+            .borrow()
+            .contracts
+            .get(&code_hash)
+            .cloned()
+            .ok_or(ProviderError::MissingCode(code_hash))
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        let cached = self
+            .state
+            .borrow()
+            .storage
+            .get(&address)
+            .and_then(|storage| storage.get(&index).copied());
+        if let Some(value) = cached {
+            return Ok(value);
+        }
+        let value = self.provider.storage(address, index, self.block_number())?;
+        self.state
+            .borrow_mut()
+            .storage
+            .entry(address)
+            .or_default()
+            .insert(index, value);
+        Ok(value)
+    }
+
+    fn block_hash_ref(&self, number: U256) -> Result<B256, Self::Error> {
+        // Blocks after the pinned one aren't part of the fork
+        if number > U256::from(self.block_number()) {
+            return Ok(B256::zero());
+        }
+        let number = number.as_u64();
+        if let Some(hash) = self.state.borrow().block_hashes.get(&number) {
+            return Ok(*hash);
+        }
+        let hash = self.provider.block_hash(number)?;
+        self.state.borrow_mut().block_hashes.insert(number, hash);
+        Ok(hash)
+    }
+}
+
+impl<P: StateProvider> Database for ForkDb<P> {
+    type Error = ProviderError;
+
+    fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        self.basic_ref(address)
+    }
+
+    fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        self.code_by_hash_ref(code_hash)
+    }
+
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        self.storage_ref(address, index)
+    }
+
+    fn block_hash(&mut self, number: U256) -> Result<B256, Self::Error> {
+        self.block_hash_ref(number)
+    }
+}
+
+/// Provider serving a saved [`ForkState`], without a node. Reading anything that
+/// wasn't saved is an error.
+#[derive(Clone, Debug, Default)]
+pub struct FixtureProvider {
+    state: ForkState,
+}
+
+impl FixtureProvider {
+    pub fn new(state: ForkState) -> Self {
+        Self { state }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ProviderError> {
+        Ok(Self::new(ForkState::load(path)?))
+    }
+
+    fn check_block(&self, block_number: u64) -> Result<(), ProviderError> {
+        if block_number != self.state.block_number {
+            return Err(ProviderError::MissingFromFixture(format!(
+                "state at block {block_number}"
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl StateProvider for FixtureProvider {
+    fn account(
+        &self,
+        address: Address,
+        block_number: u64,
+    ) -> Result<Option<AccountInfo>, ProviderError> {
+        self.check_block(block_number)?;
+        let account = self
+            .state
+            .accounts
+            .get(&address)
+            .ok_or_else(|| ProviderError::MissingFromFixture(format!("account {address:?}")))?;
+        Ok(account.clone().map(|account| {
+            let code = self.state.contracts.get(&account.code_hash).cloned();
+            AccountInfo {
+                code,
+                ..account.into()
+            }
+        }))
+    }
+
+    fn storage(
+        &self,
+        address: Address,
+        index: U256,
+        block_number: u64,
+    ) -> Result<U256, ProviderError> {
+        self.check_block(block_number)?;
+        self.state
+            .storage
+            .get(&address)
+            .and_then(|storage| storage.get(&index).copied())
+            .ok_or_else(|| {
+                ProviderError::MissingFromFixture(format!("storage {index} of {address:?}"))
+            })
+    }
+
+    fn block_hash(&self, number: u64) -> Result<B256, ProviderError> {
+        self.state
+            .block_hashes
+            .get(&number)
+            .copied()
+            .ok_or_else(|| ProviderError::MissingFromFixture(format!("hash of block {number}")))
+    }
+}
+
+fn decode_hex(data: &str) -> Result<Bytecode, hex::FromHexError> {
+    let data = data.strip_prefix("0x").unwrap_or(data);
+    Ok(hex::decode(data)?.into())
+}
+
+/// Node provider, built with the `json-rpc` feature so the HTTP client is optional
+#[cfg(feature = "json-rpc")]
+mod json_rpc {
+    use std::{
+        sync::atomic::{AtomicU64, Ordering},
+        time::Duration,
+    };
+
+    use serde::{de::DeserializeOwned, Deserialize};
+    use serde_json::json;
+
+    use super::{decode_hex, ProviderError, StateProvider};
+    use crate::{
+        cache::code_hash,
+        db::AccountInfo,
+        primitives::{Address, B256, U256},
+    };
+
+    /// How long a request to the node can take
+    const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// Provider fetching the state from a node, through its JSON-RPC API
+    #[derive(Debug)]
+    pub struct JsonRpcProvider {
+        url: String,
+        agent: ureq::Agent,
+        next_id: AtomicU64,
+    }
+
+    #[derive(Deserialize)]
+    struct JsonRpcResponse {
+        #[serde(default)]
+        result: serde_json::Value,
+        error: Option<JsonRpcError>,
+    }
+
+    #[derive(Deserialize)]
+    struct JsonRpcError {
+        code: i64,
+        message: String,
+    }
+
+    #[derive(Deserialize)]
+    struct BlockHeader {
+        hash: B256,
+    }
+
+    impl JsonRpcProvider {
+        pub fn new(url: impl Into<String>) -> Self {
+            Self {
+                url: url.into(),
+                agent: ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build(),
+                next_id: AtomicU64::new(1),
+            }
+        }
+
+        fn request<T: DeserializeOwned>(
+            &self,
+            method: &str,
+            params: serde_json::Value,
+        ) -> Result<T, ProviderError> {
+            let request = json!({
+                "jsonrpc": "2.0",
+                "id": self.next_id.fetch_add(1, Ordering::Relaxed),
+                "method": method,
+                "params": params,
+            });
+            let body = self
+                .agent
+                .post(&self.url)
+                .set("Content-Type", "application/json")
+                .send_string(&request.to_string())
+                .map_err(|error| ProviderError::Transport(error.to_string()))?
+                .into_string()?;
+            let response: JsonRpcResponse = serde_json::from_str(&body)?;
+            if let Some(JsonRpcError { code, message }) = response.error {
+                return Err(ProviderError::Rpc { code, message });
+            }
+            serde_json::from_value(response.result)
+                .map_err(|error| ProviderError::InvalidResponse(format!("{method}: {error}")))
+        }
+    }
+
+    impl StateProvider for JsonRpcProvider {
+        fn account(
+            &self,
+            address: Address,
+            block_number: u64,
+        ) -> Result<Option<AccountInfo>, ProviderError> {
+            let block = format!("{block_number:#x}");
+            let balance: U256 = self.request("eth_getBalance", json!([address, block]))?;
+            let nonce: U256 = self.request("eth_getTransactionCount", json!([address, block]))?;
+            let code: String = self.request("eth_getCode", json!([address, block]))?;
+            let code = decode_hex(&code)
+                .map_err(|error| ProviderError::InvalidResponse(format!("eth_getCode: {error}")))?;
+
+            // The API can't tell empty accounts from missing ones
+            if balance.is_zero() && nonce.is_zero() && code.is_empty() {
+                return Ok(None);
+            }
+            Ok(Some(AccountInfo {
+                balance,
+                nonce: nonce.low_u64(),
+                code_hash: code_hash(&code),
+                code: Some(code),
+            }))
+        }
+
+        fn storage(
+            &self,
+            address: Address,
+            index: U256,
+            block_number: u64,
+        ) -> Result<U256, ProviderError> {
+            let block = format!("{block_number:#x}");
+            self.request("eth_getStorageAt", json!([address, index, block]))
+        }
+
+        fn block_hash(&self, number: u64) -> Result<B256, ProviderError> {
+            let block = format!("{number:#x}");
+            let header: Option<BlockHeader> =
+                self.request("eth_getBlockByNumber", json!([block, false]))?;
+            header
+                .map(|header| header.hash)
+                .ok_or_else(|| ProviderError::InvalidResponse(format!("block {number} not found")))
+        }
+    }
+}
+
+/// Serializes the code of the contracts as hex strings
+mod hex_contracts {
+    use std::collections::HashMap;
+
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use super::decode_hex;
+    use crate::{db::Bytecode, primitives::B256};
+
+    pub fn serialize<S: Serializer>(
+        contracts: &HashMap<B256, Bytecode>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(
+            contracts
+                .iter()
+                .map(|(hash, code)| (hash, format!("0x{}", hex::encode(code)))),
+        )
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<B256, Bytecode>, D::Error> {
+        HashMap::<B256, String>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(hash, code)| Ok((hash, decode_hex(&code).map_err(D::Error::custom)?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cache::code_hash, test_utils::CountingReads};
+
+    const BLOCK_NUMBER: u64 = 100;
+
+    fn chain_state() -> (ForkState, Address) {
+        let address = Address::from_low_u64_be(40);
+        let code = Bytecode::from_static(&[0x5f, 0x54]);
+        let mut state = ForkState::new(BLOCK_NUMBER);
+        state.insert_account(
+            address,
+            Some(AccountInfo {
+                balance: U256::from(10),
+                nonce: 1,
+                code_hash: code_hash(&code),
+                code: Some(code),
+            }),
+        );
+        state.insert_account(Address::from_low_u64_be(41), None);
+        state
+            .storage
+            .insert(address, HashMap::from([(U256::one(), U256::from(2))]));
+        state
+            .block_hashes
+            .insert(BLOCK_NUMBER - 1, B256::from_low_u64_be(99));
+        (state, address)
+    }
+
+    #[test]
+    fn fetched_state_is_kept() {
+        let (state, address) = chain_state();
+        let provider = CountingReads::new(FixtureProvider::new(state));
+        let mut fork_db = ForkDb::new(provider, BLOCK_NUMBER);
+
+        for _ in 0..2 {
+            let info = fork_db.basic(address).unwrap().unwrap();
+            assert_eq!(info.balance, U256::from(10));
+            assert_eq!(
+                fork_db.code_by_hash(info.code_hash).unwrap(),
+                Bytecode::from_static(&[0x5f, 0x54])
+            );
+            assert_eq!(
+                fork_db.storage(address, U256::one()).unwrap(),
+                U256::from(2)
+            );
+            assert_eq!(
+                fork_db.block_hash(U256::from(BLOCK_NUMBER - 1)).unwrap(),
+                B256::from_low_u64_be(99)
+            );
+            assert_eq!(fork_db.basic(Address::from_low_u64_be(41)).unwrap(), None);
+        }
+
+        assert_eq!(fork_db.provider.reads(), 4);
+        // Blocks after the pinned one aren't fetched
+        assert_eq!(
+            fork_db.block_hash(U256::from(BLOCK_NUMBER + 1)).unwrap(),
+            B256::zero()
+        );
+        assert_eq!(fork_db.provider.reads(), 4);
+    }
+
+    #[test]
+    fn saved_state_is_served_offline() {
+        let (state, address) = chain_state();
+        let fork_db = ForkDb::new(FixtureProvider::new(state), BLOCK_NUMBER);
+        fork_db.basic_ref(address).unwrap();
+        fork_db.storage_ref(address, U256::one()).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fork.json");
+
+        fork_db.save(&path).unwrap();
+        let offline_db = ForkDb::from_fixture(&path).unwrap();
+
+        assert_eq!(offline_db.block_number(), BLOCK_NUMBER);
+        let info = offline_db.basic_ref(address).unwrap().unwrap();
+        assert_eq!(
+            offline_db.code_by_hash_ref(info.code_hash).unwrap(),
+            Bytecode::from_static(&[0x5f, 0x54])
+        );
+        assert_eq!(
+            offline_db.storage_ref(address, U256::one()).unwrap(),
+            U256::from(2)
+        );
+        // Only what was read before saving is available
+        assert!(matches!(
+            offline_db.storage_ref(address, U256::from(2)),
+            Err(ProviderError::MissingFromFixture(_))
+        ));
+        assert_eq!(offline_db.state(), fork_db.state());
+    }
+
+    #[test]
+    fn fixtures_only_serve_their_block() {
+        let (state, address) = chain_state();
+        let fork_db = ForkDb::new(FixtureProvider::new(state), BLOCK_NUMBER + 1);
+
+        assert!(matches!(
+            fork_db.basic_ref(address),
+            Err(ProviderError::MissingFromFixture(_))
+        ));
+    }
+}
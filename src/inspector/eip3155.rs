@@ -0,0 +1,323 @@
+use std::{collections::VecDeque, fmt::Write as _, io::Write};
+
+use crate::{primitives::U256 as EU256, program::Opcode, result::ExecutionResult};
+
+use super::{CallInputs, CreateInputs, Inspector, Step};
+
+/// Inspector writing an [EIP-3155](https://eips.ethereum.org/EIPS/eip-3155) JSON line
+/// for every executed operation, followed by a summary line when the transaction ends.
+///
+/// The gas cost of an operation is only known when the next one in the same frame
+/// starts, or when the frame returns, so lines are buffered until then. Errors while
+/// writing to the output are ignored.
+pub struct TracerEip3155<W: Write> {
+    output: W,
+    /// Steps waiting for their gas cost, in execution order
+    pending: VecDeque<TraceLine>,
+    /// Amount of lines already written, which is the index of the first pending one
+    written: usize,
+    /// Call frames being executed, the innermost last
+    frames: Vec<Frame>,
+}
+
+struct Frame {
+    gas_limit: u64,
+    /// Index of the last step executed by the frame
+    last_step: Option<usize>,
+}
+
+struct TraceLine {
+    pc: usize,
+    op: u8,
+    gas: u64,
+    gas_cost: Option<u64>,
+    mem_size: usize,
+    stack: Vec<EU256>,
+    depth: usize,
+    return_data: Vec<u8>,
+    refund: u64,
+}
+
+impl<W: Write> TracerEip3155<W> {
+    pub fn new(output: W) -> Self {
+        Self {
+            output,
+            pending: VecDeque::new(),
+            written: 0,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.output
+    }
+
+    pub fn into_inner(self) -> W {
+        self.output
+    }
+
+    fn enter_frame(&mut self, gas_limit: u64) {
+        self.frames.push(Frame {
+            gas_limit,
+            last_step: None,
+        });
+    }
+
+    fn exit_frame(&mut self, result: &ExecutionResult) {
+        let Some(frame) = self.frames.pop() else {
+            return;
+        };
+        let gas_left = frame.gas_limit.saturating_sub(result.gas_used());
+        if let Some(index) = frame.last_step {
+            self.set_gas_cost(index, gas_left);
+        }
+        self.write_finished_lines();
+
+        if self.frames.is_empty() {
+            self.write_summary(result);
+        }
+    }
+
+    /// Sets the cost of the step at `index` given the gas left after running it
+    fn set_gas_cost(&mut self, index: usize, gas_left: u64) {
+        if let Some(line) = self.pending.get_mut(index - self.written) {
+            line.gas_cost = Some(line.gas.saturating_sub(gas_left));
+        }
+    }
+
+    fn write_finished_lines(&mut self) {
+        while self
+            .pending
+            .front()
+            .is_some_and(|line| line.gas_cost.is_some())
+        {
+            let line = self.pending.pop_front().unwrap();
+            self.written += 1;
+            self.write_line(&line);
+        }
+    }
+
+    fn write_line(&mut self, line: &TraceLine) {
+        let stack: Vec<String> = line
+            .stack
+            .iter()
+            .map(|value| format!("\"0x{value:x}\""))
+            .collect();
+        let op_name = Opcode::try_from(line.op)
+            .map(|opcode| format!("{opcode:?}"))
+            .unwrap_or_else(|_| "INVALID".to_string());
+        let _ = writeln!(
+            self.output,
+            "{{\"pc\":{},\"op\":{},\"gas\":\"0x{:x}\",\"gasCost\":\"0x{:x}\",\"memSize\":{},\"stack\":[{}],\"depth\":{},\"returnData\":\"0x{}\",\"refund\":{},\"opName\":\"{}\"}}",
+            line.pc,
+            line.op,
+            line.gas,
+            line.gas_cost.unwrap_or_default(),
+            line.mem_size,
+            stack.join(","),
+            line.depth,
+            to_hex(&line.return_data),
+            line.refund,
+            op_name,
+        );
+    }
+
+    fn write_summary(&mut self, result: &ExecutionResult) {
+        let output = result.output().map(AsRef::as_ref).unwrap_or_default();
+        let error = match result {
+            ExecutionResult::Success { .. } => String::new(),
+            ExecutionResult::Revert { .. } => ",\"error\":\"execution reverted\"".to_string(),
+            ExecutionResult::Halt { reason, .. } => format!(",\"error\":\"{reason:?}\""),
+        };
+        let _ = writeln!(
+            self.output,
+            "{{\"output\":\"0x{}\",\"gasUsed\":\"0x{:x}\",\"pass\":{}{}}}",
+            to_hex(output),
+            result.gas_used(),
+            result.is_success(),
+            error,
+        );
+        let _ = self.output.flush();
+    }
+}
+
+impl<W: Write> Inspector for TracerEip3155<W> {
+    fn call(&mut self, inputs: &CallInputs) {
+        self.enter_frame(inputs.gas_limit);
+    }
+
+    fn call_end(&mut self, _inputs: &CallInputs, result: &ExecutionResult) {
+        self.exit_frame(result);
+    }
+
+    fn create(&mut self, inputs: &CreateInputs) {
+        self.enter_frame(inputs.gas_limit);
+    }
+
+    fn create_end(&mut self, _inputs: &CreateInputs, result: &ExecutionResult) {
+        self.exit_frame(result);
+    }
+
+    fn step(&mut self, step: &Step) {
+        let depth = self.frames.len();
+        let Some(last_step) = self.frames.last().map(|frame| frame.last_step) else {
+            // Steps are only traced inside the frames reported by the EVM
+            return;
+        };
+        if let Some(index) = last_step {
+            self.set_gas_cost(index, step.gas_remaining);
+        }
+
+        self.pending.push_back(TraceLine {
+            pc: step.pc,
+            op: step.opcode,
+            gas: step.gas_remaining,
+            gas_cost: None,
+            mem_size: step.memory.len(),
+            stack: step
+                .stack
+                .iter()
+                .map(|item| item.to_primitive_u256())
+                .collect(),
+            depth,
+            return_data: step.return_data.to_vec(),
+            refund: step.gas_refund,
+        });
+        if let Some(frame) = self.frames.last_mut() {
+            frame.last_step = Some(self.written + self.pending.len() - 1);
+        }
+        self.write_finished_lines();
+    }
+
+    fn step_enabled(&self) -> bool {
+        true
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .fold(String::with_capacity(bytes.len() * 2), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        constants::CallType,
+        primitives::Address,
+        result::{HaltReason, OutOfGasError, Output, SuccessReason},
+        syscall::U256,
+    };
+
+    fn call_inputs(gas_limit: u64) -> CallInputs {
+        CallInputs {
+            caller: Address::zero(),
+            target_address: Address::zero(),
+            code_address: Address::zero(),
+            value: EU256::zero(),
+            input: Default::default(),
+            gas_limit,
+            call_type: CallType::Call,
+            is_static: false,
+        }
+    }
+
+    fn step(pc: usize, opcode: u8, gas_remaining: u64, stack: &[U256]) -> Step {
+        Step {
+            address: Address::zero(),
+            pc,
+            opcode,
+            gas_remaining,
+            stack,
+            memory: &[],
+            return_data: &[],
+            gas_refund: 0,
+        }
+    }
+
+    fn output_lines(tracer: TracerEip3155<Vec<u8>>) -> Vec<String> {
+        String::from_utf8(tracer.into_inner())
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn lines_are_written_once_their_gas_cost_is_known() {
+        let mut tracer = TracerEip3155::new(Vec::new());
+        let inputs = call_inputs(100);
+        tracer.call(&inputs);
+        tracer.step(&step(0, 0x60, 100, &[]));
+        assert!(tracer.get_ref().is_empty());
+
+        tracer.step(&step(2, 0x00, 97, &[U256 { lo: 1, hi: 0 }]));
+        tracer.call_end(
+            &inputs,
+            &ExecutionResult::Success {
+                reason: SuccessReason::Stop,
+                gas_used: 3,
+                gas_refunded: 0,
+                logs: vec![],
+                output: Output::Call(Default::default()),
+            },
+        );
+
+        assert_eq!(
+            output_lines(tracer),
+            vec![
+                r#"{"pc":0,"op":96,"gas":"0x64","gasCost":"0x3","memSize":0,"stack":[],"depth":1,"returnData":"0x","refund":0,"opName":"PUSH1"}"#,
+                r#"{"pc":2,"op":0,"gas":"0x61","gasCost":"0x0","memSize":0,"stack":["0x1"],"depth":1,"returnData":"0x","refund":0,"opName":"STOP"}"#,
+                r#"{"output":"0x","gasUsed":"0x3","pass":true}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_frames_are_written_in_execution_order() {
+        let mut tracer = TracerEip3155::new(Vec::new());
+        let outer = call_inputs(1000);
+        let inner = call_inputs(100);
+        tracer.call(&outer);
+        tracer.step(&step(0, 0xf1, 1000, &[]));
+        tracer.call(&inner);
+        tracer.step(&step(0, 0x00, 100, &[]));
+        tracer.call_end(
+            &inner,
+            &ExecutionResult::Success {
+                reason: SuccessReason::Stop,
+                gas_used: 0,
+                gas_refunded: 0,
+                logs: vec![],
+                output: Output::Call(Default::default()),
+            },
+        );
+        // The call isn't finished until the outer frame runs its next operation
+        assert!(tracer.get_ref().is_empty());
+
+        tracer.step(&step(1, 0xfe, 900, &[]));
+        tracer.call_end(
+            &outer,
+            &ExecutionResult::Halt {
+                reason: HaltReason::OutOfGas(OutOfGasError::Basic),
+                gas_used: 1000,
+            },
+        );
+
+        let lines = output_lines(tracer);
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].contains(r#""pc":0,"op":241,"gas":"0x3e8","gasCost":"0x64""#));
+        assert!(lines[0].contains(r#""depth":1"#));
+        assert!(lines[1].contains(r#""opName":"STOP""#));
+        assert!(lines[1].contains(r#""depth":2"#));
+        assert!(lines[2].contains(r#""gasCost":"0x384""#));
+        assert_eq!(
+            lines[3],
+            r#"{"output":"0x","gasUsed":"0x3e8","pass":false,"error":"OutOfGas(Basic)"}"#
+        );
+    }
+}
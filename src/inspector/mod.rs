@@ -0,0 +1,162 @@
+//! # Inspection of executions
+//!
+//! An [`Inspector`] attached to an [`Evm`](crate::Evm) with
+//! [`EvmBuilder::with_inspector`](crate::builder::EvmBuilder::with_inspector) is notified
+//! when calls and contract creations start and finish, when logs are emitted and when
+//! contracts self-destruct. This is the building block for tracers, debuggers and gas
+//! profilers.
+//!
+//! Per-opcode [steps](Inspector::step) need the program to call back into the inspector
+//! before each operation. Since that code is considerably slower, it's only generated
+//! when the attached inspector [enables it](Inspector::step_enabled).
+//!
+//! [`TracerEip3155`] is an inspector that writes [EIP-3155](https://eips.ethereum.org/EIPS/eip-3155)
+//! traces, which can be compared against the ones produced by other clients.
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    constants::CallType,
+    primitives::{Address, Bytes, U256 as EU256},
+    result::ExecutionResult,
+    syscall::{Log, U256},
+};
+
+mod eip3155;
+
+pub use eip3155::TracerEip3155;
+
+/// Inspector owned by an [`Evm`](crate::Evm) and moved into its executions
+pub type BoxedInspector = Box<dyn Inspector + Send>;
+
+/// Inputs of a call frame, either the transaction itself or a `CALL`-like opcode
+#[derive(Clone, Debug, PartialEq)]
+pub struct CallInputs {
+    pub caller: Address,
+    /// Address whose storage and balance are used by the call
+    pub target_address: Address,
+    /// Address the executed code was loaded from
+    pub code_address: Address,
+    pub value: EU256,
+    pub input: Bytes,
+    /// Gas available to the frame, including the intrinsic cost for the transaction, so
+    /// the gas left when the frame returns is this minus the gas used by its result
+    pub gas_limit: u64,
+    pub call_type: CallType,
+    pub is_static: bool,
+}
+
+/// Inputs of a contract creation, either the transaction itself or a `CREATE`/`CREATE2` opcode
+#[derive(Clone, Debug, PartialEq)]
+pub struct CreateInputs {
+    pub caller: Address,
+    /// Address the contract is deployed to
+    pub address: Address,
+    pub value: EU256,
+    pub init_code: Bytes,
+    pub gas_limit: u64,
+    /// The salt, only present for `CREATE2`
+    pub salt: Option<EU256>,
+}
+
+/// State of the execution right before running an operation
+#[derive(Debug)]
+pub struct Step<'a> {
+    /// Address of the executing contract
+    pub address: Address,
+    pub pc: usize,
+    pub opcode: u8,
+    /// Gas left before paying for the operation
+    pub gas_remaining: u64,
+    /// Stack items, with the top of the stack last
+    pub stack: &'a [U256],
+    pub memory: &'a [u8],
+    /// Data returned by the last call made by the current frame
+    pub return_data: &'a [u8],
+    /// Gas to be refunded at the end of the transaction so far
+    pub gas_refund: u64,
+}
+
+/// Hooks called during the execution of a transaction.
+///
+/// Every method does nothing by default, so implementors only need to override the
+/// events they're interested in.
+pub trait Inspector {
+    /// Called before entering a call frame
+    fn call(&mut self, _inputs: &CallInputs) {}
+
+    /// Called after a call frame returns, with the gas it used and its output
+    fn call_end(&mut self, _inputs: &CallInputs, _result: &ExecutionResult) {}
+
+    /// Called before running the initialization code of a new contract
+    fn create(&mut self, _inputs: &CreateInputs) {}
+
+    /// Called after the initialization code of a new contract returns
+    fn create_end(&mut self, _inputs: &CreateInputs, _result: &ExecutionResult) {}
+
+    /// Called when a `LOG` opcode emits a log
+    fn log(&mut self, _log: &Log) {}
+
+    /// Called when `contract` self-destructs, sending its balance to `target`
+    fn selfdestruct(&mut self, _contract: Address, _target: Address, _value: EU256) {}
+
+    /// Called before executing each operation. This is only called when
+    /// [`Inspector::step_enabled`] returns true.
+    fn step(&mut self, _step: &Step) {}
+
+    /// Whether programs should be compiled with calls to [`Inspector::step`].
+    fn step_enabled(&self) -> bool {
+        false
+    }
+}
+
+/// Inspector that ignores every event
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoOpInspector;
+
+impl Inspector for NoOpInspector {}
+
+/// Allows keeping a handle to the inspector to read its results after the execution
+impl<I: Inspector> Inspector for Arc<Mutex<I>> {
+    fn call(&mut self, inputs: &CallInputs) {
+        self.lock().unwrap().call(inputs)
+    }
+
+    fn call_end(&mut self, inputs: &CallInputs, result: &ExecutionResult) {
+        self.lock().unwrap().call_end(inputs, result)
+    }
+
+    fn create(&mut self, inputs: &CreateInputs) {
+        self.lock().unwrap().create(inputs)
+    }
+
+    fn create_end(&mut self, inputs: &CreateInputs, result: &ExecutionResult) {
+        self.lock().unwrap().create_end(inputs, result)
+    }
+
+    fn log(&mut self, log: &Log) {
+        self.lock().unwrap().log(log)
+    }
+
+    fn selfdestruct(&mut self, contract: Address, target: Address, value: EU256) {
+        self.lock().unwrap().selfdestruct(contract, target, value)
+    }
+
+    fn step(&mut self, step: &Step) {
+        self.lock().unwrap().step(step)
+    }
+
+    fn step_enabled(&self) -> bool {
+        self.lock().unwrap().step_enabled()
+    }
+}
+
+impl fmt::Debug for dyn Inspector + Send {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Inspector")
+            .field("step_enabled", &self.step_enabled())
+            .finish()
+    }
+}
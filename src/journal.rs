@@ -1,13 +1,15 @@
 use crate::{
     constants::EMPTY_CODE_HASH_STR,
-    db::{AccountInfo, Bytecode, Database, Db},
+    db::{AccountInfo, Bytecode, Database, DatabaseError},
     env::AccessList,
     primitives::{Address, B256, U256},
     state::{Account, AccountStatus, EvmStorageSlot},
+    utils::delegated_address,
 };
 
 use sha3::{Digest, Keccak256};
-use std::collections::{hash_map::Entry, HashMap};
+use std::collections::HashMap;
+use std::fmt;
 use std::str::FromStr;
 
 #[derive(Clone, Default, Debug, PartialEq)]
@@ -81,12 +83,114 @@ impl From<&JournalAccount> for AccountInfo {
 type AccountState = HashMap<Address, JournalAccount>;
 type ContractState = HashMap<B256, Bytecode>;
 
-#[derive(Default, Debug)]
+/// A change made to the accounts, holding what's needed to undo it
+#[derive(Debug, Clone, PartialEq)]
+enum JournalEntry {
+    /// The account was loaded from the database
+    AccountLoaded { address: Address },
+    /// The account was inserted, replacing `previous` if it was already there
+    AccountInserted {
+        address: Address,
+        previous: Option<JournalAccount>,
+    },
+    BalanceChanged {
+        address: Address,
+        balance: U256,
+        status: AccountStatus,
+    },
+    NonceChanged {
+        address: Address,
+        nonce: u64,
+        status: AccountStatus,
+    },
+    StatusChanged {
+        address: Address,
+        status: AccountStatus,
+    },
+    CodeChanged {
+        address: Address,
+        bytecode_hash: B256,
+        status: AccountStatus,
+    },
+    /// The storage slot was read or written, `previous` is `None` if it wasn't warm
+    StorageChanged {
+        address: Address,
+        key: U256,
+        previous: Option<JournalStorageSlot>,
+    },
+}
+
+/// Position in the journal log that changes can be reverted to.
+///
+/// Checkpoints must be committed or reverted in the reverse order they were taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JournalCheckpoint {
+    log_index: usize,
+    /// Amount of checkpoints open when this one was taken
+    depth: usize,
+}
+
+/// Object safe version of [`Database`], with errors converted to [`DatabaseError`].
+///
+/// Compiled programs access the state through syscalls that can't be generic, so the
+/// journal holds the database behind this trait.
+trait DynDatabase {
+    fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, DatabaseError>;
+
+    fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytecode, DatabaseError>;
+
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, DatabaseError>;
+
+    fn block_hash(&mut self, number: U256) -> Result<B256, DatabaseError>;
+}
+
+impl<DB> DynDatabase for DB
+where
+    DB: Database,
+    DB::Error: fmt::Display,
+{
+    fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, DatabaseError> {
+        Database::basic(self, address).map_err(DatabaseError::new)
+    }
+
+    fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytecode, DatabaseError> {
+        Database::code_by_hash(self, code_hash).map_err(DatabaseError::new)
+    }
+
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, DatabaseError> {
+        Database::storage(self, address, index).map_err(DatabaseError::new)
+    }
+
+    fn block_hash(&mut self, number: U256) -> Result<B256, DatabaseError> {
+        Database::block_hash(self, number).map_err(DatabaseError::new)
+    }
+}
+
+#[derive(Default)]
 pub struct Journal<'a> {
     accounts: AccountState,
     contracts: ContractState,
     block_hashes: HashMap<U256, B256>,
-    db: Option<&'a mut Db>,
+    db: Option<&'a mut dyn DynDatabase>,
+    /// First error returned by the database. Syscalls can't fail, so execution goes
+    /// on with default values and the error is reported in the result.
+    db_error: Option<DatabaseError>,
+    /// Changes made since the outermost open checkpoint, the latest last
+    log: Vec<JournalEntry>,
+    /// Log indexes of the checkpoints neither committed nor reverted, the innermost last
+    checkpoints: Vec<usize>,
+}
+
+impl fmt::Debug for Journal<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Journal")
+            .field("accounts", &self.accounts)
+            .field("contracts", &self.contracts)
+            .field("block_hashes", &self.block_hashes)
+            .field("db_error", &self.db_error)
+            .field("checkpoints", &self.checkpoints)
+            .finish_non_exhaustive()
+    }
 }
 
 // TODO: Handle unwraps and panics
@@ -96,13 +200,22 @@ pub struct Journal<'a> {
 //  -> For the moment we seek for something that works.
 //  -> We can optimize in the future.
 impl<'a> Journal<'a> {
-    pub fn new(db: &'a mut Db) -> Self {
+    pub fn new<DB>(db: &'a mut DB) -> Self
+    where
+        DB: Database,
+        DB::Error: fmt::Display,
+    {
         Self {
             db: Some(db),
             ..Default::default()
         }
     }
 
+    /// Returns the first error the database returned, if any
+    pub fn db_error(&self) -> Option<&DatabaseError> {
+        self.db_error.as_ref()
+    }
+
     pub fn with_prefetch(mut self, accounts: &AccessList) -> Self {
         self.accounts = Self::get_prefetch_accounts(accounts);
         self
@@ -128,16 +241,21 @@ impl<'a> Journal<'a> {
     pub fn new_account(&mut self, address: Address, balance: U256) {
         // TODO: Check if account already exists and return error or panic
         let account = JournalAccount::new_created(balance);
-        self.accounts.insert(address, account);
+        self.insert_account(address, account);
     }
 
     pub fn add_account_as_warm(&mut self, address: Address) {
-        let account = self
-            .accounts
-            .entry(address)
-            .or_insert(JournalAccount::new_created(U256::zero()));
-
-        account.status &= !AccountStatus::Cold;
+        match self.accounts.get(&address) {
+            Some(account) => {
+                let status = account.status;
+                self.update_status(&address, status & !AccountStatus::Cold);
+            }
+            None => {
+                let mut account = JournalAccount::new_created(U256::zero());
+                account.status &= !AccountStatus::Cold;
+                self.insert_account(address, account);
+            }
+        }
     }
 
     pub fn new_contract(&mut self, address: Address, bytecode: Bytecode, balance: U256) {
@@ -152,51 +270,162 @@ impl<'a> Journal<'a> {
             ..Default::default()
         };
 
-        self.accounts.insert(address, account);
+        self.insert_account(address, account);
         self.contracts.insert(hash, bytecode);
     }
 
     pub fn set_balance(&mut self, address: &Address, balance: U256) {
-        if let Some(acc) = self._get_account_mut(address) {
-            acc.balance = balance;
-            acc.status |= AccountStatus::Touched;
+        let Some(acc) = self._get_account_mut(address) else {
+            return;
+        };
+        let entry = JournalEntry::BalanceChanged {
+            address: *address,
+            balance: acc.balance,
+            status: acc.status,
+        };
+        acc.balance = balance;
+        acc.status |= AccountStatus::Touched;
+        self.record(entry);
+    }
+
+    /// Moves `value` from the balance of `from` to the one of `to`. The balances are read
+    /// at the time of the transfer, so the accounts may be the same.
+    ///
+    /// Returns `false` without changing anything if either account doesn't exist or
+    /// `from` can't afford the value.
+    pub fn transfer(&mut self, from: &Address, to: &Address, value: U256) -> bool {
+        if self._get_account(to).is_none() {
+            return false;
         }
+        let Some(from_balance) = self
+            ._get_account(from)
+            .and_then(|acc| acc.balance.checked_sub(value))
+        else {
+            return false;
+        };
+        self.set_balance(from, from_balance);
+        let to_balance = self
+            ._get_account(to)
+            .map_or(U256::zero(), |acc| acc.balance);
+        self.set_balance(to, to_balance + value);
+        true
     }
 
     pub fn set_nonce(&mut self, address: &Address, nonce: u64) {
-        if let Some(acc) = self._get_account_mut(address) {
-            acc.nonce = nonce;
-            acc.status |= AccountStatus::Touched;
-        }
+        let Some(acc) = self._get_account_mut(address) else {
+            return;
+        };
+        let entry = JournalEntry::NonceChanged {
+            address: *address,
+            nonce: acc.nonce,
+            status: acc.status,
+        };
+        acc.nonce = nonce;
+        acc.status |= AccountStatus::Touched;
+        self.record(entry);
+    }
+
+    pub fn set_code(&mut self, address: &Address, bytecode: Bytecode) {
+        let hash = if bytecode.is_empty() {
+            B256::from_str(EMPTY_CODE_HASH_STR).unwrap()
+        } else {
+            let mut hasher = Keccak256::new();
+            hasher.update(&bytecode);
+            B256::from_slice(&hasher.finalize())
+        };
+        let Some(acc) = self._get_account_mut(address) else {
+            return;
+        };
+        let entry = JournalEntry::CodeChanged {
+            address: *address,
+            bytecode_hash: acc.bytecode_hash,
+            status: acc.status,
+        };
+        acc.bytecode_hash = hash;
+        acc.status |= AccountStatus::Touched;
+        self.record(entry);
+        self.contracts.insert(hash, bytecode);
     }
 
     pub fn set_status(&mut self, address: &Address, status: AccountStatus) {
-        if let Some(acc) = self._get_account_mut(address) {
-            acc.status |= status;
+        if let Some(acc) = self._get_account(address) {
+            let status = acc.status | status;
+            self.update_status(address, status);
         }
     }
 
+    pub fn account_is_selfdestructed(&self, address: &Address) -> bool {
+        self.accounts
+            .get(address)
+            .is_some_and(|acc| acc.status.contains(AccountStatus::SelfDestructed))
+    }
+
+    /// Whether the account was created in this transaction
+    pub fn account_is_created(&self, address: &Address) -> bool {
+        self.accounts
+            .get(address)
+            .is_some_and(|acc| acc.status.contains(AccountStatus::Created))
+    }
+
+    /// Whether deploying a contract at `address` collides with the account there, which
+    /// happens if it has a nonce, code or storage (EIP-684, EIP-7610). Accounts without
+    /// them, like the ones created by accessing them, can be deployed to. The database
+    /// can't tell whether an account has storage, so only the slots loaded are checked.
+    pub fn account_collides(&mut self, address: &Address) -> bool {
+        self._get_account(address).is_some_and(|acc| {
+            acc.nonce != 0
+                || acc.has_code()
+                || acc
+                    .storage
+                    .values()
+                    .any(|slot| !slot.present_value.is_zero())
+        })
+    }
+
     pub fn get_account(&mut self, address: &Address) -> Option<AccountInfo> {
         self._get_account(address).map(AccountInfo::from)
     }
 
+    /// Code executed when calling the account. For accounts delegating to another one
+    /// (EIP-7702) this is the code of the delegate.
     pub fn code_by_address(&mut self, address: &Address) -> Bytecode {
+        let code = self.account_code(address);
+        match delegated_address(&code) {
+            Some(delegate) => self.account_code(&delegate),
+            None => code,
+        }
+    }
+
+    /// Address the account delegates its execution to, if its code is a delegation designator
+    pub fn delegated_address(&mut self, address: &Address) -> Option<Address> {
+        delegated_address(&self.account_code(address))
+    }
+
+    /// Code stored in the account, without following delegations
+    pub fn account_code(&mut self, address: &Address) -> Bytecode {
         let default = Bytecode::default();
-        let Some(acc) = self._get_account_mut(address) else {
+        let Some(acc) = self._get_account(address) else {
             return default;
         };
-        acc.status &= !AccountStatus::Cold;
-        if !acc.has_code() {
+        let (status, has_code, hash) = (acc.status, acc.has_code(), acc.bytecode_hash);
+        self.update_status(address, status & !AccountStatus::Cold);
+        if !has_code {
             return default;
         }
 
-        let hash = acc.bytecode_hash;
-        self.contracts.get(&hash).cloned().unwrap_or(
-            self.db
-                .as_mut()
-                .and_then(|db| db.code_by_hash(hash).ok())
-                .unwrap_or(default),
-        )
+        if let Some(code) = self.contracts.get(&hash) {
+            return code.clone();
+        }
+        let Some(db) = self.db.as_mut() else {
+            return default;
+        };
+        match db.code_by_hash(hash) {
+            Ok(code) => code,
+            Err(error) => {
+                self.record_db_error(error);
+                default
+            }
+        }
     }
 
     /* WARM COLD HANDLING */
@@ -217,13 +446,10 @@ impl<'a> Journal<'a> {
             return;
         };
 
-        let slots: HashMap<U256, JournalStorageSlot> = keys
-            .iter()
-            .map(|key| (*key, self._fetch_storage_from_db(address, key)))
-            .collect();
-
-        let acc = self._get_account_mut(address).unwrap();
-        acc.storage.extend(slots);
+        for key in keys {
+            let slot = self._fetch_storage_from_db(address, key);
+            self.insert_slot(address, *key, slot);
+        }
     }
 
     // We ignore the `EvmStorageSlot::is_cold` attribute
@@ -244,8 +470,7 @@ impl<'a> Journal<'a> {
             .get(key)
             .cloned()
             .unwrap_or(self._fetch_storage_from_db(address, key));
-        let acc = self._get_account_mut(address).unwrap();
-        acc.storage.insert(*key, slot.clone()); // Now this key is warm
+        self.insert_slot(address, *key, slot.clone()); // Now this key is warm
         Some(slot)
     }
 
@@ -258,9 +483,9 @@ impl<'a> Journal<'a> {
             .unwrap_or(self._fetch_storage_from_db(address, &key));
 
         slot.present_value = value;
-        let acc = self._get_account_mut(address).unwrap();
-        acc.storage.insert(key, slot.clone());
-        acc.status |= AccountStatus::Touched;
+        let status = self._get_account(address).unwrap().status;
+        self.insert_slot(address, key, slot);
+        self.update_status(address, status | AccountStatus::Touched);
     }
 
     /* BLOCK HASH */
@@ -269,11 +494,14 @@ impl<'a> Journal<'a> {
         match self.block_hashes.get(number).cloned() {
             Some(hash) => hash,
             None => {
-                let block_hash = self
-                    .db
-                    .as_mut()
-                    .and_then(|db| db.block_hash(*number).ok())
-                    .unwrap_or_default();
+                let block_hash = match self.db.as_mut().map(|db| db.block_hash(*number)) {
+                    Some(Ok(hash)) => hash,
+                    Some(Err(error)) => {
+                        self.record_db_error(error);
+                        B256::default()
+                    }
+                    None => B256::default(),
+                };
                 self.block_hashes.insert(*number, block_hash);
                 block_hash
             }
@@ -323,63 +551,385 @@ impl<'a> Journal<'a> {
             .collect()
     }
 
-    pub fn eject_base(&mut self) -> Self {
-        Self {
-            accounts: self.accounts.clone(),
-            contracts: self.contracts.clone(),
-            block_hashes: self.block_hashes.clone(),
-            db: self.db.take(),
+    /* CHECKPOINTS */
+
+    /// Starts recording changes so they can be reverted with [`Journal::revert_checkpoint`]
+    pub fn checkpoint(&mut self) -> JournalCheckpoint {
+        let checkpoint = JournalCheckpoint {
+            log_index: self.log.len(),
+            depth: self.checkpoints.len(),
+        };
+        self.checkpoints.push(checkpoint.log_index);
+        checkpoint
+    }
+
+    /// Keeps the changes made since `checkpoint`, which can still be reverted by
+    /// reverting an enclosing checkpoint
+    pub fn commit_checkpoint(&mut self, checkpoint: JournalCheckpoint) {
+        self.close_checkpoint(checkpoint);
+        if self.checkpoints.is_empty() {
+            self.log.clear();
         }
     }
 
-    pub fn extend_from_successful(&mut self, other: Journal<'a>) {
-        self.accounts = other.accounts;
-        self.contracts = other.contracts;
-        self.block_hashes = other.block_hashes;
-        self.db = other.db;
+    /// Undoes every change made since `checkpoint` was taken: balances, nonces, storage,
+    /// created accounts and warm addresses and keys. Cached code and block hashes are
+    /// kept, as they don't depend on the execution.
+    pub fn revert_checkpoint(&mut self, checkpoint: JournalCheckpoint) {
+        self.close_checkpoint(checkpoint);
+        let entries = self.log.split_off(checkpoint.log_index);
+        for entry in entries.into_iter().rev() {
+            self.undo(entry);
+        }
+    }
+
+    /// Takes the state to run a nested call over, with a checkpoint so its changes
+    /// can be reverted. The result must be given back through
+    /// [`Journal::extend_from_successful`] or [`Journal::extend_from_reverted`].
+    pub fn eject_base(&mut self) -> Self {
+        let mut journal = std::mem::take(self);
+        journal.checkpoint();
+        journal
     }
 
-    pub fn extend_from_reverted(&mut self, other: Journal<'a>) {
-        // TODO: Maybe warm/cold state on both addresses and storage keys should be preserved
-        // but if that's the case, then:
-        // - What happens if the reverted callee context created a new account/storage key?
-        //   -> Should the warm state be preserved? What value should we introduce into the
-        //      self.accounts HashMap? A default one?
-        //
-        // For the moment, we will just discard changes and take the database back
-        self.db = other.db
+    pub fn extend_from_successful(&mut self, mut other: Journal<'a>) {
+        if let Some(checkpoint) = other.last_checkpoint() {
+            other.commit_checkpoint(checkpoint);
+        }
+        *self = other;
+    }
+
+    pub fn extend_from_reverted(&mut self, mut other: Journal<'a>) {
+        // Database errors aren't part of the reverted state, they make the whole
+        // transaction fail
+        if let Some(checkpoint) = other.last_checkpoint() {
+            other.revert_checkpoint(checkpoint);
+        }
+        *self = other;
     }
 
     /* PRIVATE AUXILIARY METHODS */
 
+    fn record_db_error(&mut self, error: DatabaseError) {
+        self.db_error.get_or_insert(error);
+    }
+
+    fn last_checkpoint(&self) -> Option<JournalCheckpoint> {
+        self.checkpoints.last().map(|&log_index| JournalCheckpoint {
+            log_index,
+            depth: self.checkpoints.len() - 1,
+        })
+    }
+
+    /// Closes `checkpoint` along with any checkpoint taken after it
+    fn close_checkpoint(&mut self, checkpoint: JournalCheckpoint) {
+        self.checkpoints.truncate(checkpoint.depth);
+    }
+
+    /// Adds a change to the log, as long as there's a checkpoint it can be reverted to
+    fn record(&mut self, entry: JournalEntry) {
+        if !self.checkpoints.is_empty() {
+            self.log.push(entry);
+        }
+    }
+
+    fn insert_account(&mut self, address: Address, account: JournalAccount) {
+        let previous = self.accounts.insert(address, account);
+        self.record(JournalEntry::AccountInserted { address, previous });
+    }
+
+    fn update_status(&mut self, address: &Address, status: AccountStatus) {
+        let Some(acc) = self.accounts.get_mut(address) else {
+            return;
+        };
+        if acc.status == status {
+            return;
+        }
+        let entry = JournalEntry::StatusChanged {
+            address: *address,
+            status: acc.status,
+        };
+        acc.status = status;
+        self.record(entry);
+    }
+
+    fn insert_slot(&mut self, address: &Address, key: U256, slot: JournalStorageSlot) {
+        let Some(acc) = self.accounts.get_mut(address) else {
+            return;
+        };
+        let previous = acc.storage.insert(key, slot);
+        self.record(JournalEntry::StorageChanged {
+            address: *address,
+            key,
+            previous,
+        });
+    }
+
+    fn undo(&mut self, entry: JournalEntry) {
+        match entry {
+            JournalEntry::AccountLoaded { address } => {
+                self.accounts.remove(&address);
+            }
+            JournalEntry::AccountInserted { address, previous } => match previous {
+                Some(account) => {
+                    self.accounts.insert(address, account);
+                }
+                None => {
+                    self.accounts.remove(&address);
+                }
+            },
+            JournalEntry::BalanceChanged {
+                address,
+                balance,
+                status,
+            } => {
+                if let Some(acc) = self.accounts.get_mut(&address) {
+                    acc.balance = balance;
+                    acc.status = status;
+                }
+            }
+            JournalEntry::NonceChanged {
+                address,
+                nonce,
+                status,
+            } => {
+                if let Some(acc) = self.accounts.get_mut(&address) {
+                    acc.nonce = nonce;
+                    acc.status = status;
+                }
+            }
+            JournalEntry::StatusChanged { address, status } => {
+                if let Some(acc) = self.accounts.get_mut(&address) {
+                    acc.status = status;
+                }
+            }
+            JournalEntry::CodeChanged {
+                address,
+                bytecode_hash,
+                status,
+            } => {
+                if let Some(acc) = self.accounts.get_mut(&address) {
+                    acc.bytecode_hash = bytecode_hash;
+                    acc.status = status;
+                }
+            }
+            JournalEntry::StorageChanged {
+                address,
+                key,
+                previous,
+            } => {
+                if let Some(acc) = self.accounts.get_mut(&address) {
+                    match previous {
+                        Some(slot) => acc.storage.insert(key, slot),
+                        None => acc.storage.remove(&key),
+                    };
+                }
+            }
+        }
+    }
+
     fn _get_account(&mut self, address: &Address) -> Option<&JournalAccount> {
         self._get_account_mut(address).map(|acc| &*acc)
     }
 
     fn _get_account_mut(&mut self, address: &Address) -> Option<&mut JournalAccount> {
-        let Some(db) = &mut self.db else {
-            return None;
-        };
+        let db = self.db.as_mut()?;
+
+        if !self.accounts.contains_key(address) {
+            let acc = match db.basic(*address) {
+                Ok(acc) => acc?,
+                Err(error) => {
+                    self.db_error.get_or_insert(error);
+                    return None;
+                }
+            };
+            let mut acc = JournalAccount::from(acc);
+            acc.status = AccountStatus::Loaded;
+            self.accounts.insert(*address, acc);
+            self.record(JournalEntry::AccountLoaded { address: *address });
+        }
 
-        let maybe_acc = match self.accounts.entry(*address) {
-            Entry::Occupied(e) => Some(e.into_mut()),
-            Entry::Vacant(e) => {
-                let acc = db.basic(*address).ok().flatten()?;
-                let mut acc = JournalAccount::from(acc);
-                acc.status = AccountStatus::Loaded;
-                Some(e.insert(acc))
+        self.accounts.get_mut(address)
+    }
+
+    fn _fetch_storage_from_db(&mut self, address: &Address, key: &U256) -> JournalStorageSlot {
+        let value = match self.db.as_mut().map(|db| db.storage(*address, *key)) {
+            Some(Ok(value)) => value,
+            Some(Err(error)) => {
+                self.record_db_error(error);
+                U256::default()
             }
+            None => U256::default(),
         };
+        JournalStorageSlot::from(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::Db, utils::delegation_designator};
 
-        maybe_acc
+    fn db_with_account(address: Address) -> Db {
+        let mut db = Db::new();
+        let storage = HashMap::from([(U256::from(1), U256::from(10))]);
+        db.set_account(address, 1, U256::from(100), storage);
+        db
     }
 
-    fn _fetch_storage_from_db(&mut self, address: &Address, key: &U256) -> JournalStorageSlot {
-        let value = self
-            .db
-            .as_mut()
-            .and_then(|db| db.storage(*address, *key).ok())
-            .unwrap_or_default();
-        JournalStorageSlot::from(value)
+    #[test]
+    fn revert_checkpoint_restores_the_previous_state() {
+        let address = Address::from_low_u64_be(1);
+        let created = Address::from_low_u64_be(2);
+        let mut db = db_with_account(address);
+        let mut journal = Journal::new(&mut db);
+        journal.prefetch_account(&address);
+        let before = journal.into_state();
+
+        let checkpoint = journal.checkpoint();
+        journal.set_balance(&address, U256::from(50));
+        journal.set_nonce(&address, 2);
+        journal.write_storage(&address, U256::from(1), U256::from(20));
+        journal.read_storage(&address, &U256::from(2));
+        journal.new_account(created, U256::from(5));
+        journal.add_account_as_warm(Address::from_low_u64_be(3));
+        journal.revert_checkpoint(checkpoint);
+
+        assert_eq!(journal.into_state(), before);
+        assert!(!journal.key_is_warm(&address, &U256::from(2)));
+        assert!(!journal.account_is_warm(&created));
+    }
+
+    #[test]
+    fn reverting_an_outer_checkpoint_undoes_committed_inner_ones() {
+        let address = Address::from_low_u64_be(1);
+        let mut db = db_with_account(address);
+        let mut journal = Journal::new(&mut db);
+
+        let outer = journal.checkpoint();
+        journal.set_balance(&address, U256::from(50));
+        let inner = journal.checkpoint();
+        journal.set_balance(&address, U256::from(20));
+        journal.commit_checkpoint(inner);
+        assert_eq!(
+            journal.get_account(&address).unwrap().balance,
+            U256::from(20)
+        );
+
+        journal.revert_checkpoint(outer);
+        assert!(journal.into_state().is_empty());
+    }
+
+    #[test]
+    fn transfers_read_the_balances_when_made() {
+        let address = Address::from_low_u64_be(1);
+        let receiver = Address::from_low_u64_be(2);
+        let mut db = db_with_account(address);
+        db.set_account(receiver, 0, U256::from(5), HashMap::new());
+        let mut journal = Journal::new(&mut db);
+
+        assert!(journal.transfer(&address, &address, U256::from(30)));
+        assert!(journal.transfer(&address, &receiver, U256::from(30)));
+
+        assert_eq!(
+            journal.get_account(&address).unwrap().balance,
+            U256::from(70)
+        );
+        assert_eq!(
+            journal.get_account(&receiver).unwrap().balance,
+            U256::from(35)
+        );
+    }
+
+    #[test]
+    fn only_accounts_with_nonce_code_or_storage_collide() {
+        let address = Address::from_low_u64_be(1);
+        let accessed = Address::from_low_u64_be(2);
+        let written = Address::from_low_u64_be(3);
+        let mut db = db_with_account(address);
+        let mut journal = Journal::new(&mut db);
+        journal.add_account_as_warm(accessed);
+        journal.add_account_as_warm(written);
+        journal.write_storage(&written, U256::from(1), U256::from(20));
+
+        assert!(journal.account_collides(&address));
+        assert!(!journal.account_collides(&accessed));
+        assert!(journal.account_collides(&written));
+        assert!(!journal.account_collides(&Address::from_low_u64_be(4)));
+    }
+
+    #[test]
+    fn failed_transfers_keep_the_balances() {
+        let address = Address::from_low_u64_be(1);
+        let receiver = Address::from_low_u64_be(2);
+        let missing = Address::from_low_u64_be(3);
+        let mut db = db_with_account(address);
+        db.set_account(receiver, 0, U256::from(5), HashMap::new());
+        let mut journal = Journal::new(&mut db);
+        journal.checkpoint();
+
+        assert!(!journal.transfer(&address, &receiver, U256::from(101)));
+        assert!(!journal.transfer(&address, &missing, U256::from(30)));
+        assert!(!journal.transfer(&missing, &receiver, U256::zero()));
+
+        assert_eq!(
+            journal.get_account(&address).unwrap().balance,
+            U256::from(100)
+        );
+        assert_eq!(
+            journal.get_account(&receiver).unwrap().balance,
+            U256::from(5)
+        );
+        assert!(journal
+            .log
+            .iter()
+            .all(|entry| matches!(entry, JournalEntry::AccountLoaded { .. })));
+    }
+
+    #[test]
+    fn nested_call_changes_are_kept_or_discarded() {
+        let address = Address::from_low_u64_be(1);
+        let mut db = db_with_account(address);
+        let mut journal = Journal::new(&mut db);
+        journal.set_balance(&address, U256::from(90));
+
+        let mut callee = journal.eject_base();
+        callee.write_storage(&address, U256::from(1), U256::from(20));
+        journal.extend_from_reverted(callee);
+        let slot = journal.read_storage(&address, &U256::from(1)).unwrap();
+        assert_eq!(slot.present_value, U256::from(10));
+        assert_eq!(
+            journal.get_account(&address).unwrap().balance,
+            U256::from(90)
+        );
+
+        let mut callee = journal.eject_base();
+        callee.write_storage(&address, U256::from(1), U256::from(20));
+        journal.extend_from_successful(callee);
+        let slot = journal.read_storage(&address, &U256::from(1)).unwrap();
+        assert_eq!(slot.present_value, U256::from(20));
+        assert!(journal.log.is_empty());
+    }
+
+    #[test]
+    fn code_by_address_follows_delegations() {
+        let authority = Address::from_low_u64_be(1);
+        let delegate = Address::from_low_u64_be(2);
+        let code = Bytecode::from_static(&[0x60, 0x01]);
+        let mut db = db_with_account(authority).with_contract(delegate, code.clone());
+        let mut journal = Journal::new(&mut db);
+
+        let checkpoint = journal.checkpoint();
+        journal.set_code(&authority, delegation_designator(delegate));
+        assert_eq!(journal.delegated_address(&authority), Some(delegate));
+        assert_eq!(journal.code_by_address(&authority), code);
+        assert_eq!(
+            journal.account_code(&authority),
+            delegation_designator(delegate)
+        );
+
+        journal.revert_checkpoint(checkpoint);
+        assert_eq!(journal.delegated_address(&authority), None);
+        assert!(journal.code_by_address(&authority).is_empty());
     }
 }
@@ -1,23 +1,35 @@
+use std::{collections::HashMap, fmt, sync::Arc};
+
 use builder::EvmBuilder;
-use db::{Database, DatabaseError, Db};
-use env::TransactTo;
-use executor::{Executor, OptLevel};
+use cache::{CacheStats, ExecutorCache, SharedExecutorCache};
+use constants::{CallType, SYSTEM_ADDRESS, SYSTEM_CALL_GAS_LIMIT};
+use db::{Database, DatabaseCommit};
+use env::{TransactTo, TxEnv};
+use inspector::{BoxedInspector, CallInputs};
 use journal::Journal;
-use program::Program;
-use result::{EVMError, ExecutionResult, ResultAndState};
+use precompiles::Precompiles;
+use primitives::{Address, Bytes};
+use result::{EVMError, ExecutionResult, Output, ResultAndState, SuccessReason};
+use spec::SpecId;
 use syscall::{CallFrame, SyscallContext};
 
-use crate::context::Context;
-
+pub mod block;
 pub mod builder;
+pub mod cache;
+pub mod cache_db;
 pub mod codegen;
 pub mod constants;
 pub mod context;
 pub mod db;
+pub mod disk_db;
 pub mod env;
+pub mod eof;
 pub mod errors;
 pub mod executor;
+pub mod fork;
+pub mod inspector;
 pub mod module;
+pub mod overrides;
 pub mod primitives;
 pub mod program;
 pub mod syscall;
@@ -26,12 +38,24 @@ pub use env::Env;
 pub mod journal;
 pub mod precompiles;
 pub mod result;
+pub mod simulation;
+pub mod spec;
 pub mod state;
+pub mod transaction;
+pub mod trie;
+
+#[cfg(test)]
+mod test_utils;
 
 #[derive(Debug)]
 pub struct Evm<DB: Database> {
     pub env: Env,
     pub db: DB,
+    pub executor_cache: SharedExecutorCache,
+    /// Precompiles that can be called. If `None`, the mainnet ones of the
+    /// [configured spec](env::CfgEnv::spec_id) are used.
+    pub precompiles: Option<Arc<Precompiles>>,
+    inspector: Option<BoxedInspector>,
 }
 
 impl<DB: Database + Default> Evm<DB> {
@@ -39,16 +63,41 @@ impl<DB: Database + Default> Evm<DB> {
     pub fn builder() -> EvmBuilder<DB> {
         EvmBuilder::default()
     }
+}
 
+impl<DB: Database> Evm<DB> {
     /// Creates a new EVM instance with the given environment and database.
     pub fn new(env: Env, db: DB) -> Self {
-        Self { env, db }
+        Self {
+            env,
+            db,
+            executor_cache: ExecutorCache::shared(Default::default()),
+            precompiles: None,
+            inspector: None,
+        }
+    }
+
+    /// Returns the hit/miss statistics of the compiled executors cache.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.executor_cache.lock().unwrap().stats()
     }
 }
 
-impl Evm<Db> {
+impl<DB> Evm<DB>
+where
+    DB: Database,
+    DB::Error: fmt::Display,
+{
     fn validate_transaction(&mut self) -> Result<u64, EVMError> {
         self.env.validate_transaction()?;
+        let caller_address = self.env.tx.caller;
+        let mut journal = Journal::new(&mut self.db);
+        let caller = journal.get_account(&caller_address).unwrap_or_default();
+        let caller_code = journal.account_code(&caller_address);
+        if let Some(error) = journal.db_error() {
+            return Err(EVMError::Database(error.clone()));
+        }
+        self.env.validate_tx_against_state(&caller, &caller_code)?;
         let initial_gas_consumed = self.env.consume_intrinsic_cost()?;
 
         Ok(initial_gas_consumed)
@@ -56,41 +105,79 @@ impl Evm<Db> {
 
     fn create_syscall_context(&mut self, initial_gas: u64) -> SyscallContext {
         let call_frame = CallFrame::new(self.env.tx.caller);
+        let inspector = self.inspector.take();
         let journal = Journal::new(&mut self.db).with_prefetch(&self.env.tx.access_list);
-        SyscallContext::new(self.env.clone(), journal, call_frame, initial_gas)
+        let mut context = SyscallContext::new(self.env.clone(), journal, call_frame, initial_gas)
+            .with_executor_cache(self.executor_cache.clone());
+        if let Some(precompiles) = self.precompiles.clone() {
+            context = context.with_precompiles(precompiles);
+        }
+        match inspector {
+            Some(inspector) => context.with_inspector(inspector),
+            None => context,
+        }
     }
 
-    fn run_program(
-        &mut self,
-        program: Program,
-        initial_gas_consumed: u64,
-    ) -> Result<ResultAndState, EVMError> {
-        let context = Context::new();
-        let module = context
-            .compile(&program, Default::default())
-            .expect("failed to compile program");
+    fn call(&mut self, initial_gas_consumed: u64) -> Result<ResultAndState, EVMError> {
+        let steps_enabled = self
+            .inspector
+            .as_ref()
+            .is_some_and(|inspector| inspector.step_enabled());
+        let code_address = self.env.tx.get_address();
+        let inputs = CallInputs {
+            caller: self.env.tx.caller,
+            target_address: code_address,
+            code_address,
+            value: self.env.tx.value,
+            input: self.env.tx.data.clone(),
+            gas_limit: self.env.tx.gas_limit + initial_gas_consumed,
+            call_type: CallType::Call,
+            is_static: false,
+        };
+        if let Some(inspector) = self.inspector.as_mut() {
+            inspector.call(&inputs);
+        }
 
         let gas_limit = self.env.tx.gas_limit;
+        let spec_id = self.env.cfg.spec_id;
+        let executor_cache = self.executor_cache.clone();
         let mut context = self.create_syscall_context(gas_limit + initial_gas_consumed);
-        let executor = Executor::new(&module, &context, OptLevel::Aggressive);
+        let caller_nonce = context.charge_caller();
+        // Authorizations are kept even if the execution fails
+        context.apply_authorization_list();
+        let checkpoint = context.journal.checkpoint();
+        // The value is sent within the checkpoint, so it's returned if the execution fails
+        context.transfer_value();
 
-        // TODO: improve this once we stabilize the API a bit
-        context.inner_context.program = program.to_bytecode();
-        executor.execute(&mut context, gas_limit);
-
-        context.get_result()
-    }
+        // The code is read after applying the authorizations, since they can make the
+        // called account delegate to another one
+        let bytecode = context.journal.code_by_address(&code_address);
+        // On database errors the program isn't run, and the error is returned as the result
+        if context.journal.db_error().is_none() {
+            let code_hash = cache::code_hash(&bytecode);
+            let executor = ExecutorCache::checkout(
+                &executor_cache,
+                code_hash,
+                &bytecode,
+                spec_id,
+                steps_enabled,
+            )
+            .expect("failed to compile program");
 
-    fn call(&mut self, initial_gas_consumed: u64) -> Result<ResultAndState, EVMError> {
-        let code_address = self.env.tx.get_address();
+            // TODO: improve this once we stabilize the API a bit
+            context.inner_context.program = bytecode.to_vec();
+            executor.execute(&mut context, gas_limit);
+            executor_cache.lock().unwrap().release(code_hash, &executor);
+        }
 
-        let bytecode = match self.db.code_by_address(code_address) {
-            Ok(bytecode) => bytecode,
-            Err(_) => return Err(EVMError::Database(DatabaseError)),
-        };
+        let inspector = context.take_inspector();
+        let result = context.transaction_result(checkpoint, caller_nonce);
+        self.inspector = inspector;
 
-        let program = Program::from_bytecode(&bytecode);
-        self.run_program(program, initial_gas_consumed)
+        if let (Some(inspector), Ok(result_and_state)) = (self.inspector.as_mut(), &result) {
+            inspector.call_end(&inputs, &result_and_state.result);
+        }
+        result
     }
 
     fn get_env_value(&self) -> syscall::U256 {
@@ -111,12 +198,96 @@ impl Evm<Db> {
         let gas_limit = self.env.tx.gas_limit;
         let program = self.env.tx.data.to_vec();
         let program_size = program.len() as u32;
+        // EOF initcontainers are deployed by running them directly (EIP-7698)
+        let is_eof_creation =
+            self.env.cfg.spec_id.is_enabled_in(SpecId::Prague) && eof::is_eof(&program);
         let mut context = self.create_syscall_context(gas_limit + initial_gas_consumed);
+        let caller_nonce = context.charge_caller();
+        let checkpoint = context.journal.checkpoint();
         context.inner_context.memory = program;
 
-        context.create(program_size, 0, &mut value, &mut remaining_gas);
+        if is_eof_creation {
+            context.eof_create_transaction(&mut remaining_gas);
+        } else {
+            context.create(program_size, 0, &mut value, &mut remaining_gas);
+        }
         context.inner_context.gas_remaining = Some(gas_limit.saturating_sub(remaining_gas));
-        context.get_result()
+
+        let inspector = context.take_inspector();
+        let result = context.transaction_result(checkpoint, caller_nonce);
+        self.inspector = inspector;
+        result
+    }
+
+    /// Calls the system `contract` with `data` as [`SYSTEM_ADDRESS`], like the protocol
+    /// does at the start of each block. System calls aren't validated nor charged, and
+    /// the nonce of the system address isn't bumped. If the contract has no code, nothing
+    /// is executed.
+    pub fn system_call(
+        &mut self,
+        contract: Address,
+        data: Bytes,
+    ) -> Result<ResultAndState, EVMError> {
+        let system_tx = TxEnv {
+            caller: Address::from(SYSTEM_ADDRESS),
+            gas_limit: SYSTEM_CALL_GAS_LIMIT,
+            transact_to: TransactTo::Call(contract),
+            data,
+            ..Default::default()
+        };
+        let tx = std::mem::replace(&mut self.env.tx, system_tx);
+        let result = self.run_system_call(contract);
+        self.env.tx = tx;
+        result
+    }
+
+    fn run_system_call(&mut self, contract: Address) -> Result<ResultAndState, EVMError> {
+        let mut journal = Journal::new(&mut self.db);
+        let bytecode = journal.code_by_address(&contract);
+        if let Some(error) = journal.db_error() {
+            return Err(EVMError::Database(error.clone()));
+        }
+        if bytecode.is_empty() {
+            return Ok(ResultAndState {
+                result: ExecutionResult::Success {
+                    reason: SuccessReason::Stop,
+                    gas_used: 0,
+                    gas_refunded: 0,
+                    logs: Vec::new(),
+                    output: Output::Call(Bytes::new()),
+                },
+                state: HashMap::new(),
+                fees: Default::default(),
+            });
+        }
+
+        let gas_limit = self.env.tx.gas_limit;
+        let spec_id = self.env.cfg.spec_id;
+        let steps_enabled = self
+            .inspector
+            .as_ref()
+            .is_some_and(|inspector| inspector.step_enabled());
+        let executor_cache = self.executor_cache.clone();
+        let mut context = self.create_syscall_context(gas_limit);
+        let checkpoint = context.journal.checkpoint();
+
+        let code_hash = cache::code_hash(&bytecode);
+        let executor = ExecutorCache::checkout(
+            &executor_cache,
+            code_hash,
+            &bytecode,
+            spec_id,
+            steps_enabled,
+        )
+        .expect("failed to compile program");
+        context.inner_context.program = bytecode.to_vec();
+        executor.execute(&mut context, gas_limit);
+        executor_cache.lock().unwrap().release(code_hash, &executor);
+
+        let inspector = context.take_inspector();
+        let result = context.system_call_result(checkpoint);
+        self.inspector = inspector;
+        result
     }
 
     /// Executes [the configured transaction](Env::tx).
@@ -127,9 +298,16 @@ impl Evm<Db> {
             TransactTo::Create => self.create(initial_gas_consumed),
         }
     }
+}
 
+impl<DB> Evm<DB>
+where
+    DB: Database + DatabaseCommit,
+    DB::Error: fmt::Display,
+{
+    /// Executes [the configured transaction](Env::tx) and commits its changes to the database.
     pub fn transact_commit(&mut self) -> Result<ExecutionResult, EVMError> {
-        let ResultAndState { state, result } = self.transact()?;
+        let ResultAndState { state, result, .. } = self.transact()?;
         self.db.commit(state);
         Ok(result)
     }
@@ -1,17 +1,29 @@
 use std::path::PathBuf;
 
 use evm_mlir::{
+    cache::CacheConfig,
     context::{Context, Session},
-    db::Db,
-    env::Env,
+    db::{Bytecode, Db},
+    env::{Env, TransactTo},
     executor::{Executor, OptLevel},
+    inspector::TracerEip3155,
     journal::Journal,
+    primitives::Address,
     program::Program,
     syscall::SyscallContext,
+    Evm,
 };
 
 fn main() {
-    let args: Vec<String> = std::env::args().collect();
+    let mut args: Vec<String> = std::env::args().collect();
+    // With `--trace`, an EIP-3155 trace of the execution is written to stderr
+    let trace = match args.iter().position(|arg| arg == "--trace") {
+        Some(index) => {
+            args.remove(index);
+            true
+        }
+        None => false,
+    };
     let path = args.get(1).expect("No path provided").as_str();
     let opt_level = match args.get(2).map(String::as_str) {
         None | Some("2") => OptLevel::Default,
@@ -21,6 +33,12 @@ fn main() {
         _ => panic!("Invalid optimization level"),
     };
     let bytecode = std::fs::read(path).expect("Could not read file");
+
+    if trace {
+        run_traced(bytecode, opt_level);
+        return;
+    }
+
     let program = Program::from_bytecode(&bytecode);
 
     let session = Session {
@@ -38,8 +56,25 @@ fn main() {
     let mut db = Db::default();
     let journal = Journal::new(&mut db);
     let mut context = SyscallContext::new(env, journal, Default::default(), initial_gas);
-    let executor = Executor::new(&module, &context, opt_level);
+    let executor = Executor::new(&module, opt_level);
 
     let result = executor.execute(&mut context, initial_gas);
     println!("Execution result: {result}");
 }
+
+fn run_traced(bytecode: Vec<u8>, opt_level: OptLevel) {
+    let address = Address::from_low_u64_be(40);
+    let db = Db::new().with_contract(address, Bytecode::from(bytecode));
+    let mut evm = Evm::builder()
+        .with_db(db)
+        .with_cache_config(CacheConfig {
+            opt_level,
+            ..Default::default()
+        })
+        .with_inspector(TracerEip3155::new(std::io::stderr()))
+        .build();
+    evm.env.tx.transact_to = TransactTo::Call(address);
+
+    let result = evm.transact().expect("failed to run transaction");
+    println!("Execution result: {:?}", result.result);
+}
@@ -0,0 +1,208 @@
+//! # State overrides
+//!
+//! Geth style state overrides, replacing parts of the state of some accounts for a
+//! single execution. An [`OverrideDb`] applies them on top of any [`Database`], so the
+//! journal reads the overridden state while the wrapped database isn't modified.
+use std::collections::HashMap;
+
+use crate::{
+    cache::code_hash,
+    db::{AccountInfo, Bytecode, Database},
+    primitives::{Address, B256, U256},
+};
+
+/// State overrides, by the address of the overridden account
+pub type StateOverride = HashMap<Address, AccountOverride>;
+
+/// Replacements for the state of an account. What is left as `None` is read from the
+/// database.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AccountOverride {
+    pub balance: Option<U256>,
+    pub nonce: Option<u64>,
+    pub code: Option<Bytecode>,
+    /// Replaces the whole storage of the account. Slots missing from it hold zero.
+    pub state: Option<HashMap<U256, U256>>,
+    /// Replaces single storage slots, on top of the [`state`](Self::state) if given
+    pub state_diff: Option<HashMap<U256, U256>>,
+}
+
+/// Database reading through [`StateOverride`]s before reading from the wrapped one.
+/// Accounts that don't exist in the wrapped database are created by their overrides.
+#[derive(Debug)]
+pub struct OverrideDb<'a, DB> {
+    db: &'a mut DB,
+    overrides: &'a StateOverride,
+}
+
+impl<'a, DB> OverrideDb<'a, DB> {
+    pub fn new(db: &'a mut DB, overrides: &'a StateOverride) -> Self {
+        Self { db, overrides }
+    }
+}
+
+impl<DB: Database> Database for OverrideDb<'_, DB> {
+    type Error = DB::Error;
+
+    fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        let account = self.db.basic(address)?;
+        let Some(account_override) = self.overrides.get(&address) else {
+            return Ok(account);
+        };
+
+        let mut account = account.unwrap_or_else(AccountInfo::empty);
+        if let Some(balance) = account_override.balance {
+            account.balance = balance;
+        }
+        if let Some(nonce) = account_override.nonce {
+            account.nonce = nonce;
+        }
+        if let Some(code) = &account_override.code {
+            account.code_hash = code_hash(code);
+            account.code = Some(code.clone());
+        }
+        Ok(Some(account))
+    }
+
+    fn code_by_hash(&mut self, hash: B256) -> Result<Bytecode, Self::Error> {
+        let overridden_code = self
+            .overrides
+            .values()
+            .filter_map(|account_override| account_override.code.as_ref())
+            .find(|code| code_hash(code) == hash);
+        match overridden_code {
+            Some(code) => Ok(code.clone()),
+            None => self.db.code_by_hash(hash),
+        }
+    }
+
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        if let Some(account_override) = self.overrides.get(&address) {
+            if let Some(value) = account_override
+                .state_diff
+                .as_ref()
+                .and_then(|state_diff| state_diff.get(&index))
+            {
+                return Ok(*value);
+            }
+            if let Some(state) = &account_override.state {
+                return Ok(state.get(&index).copied().unwrap_or_default());
+            }
+        }
+        self.db.storage(address, index)
+    }
+
+    fn block_hash(&mut self, number: U256) -> Result<B256, Self::Error> {
+        self.db.block_hash(number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Db;
+
+    fn db_with_account(address: Address) -> Db {
+        let mut db = Db::new().with_contract(address, Bytecode::from_static(&[0x00]));
+        db.set_balance(address, U256::from(10));
+        db.write_storage(address, U256::from(1), U256::from(11));
+        db.write_storage(address, U256::from(2), U256::from(12));
+        db
+    }
+
+    #[test]
+    fn overrides_replace_the_account_fields() {
+        let address = Address::from_low_u64_be(1);
+        let mut db = db_with_account(address);
+        let code = Bytecode::from_static(&[0x60, 0x00]);
+        let overrides = StateOverride::from([(
+            address,
+            AccountOverride {
+                balance: Some(U256::from(20)),
+                code: Some(code.clone()),
+                ..Default::default()
+            },
+        )]);
+        let mut override_db = OverrideDb::new(&mut db, &overrides);
+
+        let account = override_db.basic(address).unwrap().unwrap();
+
+        assert_eq!(account.balance, U256::from(20));
+        // The nonce isn't overridden
+        assert_eq!(account.nonce, 1);
+        assert_eq!(override_db.code_by_hash(account.code_hash).unwrap(), code);
+        assert_eq!(db.get_balance(address), Some(U256::from(10)));
+    }
+
+    #[test]
+    fn overrides_create_missing_accounts() {
+        let address = Address::from_low_u64_be(2);
+        let mut db = Db::new();
+        let overrides = StateOverride::from([(
+            address,
+            AccountOverride {
+                nonce: Some(3),
+                ..Default::default()
+            },
+        )]);
+        let mut override_db = OverrideDb::new(&mut db, &overrides);
+
+        let account = override_db.basic(address).unwrap().unwrap();
+
+        assert_eq!(account.nonce, 3);
+        assert!(!account.has_code());
+        assert_eq!(override_db.basic(Address::zero()).unwrap(), None);
+    }
+
+    #[test]
+    fn storage_diffs_apply_on_top_of_the_storage() {
+        let address = Address::from_low_u64_be(1);
+        let mut db = db_with_account(address);
+        let overrides = StateOverride::from([(
+            address,
+            AccountOverride {
+                state_diff: Some(HashMap::from([(U256::from(2), U256::from(22))])),
+                ..Default::default()
+            },
+        )]);
+        let mut override_db = OverrideDb::new(&mut db, &overrides);
+
+        assert_eq!(
+            override_db.storage(address, U256::from(1)).unwrap(),
+            U256::from(11)
+        );
+        assert_eq!(
+            override_db.storage(address, U256::from(2)).unwrap(),
+            U256::from(22)
+        );
+    }
+
+    #[test]
+    fn storage_overrides_replace_the_whole_storage() {
+        let address = Address::from_low_u64_be(1);
+        let mut db = db_with_account(address);
+        let overrides = StateOverride::from([(
+            address,
+            AccountOverride {
+                state: Some(HashMap::from([(U256::from(2), U256::from(22))])),
+                state_diff: Some(HashMap::from([(U256::from(3), U256::from(33))])),
+                ..Default::default()
+            },
+        )]);
+        let mut override_db = OverrideDb::new(&mut db, &overrides);
+
+        assert_eq!(
+            override_db.storage(address, U256::from(1)).unwrap(),
+            U256::zero()
+        );
+        assert_eq!(
+            override_db.storage(address, U256::from(2)).unwrap(),
+            U256::from(22)
+        );
+        assert_eq!(
+            override_db.storage(address, U256::from(3)).unwrap(),
+            U256::from(33)
+        );
+        assert_eq!(db.read_storage(address, U256::from(1)), U256::from(11));
+    }
+}
@@ -2,31 +2,52 @@ use crate::{
     constants::{
         precompiles::*,
         return_codes::{REVERT_RETURN_CODE, SUCCESS_RETURN_CODE},
+        VERSIONED_HASH_VERSION_KZG,
     },
+    env::AccessList,
     primitives::U256,
     result::PrecompileError,
+    spec::SpecId,
     utils::{left_pad, right_pad},
 };
 use bytes::Bytes;
+use c_kzg::{ethereum_kzg_settings, Bytes32, Bytes48, KzgProof};
 use ethereum_types::Address;
 use lambdaworks_math::{
     cyclic_group::IsGroup,
     elliptic_curve::{
-        short_weierstrass::curves::bn_254::{
-            curve::{BN254Curve, BN254FieldElement, BN254TwistCurveFieldElement},
-            field_extension::Degree12ExtensionField,
-            pairing::BN254AtePairing,
-            twist::BN254TwistCurve,
+        short_weierstrass::{
+            curves::{
+                bls12_381::{
+                    curve::{BLS12381Curve, BLS12381FieldElement, BLS12381TwistCurveFieldElement},
+                    field_extension::BLS12381_PRIME_FIELD_ORDER,
+                    pairing::BLS12381AtePairing,
+                    twist::BLS12381TwistCurve,
+                },
+                bn_254::{
+                    curve::{BN254Curve, BN254FieldElement, BN254TwistCurveFieldElement},
+                    field_extension::Degree12ExtensionField,
+                    pairing::BN254AtePairing,
+                    twist::BN254TwistCurve,
+                },
+            },
+            point::ShortWeierstrassProjectivePoint,
         },
         traits::{IsEllipticCurve, IsPairing},
     },
     field::{element::FieldElement, extensions::quadratic::QuadraticExtensionFieldElement},
+    msm::pippenger::msm,
     traits::ByteConversion,
-    unsigned_integer::element::U256 as LambdaWorksU256,
+    unsigned_integer::element::{U256 as LambdaWorksU256, U384},
 };
 use num_bigint::BigUint;
 use secp256k1::{ecdsa, Message, Secp256k1};
 use sha3::{Digest, Keccak256};
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, OnceLock},
+};
 
 /// ECDSA public key recovery function.
 /// More info in https://eips.ethereum.org/EIPS/eip-2, https://eips.ethereum.org/EIPS/eip-1271 and https://www.evm.codes/precompiled.
@@ -44,22 +65,31 @@ pub fn ecrecover(
     let v = calldata[ECR_V_POS] as i32 - ECR_V_BASE;
     let sig = &calldata[(ECR_V_POS + 1)..ECR_SIG_END];
 
-    let msg = Message::from_digest_slice(hash).map_err(|_| PrecompileError::Secp256k1Error)?;
-    let id = ecdsa::RecoveryId::from_i32(v).map_err(|_| PrecompileError::Secp256k1Error)?;
-    let sig = ecdsa::RecoverableSignature::from_compact(sig, id)
-        .map_err(|_| PrecompileError::Secp256k1Error)?;
+    let address = recover_address(hash, v, sig).map_err(|_| PrecompileError::Secp256k1Error)?;
+
+    *consumed_gas += ECRECOVER_COST;
+    let mut output = [0u8; 32];
+    output[ECR_PADDING_LEN..].copy_from_slice(address.as_bytes());
+    Ok(Bytes::copy_from_slice(&output))
+}
+
+/// Recovers the address of the account that signed a 32-byte hash, given the 64-byte
+/// signature `r || s` and the recovery id (0 or 1).
+pub(crate) fn recover_address(
+    hash: &[u8],
+    recovery_id: i32,
+    signature: &[u8],
+) -> Result<Address, secp256k1::Error> {
+    let msg = Message::from_digest_slice(hash)?;
+    let id = ecdsa::RecoveryId::from_i32(recovery_id)?;
+    let sig = ecdsa::RecoverableSignature::from_compact(signature, id)?;
 
     let secp = Secp256k1::new();
-    let public_address = secp
-        .recover_ecdsa(&msg, &sig)
-        .map_err(|_| PrecompileError::Secp256k1Error)?;
+    let public_key = secp.recover_ecdsa(&msg, &sig)?;
 
-    *consumed_gas += ECRECOVER_COST;
     let mut hasher = Keccak256::new();
-    hasher.update(&public_address.serialize_uncompressed()[1..]);
-    let mut address_hash = hasher.finalize();
-    address_hash[..ECR_PADDING_LEN].fill(0);
-    Ok(Bytes::copy_from_slice(&address_hash))
+    hasher.update(&public_key.serialize_uncompressed()[1..]);
+    Ok(Address::from_slice(&hasher.finalize()[12..]))
 }
 
 /// Hashing function.
@@ -542,51 +572,570 @@ pub fn blake2f(
     Ok(Bytes::from(out))
 }
 
-pub fn is_precompile(callee_address: Address) -> bool {
-    let addr_as_u64 = callee_address.to_low_u64_be();
-    // TODO: replace 10 with point evaluation address constant and include it in the range (..=10)
-    callee_address[0..12] == [0u8; 12] && (ECRECOVER_ADDRESS..10).contains(&addr_as_u64)
+/// Verifies a KZG proof that the blob committed to by a versioned hash evaluates to `y` at `z`.
+/// More info in https://eips.ethereum.org/EIPS/eip-4844#point-evaluation-precompile.
+///
+/// # Returns
+/// - FIELD_ELEMENTS_PER_BLOB and BLS_MODULUS, each as a 32-byte big endian number
+pub fn point_evaluation(
+    calldata: &Bytes,
+    gas_limit: u64,
+    consumed_gas: &mut u64,
+) -> Result<Bytes, PrecompileError> {
+    if gas_limit < POINT_EVALUATION_COST {
+        return Err(PrecompileError::NotEnoughGas);
+    }
+    if calldata.len() != POINT_EVALUATION_CALLDATA_LEN {
+        return Err(PrecompileError::InvalidCalldata);
+    }
+
+    let versioned_hash = &calldata[..PE_VERSIONED_HASH_END];
+    let commitment = &calldata[PE_Y_END..PE_COMMITMENT_END];
+    if kzg_to_versioned_hash(commitment) != versioned_hash {
+        return Err(PrecompileError::InvalidVersionedHash);
+    }
+
+    let z = Bytes32::from_slice(&calldata[PE_VERSIONED_HASH_END..PE_Z_END])
+        .map_err(|_| PrecompileError::InvalidCalldata)?;
+    let y = Bytes32::from_slice(&calldata[PE_Z_END..PE_Y_END])
+        .map_err(|_| PrecompileError::InvalidCalldata)?;
+    let commitment =
+        Bytes48::from_slice(commitment).map_err(|_| PrecompileError::InvalidCalldata)?;
+    let proof = Bytes48::from_slice(&calldata[PE_COMMITMENT_END..])
+        .map_err(|_| PrecompileError::InvalidCalldata)?;
+
+    // Points outside of the field or the curve are reported as errors
+    let is_valid = KzgProof::verify_kzg_proof(&commitment, &z, &y, &proof, ethereum_kzg_settings())
+        .map_err(|_| PrecompileError::InvalidKzgProof)?;
+    if !is_valid {
+        return Err(PrecompileError::InvalidKzgProof);
+    }
+
+    *consumed_gas += POINT_EVALUATION_COST;
+    let mut output = [0u8; 64];
+    U256::from(FIELD_ELEMENTS_PER_BLOB).to_big_endian(&mut output[..32]);
+    output[32..].copy_from_slice(&BLS_MODULUS);
+    Ok(Bytes::copy_from_slice(&output))
 }
 
-pub fn execute_precompile(
-    callee_address: Address,
-    calldata: Bytes,
-    gas_to_send: u64,
+/// Versioned hash of a blob commitment: its sha256 hash with the first byte replaced by the version.
+fn kzg_to_versioned_hash(commitment: &[u8]) -> [u8; 32] {
+    let mut hash: [u8; 32] = sha2::Sha256::digest(commitment).into();
+    hash[0] = VERSIONED_HASH_VERSION_KZG;
+    hash
+}
+
+type BlsG1Point = ShortWeierstrassProjectivePoint<BLS12381Curve>;
+type BlsG2Point = ShortWeierstrassProjectivePoint<BLS12381TwistCurve>;
+
+/// Point addition on the BLS12-381 curve. Points don't need to be in the subgroup.
+/// More info in https://eips.ethereum.org/EIPS/eip-2537.
+pub fn bls12_381_g1add(
+    calldata: &Bytes,
+    gas_limit: u64,
     consumed_gas: &mut u64,
-) -> (u8, Bytes) {
-    let result = match callee_address {
-        x if x == Address::from_low_u64_be(ECRECOVER_ADDRESS) => {
-            ecrecover(&calldata, gas_to_send, consumed_gas)
-        }
-        x if x == Address::from_low_u64_be(IDENTITY_ADDRESS) => {
-            identity(&calldata, gas_to_send, consumed_gas)
-        }
-        x if x == Address::from_low_u64_be(SHA2_256_ADDRESS) => {
-            sha2_256(&calldata, gas_to_send, consumed_gas)
-        }
-        x if x == Address::from_low_u64_be(RIPEMD_160_ADDRESS) => {
-            ripemd_160(&calldata, gas_to_send, consumed_gas)
+) -> Result<Bytes, PrecompileError> {
+    if gas_limit < BLS12_381_G1ADD_COST {
+        return Err(PrecompileError::NotEnoughGas);
+    }
+    if calldata.len() != 2 * BLS12_381_G1_POINT_SIZE {
+        return Err(PrecompileError::InvalidCalldata);
+    }
+
+    let p1 = bls12_381_decode_g1(&calldata[..BLS12_381_G1_POINT_SIZE], false)?;
+    let p2 = bls12_381_decode_g1(&calldata[BLS12_381_G1_POINT_SIZE..], false)?;
+
+    *consumed_gas += BLS12_381_G1ADD_COST;
+    Ok(bls12_381_encode_g1(&p1.operate_with(&p2)))
+}
+
+/// Multi-scalar multiplication on the BLS12-381 curve, of k pairs of point and 32-byte scalar.
+/// More info in https://eips.ethereum.org/EIPS/eip-2537.
+pub fn bls12_381_g1msm(
+    calldata: &Bytes,
+    gas_limit: u64,
+    consumed_gas: &mut u64,
+) -> Result<Bytes, PrecompileError> {
+    let pair_size = BLS12_381_G1_POINT_SIZE + BLS12_381_SCALAR_SIZE;
+    if calldata.is_empty() || calldata.len() % pair_size != 0 {
+        return Err(PrecompileError::InvalidCalldata);
+    }
+    let pairs = (calldata.len() / pair_size) as u64;
+    let gas_cost = bls12_381_msm_cost(pairs, BLS12_381_G1MSM_MUL_COST, &BLS12_381_G1MSM_DISCOUNTS);
+    if gas_limit < gas_cost {
+        return Err(PrecompileError::NotEnoughGas);
+    }
+
+    let result = bls12_381_msm(calldata, BLS12_381_G1_POINT_SIZE, |point| {
+        bls12_381_decode_g1(point, true)
+    })?;
+
+    *consumed_gas += gas_cost;
+    Ok(bls12_381_encode_g1(&result))
+}
+
+/// Point addition on the twist of the BLS12-381 curve. Points don't need to be in the subgroup.
+/// More info in https://eips.ethereum.org/EIPS/eip-2537.
+pub fn bls12_381_g2add(
+    calldata: &Bytes,
+    gas_limit: u64,
+    consumed_gas: &mut u64,
+) -> Result<Bytes, PrecompileError> {
+    if gas_limit < BLS12_381_G2ADD_COST {
+        return Err(PrecompileError::NotEnoughGas);
+    }
+    if calldata.len() != 2 * BLS12_381_G2_POINT_SIZE {
+        return Err(PrecompileError::InvalidCalldata);
+    }
+
+    let p1 = bls12_381_decode_g2(&calldata[..BLS12_381_G2_POINT_SIZE], false)?;
+    let p2 = bls12_381_decode_g2(&calldata[BLS12_381_G2_POINT_SIZE..], false)?;
+
+    *consumed_gas += BLS12_381_G2ADD_COST;
+    Ok(bls12_381_encode_g2(&p1.operate_with(&p2)))
+}
+
+/// Multi-scalar multiplication on the twist of the BLS12-381 curve, of k pairs of point and
+/// 32-byte scalar.
+/// More info in https://eips.ethereum.org/EIPS/eip-2537.
+pub fn bls12_381_g2msm(
+    calldata: &Bytes,
+    gas_limit: u64,
+    consumed_gas: &mut u64,
+) -> Result<Bytes, PrecompileError> {
+    let pair_size = BLS12_381_G2_POINT_SIZE + BLS12_381_SCALAR_SIZE;
+    if calldata.is_empty() || calldata.len() % pair_size != 0 {
+        return Err(PrecompileError::InvalidCalldata);
+    }
+    let pairs = (calldata.len() / pair_size) as u64;
+    let gas_cost = bls12_381_msm_cost(pairs, BLS12_381_G2MSM_MUL_COST, &BLS12_381_G2MSM_DISCOUNTS);
+    if gas_limit < gas_cost {
+        return Err(PrecompileError::NotEnoughGas);
+    }
+
+    let result = bls12_381_msm(calldata, BLS12_381_G2_POINT_SIZE, |point| {
+        bls12_381_decode_g2(point, true)
+    })?;
+
+    *consumed_gas += gas_cost;
+    Ok(bls12_381_encode_g2(&result))
+}
+
+/// Checks whether the product of the pairings of k pairs of G1 and G2 points is the identity.
+/// More info in https://eips.ethereum.org/EIPS/eip-2537.
+///
+/// # Returns
+/// - 1 if it is, 0 otherwise, as a 32-byte big endian number
+pub fn bls12_381_pairing(
+    calldata: &Bytes,
+    gas_limit: u64,
+    consumed_gas: &mut u64,
+) -> Result<Bytes, PrecompileError> {
+    let pair_size = BLS12_381_G1_POINT_SIZE + BLS12_381_G2_POINT_SIZE;
+    if calldata.is_empty() || calldata.len() % pair_size != 0 {
+        return Err(PrecompileError::InvalidCalldata);
+    }
+    let gas_cost = bls12_381_pairing_cost((calldata.len() / pair_size) as u64);
+    if gas_limit < gas_cost {
+        return Err(PrecompileError::NotEnoughGas);
+    }
+
+    let mut points = Vec::with_capacity(calldata.len() / pair_size);
+    for pair in calldata.chunks_exact(pair_size) {
+        let p = bls12_381_decode_g1(&pair[..BLS12_381_G1_POINT_SIZE], true)?;
+        let q = bls12_381_decode_g2(&pair[BLS12_381_G1_POINT_SIZE..], true)?;
+        points.push((p, q));
+    }
+    let pairs: Vec<_> = points.iter().map(|(p, q)| (p, q)).collect();
+    let result = BLS12381AtePairing::compute_batch(&pairs)
+        .map_err(|_| PrecompileError::PointNotInSubgroup)?;
+
+    *consumed_gas += gas_cost;
+    let mut output = vec![0_u8; 32];
+    output[31] = (result == FieldElement::one()) as u8;
+    Ok(Bytes::from(output))
+}
+
+/// Maps a base field element to a point in the BLS12-381 subgroup, with the simplified SWU map
+/// and cofactor clearing of RFC 9380.
+/// More info in https://eips.ethereum.org/EIPS/eip-2537.
+pub fn bls12_381_map_fp_to_g1(
+    calldata: &Bytes,
+    gas_limit: u64,
+    consumed_gas: &mut u64,
+) -> Result<Bytes, PrecompileError> {
+    if gas_limit < BLS12_381_MAP_FP_TO_G1_COST {
+        return Err(PrecompileError::NotEnoughGas);
+    }
+    if calldata.len() != BLS12_381_FP_SIZE {
+        return Err(PrecompileError::InvalidCalldata);
+    }
+
+    let u = blst_fp_from_bytes(bls12_381_fp_bytes(calldata)?);
+    let mut point = blst::blst_p1::default();
+    let mut affine = blst::blst_p1_affine::default();
+    // SAFETY: all the pointers are valid, and a null `v` maps a single element
+    unsafe {
+        blst::blst_map_to_g1(&mut point, &u, std::ptr::null());
+        blst::blst_p1_to_affine(&mut affine, &point);
+    }
+
+    *consumed_gas += BLS12_381_MAP_FP_TO_G1_COST;
+    let mut output = [0u8; BLS12_381_G1_POINT_SIZE];
+    for (out, fp) in output
+        .chunks_exact_mut(BLS12_381_FP_SIZE)
+        .zip([&affine.x, &affine.y])
+    {
+        blst_fp_to_bytes(out, fp);
+    }
+    Ok(Bytes::copy_from_slice(&output))
+}
+
+/// Maps an element of the quadratic extension field to a point in the subgroup of the twist
+/// of BLS12-381, with the simplified SWU map and cofactor clearing of RFC 9380.
+/// More info in https://eips.ethereum.org/EIPS/eip-2537.
+pub fn bls12_381_map_fp2_to_g2(
+    calldata: &Bytes,
+    gas_limit: u64,
+    consumed_gas: &mut u64,
+) -> Result<Bytes, PrecompileError> {
+    if gas_limit < BLS12_381_MAP_FP2_TO_G2_COST {
+        return Err(PrecompileError::NotEnoughGas);
+    }
+    if calldata.len() != 2 * BLS12_381_FP_SIZE {
+        return Err(PrecompileError::InvalidCalldata);
+    }
+
+    let u = blst::blst_fp2 {
+        fp: [
+            blst_fp_from_bytes(bls12_381_fp_bytes(&calldata[..BLS12_381_FP_SIZE])?),
+            blst_fp_from_bytes(bls12_381_fp_bytes(&calldata[BLS12_381_FP_SIZE..])?),
+        ],
+    };
+    let mut point = blst::blst_p2::default();
+    let mut affine = blst::blst_p2_affine::default();
+    // SAFETY: all the pointers are valid, and a null `v` maps a single element
+    unsafe {
+        blst::blst_map_to_g2(&mut point, &u, std::ptr::null());
+        blst::blst_p2_to_affine(&mut affine, &point);
+    }
+
+    *consumed_gas += BLS12_381_MAP_FP2_TO_G2_COST;
+    let mut output = [0u8; BLS12_381_G2_POINT_SIZE];
+    for (out, fp) in output.chunks_exact_mut(BLS12_381_FP_SIZE).zip([
+        &affine.x.fp[0],
+        &affine.x.fp[1],
+        &affine.y.fp[0],
+        &affine.y.fp[1],
+    ]) {
+        blst_fp_to_bytes(out, fp);
+    }
+    Ok(Bytes::copy_from_slice(&output))
+}
+
+/// Checks the encoding of a 64-byte field element, returning its 48 significant bytes
+fn bls12_381_fp_bytes(encoded: &[u8]) -> Result<&[u8], PrecompileError> {
+    let (padding, bytes) = encoded.split_at(BLS12_381_FP_PADDING);
+    if padding.iter().any(|byte| *byte != 0) {
+        return Err(PrecompileError::InvalidFieldElement);
+    }
+    // Slice lengths are checked, so unwrap is safe
+    if U384::from_bytes_be(bytes).unwrap() >= BLS12381_PRIME_FIELD_ORDER {
+        return Err(PrecompileError::InvalidFieldElement);
+    }
+    Ok(bytes)
+}
+
+fn bls12_381_decode_fp(encoded: &[u8]) -> Result<BLS12381FieldElement, PrecompileError> {
+    let bytes = bls12_381_fp_bytes(encoded)?;
+    BLS12381FieldElement::from_bytes_be(bytes).map_err(|_| PrecompileError::InvalidFieldElement)
+}
+
+/// Decodes a G1 point, where all zeros is the point at infinity
+fn bls12_381_decode_g1(
+    encoded: &[u8],
+    subgroup_check: bool,
+) -> Result<BlsG1Point, PrecompileError> {
+    if encoded.iter().all(|byte| *byte == 0) {
+        return Ok(BlsG1Point::neutral_element());
+    }
+    let x = bls12_381_decode_fp(&encoded[..BLS12_381_FP_SIZE])?;
+    let y = bls12_381_decode_fp(&encoded[BLS12_381_FP_SIZE..])?;
+    let point = BLS12381Curve::create_point_from_affine(x, y)
+        .map_err(|_| PrecompileError::InvalidEcPoint)?;
+    if subgroup_check && !point.is_in_subgroup() {
+        return Err(PrecompileError::PointNotInSubgroup);
+    }
+    Ok(point)
+}
+
+/// Decodes a G2 point, where all zeros is the point at infinity
+fn bls12_381_decode_g2(
+    encoded: &[u8],
+    subgroup_check: bool,
+) -> Result<BlsG2Point, PrecompileError> {
+    if encoded.iter().all(|byte| *byte == 0) {
+        return Ok(BlsG2Point::neutral_element());
+    }
+    let mut coordinates = encoded.chunks_exact(BLS12_381_FP_SIZE);
+    let mut next_fp2 = || -> Result<BLS12381TwistCurveFieldElement, PrecompileError> {
+        // The chunk count is checked, so unwrap is safe
+        let c0 = bls12_381_decode_fp(coordinates.next().unwrap())?;
+        let c1 = bls12_381_decode_fp(coordinates.next().unwrap())?;
+        Ok(BLS12381TwistCurveFieldElement::new([c0, c1]))
+    };
+    let x = next_fp2()?;
+    let y = next_fp2()?;
+    let point = BLS12381TwistCurve::create_point_from_affine(x, y)
+        .map_err(|_| PrecompileError::InvalidEcPoint)?;
+    if subgroup_check && !point.is_in_subgroup() {
+        return Err(PrecompileError::PointNotInSubgroup);
+    }
+    Ok(point)
+}
+
+fn bls12_381_encode_g1(point: &BlsG1Point) -> Bytes {
+    let mut output = [0u8; BLS12_381_G1_POINT_SIZE];
+    if !point.is_neutral_element() {
+        let point = point.to_affine();
+        for (out, fp) in output
+            .chunks_exact_mut(BLS12_381_FP_SIZE)
+            .zip([point.x(), point.y()])
+        {
+            out[BLS12_381_FP_PADDING..].copy_from_slice(&fp.to_bytes_be());
         }
-        x if x == Address::from_low_u64_be(MODEXP_ADDRESS) => {
-            modexp(&calldata, gas_to_send, consumed_gas)
+    }
+    Bytes::copy_from_slice(&output)
+}
+
+fn bls12_381_encode_g2(point: &BlsG2Point) -> Bytes {
+    let mut output = [0u8; BLS12_381_G2_POINT_SIZE];
+    if !point.is_neutral_element() {
+        let point = point.to_affine();
+        let coordinates = point.x().value().iter().chain(point.y().value());
+        for (out, fp) in output.chunks_exact_mut(BLS12_381_FP_SIZE).zip(coordinates) {
+            out[BLS12_381_FP_PADDING..].copy_from_slice(&fp.to_bytes_be());
         }
-        x if x == Address::from_low_u64_be(ECADD_ADDRESS) => {
-            ecadd(&calldata, gas_to_send, consumed_gas)
+    }
+    Bytes::copy_from_slice(&output)
+}
+
+/// Decodes the pairs of point and scalar of a multi-scalar multiplication and computes it
+fn bls12_381_msm<G: IsGroup>(
+    calldata: &[u8],
+    point_size: usize,
+    decode_point: impl Fn(&[u8]) -> Result<G, PrecompileError>,
+) -> Result<G, PrecompileError> {
+    let pair_size = point_size + BLS12_381_SCALAR_SIZE;
+    let mut points = Vec::with_capacity(calldata.len() / pair_size);
+    let mut scalars = Vec::with_capacity(calldata.len() / pair_size);
+    for pair in calldata.chunks_exact(pair_size) {
+        points.push(decode_point(&pair[..point_size])?);
+        // Slice lengths are checked, so unwrap is safe
+        scalars.push(LambdaWorksU256::from_bytes_be(&pair[point_size..]).unwrap());
+    }
+    msm(&scalars, &points).map_err(|_| PrecompileError::InvalidCalldata)
+}
+
+fn blst_fp_from_bytes(bytes: &[u8]) -> blst::blst_fp {
+    let mut fp = blst::blst_fp::default();
+    // SAFETY: `bytes` holds the 48 bytes read by blst
+    unsafe { blst::blst_fp_from_bendian(&mut fp, bytes.as_ptr()) };
+    fp
+}
+
+/// Writes a field element in its 64-byte encoding
+fn blst_fp_to_bytes(out: &mut [u8], fp: &blst::blst_fp) {
+    let mut bytes = [0u8; 48];
+    // SAFETY: `bytes` holds the 48 bytes written by blst
+    unsafe { blst::blst_bendian_from_fp(bytes.as_mut_ptr(), fp) };
+    out[BLS12_381_FP_PADDING..].copy_from_slice(&bytes);
+}
+
+/// A contract implemented natively instead of in EVM bytecode.
+///
+/// It's implemented for every function with the signature of the mainnet precompiles
+/// in this module, so they can be registered as they are.
+pub trait Precompile: Send + Sync {
+    /// Runs the precompile with the given calldata, adding the gas it used to
+    /// `consumed_gas`. Fails with [`PrecompileError::NotEnoughGas`] if it needs more
+    /// than `gas_limit`.
+    fn run(
+        &self,
+        calldata: &Bytes,
+        gas_limit: u64,
+        consumed_gas: &mut u64,
+    ) -> Result<Bytes, PrecompileError>;
+}
+
+impl<F> Precompile for F
+where
+    F: Fn(&Bytes, u64, &mut u64) -> Result<Bytes, PrecompileError> + Send + Sync,
+{
+    fn run(
+        &self,
+        calldata: &Bytes,
+        gas_limit: u64,
+        consumed_gas: &mut u64,
+    ) -> Result<Bytes, PrecompileError> {
+        self(calldata, gas_limit, consumed_gas)
+    }
+}
+
+/// Registry of the precompiles available to the executed code, by address.
+///
+/// [`Precompiles::new`] returns the mainnet precompiles of a hardfork, to which custom
+/// ones can be added or from which some can be removed.
+#[derive(Clone)]
+pub struct Precompiles {
+    precompiles: HashMap<Address, Arc<dyn Precompile>>,
+}
+
+impl Precompiles {
+    /// Returns the mainnet precompiles active in the given hardfork
+    pub fn new(spec_id: SpecId) -> Self {
+        let mut precompiles = Self::empty()
+            .with_precompile(Address::from_low_u64_be(ECRECOVER_ADDRESS), ecrecover)
+            .with_precompile(Address::from_low_u64_be(SHA2_256_ADDRESS), sha2_256)
+            .with_precompile(Address::from_low_u64_be(RIPEMD_160_ADDRESS), ripemd_160)
+            .with_precompile(Address::from_low_u64_be(IDENTITY_ADDRESS), identity);
+        if spec_id.is_enabled_in(SpecId::Byzantium) {
+            precompiles = precompiles
+                .with_precompile(Address::from_low_u64_be(MODEXP_ADDRESS), modexp)
+                .with_precompile(Address::from_low_u64_be(ECADD_ADDRESS), ecadd)
+                .with_precompile(Address::from_low_u64_be(ECMUL_ADDRESS), ecmul)
+                .with_precompile(Address::from_low_u64_be(ECPAIRING_ADDRESS), ecpairing);
         }
-        x if x == Address::from_low_u64_be(ECMUL_ADDRESS) => {
-            ecmul(&calldata, gas_to_send, consumed_gas)
+        if spec_id.is_enabled_in(SpecId::Istanbul) {
+            precompiles =
+                precompiles.with_precompile(Address::from_low_u64_be(BLAKE2F_ADDRESS), blake2f);
         }
-        x if x == Address::from_low_u64_be(ECPAIRING_ADDRESS) => {
-            ecpairing(&calldata, gas_to_send, consumed_gas)
+        if spec_id.is_enabled_in(SpecId::Cancun) {
+            precompiles = precompiles.with_precompile(
+                Address::from_low_u64_be(POINT_EVALUATION_ADDRESS),
+                point_evaluation,
+            );
         }
-        x if x == Address::from_low_u64_be(BLAKE2F_ADDRESS) => {
-            blake2f(&calldata, gas_to_send, consumed_gas)
+        if spec_id.is_enabled_in(SpecId::Prague) {
+            precompiles = precompiles
+                .with_precompile(
+                    Address::from_low_u64_be(BLS12_381_G1ADD_ADDRESS),
+                    bls12_381_g1add,
+                )
+                .with_precompile(
+                    Address::from_low_u64_be(BLS12_381_G1MSM_ADDRESS),
+                    bls12_381_g1msm,
+                )
+                .with_precompile(
+                    Address::from_low_u64_be(BLS12_381_G2ADD_ADDRESS),
+                    bls12_381_g2add,
+                )
+                .with_precompile(
+                    Address::from_low_u64_be(BLS12_381_G2MSM_ADDRESS),
+                    bls12_381_g2msm,
+                )
+                .with_precompile(
+                    Address::from_low_u64_be(BLS12_381_PAIRING_ADDRESS),
+                    bls12_381_pairing,
+                )
+                .with_precompile(
+                    Address::from_low_u64_be(BLS12_381_MAP_FP_TO_G1_ADDRESS),
+                    bls12_381_map_fp_to_g1,
+                )
+                .with_precompile(
+                    Address::from_low_u64_be(BLS12_381_MAP_FP2_TO_G2_ADDRESS),
+                    bls12_381_map_fp2_to_g2,
+                );
         }
-        _ => {
-            unreachable!()
+        precompiles
+    }
+
+    /// Returns a registry without precompiles
+    pub fn empty() -> Self {
+        Self {
+            precompiles: HashMap::new(),
         }
-    };
-    match result {
+    }
+
+    /// Adds a precompile at the given address, replacing the one that was there
+    pub fn with_precompile(
+        mut self,
+        address: Address,
+        precompile: impl Precompile + 'static,
+    ) -> Self {
+        self.insert(address, precompile);
+        self
+    }
+
+    /// Removes the precompile at the given address
+    pub fn without_precompile(mut self, address: &Address) -> Self {
+        self.remove(address);
+        self
+    }
+
+    pub fn insert(&mut self, address: Address, precompile: impl Precompile + 'static) {
+        self.precompiles.insert(address, Arc::new(precompile));
+    }
+
+    /// Removes the precompile at the given address, returning whether there was one
+    pub fn remove(&mut self, address: &Address) -> bool {
+        self.precompiles.remove(address).is_some()
+    }
+
+    pub fn contains(&self, address: &Address) -> bool {
+        self.precompiles.contains_key(address)
+    }
+
+    pub fn get(&self, address: &Address) -> Option<&dyn Precompile> {
+        self.precompiles.get(address).map(AsRef::as_ref)
+    }
+
+    pub fn addresses(&self) -> impl Iterator<Item = &Address> {
+        self.precompiles.keys()
+    }
+
+    /// Returns the precompile addresses as an access list, since they're always warm (EIP-2929)
+    pub fn warm_addresses(&self) -> AccessList {
+        let mut addresses: Vec<Address> = self.addresses().copied().collect();
+        addresses.sort();
+        addresses
+            .into_iter()
+            .map(|address| (address, Vec::new()))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.precompiles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.precompiles.is_empty()
+    }
+}
+
+/// The mainnet precompiles of the latest hardfork
+impl Default for Precompiles {
+    fn default() -> Self {
+        Self::new(SpecId::default())
+    }
+}
+
+impl fmt::Debug for Precompiles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Precompiles")
+            .field("addresses", &self.warm_addresses())
+            .finish()
+    }
+}
+
+/// Runs a precompile as called from the EVM. If it fails, all the sent gas is consumed.
+pub fn run_precompile(
+    precompile: &dyn Precompile,
+    calldata: &Bytes,
+    gas_to_send: u64,
+    consumed_gas: &mut u64,
+) -> (u8, Bytes) {
+    match precompile.run(calldata, gas_to_send, consumed_gas) {
         Ok(res) => (SUCCESS_RETURN_CODE, res),
         Err(_) => {
             *consumed_gas += gas_to_send;
@@ -595,10 +1144,77 @@ pub fn execute_precompile(
     }
 }
 
+fn mainnet_precompiles() -> &'static Precompiles {
+    static PRECOMPILES: OnceLock<Precompiles> = OnceLock::new();
+    PRECOMPILES.get_or_init(Precompiles::default)
+}
+
+/// Returns whether the address has a mainnet precompile in the latest hardfork
+pub fn is_precompile(callee_address: Address) -> bool {
+    mainnet_precompiles().contains(&callee_address)
+}
+
+/// Runs the mainnet precompile of the latest hardfork at the given address
+pub fn execute_precompile(
+    callee_address: Address,
+    calldata: Bytes,
+    gas_to_send: u64,
+    consumed_gas: &mut u64,
+) -> (u8, Bytes) {
+    let precompile = mainnet_precompiles()
+        .get(&callee_address)
+        .expect("not a precompile address");
+    run_precompile(precompile, &calldata, gas_to_send, consumed_gas)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn precompiles_follow_the_spec() {
+        let frontier = Precompiles::new(SpecId::Frontier);
+        let cancun = Precompiles::new(SpecId::Cancun);
+
+        assert_eq!(frontier.len(), 4);
+        assert!(frontier.contains(&Address::from_low_u64_be(IDENTITY_ADDRESS)));
+        assert!(!frontier.contains(&Address::from_low_u64_be(MODEXP_ADDRESS)));
+        assert!(!Precompiles::new(SpecId::Shanghai)
+            .contains(&Address::from_low_u64_be(POINT_EVALUATION_ADDRESS)));
+        assert_eq!(cancun.len(), 10);
+        assert_eq!(
+            cancun.warm_addresses().first(),
+            Some(&(Address::from_low_u64_be(ECRECOVER_ADDRESS), vec![]))
+        );
+    }
+
+    #[test]
+    fn custom_precompiles_can_be_registered_and_removed() {
+        let address = Address::from_low_u64_be(0x100);
+        let precompiles = Precompiles::empty()
+            .with_precompile(address, |_: &Bytes, _: u64, consumed_gas: &mut u64| {
+                *consumed_gas += 1;
+                Err(PrecompileError::Other("unsupported".to_string()))
+            })
+            .with_precompile(Address::from_low_u64_be(IDENTITY_ADDRESS), identity);
+        let mut consumed_gas = 0;
+
+        let (return_code, return_data) = run_precompile(
+            precompiles.get(&address).unwrap(),
+            &Bytes::new(),
+            100,
+            &mut consumed_gas,
+        );
+
+        assert_eq!(return_code, REVERT_RETURN_CODE);
+        assert_eq!(return_data, Bytes::new());
+        assert_eq!(consumed_gas, 101);
+
+        let precompiles =
+            precompiles.without_precompile(&Address::from_low_u64_be(IDENTITY_ADDRESS));
+        assert_eq!(precompiles.addresses().collect::<Vec<_>>(), vec![&address]);
+    }
+
     fn calldata_for_modexp(b_size: u16, e_size: u16, m_size: u16, b: u8, e: u8, m: u8) -> Bytes {
         let calldata_size = (b_size + e_size + m_size + MXP_PARAMS_OFFSET as u16) as usize;
         let b_data_size = U256::from(b_size);
@@ -1525,4 +2141,415 @@ mod tests {
         assert_eq!(return_data, expected_result);
         assert_eq!(consumed_gas, 1);
     }
+
+    /// Calldata for a correct proof, from the c-kzg-4844 test vectors (verify_kzg_proof_case_correct_proof_31ebd010e6098750)
+    fn calldata_for_point_evaluation() -> Vec<u8> {
+        let commitment = hex::decode("8f59a8d2a1a625a17f3fea0fe5eb8c896db3764f3185481bc22f91b4aaffcca25f26936857bc3a7c2539ea8ec3a952b7").unwrap();
+        let versioned_hash = kzg_to_versioned_hash(&commitment).to_vec();
+        let z = hex::decode("73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000000")
+            .unwrap();
+        let y = hex::decode("1522a4a7f34e1ea350ae07c29c96c7e79655aa926122e95fe69fcbd932ca49e9")
+            .unwrap();
+        let proof = hex::decode("a62ad71d14c5719385c0686f1871430475bf3a00f0aa3f7b8dd99a9abc2160744faf0070725e00b60ad9a026a15b1a8c").unwrap();
+        [versioned_hash, z, y, commitment, proof].concat()
+    }
+
+    #[test]
+    fn point_evaluation_happy_path() {
+        let callee_address = Address::from_low_u64_be(POINT_EVALUATION_ADDRESS);
+        let calldata = Bytes::from(calldata_for_point_evaluation());
+        let gas_limit = 100_000;
+        let mut consumed_gas = 0;
+
+        let expected_result = Bytes::from(hex::decode(
+        "000000000000000000000000000000000000000000000000000000000000100073eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000001"
+        ).unwrap());
+
+        let (return_code, return_data) =
+            execute_precompile(callee_address, calldata, gas_limit, &mut consumed_gas);
+
+        assert_eq!(return_code, SUCCESS_RETURN_CODE);
+        assert_eq!(return_data, expected_result);
+        assert_eq!(consumed_gas, POINT_EVALUATION_COST);
+    }
+
+    #[test]
+    fn point_evaluation_with_wrong_versioned_hash() {
+        let mut calldata = calldata_for_point_evaluation();
+        calldata[0] = 0;
+        let calldata = Bytes::from(calldata);
+        let mut consumed_gas = 0;
+
+        let result = point_evaluation(&calldata, 100_000, &mut consumed_gas);
+
+        assert_eq!(result.unwrap_err(), PrecompileError::InvalidVersionedHash);
+    }
+
+    #[test]
+    fn point_evaluation_with_wrong_evaluation() {
+        let mut calldata = calldata_for_point_evaluation();
+        // Change the claimed evaluation y
+        calldata[PE_Y_END - 1] ^= 1;
+        let calldata = Bytes::from(calldata);
+        let mut consumed_gas = 0;
+
+        let result = point_evaluation(&calldata, 100_000, &mut consumed_gas);
+
+        assert_eq!(result.unwrap_err(), PrecompileError::InvalidKzgProof);
+    }
+
+    #[test]
+    fn point_evaluation_with_invalid_calldata_length() {
+        let callee_address = Address::from_low_u64_be(POINT_EVALUATION_ADDRESS);
+        let mut calldata = calldata_for_point_evaluation();
+        calldata.pop();
+        let gas_limit = 100_000;
+        let mut consumed_gas = 0;
+
+        let (return_code, return_data) = execute_precompile(
+            callee_address,
+            Bytes::from(calldata),
+            gas_limit,
+            &mut consumed_gas,
+        );
+
+        assert_eq!(return_code, REVERT_RETURN_CODE);
+        assert_eq!(return_data, Bytes::new());
+        assert_eq!(consumed_gas, gas_limit);
+    }
+
+    #[test]
+    fn point_evaluation_with_not_enough_gas() {
+        let calldata = Bytes::from(calldata_for_point_evaluation());
+        let mut consumed_gas = 0;
+
+        let result = point_evaluation(&calldata, POINT_EVALUATION_COST - 1, &mut consumed_gas);
+
+        assert_eq!(result.unwrap_err(), PrecompileError::NotEnoughGas);
+        assert_eq!(consumed_gas, 0);
+    }
+
+    const BLS_G1_X: &str = "17f1d3a73197d7942695638c4fa9ac0fc3688c4f9774b905a14e3a3f171bac586c55e83ff97a1aeffb3af00adb22c6bb";
+    const BLS_G1_Y: &str = "08b3f481e3aaa0f1a09e30ed741d8ae4fcf5e095d5d00af600db18cb2c04b3edd03cc744a2888ae40caa232946c5e7e1";
+    const BLS_G1_NEG_Y: &str = "114d1d6855d545a8aa7d76c8cf2e21f267816aef1db507c96655b9d5caac42364e6f38ba0ecb751bad54dcd6b939c2ca";
+    const BLS_G2_X0: &str = "024aa2b2f08f0a91260805272dc51051c6e47ad4fa403b02b4510b647ae3d1770bac0326a805bbefd48056c8c121bdb8";
+    const BLS_G2_X1: &str = "13e02b6052719f607dacd3a088274f65596bd0d09920b61ab5da61bbdc7f5049334cf11213945d57e5ac7d055d042b7e";
+    const BLS_G2_Y0: &str = "0ce5d527727d6e118cc9cdc6da2e351aadfd9baa8cbdd3a76d429a695160d12c923ac9cc3baca289e193548608b82801";
+    const BLS_G2_Y1: &str = "0606c4a02ea734cc32acd2b02bc28b99cb3e287e85a763af267492ab572e99ab3f370d275cec1da1aaa9075ff05f79be";
+
+    /// Encodes field elements given in hex, padding each of them to 64 bytes
+    fn bls_calldata(elements: &[&str]) -> Vec<u8> {
+        elements
+            .iter()
+            .flat_map(|element| {
+                let bytes = hex::decode(element).unwrap();
+                let mut fp = vec![0u8; BLS12_381_FP_SIZE - bytes.len()];
+                fp.extend(bytes);
+                fp
+            })
+            .collect()
+    }
+
+    fn bls_g1_generator() -> Vec<u8> {
+        bls_calldata(&[BLS_G1_X, BLS_G1_Y])
+    }
+
+    fn bls_g2_generator() -> Vec<u8> {
+        bls_calldata(&[BLS_G2_X0, BLS_G2_X1, BLS_G2_Y0, BLS_G2_Y1])
+    }
+
+    #[test]
+    fn bls12_381_precompiles_are_activated_in_prague() {
+        let address = Address::from_low_u64_be(BLS12_381_G1ADD_ADDRESS);
+        assert!(!Precompiles::new(SpecId::Cancun).contains(&address));
+        assert!(Precompiles::new(SpecId::Prague).contains(&address));
+        assert_eq!(Precompiles::new(SpecId::Prague).len(), 17);
+    }
+
+    #[test]
+    fn bls12_381_g1add_happy_path() {
+        let mut calldata = bls_g1_generator();
+        calldata.extend(bls_calldata(&[
+            "112b98340eee2777cc3c14163dea3ec97977ac3dc5c70da32e6e87578f44912e902ccef9efe28d4a78b8999dfbca9426",
+            "186b28d92356c4dfec4b5201ad099dbdede3781f8998ddf929b4cd7756192185ca7b8f4ef7088f813270ac3d48868a21",
+        ]));
+        let mut consumed_gas = 0;
+
+        let result = bls12_381_g1add(&Bytes::from(calldata), 1000, &mut consumed_gas).unwrap();
+
+        let expected_result = bls_calldata(&[
+            "0a40300ce2dec9888b60690e9a41d3004fda4886854573974fab73b046d3147ba5b7a5bde85279ffede1b45b3918d82d",
+            "06d3d887e9f53b9ec4eb6cedf5607226754b07c01ace7834f57f3e7315faefb739e59018e22c492006190fba4a870025",
+        ]);
+        assert_eq!(result, Bytes::from(expected_result));
+        assert_eq!(consumed_gas, BLS12_381_G1ADD_COST);
+    }
+
+    #[test]
+    fn bls12_381_g1add_infinity_with_valid_point() {
+        let mut calldata = vec![0u8; BLS12_381_G1_POINT_SIZE];
+        calldata.extend(bls_g1_generator());
+        let mut consumed_gas = 0;
+
+        let result = bls12_381_g1add(&Bytes::from(calldata), 1000, &mut consumed_gas).unwrap();
+
+        assert_eq!(result, Bytes::from(bls_g1_generator()));
+    }
+
+    #[test]
+    fn bls12_381_g1add_point_outside_subgroup() {
+        // (0, 2) is on the curve, but not in the subgroup, which isn't checked by addition
+        let point = bls_calldata(&["00", "02"]);
+        let calldata = [point.as_slice(), &[0u8; BLS12_381_G1_POINT_SIZE]].concat();
+        let mut consumed_gas = 0;
+
+        let result = bls12_381_g1add(&Bytes::from(calldata), 1000, &mut consumed_gas).unwrap();
+
+        assert_eq!(result, Bytes::from(point));
+    }
+
+    #[test]
+    fn bls12_381_g1add_point_not_on_curve() {
+        let mut calldata = bls_calldata(&["01", "01"]);
+        calldata.extend(bls_g1_generator());
+        let mut consumed_gas = 0;
+
+        let result = bls12_381_g1add(&Bytes::from(calldata), 1000, &mut consumed_gas);
+
+        assert_eq!(result.unwrap_err(), PrecompileError::InvalidEcPoint);
+        assert_eq!(consumed_gas, 0);
+    }
+
+    #[test]
+    fn bls12_381_g1add_with_invalid_field_elements() {
+        let mut calldata = bls_g1_generator();
+        calldata.extend(bls_g1_generator());
+        // Non-zero padding
+        let mut with_padding = calldata.clone();
+        with_padding[0] = 1;
+        // The field modulus
+        let mut with_modulus = bls_calldata(&[
+            "1a0111ea397fe69a4b1ba7b6434bacd764774b84f38512bf6730d2a0f6b0f6241eabfffeb153ffffb9feffffffffaaab",
+            BLS_G1_Y,
+        ]);
+        with_modulus.extend(bls_g1_generator());
+        let mut consumed_gas = 0;
+
+        for calldata in [with_padding, with_modulus] {
+            let result = bls12_381_g1add(&Bytes::from(calldata), 1000, &mut consumed_gas);
+            assert_eq!(result.unwrap_err(), PrecompileError::InvalidFieldElement);
+        }
+    }
+
+    #[test]
+    fn bls12_381_g1add_with_invalid_calldata_length() {
+        let calldata = Bytes::from(bls_g1_generator());
+        let mut consumed_gas = 0;
+
+        let result = bls12_381_g1add(&calldata, 1000, &mut consumed_gas);
+
+        assert_eq!(result.unwrap_err(), PrecompileError::InvalidCalldata);
+    }
+
+    #[test]
+    fn bls12_381_g1msm_happy_path() {
+        let mut calldata = bls_g1_generator();
+        calldata.extend(LambdaWorksU256::from_u64(2).to_bytes_be());
+        let mut consumed_gas = 0;
+
+        let result = bls12_381_g1msm(&Bytes::from(calldata), 20_000, &mut consumed_gas).unwrap();
+
+        let expected_result = bls_calldata(&[
+            "0572cbea904d67468808c8eb50a9450c9721db309128012543902d0ac358a62ae28f75bb8f1c7c42c39a8c5529bf0f4e",
+            "166a9d8cabc673a322fda673779d8e3822ba3ecb8670e461f73bb9021d5fd76a4c56d9d4cd16bd1bba86881979749d28",
+        ]);
+        assert_eq!(result, Bytes::from(expected_result));
+        assert_eq!(consumed_gas, BLS12_381_G1MSM_MUL_COST);
+    }
+
+    #[test]
+    fn bls12_381_g1msm_sums_the_products() {
+        // G * 3 + G * (r - 3) is the point at infinity
+        let mut calldata = bls_g1_generator();
+        calldata.extend(LambdaWorksU256::from_u64(3).to_bytes_be());
+        calldata.extend(bls_g1_generator());
+        calldata.extend(
+            hex::decode("73eda753299d7d483339d80809a1d80553bda402fffe5bfefffffffefffffffe")
+                .unwrap(),
+        );
+        let mut consumed_gas = 0;
+
+        let result = bls12_381_g1msm(&Bytes::from(calldata), 30_000, &mut consumed_gas).unwrap();
+
+        assert_eq!(result, Bytes::from(vec![0u8; BLS12_381_G1_POINT_SIZE]));
+        assert_eq!(consumed_gas, 2 * BLS12_381_G1MSM_MUL_COST * 949 / 1000);
+    }
+
+    #[test]
+    fn bls12_381_g1msm_point_outside_subgroup() {
+        let mut calldata = bls_calldata(&["00", "02"]);
+        calldata.extend([0u8; BLS12_381_SCALAR_SIZE]);
+        let mut consumed_gas = 0;
+
+        let result = bls12_381_g1msm(&Bytes::from(calldata), 20_000, &mut consumed_gas);
+
+        assert_eq!(result.unwrap_err(), PrecompileError::PointNotInSubgroup);
+    }
+
+    #[test]
+    fn bls12_381_g1msm_with_not_enough_gas() {
+        let mut calldata = bls_g1_generator();
+        calldata.extend([1u8; BLS12_381_SCALAR_SIZE]);
+        let mut consumed_gas = 0;
+
+        let result = bls12_381_g1msm(
+            &Bytes::from(calldata),
+            BLS12_381_G1MSM_MUL_COST - 1,
+            &mut consumed_gas,
+        );
+
+        assert_eq!(result.unwrap_err(), PrecompileError::NotEnoughGas);
+    }
+
+    #[test]
+    fn bls12_381_msm_cost_is_discounted() {
+        let g1_cost =
+            |pairs| bls12_381_msm_cost(pairs, BLS12_381_G1MSM_MUL_COST, &BLS12_381_G1MSM_DISCOUNTS);
+        let g2_cost =
+            |pairs| bls12_381_msm_cost(pairs, BLS12_381_G2MSM_MUL_COST, &BLS12_381_G2MSM_DISCOUNTS);
+
+        assert_eq!(g1_cost(1), 12_000);
+        assert_eq!(g1_cost(2), 22_776);
+        assert_eq!(g1_cost(128), 128 * 12_000 * 519 / 1000);
+        // The maximum discount applies past the end of the table
+        assert_eq!(g1_cost(200), 200 * 12_000 * 519 / 1000);
+        assert_eq!(g2_cost(2), 45_000);
+        assert_eq!(g2_cost(200), 200 * 22_500 * 524 / 1000);
+    }
+
+    #[test]
+    fn bls12_381_g2add_happy_path() {
+        let mut calldata = bls_g2_generator();
+        calldata.extend(bls_calldata(&[
+            "103121a2ceaae586d240843a398967325f8eb5a93e8fea99b62b9f88d8556c80dd726a4b30e84a36eeabaf3592937f27",
+            "086b990f3da2aeac0a36143b7d7c824428215140db1bb859338764cb58458f081d92664f9053b50b3fbd2e4723121b68",
+            "0f9e7ba9a86a8f7624aa2b42dcc8772e1af4ae115685e60abc2c9b90242167acef3d0be4050bf935eed7c3b6fc7ba77e",
+            "0d22c3652d0dc6f0fc9316e14268477c2049ef772e852108d269d9c38dba1d4802e8dae479818184c08f9a569d878451",
+        ]));
+        let mut consumed_gas = 0;
+
+        let result = bls12_381_g2add(&Bytes::from(calldata), 1000, &mut consumed_gas).unwrap();
+
+        let expected_result = bls_calldata(&[
+            "0b54a8a7b08bd6827ed9a797de216b8c9057b3a9ca93e2f88e7f04f19accc42da90d883632b9ca4dc38d013f71ede4db",
+            "077eba4eecf0bd764dce8ed5f45040dd8f3b3427cb35230509482c14651713282946306247866dfe39a8e33016fcbe52",
+            "14e60a76a29ef85cbd69f251b9f29147b67cfe3ed2823d3f9776b3a0efd2731941d47436dc6d2b58d9e65f8438bad073",
+            "1586c3c910d95754fef7a732df78e279c3d37431c6a2b77e67a00c7c130a8fcd4d19f159cbeb997a178108fffffcbd20",
+        ]);
+        assert_eq!(result, Bytes::from(expected_result));
+        assert_eq!(consumed_gas, BLS12_381_G2ADD_COST);
+    }
+
+    #[test]
+    fn bls12_381_g2msm_happy_path() {
+        let mut calldata = bls_g2_generator();
+        calldata.extend(LambdaWorksU256::from_u64(1).to_bytes_be());
+        let mut consumed_gas = 0;
+
+        let result = bls12_381_g2msm(&Bytes::from(calldata), 30_000, &mut consumed_gas).unwrap();
+
+        assert_eq!(result, Bytes::from(bls_g2_generator()));
+        assert_eq!(consumed_gas, BLS12_381_G2MSM_MUL_COST);
+    }
+
+    #[test]
+    fn bls12_381_pairing_happy_path() {
+        // e(G1, G2) * e(-G1, G2) = 1
+        let calldata = [
+            bls_g1_generator(),
+            bls_g2_generator(),
+            bls_calldata(&[BLS_G1_X, BLS_G1_NEG_Y]),
+            bls_g2_generator(),
+        ]
+        .concat();
+        let mut consumed_gas = 0;
+
+        let result = bls12_381_pairing(&Bytes::from(calldata), 200_000, &mut consumed_gas).unwrap();
+
+        let mut expected_result = vec![0u8; 32];
+        expected_result[31] = 1;
+        assert_eq!(result, Bytes::from(expected_result));
+        assert_eq!(
+            consumed_gas,
+            BLS12_381_PAIRING_STATIC_COST + 2 * BLS12_381_PAIRING_PAIR_COST
+        );
+    }
+
+    #[test]
+    fn bls12_381_pairing_not_equal_to_one() {
+        let calldata = [bls_g1_generator(), bls_g2_generator()].concat();
+        let mut consumed_gas = 0;
+
+        let result = bls12_381_pairing(&Bytes::from(calldata), 200_000, &mut consumed_gas).unwrap();
+
+        assert_eq!(result, Bytes::from(vec![0u8; 32]));
+    }
+
+    #[test]
+    fn bls12_381_pairing_with_empty_calldata() {
+        let mut consumed_gas = 0;
+
+        let result = bls12_381_pairing(&Bytes::new(), 200_000, &mut consumed_gas);
+
+        assert_eq!(result.unwrap_err(), PrecompileError::InvalidCalldata);
+    }
+
+    #[test]
+    fn bls12_381_map_fp_to_g1_happy_path() {
+        // Test vector of RFC 9380, for the empty message
+        let calldata = bls_calldata(&["156c8a6a2c184569d69a76be144b5cdc5141d2d2ca4fe341f011e25e3969c55ad9e9b9ce2eb833c81a908e5fa4ac5f03"]);
+        let mut consumed_gas = 0;
+
+        let result =
+            bls12_381_map_fp_to_g1(&Bytes::from(calldata), 10_000, &mut consumed_gas).unwrap();
+
+        let expected_result = bls_calldata(&[
+            "184bb665c37ff561a89ec2122dd343f20e0f4cbcaec84e3c3052ea81d1834e192c426074b02ed3dca4e7676ce4ce48ba",
+            "04407b8d35af4dacc809927071fc0405218f1401a6d15af775810e4e460064bcc9468beeba82fdc751be70476c888bf3",
+        ]);
+        assert_eq!(result, Bytes::from(expected_result));
+        assert_eq!(consumed_gas, BLS12_381_MAP_FP_TO_G1_COST);
+    }
+
+    #[test]
+    fn bls12_381_map_fp2_to_g2_happy_path() {
+        // Test vector of RFC 9380, for the empty message
+        let calldata = bls_calldata(&[
+            "07355d25caf6e7f2f0cb2812ca0e513bd026ed09dda65b177500fa31714e09ea0ded3a078b526bed3307f804d4b93b04",
+            "02829ce3c021339ccb5caf3e187f6370e1e2a311dec9b75363117063ab2015603ff52c3d3b98f19c2f65575e99e8b78c",
+        ]);
+        let mut consumed_gas = 0;
+
+        let result =
+            bls12_381_map_fp2_to_g2(&Bytes::from(calldata), 30_000, &mut consumed_gas).unwrap();
+
+        let expected_result = bls_calldata(&[
+            "00e7f4568a82b4b7dc1f14c6aaa055edf51502319c723c4dc2688c7fe5944c213f510328082396515734b6612c4e7bb7",
+            "126b855e9e69b1f691f816e48ac6977664d24d99f8724868a184186469ddfd4617367e94527d4b74fc86413483afb35b",
+            "0caead0fd7b6176c01436833c79d305c78be307da5f6af6c133c47311def6ff1e0babf57a0fb5539fce7ee12407b0a42",
+            "1498aadcf7ae2b345243e281ae076df6de84455d766ab6fcdaad71fab60abb2e8b980a440043cd305db09d283c895e3d",
+        ]);
+        assert_eq!(result, Bytes::from(expected_result));
+        assert_eq!(consumed_gas, BLS12_381_MAP_FP2_TO_G2_COST);
+    }
+
+    #[test]
+    fn bls12_381_map_fp_to_g1_with_invalid_field_element() {
+        let calldata = bls_calldata(&["1a0111ea397fe69a4b1ba7b6434bacd764774b84f38512bf6730d2a0f6b0f6241eabfffeb153ffffb9feffffffffaaab"]);
+        let mut consumed_gas = 0;
+
+        let result = bls12_381_map_fp_to_g1(&Bytes::from(calldata), 10_000, &mut consumed_gas);
+
+        assert_eq!(result.unwrap_err(), PrecompileError::InvalidFieldElement);
+    }
 }
@@ -1,5 +1,5 @@
 pub use bytes::Bytes;
-pub use ethereum_types::{Address, H160, U256};
+pub use ethereum_types::{Address, Bloom, H160, U256};
 
 use ethereum_types::H256;
 
@@ -2,7 +2,12 @@ use num_bigint::BigUint;
 use std::{cmp::min, fmt};
 use thiserror::Error;
 
-#[derive(Debug)]
+use crate::{
+    eof::{self, EofContainer},
+    spec::SpecId,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Opcode {
     STOP = 0x00,
     ADD = 0x01,
@@ -147,16 +152,36 @@ pub enum Opcode {
     LOG2 = 0xA2,
     LOG3 = 0xA3,
     LOG4 = 0xA4,
-    // unused 0xA5-0xEF
+    // unused 0xA5-0xCF
+    // The opcodes from here up to CREATE are only valid in EOF code
+    DATALOAD = 0xD0,
+    DATALOADN = 0xD1,
+    DATASIZE = 0xD2,
+    DATACOPY = 0xD3,
+    // unused 0xD4-0xDF
+    RJUMP = 0xE0,
+    RJUMPI = 0xE1,
+    RJUMPV = 0xE2,
+    CALLF = 0xE3,
+    RETF = 0xE4,
+    JUMPF = 0xE5,
+    // unused 0xE6-0xEB
+    EOFCREATE = 0xEC,
+    // unused 0xED
+    RETURNCONTRACT = 0xEE,
+    // unused 0xEF
     CREATE = 0xF0,
     CALL = 0xF1,
     CALLCODE = 0xF2,
     RETURN = 0xF3,
     DELEGATECALL = 0xF4,
     CREATE2 = 0xF5,
-    // unused 0xF6-0xF9
+    // unused 0xF6-0xF7
+    EXTCALL = 0xF8,
+    EXTDELEGATECALL = 0xF9,
     STATICCALL = 0xFA,
-    // unused 0xFB-0xFC
+    EXTSTATICCALL = 0xFB,
+    // unused 0xFC
     REVERT = 0xFD,
     INVALID = 0xFE,
     SELFDESTRUCT = 0xFF,
@@ -321,13 +346,28 @@ impl TryFrom<u8> for Opcode {
             x if x == Opcode::LOG2 as u8 => Opcode::LOG2,
             x if x == Opcode::LOG3 as u8 => Opcode::LOG3,
             x if x == Opcode::LOG4 as u8 => Opcode::LOG4,
+            x if x == Opcode::DATALOAD as u8 => Opcode::DATALOAD,
+            x if x == Opcode::DATALOADN as u8 => Opcode::DATALOADN,
+            x if x == Opcode::DATASIZE as u8 => Opcode::DATASIZE,
+            x if x == Opcode::DATACOPY as u8 => Opcode::DATACOPY,
+            x if x == Opcode::RJUMP as u8 => Opcode::RJUMP,
+            x if x == Opcode::RJUMPI as u8 => Opcode::RJUMPI,
+            x if x == Opcode::RJUMPV as u8 => Opcode::RJUMPV,
+            x if x == Opcode::CALLF as u8 => Opcode::CALLF,
+            x if x == Opcode::RETF as u8 => Opcode::RETF,
+            x if x == Opcode::JUMPF as u8 => Opcode::JUMPF,
+            x if x == Opcode::EOFCREATE as u8 => Opcode::EOFCREATE,
+            x if x == Opcode::RETURNCONTRACT as u8 => Opcode::RETURNCONTRACT,
             x if x == Opcode::CREATE as u8 => Opcode::CREATE,
             x if x == Opcode::CALL as u8 => Opcode::CALL,
             x if x == Opcode::CALLCODE as u8 => Opcode::CALLCODE,
             x if x == Opcode::RETURN as u8 => Opcode::RETURN,
             x if x == Opcode::DELEGATECALL as u8 => Opcode::DELEGATECALL,
             x if x == Opcode::CREATE2 as u8 => Opcode::CREATE2,
+            x if x == Opcode::EXTCALL as u8 => Opcode::EXTCALL,
+            x if x == Opcode::EXTDELEGATECALL as u8 => Opcode::EXTDELEGATECALL,
             x if x == Opcode::STATICCALL as u8 => Opcode::STATICCALL,
+            x if x == Opcode::EXTSTATICCALL as u8 => Opcode::EXTSTATICCALL,
             x if x == Opcode::REVERT as u8 => Opcode::REVERT,
             x if x == Opcode::SELFDESTRUCT as u8 => Opcode::SELFDESTRUCT,
             x => return Err(OpcodeParseError(x)),
@@ -423,6 +463,23 @@ pub enum Operation {
     Revert,
     Invalid,
     SelfDestruct,
+    // EOF only operations. The `pc` of the jumps and calls is the offset in the code
+    // sections laid out one after another.
+    Rjump { pc: usize, offset: i16 },
+    Rjumpi { pc: usize, offset: i16 },
+    Rjumpv { pc: usize, offsets: Vec<i16> },
+    Callf { pc: usize, index: u16 },
+    Retf,
+    Jumpf(u16),
+    DataLoad,
+    DataLoadN(u16),
+    DataSize,
+    DataCopy,
+    EofCreate(u8),
+    ReturnContract(u8),
+    ExtCall,
+    ExtDelegateCall,
+    ExtStaticCall,
 }
 
 impl Operation {
@@ -519,6 +576,35 @@ impl Operation {
             Operation::Revert => vec![Opcode::REVERT as u8],
             Operation::Invalid => vec![Opcode::INVALID as u8],
             Operation::SelfDestruct => vec![Opcode::SELFDESTRUCT as u8],
+            Operation::Rjump { offset, .. } => {
+                [&[Opcode::RJUMP as u8], &offset.to_be_bytes()[..]].concat()
+            }
+            Operation::Rjumpi { offset, .. } => {
+                [&[Opcode::RJUMPI as u8], &offset.to_be_bytes()[..]].concat()
+            }
+            Operation::Rjumpv { offsets, .. } => {
+                let mut bytecode = vec![Opcode::RJUMPV as u8, (offsets.len() - 1) as u8];
+                for offset in offsets {
+                    bytecode.extend_from_slice(&offset.to_be_bytes());
+                }
+                bytecode
+            }
+            Operation::Callf { index, .. } => {
+                [&[Opcode::CALLF as u8], &index.to_be_bytes()[..]].concat()
+            }
+            Operation::Retf => vec![Opcode::RETF as u8],
+            Operation::Jumpf(index) => [&[Opcode::JUMPF as u8], &index.to_be_bytes()[..]].concat(),
+            Operation::DataLoad => vec![Opcode::DATALOAD as u8],
+            Operation::DataLoadN(offset) => {
+                [&[Opcode::DATALOADN as u8], &offset.to_be_bytes()[..]].concat()
+            }
+            Operation::DataSize => vec![Opcode::DATASIZE as u8],
+            Operation::DataCopy => vec![Opcode::DATACOPY as u8],
+            Operation::EofCreate(index) => vec![Opcode::EOFCREATE as u8, *index],
+            Operation::ReturnContract(index) => vec![Opcode::RETURNCONTRACT as u8, *index],
+            Operation::ExtCall => vec![Opcode::EXTCALL as u8],
+            Operation::ExtDelegateCall => vec![Opcode::EXTDELEGATECALL as u8],
+            Operation::ExtStaticCall => vec![Opcode::EXTSTATICCALL as u8],
         }
     }
 }
@@ -527,6 +613,8 @@ impl Operation {
 pub struct Program {
     pub(crate) operations: Vec<Operation>,
     pub(crate) code_size: u32,
+    /// Container the operations were taken from, if the code is EOF
+    pub(crate) eof: Option<EofContainer>,
 }
 
 impl Program {
@@ -536,7 +624,7 @@ impl Program {
         let mut failed_opcodes = vec![];
 
         while pc < bytecode.len() {
-            match Self::parse_operation(bytecode, pc) {
+            match Self::parse_operation(bytecode, pc, false) {
                 Ok((op, new_pc)) => {
                     operations.push(op);
                     pc = new_pc;
@@ -554,6 +642,7 @@ impl Program {
             Ok(Program {
                 operations,
                 code_size,
+                eof: None,
             })
         } else {
             Err(ParseError(failed_opcodes))
@@ -565,7 +654,7 @@ impl Program {
         let mut pc = 0;
 
         while pc < bytecode.len() {
-            match Self::parse_operation(bytecode, pc) {
+            match Self::parse_operation(bytecode, pc, false) {
                 Ok((op, new_pc)) => {
                     operations.push(op);
                     pc = new_pc;
@@ -582,23 +671,145 @@ impl Program {
         Program {
             operations,
             code_size,
+            eof: None,
+        }
+    }
+
+    /// Parses the code sections of a container, which must have been validated
+    pub fn from_eof(container: EofContainer) -> Self {
+        let code = container.code_sections.concat();
+        let mut operations = vec![];
+        let mut pc = 0;
+
+        while pc < code.len() {
+            let (op, new_pc) = Self::parse_operation(&code, pc, true)
+                .expect("validated EOF code has no undefined opcodes");
+            operations.push(op);
+            pc = new_pc;
+        }
+
+        let code_size = Self::get_codesize(&operations);
+
+        Program {
+            operations,
+            code_size,
+            eof: Some(container),
         }
     }
 
+    /// Parses the code of an account under the rules of `spec_id`. From Prague, code
+    /// starting with the EOF magic is parsed as a container, and runs as a single
+    /// `INVALID` if the container isn't valid.
+    pub fn for_spec(bytecode: &[u8], spec_id: SpecId) -> Self {
+        if !spec_id.is_enabled_in(SpecId::Prague) || !eof::is_eof(bytecode) {
+            return Self::from_bytecode(bytecode);
+        }
+        let container = EofContainer::decode(bytecode).and_then(|container| {
+            container.validate(None)?;
+            Ok(container)
+        });
+        match container {
+            Ok(container) => Self::from_eof(container),
+            Err(_) => Self::from(vec![Operation::Invalid]),
+        }
+    }
+
+    pub fn is_eof(&self) -> bool {
+        self.eof.is_some()
+    }
+
     pub fn to_bytecode(self) -> Vec<u8> {
+        if let Some(container) = self.eof {
+            return container.encode().to_vec();
+        }
         self.operations
             .iter()
             .flat_map(Operation::to_bytecode)
             .collect::<Vec<u8>>()
     }
 
+    /// Parses the operation at `pc`, returning it along with the offset of the next one.
+    /// EOF only opcodes are undefined in legacy code.
     fn parse_operation(
         bytecode: &[u8],
         mut pc: usize,
+        eof: bool,
     ) -> Result<(Operation, usize), OpcodeParseError> {
         let opcode = Opcode::try_from(bytecode[pc])?;
+        let read_u16 = |pc: usize| u16::from_be_bytes([bytecode[pc + 1], bytecode[pc + 2]]);
 
         let op = match opcode {
+            Opcode::DATALOAD
+            | Opcode::DATALOADN
+            | Opcode::DATASIZE
+            | Opcode::DATACOPY
+            | Opcode::RJUMP
+            | Opcode::RJUMPI
+            | Opcode::RJUMPV
+            | Opcode::CALLF
+            | Opcode::RETF
+            | Opcode::JUMPF
+            | Opcode::EOFCREATE
+            | Opcode::RETURNCONTRACT
+            | Opcode::EXTCALL
+            | Opcode::EXTDELEGATECALL
+            | Opcode::EXTSTATICCALL
+                if !eof =>
+            {
+                return Err(OpcodeParseError(bytecode[pc]));
+            }
+            Opcode::DATALOAD => Operation::DataLoad,
+            Opcode::DATALOADN => {
+                let offset = read_u16(pc);
+                pc += 2;
+                Operation::DataLoadN(offset)
+            }
+            Opcode::DATASIZE => Operation::DataSize,
+            Opcode::DATACOPY => Operation::DataCopy,
+            Opcode::RJUMP => {
+                let offset = read_u16(pc) as i16;
+                let op = Operation::Rjump { pc, offset };
+                pc += 2;
+                op
+            }
+            Opcode::RJUMPI => {
+                let offset = read_u16(pc) as i16;
+                let op = Operation::Rjumpi { pc, offset };
+                pc += 2;
+                op
+            }
+            Opcode::RJUMPV => {
+                let count = bytecode[pc + 1] as usize + 1;
+                let offsets = (0..count)
+                    .map(|i| read_u16(pc + 1 + 2 * i) as i16)
+                    .collect();
+                let op = Operation::Rjumpv { pc, offsets };
+                pc += 1 + 2 * count;
+                op
+            }
+            Opcode::CALLF => {
+                let index = read_u16(pc);
+                let op = Operation::Callf { pc, index };
+                pc += 2;
+                op
+            }
+            Opcode::RETF => Operation::Retf,
+            Opcode::JUMPF => {
+                let index = read_u16(pc);
+                pc += 2;
+                Operation::Jumpf(index)
+            }
+            Opcode::EOFCREATE => {
+                pc += 1;
+                Operation::EofCreate(bytecode[pc])
+            }
+            Opcode::RETURNCONTRACT => {
+                pc += 1;
+                Operation::ReturnContract(bytecode[pc])
+            }
+            Opcode::EXTCALL => Operation::ExtCall,
+            Opcode::EXTDELEGATECALL => Operation::ExtDelegateCall,
+            Opcode::EXTSTATICCALL => Operation::ExtStaticCall,
             Opcode::STOP => Operation::Stop,
             Opcode::ADD => Operation::Add,
             Opcode::MUL => Operation::Mul,
@@ -984,6 +1195,13 @@ impl Program {
             .map(|op| match op {
                 // the size in bytes to push + 1 from the PUSHN opcode
                 Operation::Push((size, _)) => (size + 1) as u32,
+                Operation::Rjump { .. }
+                | Operation::Rjumpi { .. }
+                | Operation::Callf { .. }
+                | Operation::Jumpf(_)
+                | Operation::DataLoadN(_) => 3,
+                Operation::Rjumpv { offsets, .. } => 2 + 2 * offsets.len() as u32,
+                Operation::EofCreate(_) | Operation::ReturnContract(_) => 2,
                 _ => 1,
             })
             .sum()
@@ -997,6 +1215,7 @@ impl From<Vec<Operation>> for Program {
         Program {
             operations,
             code_size,
+            eof: None,
         }
     }
 }
@@ -1,18 +1,41 @@
 use crate::{
     db::DatabaseError,
-    primitives::{Address, Bytes, U256},
+    primitives::{Address, Bloom, Bytes, B256, U256},
     state::Account,
     syscall::Log,
 };
 use core::fmt;
+use sha3::{Digest, Keccak256};
 use std::{boxed::Box, collections::HashMap, string::String, vec::Vec};
 
+/// Bloom filter of the addresses and topics of `logs` (2048 bits, with 3 bits set for each)
+pub fn logs_bloom<'a>(logs: impl IntoIterator<Item = &'a Log>) -> Bloom {
+    let mut bloom = Bloom::zero();
+    for log in logs {
+        log.accrue_bloom(&mut bloom);
+    }
+    bloom
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ResultAndState {
     /// Status of execution
     pub result: ExecutionResult,
     /// State that got updated
     pub state: HashMap<Address, Account>,
+    /// Fees paid by the transaction
+    pub fees: TransactionFees,
+}
+
+/// Fees paid by a transaction for the gas it used, after refunds, and its blob gas.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct TransactionFees {
+    /// Price paid for each unit of gas, see [`Env::effective_gas_price`](crate::Env::effective_gas_price)
+    pub effective_gas_price: U256,
+    /// Base fee (EIP-1559, zero before London) and blob fee (EIP-4844) burned
+    pub burned: U256,
+    /// Priority fee paid to the block's coinbase
+    pub tip: U256,
 }
 
 /// Result of a transaction execution.
@@ -84,6 +107,17 @@ impl ExecutionResult {
         }
     }
 
+    /// Keccak256 hash of the RLP encoded list of logs, which the Ethereum tests commit to.
+    pub fn logs_hash(&self) -> B256 {
+        let encoded = rlp::encode_list::<Log, _>(self.logs());
+        B256::from_slice(&Keccak256::digest(&encoded))
+    }
+
+    /// Bloom filter of the addresses and topics of the logs, as in receipts.
+    pub fn logs_bloom(&self) -> Bloom {
+        logs_bloom(self.logs())
+    }
+
     /// Consumes `self` and returns the logs if execution is successful, or an empty list otherwise.
     pub fn into_logs(self) -> Vec<Log> {
         match self {
@@ -248,6 +282,13 @@ pub enum InvalidTransaction {
     BlobVersionNotSupported,
     /// EOF crate should have `to` address
     EofCrateShouldHaveToAddress,
+    /// Authorization list is not supported for blocks before the Prague hardfork.
+    AuthorizationListNotSupported,
+    /// There should be at least one authorization in a set code transaction.
+    EmptyAuthorizationList,
+    /// Set code transaction can't be a create transaction.
+    /// `to` must be present
+    SetCodeCreateTransaction,
 }
 
 impl fmt::Display for InvalidTransaction {
@@ -304,6 +345,11 @@ impl fmt::Display for InvalidTransaction {
             }
             Self::BlobVersionNotSupported => write!(f, "blob version not supported"),
             Self::EofCrateShouldHaveToAddress => write!(f, "EOF crate should have `to` address"),
+            Self::AuthorizationListNotSupported => {
+                write!(f, "authorization list not supported")
+            }
+            Self::EmptyAuthorizationList => write!(f, "empty authorization list"),
+            Self::SetCodeCreateTransaction => write!(f, "set code create transaction"),
         }
     }
 }
@@ -357,6 +403,16 @@ pub enum HaltReason {
     CreateContractStartingWithEF,
     /// EIP-3860: Limit and meter initcode. Initcode size limit exceeded.
     CreateInitCodeSizeLimit,
+    /// The initcode of an EOF creation isn't a valid container
+    InvalidEOFInitCode,
+    /// `RETURNCONTRACT` made the data section of the deployed container too big
+    EofAuxDataOverflow,
+    /// `RETURNCONTRACT` didn't fill the data section declared by the deployed container
+    EofAuxDataTooSmall,
+    /// `CALLF` nested more than the return stack holds
+    EOFFunctionStackOverflow,
+    /// The address given to the `EXTCALL` family has more than 20 bytes
+    InvalidEXTCALLTarget,
 
     /* Internal Halts that can be only found inside Inspector */
     OverflowPayment,
@@ -387,4 +443,86 @@ pub enum PrecompileError {
     NotEnoughGas,
     Secp256k1Error,
     InvalidEcPoint,
+    /// The commitment doesn't match the versioned hash
+    InvalidVersionedHash,
+    /// The KZG proof couldn't be verified
+    InvalidKzgProof,
+    /// The field element has non-zero padding or isn't below the modulus
+    InvalidFieldElement,
+    /// The point isn't in the prime order subgroup of the curve
+    PointNotInSubgroup,
+    /// Error of a custom precompile
+    Other(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::syscall::{LogData, U256 as SyscallU256};
+    use ethereum_types::BloomInput;
+
+    fn success_with_logs(logs: Vec<Log>) -> ExecutionResult {
+        ExecutionResult::Success {
+            reason: SuccessReason::Stop,
+            gas_used: 0,
+            gas_refunded: 0,
+            logs,
+            output: Output::Call(Bytes::new()),
+        }
+    }
+
+    fn log() -> Log {
+        Log {
+            address: Address::from_low_u64_be(1),
+            data: LogData {
+                topics: vec![SyscallU256 { hi: 0, lo: 2 }],
+                data: vec![0xff],
+            },
+        }
+    }
+
+    #[test]
+    fn logs_hash_without_logs_is_the_hash_of_an_empty_list() {
+        let expected =
+            B256::from_str("0x1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347")
+                .unwrap();
+        assert_eq!(success_with_logs(vec![]).logs_hash(), expected);
+        let revert = ExecutionResult::Revert {
+            gas_used: 0,
+            output: Bytes::new(),
+        };
+        assert_eq!(revert.logs_hash(), expected);
+    }
+
+    #[test]
+    fn logs_are_encoded_as_address_topics_and_data() {
+        let log = log();
+
+        let encoded = rlp::encode(&log);
+
+        // A 57 bytes list with the address, a list with the 32 bytes topic and the data
+        assert_eq!(encoded[..3], [0xf8, 0x39, 0x94]);
+        assert_eq!(encoded[3..23], *log.address.as_bytes());
+        assert_eq!(encoded[23..25], [0xe1, 0xa0]);
+        assert_eq!(encoded[25..57], log.data.topics[0].to_fixed_be_bytes());
+        assert_eq!(encoded[57..], [0x81, 0xff]);
+        assert_ne!(
+            success_with_logs(vec![log]).logs_hash(),
+            success_with_logs(vec![]).logs_hash()
+        );
+    }
+
+    #[test]
+    fn logs_bloom_contains_addresses_and_topics() {
+        let log = log();
+
+        let bloom = success_with_logs(vec![log.clone()]).logs_bloom();
+
+        assert!(bloom.contains_input(BloomInput::Raw(log.address.as_bytes())));
+        assert!(bloom.contains_input(BloomInput::Raw(&log.data.topics[0].to_fixed_be_bytes())));
+        assert!(!bloom.contains_input(BloomInput::Raw(&[0xff])));
+        assert_eq!(success_with_logs(vec![]).logs_bloom(), Bloom::zero());
+    }
 }
@@ -0,0 +1,128 @@
+//! # Simulation
+//!
+//! Runs transactions over the state of an [`Evm`] without committing their changes,
+//! like the `eth_call` and `eth_estimateGas` methods of the JSON-RPC API. Simulated
+//! calls usually skip some of the checks of real transactions, which can be disabled
+//! in the [`CfgEnv`](crate::env::CfgEnv) of the [`Evm`].
+use std::fmt;
+
+use thiserror::Error;
+
+use crate::{
+    constants::call_opcode::STIPEND_GAS_ADDITION,
+    db::Database,
+    env::TxEnv,
+    overrides::{OverrideDb, StateOverride},
+    primitives::U256,
+    result::{EVMError, ExecutionResult, InvalidTransaction, ResultAndState},
+    Evm,
+};
+
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum EstimateGasError {
+    #[error("{0}")]
+    Evm(EVMError),
+    /// The transaction doesn't succeed even with the highest gas limit
+    #[error("transaction fails with the highest gas limit")]
+    ExecutionFailed(ExecutionResult),
+}
+
+impl From<EVMError> for EstimateGasError {
+    fn from(error: EVMError) -> Self {
+        Self::Evm(error)
+    }
+}
+
+impl<DB> Evm<DB>
+where
+    DB: Database,
+    DB::Error: fmt::Display,
+{
+    /// Runs `tx` over the current state, returning its result and the changes it would
+    /// make without committing them. The [configured transaction](crate::Env::tx) is
+    /// left untouched.
+    pub fn simulate(&mut self, tx: TxEnv) -> Result<ResultAndState, EVMError> {
+        let configured_tx = std::mem::replace(&mut self.env.tx, tx);
+        let result = self.transact();
+        self.env.tx = configured_tx;
+        result
+    }
+
+    /// Runs `tx` like [`simulate`](Self::simulate), but reading the state through the
+    /// given `overrides`. The database is left untouched, and the returned changes are
+    /// relative to the overridden state.
+    pub fn simulate_with_overrides(
+        &mut self,
+        tx: TxEnv,
+        overrides: &StateOverride,
+    ) -> Result<ResultAndState, EVMError> {
+        let mut env = self.env.clone();
+        env.tx = tx;
+        let mut evm = Evm {
+            env,
+            db: OverrideDb::new(&mut self.db, overrides),
+            executor_cache: self.executor_cache.clone(),
+            precompiles: self.precompiles.clone(),
+            inspector: self.inspector.take(),
+        };
+        let result = evm.transact();
+        self.inspector = evm.inspector.take();
+        result
+    }
+
+    /// Estimates the lowest gas limit `tx` succeeds with, up to its gas limit and the
+    /// block's one, unless [disabled](crate::env::CfgEnv::disable_block_gas_limit).
+    ///
+    /// Calls only get 63/64 of the gas left (EIP-150), so a transaction can need a
+    /// higher gas limit than the gas it uses. The limit is binary searched, starting
+    /// from a guess that covers what is held back by a few nested calls. Every attempt
+    /// runs the programs compiled by the first one, kept in the executor cache.
+    pub fn estimate_gas(&mut self, tx: TxEnv) -> Result<u64, EstimateGasError> {
+        let mut high = tx.gas_limit;
+        if !self.env.cfg.disable_block_gas_limit {
+            high = high.min(self.env.block.gas_limit.min(U256::from(u64::MAX)).as_u64());
+        }
+        let ResultAndState { result, .. } = self.simulate(TxEnv {
+            gas_limit: high,
+            ..tx.clone()
+        })?;
+        if !result.is_success() {
+            return Err(EstimateGasError::ExecutionFailed(result));
+        }
+
+        // The gas used is charged before refunds, so any lower limit runs out of gas
+        let mut low = result.gas_used().saturating_sub(1);
+        let optimistic = (result.gas_used() + STIPEND_GAS_ADDITION) * 64 / 63;
+        if optimistic < high {
+            if self.succeeds_with_gas_limit(&tx, optimistic)? {
+                high = optimistic;
+            } else {
+                low = optimistic;
+            }
+        }
+        while low + 1 < high {
+            let gas_limit = low + (high - low) / 2;
+            if self.succeeds_with_gas_limit(&tx, gas_limit)? {
+                high = gas_limit;
+            } else {
+                low = gas_limit;
+            }
+        }
+        Ok(high)
+    }
+
+    fn succeeds_with_gas_limit(&mut self, tx: &TxEnv, gas_limit: u64) -> Result<bool, EVMError> {
+        let tx = TxEnv {
+            gas_limit,
+            ..tx.clone()
+        };
+        match self.simulate(tx) {
+            Ok(ResultAndState { result, .. }) => Ok(result.is_success()),
+            // The gas limit doesn't even cover the intrinsic cost
+            Err(EVMError::Transaction(InvalidTransaction::CallGasCostMoreThanGasLimit)) => {
+                Ok(false)
+            }
+            Err(error) => Err(error),
+        }
+    }
+}
@@ -0,0 +1,266 @@
+//! # Hardforks
+//!
+//! The rules of the EVM changed with each hardfork of the network. The [`SpecId`] in
+//! [`CfgEnv`](crate::env::CfgEnv) selects which of them a transaction runs under:
+//! operations introduced by a later fork halt with
+//! [`HaltReason::NotActivated`](crate::result::HaltReason::NotActivated), and gas is
+//! charged following the fork's [`GasSchedule`].
+//!
+//! Programs are compiled for a single spec, so the executor cache keeps them apart.
+use std::{fmt, str::FromStr};
+
+use thiserror::Error;
+
+use crate::{constants::gas_cost, program::Operation};
+
+/// Ethereum hardforks, in activation order
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum SpecId {
+    Frontier,
+    FrontierThawing,
+    Homestead,
+    DaoFork,
+    Tangerine,
+    SpuriousDragon,
+    Byzantium,
+    Constantinople,
+    Petersburg,
+    Istanbul,
+    MuirGlacier,
+    Berlin,
+    London,
+    ArrowGlacier,
+    GrayGlacier,
+    Merge,
+    Shanghai,
+    #[default]
+    Cancun,
+    Prague,
+}
+
+impl SpecId {
+    /// Whether the changes introduced by `fork` apply to this spec
+    pub fn is_enabled_in(self, fork: SpecId) -> bool {
+        self >= fork
+    }
+
+    /// Whether `SSTORE` is charged by net gas metering (EIP-1283 and EIP-2200).
+    /// EIP-1283 was removed by Petersburg and replaced by EIP-2200 in Istanbul.
+    pub fn has_sstore_net_metering(self) -> bool {
+        self == SpecId::Constantinople || self.is_enabled_in(SpecId::Istanbul)
+    }
+
+    pub fn gas_schedule(self) -> GasSchedule {
+        let is_enabled = |fork| self.is_enabled_in(fork);
+        GasSchedule {
+            sload: match self {
+                _ if is_enabled(SpecId::Istanbul) => 800,
+                _ if is_enabled(SpecId::Tangerine) => 200,
+                _ => 50,
+            },
+            balance: match self {
+                _ if is_enabled(SpecId::Istanbul) => 700,
+                _ if is_enabled(SpecId::Tangerine) => 400,
+                _ => 20,
+            },
+            extcode: if is_enabled(SpecId::Tangerine) {
+                700
+            } else {
+                20
+            },
+            extcodehash: if is_enabled(SpecId::Istanbul) {
+                700
+            } else {
+                400
+            },
+            call: if is_enabled(SpecId::Tangerine) {
+                700
+            } else {
+                40
+            },
+            selfdestruct: if is_enabled(SpecId::Tangerine) {
+                gas_cost::SELFDESTRUCT
+            } else {
+                0
+            },
+            exp_byte: if is_enabled(SpecId::SpuriousDragon) {
+                50
+            } else {
+                10
+            },
+            tx_data_non_zero: if is_enabled(SpecId::Istanbul) {
+                gas_cost::TX_DATA_COST_PER_NON_ZERO
+            } else {
+                68
+            },
+            max_refund_quotient: if is_enabled(SpecId::London) { 5 } else { 2 },
+            sstore_clears_refund: if is_enabled(SpecId::London) {
+                4_800
+            } else {
+                15_000
+            },
+            selfdestruct_refund: if is_enabled(SpecId::London) {
+                0
+            } else {
+                24_000
+            },
+        }
+    }
+}
+
+impl fmt::Display for SpecId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+#[error("unknown spec: {0}")]
+pub struct UnknownSpecError(pub String);
+
+/// Parses the fork names used by the [Ethereum tests](https://github.com/ethereum/tests)
+impl FromStr for SpecId {
+    type Err = UnknownSpecError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let spec_id = match name {
+            "Frontier" => SpecId::Frontier,
+            "FrontierThawing" => SpecId::FrontierThawing,
+            "Homestead" => SpecId::Homestead,
+            "DaoFork" => SpecId::DaoFork,
+            "EIP150" | "Tangerine" => SpecId::Tangerine,
+            "EIP158" | "SpuriousDragon" => SpecId::SpuriousDragon,
+            "Byzantium" => SpecId::Byzantium,
+            "Constantinople" => SpecId::Constantinople,
+            "ConstantinopleFix" | "Petersburg" => SpecId::Petersburg,
+            "Istanbul" => SpecId::Istanbul,
+            "MuirGlacier" => SpecId::MuirGlacier,
+            "Berlin" => SpecId::Berlin,
+            "London" => SpecId::London,
+            "ArrowGlacier" => SpecId::ArrowGlacier,
+            "GrayGlacier" => SpecId::GrayGlacier,
+            "Merge" | "Paris" => SpecId::Merge,
+            "Shanghai" => SpecId::Shanghai,
+            "Cancun" => SpecId::Cancun,
+            "Prague" => SpecId::Prague,
+            _ => return Err(UnknownSpecError(name.to_string())),
+        };
+        Ok(spec_id)
+    }
+}
+
+/// Gas costs that changed between hardforks.
+///
+/// From Berlin on, accesses to accounts and storage slots cost depending on whether
+/// they are warm (EIP-2929), and the `sload`, `balance`, `extcode`, `extcodehash` and
+/// `call` costs are replaced by the warm and cold costs in
+/// [`gas_cost`](crate::constants::gas_cost).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct GasSchedule {
+    pub sload: i64,
+    pub balance: i64,
+    /// Base cost of `EXTCODESIZE` and `EXTCODECOPY`
+    pub extcode: i64,
+    pub extcodehash: i64,
+    /// Base cost of the `CALL` family
+    pub call: i64,
+    pub selfdestruct: i64,
+    /// Cost of each byte of the `EXP` exponent
+    pub exp_byte: i64,
+    pub tx_data_non_zero: u64,
+    /// The refund is capped to the gas used divided by this
+    pub max_refund_quotient: u64,
+    /// Refund for setting a storage slot to zero
+    pub sstore_clears_refund: i64,
+    pub selfdestruct_refund: u64,
+}
+
+impl Operation {
+    /// Hardfork that introduced the operation
+    pub fn introduced_in(&self) -> SpecId {
+        match self {
+            Operation::DelegateCall => SpecId::Homestead,
+            Operation::ReturnDataSize
+            | Operation::ReturnDataCopy
+            | Operation::StaticCall
+            | Operation::Revert => SpecId::Byzantium,
+            Operation::Shl
+            | Operation::Shr
+            | Operation::Sar
+            | Operation::ExtcodeHash
+            | Operation::Create2 => SpecId::Constantinople,
+            Operation::Chainid | Operation::SelfBalance => SpecId::Istanbul,
+            Operation::Basefee => SpecId::London,
+            Operation::Push0 => SpecId::Shanghai,
+            Operation::Tload
+            | Operation::Tstore
+            | Operation::Mcopy
+            | Operation::BlobHash
+            | Operation::BlobBaseFee => SpecId::Cancun,
+            Operation::Rjump { .. }
+            | Operation::Rjumpi { .. }
+            | Operation::Rjumpv { .. }
+            | Operation::Callf { .. }
+            | Operation::Retf
+            | Operation::Jumpf(_)
+            | Operation::DataLoad
+            | Operation::DataLoadN(_)
+            | Operation::DataSize
+            | Operation::DataCopy
+            | Operation::EofCreate(_)
+            | Operation::ReturnContract(_)
+            | Operation::ExtCall
+            | Operation::ExtDelegateCall
+            | Operation::ExtStaticCall => SpecId::Prague,
+            _ => SpecId::Frontier,
+        }
+    }
+
+    pub fn is_activated_in(&self, spec_id: SpecId) -> bool {
+        spec_id.is_enabled_in(self.introduced_in())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forks_are_enabled_in_later_specs() {
+        assert!(SpecId::Cancun.is_enabled_in(SpecId::Berlin));
+        assert!(SpecId::Berlin.is_enabled_in(SpecId::Berlin));
+        assert!(!SpecId::Istanbul.is_enabled_in(SpecId::Berlin));
+    }
+
+    #[test]
+    fn parses_ethereum_tests_fork_names() {
+        assert_eq!("EIP150".parse(), Ok(SpecId::Tangerine));
+        assert_eq!("ConstantinopleFix".parse(), Ok(SpecId::Petersburg));
+        assert_eq!("Cancun".parse(), Ok(SpecId::Cancun));
+        assert_eq!(
+            "Osaka".parse::<SpecId>(),
+            Err(UnknownSpecError("Osaka".to_string()))
+        );
+    }
+
+    #[test]
+    fn operations_are_activated_by_their_fork() {
+        assert!(!Operation::Push0.is_activated_in(SpecId::Merge));
+        assert!(Operation::Push0.is_activated_in(SpecId::Shanghai));
+        assert!(!Operation::Revert.is_activated_in(SpecId::Homestead));
+        assert!(Operation::Add.is_activated_in(SpecId::Frontier));
+        assert!(!Operation::Retf.is_activated_in(SpecId::Cancun));
+        assert!(Operation::Retf.is_activated_in(SpecId::Prague));
+    }
+
+    #[test]
+    fn refund_rules_changed_in_london() {
+        let berlin = SpecId::Berlin.gas_schedule();
+        let london = SpecId::London.gas_schedule();
+        assert_eq!(berlin.max_refund_quotient, 2);
+        assert_eq!(london.max_refund_quotient, 5);
+        assert_eq!(london.sstore_clears_refund, 4_800);
+        assert_eq!(london.selfdestruct_refund, 0);
+    }
+}
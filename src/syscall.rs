@@ -18,26 +18,37 @@
 use std::ffi::c_void;
 
 use crate::{
+    cache::{self, ExecutorCache, SharedExecutorCache},
     constants::{
         call_opcode::{self},
+        eof::MAX_RETURN_STACK_HEIGHT,
         gas_cost::{self, MAX_CODE_SIZE},
-        return_codes, CallType,
+        return_codes, CallType, CALL_STACK_LIMIT, SYSTEM_ADDRESS,
     },
-    context::Context,
-    db::AccountInfo,
+    db::{AccountInfo, Bytecode},
     env::{Env, TransactTo},
-    executor::{Executor, OptLevel},
-    journal::Journal,
-    precompiles::*,
-    primitives::{Address, Bytes, B256, U256 as EU256},
+    eof::{self, ContainerKind, EofContainer},
+    inspector::{BoxedInspector, CallInputs, CreateInputs, Step},
+    journal::{Journal, JournalCheckpoint},
+    precompiles::{run_precompile, Precompiles},
+    primitives::{Address, Bloom, Bytes, B256, U256 as EU256},
     program::Program,
-    result::{EVMError, ExecutionResult, HaltReason, Output, ResultAndState, SuccessReason},
-    state::AccountStatus,
-    utils::{compute_contract_address, compute_contract_address2},
+    result::{
+        EVMError, ExecutionResult, HaltReason, Output, ResultAndState, SuccessReason,
+        TransactionFees,
+    },
+    spec::{GasSchedule, SpecId},
+    state::{Account, AccountStatus},
+    utils::{
+        compute_contract_address, compute_contract_address2, delegated_address,
+        delegation_designator,
+    },
 };
+use ethereum_types::BloomInput;
 use melior::ExecutionEngine;
+use rlp::{Encodable, RlpStream};
 use sha3::{Digest, Keccak256};
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
 /// Function type for the main entrypoint of the generated code
 pub type MainFunc = extern "C" fn(&mut SyscallContext, initial_gas: u64) -> u8;
@@ -58,6 +69,13 @@ impl U256 {
         U256 { hi, lo }
     }
 
+    pub fn to_fixed_be_bytes(&self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        bytes[..16].copy_from_slice(&self.hi.to_be_bytes());
+        bytes[16..].copy_from_slice(&self.lo.to_be_bytes());
+        bytes
+    }
+
     pub fn copy_from(&mut self, value: &Address) {
         let mut buffer = [0u8; 32];
         buffer[12..32].copy_from_slice(&value.0);
@@ -90,6 +108,8 @@ pub enum ExitStatusCode {
     Stop,
     Revert,
     Error,
+    /// Initcode ended with `RETURNCONTRACT`
+    ReturnContract,
     Default,
 }
 impl ExitStatusCode {
@@ -103,6 +123,7 @@ impl ExitStatusCode {
             x if x == Self::Stop.to_u8() => Self::Stop,
             x if x == Self::Revert.to_u8() => Self::Revert,
             x if x == Self::Error.to_u8() => Self::Error,
+            x if x == Self::ReturnContract.to_u8() => Self::ReturnContract,
             _ => Self::Default,
         }
     }
@@ -120,7 +141,10 @@ pub struct InnerContext {
     pub gas_remaining: Option<u64>,
     gas_refund: u64,
     exit_status: Option<ExitStatusCode>,
-    logs: Vec<LogData>,
+    /// Logs emitted by this frame and by the successful frames nested in it, in order
+    logs: Vec<Log>,
+    /// Container deployed by `RETURNCONTRACT`, and its address if known
+    deployed_container: Option<(Bytes, Option<Address>)>,
 }
 
 impl InnerContext {
@@ -166,6 +190,10 @@ pub struct CallFrame {
     pub caller: Address,
     ctx_is_static: bool,
     last_call_return_data: Vec<u8>,
+    /// Amount of frames this one is nested in
+    depth: usize,
+    /// Offsets the code sections entered with `CALLF` return to
+    return_stack: Vec<u32>,
 }
 
 impl CallFrame {
@@ -176,6 +204,27 @@ impl CallFrame {
             ..Default::default()
         }
     }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Whether calls and creates from this frame fail for being too deep
+    fn is_at_depth_limit(&self) -> bool {
+        self.depth >= CALL_STACK_LIMIT
+    }
+}
+
+/// Stack space that must be left for a nested frame to run on the current stack
+const STACK_RED_ZONE: usize = 256 * 1024;
+/// Size of the stacks allocated when the current one runs out of space
+const STACK_GROWTH_SIZE: usize = 4 * 1024 * 1024;
+
+/// Runs `f`, a nested frame, on a heap allocated stack if the current one is close
+/// to its end. Each frame uses native stack for the compiled code and the syscalls
+/// it calls into, so frames near the call depth limit would overflow it otherwise.
+fn with_stack_space<R>(f: impl FnOnce() -> R) -> R {
+    stacker::maybe_grow(STACK_RED_ZONE, STACK_GROWTH_SIZE, f)
 }
 
 /// The context passed to syscalls
@@ -188,6 +237,9 @@ pub struct SyscallContext<'c> {
     pub halt_reason: Option<HaltReason>,
     initial_gas: u64,
     pub transient_storage: HashMap<(Address, EU256), EU256>, // TODO: Move this to Journal
+    executor_cache: SharedExecutorCache,
+    precompiles: Arc<Precompiles>,
+    inspector: Option<BoxedInspector>,
 }
 
 #[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
@@ -202,9 +254,33 @@ pub struct Log {
     pub data: LogData,
 }
 
+impl Log {
+    /// Adds the address and topics of the log to `bloom`
+    pub fn accrue_bloom(&self, bloom: &mut Bloom) {
+        bloom.accrue(BloomInput::Raw(self.address.as_bytes()));
+        for topic in &self.data.topics {
+            bloom.accrue(BloomInput::Raw(&topic.to_fixed_be_bytes()));
+        }
+    }
+}
+
+/// Logs are encoded as `rlp([address, [topics...], data])`, like in receipts
+impl Encodable for Log {
+    fn rlp_append(&self, stream: &mut RlpStream) {
+        stream.begin_list(3);
+        stream.append(&self.address);
+        stream.begin_list(self.data.topics.len());
+        for topic in &self.data.topics {
+            stream.append(&B256::from(topic.to_fixed_be_bytes()));
+        }
+        stream.append(&self.data.data);
+    }
+}
+
 /// Accessors for disponibilizing the execution results
 impl<'c> SyscallContext<'c> {
     pub fn new(env: Env, journal: Journal<'c>, call_frame: CallFrame, initial_gas: u64) -> Self {
+        let precompiles = Arc::new(Precompiles::new(env.cfg.spec_id));
         Self {
             env,
             initial_gas,
@@ -213,7 +289,253 @@ impl<'c> SyscallContext<'c> {
             halt_reason: None,
             inner_context: Default::default(),
             transient_storage: Default::default(),
+            executor_cache: ExecutorCache::shared(Default::default()),
+            precompiles,
+            inspector: None,
+        }
+    }
+
+    /// Sets the cache used to look up the executors of called and created contracts.
+    pub fn with_executor_cache(mut self, executor_cache: SharedExecutorCache) -> Self {
+        self.executor_cache = executor_cache;
+        self
+    }
+
+    /// Sets the precompiles that can be called, instead of the mainnet ones of the spec.
+    pub fn with_precompiles(mut self, precompiles: Arc<Precompiles>) -> Self {
+        self.precompiles = precompiles;
+        self
+    }
+
+    /// Sets the inspector notified of the calls, creations, logs and steps of the execution.
+    pub fn with_inspector(mut self, inspector: BoxedInspector) -> Self {
+        self.inspector = Some(inspector);
+        self
+    }
+
+    /// Takes the inspector out of the context, to give it back to its owner.
+    pub fn take_inspector(&mut self) -> Option<BoxedInspector> {
+        self.inspector.take()
+    }
+
+    /// Returns whether executed programs should report each operation to the inspector.
+    pub fn steps_enabled(&self) -> bool {
+        self.inspector
+            .as_ref()
+            .is_some_and(|inspector| inspector.step_enabled())
+    }
+
+    fn spec_id(&self) -> SpecId {
+        self.env.cfg.spec_id
+    }
+
+    /// Gas cost of accessing an account or storage slot. From Berlin on it depends on
+    /// whether it is cold, before that it's the spec's cost selected by `cost_before_berlin`.
+    fn access_cost(
+        &self,
+        is_cold: bool,
+        cold_cost: i64,
+        warm_cost: i64,
+        cost_before_berlin: impl Fn(&GasSchedule) -> i64,
+    ) -> i64 {
+        let spec_id = self.spec_id();
+        if !spec_id.is_enabled_in(SpecId::Berlin) {
+            cost_before_berlin(&spec_id.gas_schedule())
+        } else if is_cold {
+            cold_cost
+        } else {
+            warm_cost
+        }
+    }
+
+    /// Gas cost of a `CALL` for the account it creates. Before Spurious Dragon calling an
+    /// account that doesn't exist creates it, since then (EIP-161) only sending value to an
+    /// empty account is charged.
+    fn new_account_cost(&self, callee: Option<&AccountInfo>, value: EU256) -> u64 {
+        let creates_account = if self.spec_id().is_enabled_in(SpecId::SpuriousDragon) {
+            !value.is_zero() && callee.map_or(true, AccountInfo::is_empty)
+        } else {
+            callee.is_none()
+        };
+        if creates_account {
+            call_opcode::EMPTY_CALLEE_COST
+        } else {
+            0
+        }
+    }
+
+    /// Applies the authorizations of a set code transaction (EIP-7702), writing a
+    /// delegation designator into the code of each authority. Invalid authorizations
+    /// are skipped, and authorities that already exist get part of the intrinsic cost
+    /// refunded.
+    pub fn apply_authorization_list(&mut self) {
+        let Some(authorization_list) = self.env.tx.authorization_list.clone() else {
+            return;
+        };
+        let chain_id = EU256::from(self.env.cfg.chain_id);
+        for authorization in authorization_list {
+            if !authorization.chain_id.is_zero() && authorization.chain_id != chain_id {
+                continue;
+            }
+            if authorization.nonce == u64::MAX {
+                continue;
+            }
+            let Some(authority) = authorization.recover_authority() else {
+                continue;
+            };
+
+            let account = self.journal.get_account(&authority);
+            self.journal.add_account_as_warm(authority);
+            let code = self.journal.account_code(&authority);
+            if !code.is_empty() && delegated_address(&code).is_none() {
+                continue;
+            }
+            let nonce = account.as_ref().map_or(0, |account| account.nonce);
+            if nonce != authorization.nonce {
+                continue;
+            }
+
+            if account.is_some_and(|account| !account.is_empty()) {
+                self.inner_context.gas_refund +=
+                    gas_cost::PER_EMPTY_ACCOUNT_COST - gas_cost::PER_AUTH_BASE_COST;
+            }
+            // Delegating to the zero address clears the delegation
+            let code = if authorization.address.is_zero() {
+                Bytecode::new()
+            } else {
+                delegation_designator(authorization.address)
+            };
+            self.journal.set_code(&authority, code);
+            self.journal.set_nonce(&authority, nonce + 1);
+        }
+    }
+
+    /// Charges the caller for the whole gas limit and the blob gas of the transaction up
+    /// front, creating its account if it doesn't exist, and returns its nonce. Calls bump
+    /// the nonce here, while creations bump it once the address of the new account is
+    /// computed.
+    pub fn charge_caller(&mut self) -> u64 {
+        let caller = self.env.tx.caller;
+        let account = match self.journal.get_account(&caller) {
+            Some(account) => account,
+            None => {
+                self.journal.new_account(caller, EU256::zero());
+                AccountInfo::default()
+            }
+        };
+        let gas_cost = EU256::from(self.initial_gas) * self.env.effective_gas_price();
+        let fee = gas_cost + self.env.blob_fee();
+        self.journal
+            .set_balance(&caller, account.balance.saturating_sub(fee));
+        if matches!(self.env.tx.transact_to, TransactTo::Call(_)) {
+            self.journal.set_nonce(&caller, account.nonce + 1);
         }
+        account.nonce
+    }
+
+    /// Sends the value of a call transaction from the caller to the called account,
+    /// creating the account if it doesn't exist, or touching it otherwise. The caller
+    /// can afford it, as the value was checked along with the gas when validating the
+    /// transaction.
+    pub fn transfer_value(&mut self) {
+        let TransactTo::Call(to) = self.env.tx.transact_to else {
+            return;
+        };
+        if self.journal.get_account(&to).is_none() {
+            self.journal.new_account(to, EU256::zero());
+        }
+        self.journal
+            .transfer(&self.env.tx.caller, &to, self.env.tx.value);
+    }
+
+    /// Result of the transaction, with its fees settled in the resulting state.
+    ///
+    /// Changes made since `checkpoint` are reverted if the execution wasn't successful,
+    /// keeping the caller's nonce bumped. The caller gets the unused and refunded gas
+    /// back, and the block's coinbase is paid the priority fee for the rest, while the
+    /// base fee is burned.
+    pub fn transaction_result(
+        &mut self,
+        checkpoint: JournalCheckpoint,
+        caller_nonce: u64,
+    ) -> Result<ResultAndState, EVMError> {
+        let result = self.execution_result()?;
+        let caller = self.env.tx.caller;
+        if result.is_success() {
+            self.journal.commit_checkpoint(checkpoint);
+        } else {
+            self.journal.revert_checkpoint(checkpoint);
+            self.journal.set_nonce(&caller, caller_nonce + 1);
+        }
+
+        let gas_price = self.env.effective_gas_price();
+        let gas_spent = result.gas_used() - result.gas_refunded();
+        let reimbursement = EU256::from(self.initial_gas.saturating_sub(gas_spent)) * gas_price;
+        let caller_balance = self
+            .journal
+            .get_account(&caller)
+            .map(|account| account.balance)
+            .unwrap_or_default();
+        self.journal
+            .set_balance(&caller, caller_balance.saturating_add(reimbursement));
+
+        let priority_fee = self.env.priority_fee_per_gas();
+        let fees = TransactionFees {
+            effective_gas_price: gas_price,
+            burned: EU256::from(gas_spent) * (gas_price - priority_fee) + self.env.blob_fee(),
+            tip: EU256::from(gas_spent) * priority_fee,
+        };
+        if !fees.tip.is_zero() {
+            let coinbase = self.env.block.coinbase;
+            match self.journal.get_account(&coinbase) {
+                Some(account) => self
+                    .journal
+                    .set_balance(&coinbase, account.balance.saturating_add(fees.tip)),
+                None => self.journal.new_account(coinbase, fees.tip),
+            }
+        }
+
+        let state = self.final_state();
+        Ok(ResultAndState {
+            result,
+            state,
+            fees,
+        })
+    }
+
+    /// State changed by the transaction. From Spurious Dragon on, accounts touched and
+    /// left empty are removed (EIP-161), so they're marked as self-destructed.
+    fn final_state(&self) -> HashMap<Address, Account> {
+        let mut state = self.journal.into_state();
+        if self.spec_id().is_enabled_in(SpecId::SpuriousDragon) {
+            for account in state.values_mut() {
+                if (account.is_touched() || account.is_created()) && account.info.is_empty() {
+                    account.status |= AccountStatus::SelfDestructed;
+                }
+            }
+        }
+        state
+    }
+
+    /// Result of a system call, whose changes are only kept if it was successful. The
+    /// system address only sends the call, so it's left out of the resulting state.
+    pub fn system_call_result(
+        &mut self,
+        checkpoint: JournalCheckpoint,
+    ) -> Result<ResultAndState, EVMError> {
+        let result = self.execution_result()?;
+        if result.is_success() {
+            self.journal.commit_checkpoint(checkpoint);
+        } else {
+            self.journal.revert_checkpoint(checkpoint);
+        }
+        let mut state = self.final_state();
+        state.remove(&Address::from(SYSTEM_ADDRESS));
+        Ok(ResultAndState {
+            result,
+            state,
+            fees: Default::default(),
+        })
     }
 
     pub fn return_values(&self) -> &[u8] {
@@ -225,17 +547,29 @@ impl<'c> SyscallContext<'c> {
     }
 
     pub fn logs(&self) -> Vec<Log> {
-        self.inner_context
-            .logs
-            .iter()
-            .map(|logdata| Log {
-                address: self.env.tx.get_address(),
-                data: logdata.clone(),
-            })
-            .collect()
+        self.inner_context.logs.clone()
     }
 
     pub fn get_result(&self) -> Result<ResultAndState, EVMError> {
+        let result = self.execution_result()?;
+        // TODO: Check if this is ok
+        let state = self.final_state();
+
+        Ok(ResultAndState {
+            result,
+            state,
+            fees: Default::default(),
+        })
+    }
+
+    /// Result of the execution, without building the resulting state.
+    ///
+    /// Nested calls use this instead of [`SyscallContext::get_result`], since their
+    /// changes stay in the journal.
+    pub fn execution_result(&self) -> Result<ExecutionResult, EVMError> {
+        if let Some(error) = self.journal.db_error() {
+            return Err(EVMError::Database(error.clone()));
+        }
         let gas_remaining = self.inner_context.gas_remaining.unwrap_or(0);
         let gas_initial = self.initial_gas;
         // TODO: Probably here we need to add the access_list_cost to gas_used, but we need a refactor of most tests
@@ -243,7 +577,7 @@ impl<'c> SyscallContext<'c> {
         let gas_refunded = self
             .inner_context
             .gas_refund
-            .min(gas_used / GAS_REFUND_DENOMINATOR);
+            .min(gas_used / self.spec_id().gas_schedule().max_refund_quotient);
         let exit_status = self
             .inner_context
             .exit_status
@@ -266,6 +600,20 @@ impl<'c> SyscallContext<'c> {
                 output: Output::Call(return_values.into()), // TODO: add case Output::Create
                 logs: self.logs(),
             },
+            ExitStatusCode::ReturnContract => {
+                let (container, address) = self
+                    .inner_context
+                    .deployed_container
+                    .clone()
+                    .unwrap_or_default();
+                ExecutionResult::Success {
+                    reason: SuccessReason::EofReturnContract,
+                    gas_used,
+                    gas_refunded,
+                    output: Output::Create(container, address),
+                    logs: self.logs(),
+                }
+            }
             ExitStatusCode::Revert => ExecutionResult::Revert {
                 output: return_values.into(),
                 gas_used,
@@ -276,10 +624,270 @@ impl<'c> SyscallContext<'c> {
             },
         };
 
-        // TODO: Check if this is ok
-        let state = self.journal.into_state();
+        Ok(result)
+    }
+
+    /// Runs the code of `code_address` in a nested frame, reporting it to the inspector.
+    /// The `(from, to, value)` transfer is made within the frame, so along with the rest
+    /// of its changes it's kept only if the frame succeeds, and the frame reverts
+    /// without running if it can't be made. Returns `None` if the database failed,
+    /// carrying the error over to our journal to fail the transaction.
+    fn execute_call_frame(
+        &mut self,
+        env: Env,
+        call_frame: CallFrame,
+        inputs: &CallInputs,
+        code_address: Address,
+        value_transfer: Option<(Address, Address, EU256)>,
+    ) -> Option<ExecutionResult> {
+        if let Some(inspector) = self.inspector.as_mut() {
+            inspector.call(inputs);
+        }
+
+        let bytecode = self.journal.code_by_address(&code_address);
+        let code_hash = cache::code_hash(&bytecode);
+        let executor = ExecutorCache::checkout(
+            &self.executor_cache,
+            code_hash,
+            &bytecode,
+            self.spec_id(),
+            self.steps_enabled(),
+        )
+        .expect("failed to compile program");
+
+        let gas_limit = env.tx.gas_limit;
+        let mut journal = self.journal.eject_base();
+        if let Some((from, to, value)) = value_transfer {
+            if !journal.transfer(&from, &to, value) {
+                self.journal.extend_from_reverted(journal);
+                let result = ExecutionResult::Revert {
+                    output: Bytes::new(),
+                    gas_used: 0,
+                };
+                if let Some(inspector) = self.inspector.as_mut() {
+                    inspector.call_end(inputs, &result);
+                }
+                return Some(result);
+            }
+        }
+        let mut context = SyscallContext::new(env, journal, call_frame, gas_limit)
+            .with_executor_cache(self.executor_cache.clone())
+            .with_precompiles(self.precompiles.clone());
+        context.inner_context.program = bytecode.to_vec();
+        context.inspector = self.inspector.take();
+
+        with_stack_space(|| executor.execute(&mut context, gas_limit));
+        self.executor_cache
+            .lock()
+            .unwrap()
+            .release(code_hash, &executor);
+        self.inspector = context.inspector.take();
+
+        let Ok(result) = context.execution_result() else {
+            self.journal.extend_from_reverted(context.journal);
+            return None;
+        };
+        if let Some(inspector) = self.inspector.as_mut() {
+            inspector.call_end(inputs, &result);
+        }
+
+        if result.is_success() {
+            self.journal.extend_from_successful(context.journal);
+            self.inner_context
+                .logs
+                .append(&mut context.inner_context.logs);
+        } else {
+            self.journal.extend_from_reverted(context.journal);
+        }
+        Some(result)
+    }
+
+    /// Runs an EOF initcontainer in a nested frame creating `address`, and deploys the
+    /// container it returns with `RETURNCONTRACT` (EIP-7620). The gas used by a
+    /// successful creation includes the code deposit. Returns `None` if the database
+    /// failed, carrying the error over to our journal to fail the transaction.
+    fn run_eof_initcode(
+        &mut self,
+        address: Address,
+        initcontainer: Bytes,
+        input: Bytes,
+        value: EU256,
+        gas_limit: u64,
+        salt: Option<EU256>,
+    ) -> Option<ExecutionResult> {
+        let sender_address = self.env.tx.get_address();
+        if self.journal.account_collides(&address) {
+            return Some(ExecutionResult::Halt {
+                reason: HaltReason::CreateCollision,
+                gas_used: gas_limit,
+            });
+        }
+        self.journal.add_account_as_warm(address);
+
+        let mut env = self.env.clone();
+        env.tx.transact_to = TransactTo::Call(address);
+        env.tx.gas_limit = gas_limit;
+        env.tx.value = value;
+        env.tx.data = input;
+        let call_frame = CallFrame {
+            depth: self.call_frame.depth + 1,
+            ..CallFrame::new(sender_address)
+        };
+
+        let inputs = CreateInputs {
+            caller: sender_address,
+            address,
+            value,
+            init_code: initcontainer.clone(),
+            gas_limit,
+            salt,
+        };
+        if let Some(inspector) = self.inspector.as_mut() {
+            inspector.create(&inputs);
+        }
+
+        let code_hash = cache::code_hash(&initcontainer);
+        let executor = ExecutorCache::checkout(
+            &self.executor_cache,
+            code_hash,
+            &initcontainer,
+            self.spec_id(),
+            self.steps_enabled(),
+        )
+        .expect("failed to compile program");
+
+        let journal = self.journal.eject_base();
+        let mut context = SyscallContext::new(env, journal, call_frame, gas_limit)
+            .with_executor_cache(self.executor_cache.clone())
+            .with_precompiles(self.precompiles.clone());
+        context.journal.new_account(address, value);
+        context.inner_context.program = initcontainer.to_vec();
+        context.inspector = self.inspector.take();
+        with_stack_space(|| executor.execute(&mut context, gas_limit));
+        self.executor_cache
+            .lock()
+            .unwrap()
+            .release(code_hash, &executor);
+        self.inspector = context.inspector.take();
+
+        let Ok(result) = context.execution_result() else {
+            self.journal.extend_from_reverted(context.journal);
+            return None;
+        };
+
+        let result = match result {
+            ExecutionResult::Success {
+                reason,
+                gas_used,
+                gas_refunded,
+                logs,
+                output: Output::Create(container, _),
+            } => {
+                let deposit_cost = container.len() as u64 * gas_cost::BYTE_DEPOSIT_COST as u64;
+                if container.len() > MAX_CODE_SIZE {
+                    ExecutionResult::Halt {
+                        reason: HaltReason::CreateContractSizeLimit,
+                        gas_used: gas_limit,
+                    }
+                } else if gas_used + deposit_cost > gas_limit {
+                    ExecutionResult::Halt {
+                        reason: HaltReason::OutOfGas(crate::result::OutOfGasError::Basic),
+                        gas_used: gas_limit,
+                    }
+                } else {
+                    ExecutionResult::Success {
+                        reason,
+                        gas_used: gas_used + deposit_cost,
+                        gas_refunded,
+                        logs,
+                        output: Output::Create(container, Some(address)),
+                    }
+                }
+            }
+            result => result,
+        };
+
+        match &result {
+            ExecutionResult::Success {
+                output: Output::Create(container, _),
+                ..
+            } => {
+                context
+                    .journal
+                    .new_contract(address, container.clone(), value);
+                self.journal.extend_from_successful(context.journal);
+                self.inner_context
+                    .logs
+                    .append(&mut context.inner_context.logs);
+                let sender_balance = self
+                    .journal
+                    .get_account(&sender_address)
+                    .unwrap_or_default()
+                    .balance;
+                self.journal
+                    .set_balance(&sender_address, sender_balance - value);
+            }
+            _ => self.journal.extend_from_reverted(context.journal),
+        }
+        if let Some(inspector) = self.inspector.as_mut() {
+            inspector.create_end(&inputs, &result);
+        }
+        Some(result)
+    }
+
+    /// Runs a creation transaction whose data is an EOF initcontainer followed by the
+    /// calldata of its initcode (EIP-7698), making the result of the initcode ours.
+    /// Like [`Self::create`], `remaining_gas` is replaced by the gas the creation used.
+    pub fn eof_create_transaction(&mut self, remaining_gas: &mut u64) {
+        let gas_limit = *remaining_gas;
+        let sender_address = self.env.tx.get_address();
+        let sender = self
+            .journal
+            .get_account(&sender_address)
+            .unwrap_or_default();
+        // The nonce is bumped even if the initcontainer is invalid
+        self.journal
+            .set_nonce(&sender_address, sender.nonce.saturating_add(1));
+
+        let data = self.env.tx.data.clone();
+        let input_size =
+            EofContainer::decode_with_trailing(&data).and_then(|(container, input)| {
+                container.validate(Some(ContainerKind::Initcode))?;
+                Ok(input.len())
+            });
+        let Ok(input_size) = input_size else {
+            self.halt_reason = Some(HaltReason::InvalidEOFInitCode);
+            *remaining_gas = gas_limit;
+            self.write_result(0, 0, 0, ExitStatusCode::Error.to_u8());
+            return;
+        };
+        let initcontainer = data.slice(..data.len() - input_size);
+        let input = data.slice(data.len() - input_size..);
+        let address = compute_contract_address(sender_address, sender.nonce);
+
+        let value = self.env.tx.value;
+        let Some(result) =
+            self.run_eof_initcode(address, initcontainer, input, value, gas_limit, None)
+        else {
+            return;
+        };
+        *remaining_gas = result.gas_used() - result.gas_refunded();
 
-        Ok(ResultAndState { result, state })
+        match result {
+            ExecutionResult::Success { output, .. } => {
+                self.inner_context.deployed_container = Some((output.into_data(), Some(address)));
+                self.write_result(0, 0, 0, ExitStatusCode::ReturnContract.to_u8());
+            }
+            ExecutionResult::Revert { output, .. } => {
+                let size = output.len() as u32;
+                self.inner_context.memory = output.to_vec();
+                self.write_result(0, size, 0, ExitStatusCode::Revert.to_u8());
+            }
+            ExecutionResult::Halt { reason, .. } => {
+                self.halt_reason = Some(reason);
+                self.write_result(0, 0, 0, ExitStatusCode::Error.to_u8());
+            }
+        }
     }
 }
 
@@ -334,7 +942,6 @@ impl<'c> SyscallContext<'c> {
         consumed_gas: &mut u64,
         call_type: u8,
     ) -> u8 {
-        //TODO: Add call depth check
         //TODO: Check that the args offsets and sizes are correct -> This from the MLIR side
         //TODO: This should instead add the account fetch (warm or cold) cost
         //For the moment we consider warm access
@@ -351,13 +958,20 @@ impl<'c> SyscallContext<'c> {
 
         let calldata = Bytes::copy_from_slice(&self.inner_context.memory[off..off + size]);
 
-        *consumed_gas = gas_cost::CALL_WARM as u64;
+        *consumed_gas = self.access_cost(false, gas_cost::CALL_COLD, gas_cost::CALL_WARM, |gas| {
+            gas.call
+        }) as u64;
 
-        let (return_code, return_data) = if is_precompile(callee_address) {
-            execute_precompile(callee_address, calldata, gas_to_send, consumed_gas)
+        let precompiles = self.precompiles.clone();
+        let (return_code, return_data) = if let Some(precompile) = precompiles.get(&callee_address)
+        {
+            if self.call_frame.is_at_depth_limit() {
+                self.call_frame.last_call_return_data.clear();
+                return return_codes::REVERT_RETURN_CODE;
+            }
+            run_precompile(precompile, &calldata, gas_to_send, consumed_gas)
         } else {
             // Execute subcontext
-            //TODO: Add call depth check
             //TODO: Check that the args offsets and sizes are correct -> This from the MLIR side
             let value = value_to_transfer.to_primitive_u256();
             let call_type = CallType::try_from(call_type)
@@ -366,19 +980,43 @@ impl<'c> SyscallContext<'c> {
             let callee_account = match self.journal.get_account(&callee_address) {
                 Some(account) => {
                     let is_cold = !self.journal.account_is_warm(&callee_address);
-
                     if is_cold {
-                        *consumed_gas = gas_cost::CALL_COLD as u64;
                         self.journal.add_account_as_warm(callee_address);
-                    } else {
-                        *consumed_gas = gas_cost::CALL_WARM as u64;
+                    }
+                    *consumed_gas =
+                        self.access_cost(is_cold, gas_cost::CALL_COLD, gas_cost::CALL_WARM, |gas| {
+                            gas.call
+                        }) as u64;
+
+                    // Calling a delegated account also accesses its delegate (EIP-7702)
+                    if let Some(delegate) = self.journal.delegated_address(&callee_address) {
+                        let _ = self.journal.get_account(&delegate);
+                        let is_cold = !self.journal.account_is_warm(&delegate);
+                        if is_cold {
+                            self.journal.add_account_as_warm(delegate);
+                        }
+                        *consumed_gas += self.access_cost(
+                            is_cold,
+                            gas_cost::CALL_COLD,
+                            gas_cost::CALL_WARM,
+                            |gas| gas.call,
+                        ) as u64;
                     }
 
-                    account
+                    Some(account)
                 }
                 None => {
-                    *consumed_gas = 0;
-                    return return_codes::REVERT_RETURN_CODE;
+                    // A CALL creates the account, which is removed at the end of the
+                    // transaction if it's left empty from Spurious Dragon on (EIP-161).
+                    // The other calls only run its empty code.
+                    if call_type == CallType::Call {
+                        self.journal.add_account_as_warm(callee_address);
+                    }
+                    *consumed_gas =
+                        self.access_cost(true, gas_cost::CALL_COLD, gas_cost::CALL_WARM, |gas| {
+                            gas.call
+                        }) as u64;
+                    None
                 }
             };
 
@@ -395,31 +1033,34 @@ impl<'c> SyscallContext<'c> {
                     return return_codes::REVERT_RETURN_CODE;
                 }
                 *consumed_gas += call_opcode::NOT_ZERO_VALUE_COST;
-                if callee_account.is_empty() {
-                    *consumed_gas += call_opcode::EMPTY_CALLEE_COST;
-                }
-                if available_gas < *consumed_gas {
-                    self.halt_reason =
-                        Some(HaltReason::OutOfGas(crate::result::OutOfGasError::Basic));
-                    return return_codes::HALT_RETURN_CODE; //It acctually doesn't matter what we return here
-                }
                 stipend = call_opcode::STIPEND_GAS_ADDITION;
+            }
+            if call_type == CallType::Call {
+                *consumed_gas += self.new_account_cost(callee_account.as_ref(), value);
+            }
+            if available_gas < *consumed_gas {
+                self.halt_reason = Some(HaltReason::OutOfGas(crate::result::OutOfGasError::Basic));
+                return return_codes::HALT_RETURN_CODE; //It acctually doesn't matter what we return here
+            }
 
-                //TODO: Maybe we should increment the nonce too
-                let caller_balance = caller_account.balance;
-                self.journal
-                    .set_balance(&caller_address, caller_balance - value);
-
-                let callee_balance = callee_account.balance;
-                self.journal
-                    .set_balance(&callee_address, callee_balance + value);
+            // The call fails without running, the gas that would have been sent is kept
+            if self.call_frame.is_at_depth_limit() {
+                self.call_frame.last_call_return_data.clear();
+                return return_codes::REVERT_RETURN_CODE;
             }
 
-            let remaining_gas = available_gas.saturating_sub(*consumed_gas);
-            gas_to_send = std::cmp::min(
-                remaining_gas / call_opcode::GAS_CAP_DIVISION_FACTOR,
-                gas_to_send,
-            );
+            // All but one 64th of the remaining gas can be sent since Tangerine (EIP-150),
+            // before that the requested gas must be available
+            let remaining_gas = available_gas - *consumed_gas;
+            if self.spec_id().is_enabled_in(SpecId::Tangerine) {
+                gas_to_send = std::cmp::min(
+                    remaining_gas - remaining_gas / call_opcode::GAS_CAP_DIVISION_FACTOR,
+                    gas_to_send,
+                );
+            } else if gas_to_send > remaining_gas {
+                self.halt_reason = Some(HaltReason::OutOfGas(crate::result::OutOfGasError::Basic));
+                return return_codes::HALT_RETURN_CODE;
+            }
             *consumed_gas += gas_to_send;
             gas_to_send += stipend;
 
@@ -442,44 +1083,43 @@ impl<'c> SyscallContext<'c> {
             let size = args_size as usize;
             env.tx.data = Bytes::from(self.inner_context.memory[off..off + size].to_vec());
 
-            //NOTE: We could optimize this by not making the call if the bytecode is zero.
-            //We would have to refund the stipend here
-            //TODO: Check if returning REVERT because of database fail is ok
-            let bytecode = self.journal.code_by_address(&callee_address);
-
-            let program = Program::from_bytecode(&bytecode);
-
-            let context = Context::new();
-            let module = context
-                .compile(&program, Default::default())
-                .expect("failed to compile program");
-
             let is_static = self.call_frame.ctx_is_static || call_type == CallType::StaticCall;
 
+            let inputs = CallInputs {
+                caller: new_frame_caller,
+                target_address: transact_to,
+                code_address: callee_address,
+                value: new_value,
+                input: env.tx.data.clone(),
+                gas_limit: gas_to_send,
+                call_type,
+                is_static,
+            };
             let call_frame = CallFrame {
                 caller: new_frame_caller,
                 ctx_is_static: is_static,
+                depth: self.call_frame.depth + 1,
                 ..Default::default()
             };
 
-            let journal = self.journal.eject_base();
-
-            let mut context = SyscallContext::new(env.clone(), journal, call_frame, gas_to_send);
-            let executor = Executor::new(&module, &context, OptLevel::Aggressive);
-
-            executor.execute(&mut context, env.tx.gas_limit);
-
-            let result = context.get_result().unwrap().result;
+            //NOTE: We could optimize this by not making the call if the bytecode is zero.
+            //We would have to refund the stipend here
+            // A CALL touches the callee even if it sends no value (EIP-161). CALLCODE
+            // runs in our account, so the value it sends stays with us.
+            let value_transfer =
+                (call_type == CallType::Call).then_some((caller_address, callee_address, value));
+            let Some(result) =
+                self.execute_call_frame(env, call_frame, &inputs, callee_address, value_transfer)
+            else {
+                return return_codes::HALT_RETURN_CODE;
+            };
 
             let unused_gas = gas_to_send - result.gas_used();
             *consumed_gas -= unused_gas;
             *consumed_gas -= result.gas_refunded();
             let return_code = if result.is_success() {
-                self.journal.extend_from_successful(context.journal);
                 return_codes::SUCCESS_RETURN_CODE
             } else {
-                //TODO: If we revert, should we still send the value to the called contract?
-                self.journal.extend_from_reverted(context.journal);
                 return_codes::REVERT_RETURN_CODE
             };
             let output = result.into_output().unwrap_or_default();
@@ -507,6 +1147,141 @@ impl<'c> SyscallContext<'c> {
         return_code
     }
 
+    /// `EXTCALL`, `EXTDELEGATECALL` and `EXTSTATICCALL` (EIP-7069). Unlike the legacy
+    /// calls, all but 1/64 of the gas is sent and the output is only kept as return
+    /// data. `address` is replaced by the status pushed to the stack: 0 on success, 1
+    /// if the callee reverted or the call couldn't be made, and 2 if the callee failed.
+    pub extern "C" fn ext_call(
+        &mut self,
+        address: &mut U256,
+        value_to_transfer: &U256,
+        args_offset: u32,
+        args_size: u32,
+        available_gas: u64,
+        consumed_gas: &mut u64,
+        call_type: u8,
+    ) -> u8 {
+        // The address is given as a full word, but must fit in 20 bytes
+        if address.hi >> 32 != 0 {
+            self.halt_reason = Some(HaltReason::InvalidEXTCALLTarget);
+            return return_codes::HALT_RETURN_CODE;
+        }
+        let callee_address = Address::from(&*address);
+        let value = value_to_transfer.to_primitive_u256();
+        let call_type = CallType::try_from(call_type)
+            .expect("Error while parsing CallType on ext_call syscall");
+
+        let callee_account = self.journal.get_account(&callee_address);
+        let is_cold = !self.journal.account_is_warm(&callee_address);
+        if is_cold {
+            self.journal.add_account_as_warm(callee_address);
+        }
+        *consumed_gas = if is_cold {
+            gas_cost::EXTCALL_COLD
+        } else {
+            gas_cost::EXTCALL_WARM
+        } as u64;
+        if !value.is_zero() {
+            *consumed_gas += call_opcode::NOT_ZERO_VALUE_COST;
+        }
+        *consumed_gas += self.new_account_cost(callee_account.as_ref(), value);
+        let Some(remaining_gas) = available_gas.checked_sub(*consumed_gas) else {
+            self.halt_reason = Some(HaltReason::OutOfGas(crate::result::OutOfGasError::Basic));
+            return return_codes::HALT_RETURN_CODE;
+        };
+
+        self.call_frame.last_call_return_data.clear();
+        let mut set_status = |status: u128| *address = U256 { lo: status, hi: 0 };
+
+        let caller_address = self.env.tx.get_address();
+        let caller_account = self
+            .journal
+            .get_account(&caller_address)
+            .unwrap_or_default();
+        // EOF is only enabled from Prague, so the gas is always capped as in EIP-150
+        let gas_to_send = remaining_gas.saturating_sub(std::cmp::max(
+            remaining_gas / call_opcode::GAS_CAP_DIVISION_FACTOR,
+            call_opcode::EXT_MIN_RETAINED_GAS,
+        ));
+        // Delegating to legacy code isn't allowed
+        let is_legacy_delegate = call_type == CallType::DelegateCall
+            && !eof::is_eof(&self.journal.code_by_address(&callee_address));
+        if gas_to_send < call_opcode::EXT_MIN_CALLEE_GAS
+            || caller_account.balance < value
+            || self.call_frame.is_at_depth_limit()
+            || is_legacy_delegate
+        {
+            set_status(call_opcode::EXT_CALL_REVERT);
+            return return_codes::SUCCESS_RETURN_CODE;
+        }
+
+        let off = args_offset as usize;
+        let calldata =
+            Bytes::copy_from_slice(&self.inner_context.memory[off..off + args_size as usize]);
+
+        let precompiles = self.precompiles.clone();
+        let (status, return_data) = if let Some(precompile) = precompiles.get(&callee_address) {
+            let mut precompile_gas = 0;
+            let (return_code, output) =
+                run_precompile(precompile, &calldata, gas_to_send, &mut precompile_gas);
+            *consumed_gas += precompile_gas;
+            let status = if return_code == return_codes::SUCCESS_RETURN_CODE {
+                call_opcode::EXT_CALL_SUCCESS
+            } else {
+                call_opcode::EXT_CALL_FAILURE
+            };
+            (status, output)
+        } else {
+            let mut env = self.env.clone();
+            let this_address = self.env.tx.get_address();
+            let (new_frame_caller, new_value, transact_to) = match call_type {
+                CallType::DelegateCall => (self.call_frame.caller, self.env.tx.value, this_address),
+                _ => (this_address, value, callee_address),
+            };
+            env.tx.value = new_value;
+            env.tx.transact_to = TransactTo::Call(transact_to);
+            env.tx.gas_limit = gas_to_send;
+            env.tx.data = calldata;
+
+            let is_static = self.call_frame.ctx_is_static || call_type == CallType::StaticCall;
+            let inputs = CallInputs {
+                caller: new_frame_caller,
+                target_address: transact_to,
+                code_address: callee_address,
+                value: new_value,
+                input: env.tx.data.clone(),
+                gas_limit: gas_to_send,
+                call_type,
+                is_static,
+            };
+            let call_frame = CallFrame {
+                caller: new_frame_caller,
+                ctx_is_static: is_static,
+                depth: self.call_frame.depth + 1,
+                ..Default::default()
+            };
+
+            let value_transfer =
+                (!value.is_zero()).then_some((caller_address, callee_address, value));
+            let Some(result) =
+                self.execute_call_frame(env, call_frame, &inputs, callee_address, value_transfer)
+            else {
+                return return_codes::HALT_RETURN_CODE;
+            };
+            *consumed_gas += result.gas_used() - result.gas_refunded();
+            let status = match &result {
+                ExecutionResult::Success { .. } => call_opcode::EXT_CALL_SUCCESS,
+                ExecutionResult::Revert { .. } => call_opcode::EXT_CALL_REVERT,
+                ExecutionResult::Halt { .. } => call_opcode::EXT_CALL_FAILURE,
+            };
+            (status, result.into_output().unwrap_or_default())
+        };
+
+        self.call_frame.last_call_return_data = return_data.to_vec();
+        set_status(status);
+        return_codes::SUCCESS_RETURN_CODE
+    }
+
     pub extern "C" fn store_in_selfbalance_ptr(&mut self, balance: &mut U256) {
         let account = match self.env.tx.transact_to {
             TransactTo::Call(address) => self.journal.get_account(&address).unwrap_or_default(),
@@ -546,7 +1321,7 @@ impl<'c> SyscallContext<'c> {
     }
 
     pub extern "C" fn store_in_gasprice_ptr(&self, value: &mut U256) {
-        let aux = &self.env.tx.gas_price;
+        let aux = &self.env.effective_gas_price();
         value.lo = aux.low_u128();
         value.hi = (aux >> 128).low_u128();
     }
@@ -555,6 +1330,10 @@ impl<'c> SyscallContext<'c> {
         self.env.cfg.chain_id
     }
 
+    pub extern "C" fn context_is_static(&self) -> u8 {
+        self.call_frame.ctx_is_static as u8
+    }
+
     pub extern "C" fn get_calldata_ptr(&mut self) -> *const u8 {
         self.env.tx.data.as_ptr()
     }
@@ -625,11 +1404,9 @@ impl<'c> SyscallContext<'c> {
         stg_value.hi = (result >> 128).low_u128();
         stg_value.lo = result.low_u128();
 
-        if is_cold {
-            gas_cost::SLOAD_COLD
-        } else {
-            gas_cost::SLOAD_WARM
-        }
+        self.access_cost(is_cold, gas_cost::SLOAD_COLD, gas_cost::SLOAD_WARM, |gas| {
+            gas.sload
+        })
     }
 
     pub extern "C" fn write_storage(&mut self, stg_key: &U256, stg_value: &mut U256) -> i64 {
@@ -649,18 +1426,41 @@ impl<'c> SyscallContext<'c> {
             None => (value, value),
         };
 
-        // Compute the gas cost
-        let mut gas_cost: i64 = if original.is_zero() && current.is_zero() && current != value {
+        let spec_id = self.spec_id();
+        let clears_refund = spec_id.gas_schedule().sstore_clears_refund;
+
+        if !spec_id.has_sstore_net_metering() {
+            let gas_cost = if current.is_zero() && !value.is_zero() {
+                20_000
+            } else {
+                5_000
+            };
+            if !current.is_zero() && value.is_zero() {
+                self.inner_context.gas_refund += clears_refund as u64;
+            }
+            return gas_cost;
+        }
+
+        // Cost of an access that doesn't change the slot's original value, and of
+        // resetting it to a non zero value
+        let (access_cost, reset_cost) = if spec_id.is_enabled_in(SpecId::Berlin) {
+            (gas_cost::SLOAD_WARM, 5_000 - gas_cost::SLOAD_COLD)
+        } else {
+            (spec_id.gas_schedule().sload, 5_000)
+        };
+
+        // Compute the gas cost
+        let mut gas_cost: i64 = if original.is_zero() && current.is_zero() && current != value {
             20_000
         } else if original == current && current != value {
-            2_900
+            reset_cost
         } else {
-            100
+            access_cost
         };
 
         // When the value is cold, add extra 2100 gas
-        if is_cold {
-            gas_cost += 2_100;
+        if is_cold && spec_id.is_enabled_in(SpecId::Berlin) {
+            gas_cost += gas_cost::SLOAD_COLD;
         }
 
         // Compute the gas refund
@@ -671,15 +1471,15 @@ impl<'c> SyscallContext<'c> {
         let reset_to_original = (current != value) && (original == value);
 
         let gas_refund: i64 = if reset_non_zero_to_zero {
-            4_800
+            clears_refund
         } else if undo_reset_to_zero_into_original {
-            -2_000
+            reset_cost - access_cost - clears_refund
         } else if undo_reset_to_zero {
-            -4_800
+            -clears_refund
         } else if reset_back_to_zero {
-            19_900
+            20_000 - access_cost
         } else if reset_to_original {
-            2_800
+            reset_cost - access_cost
         } else {
             0
         };
@@ -772,7 +1572,13 @@ impl<'c> SyscallContext<'c> {
         self.inner_context.resize_memory_if_necessary(offset, size);
         let data: Vec<u8> = self.inner_context.memory[offset..offset + size].into();
 
-        let log = LogData { data, topics };
+        let log = Log {
+            address: self.env.tx.get_address(),
+            data: LogData { data, topics },
+        };
+        if let Some(inspector) = self.inspector.as_mut() {
+            inspector.log(&log);
+        }
         self.inner_context.logs.push(log);
     }
 
@@ -782,10 +1588,13 @@ impl<'c> SyscallContext<'c> {
         let codesize = self.journal.code_by_address(&Address::from(address)).len();
         if is_cold {
             self.journal.add_account_as_warm(Address::from(address));
-            *gas = gas_cost::EXTCODESIZE_COLD as u64;
-        } else {
-            *gas = gas_cost::EXTCODESIZE_WARM as u64;
         }
+        *gas = self.access_cost(
+            is_cold,
+            gas_cost::EXTCODESIZE_COLD,
+            gas_cost::EXTCODESIZE_WARM,
+            |gas| gas.extcode,
+        ) as u64;
 
         codesize as u64
     }
@@ -815,7 +1624,12 @@ impl<'c> SyscallContext<'c> {
     }
 
     pub extern "C" fn store_in_balance(&mut self, address: &U256, balance: &mut U256) -> i64 {
-        let mut gas_cost = gas_cost::BALANCE_COLD;
+        let mut gas_cost = self.access_cost(
+            true,
+            gas_cost::BALANCE_COLD,
+            gas_cost::BALANCE_WARM,
+            |gas| gas.balance,
+        );
 
         // addresses longer than 20 bytes should be invalid
         if (address.hi >> 32) != 0 {
@@ -842,10 +1656,13 @@ impl<'c> SyscallContext<'c> {
             };
             if is_cold {
                 self.journal.add_account_as_warm(address);
-                gas_cost = gas_cost::BALANCE_COLD
-            } else {
-                gas_cost = gas_cost::BALANCE_WARM
-            };
+            }
+            gas_cost = self.access_cost(
+                is_cold,
+                gas_cost::BALANCE_COLD,
+                gas_cost::BALANCE_WARM,
+                |gas| gas.balance,
+            );
         }
         gas_cost
     }
@@ -887,10 +1704,13 @@ impl<'c> SyscallContext<'c> {
         let is_cold = !self.journal.account_is_warm(&address);
         if is_cold {
             self.journal.add_account_as_warm(Address::from(address));
-            gas_cost::EXTCODECOPY_COLD as u64
-        } else {
-            gas_cost::EXTCODECOPY_WARM as u64
         }
+        self.access_cost(
+            is_cold,
+            gas_cost::EXTCODECOPY_COLD,
+            gas_cost::EXTCODECOPY_WARM,
+            |gas| gas.extcode,
+        ) as u64
     }
 
     pub extern "C" fn get_code_hash(&mut self, address: &mut U256) -> u64 {
@@ -898,14 +1718,26 @@ impl<'c> SyscallContext<'c> {
             .journal
             .account_is_warm(&Address::from(address as &U256));
 
-        let gas_cost = if is_cold {
-            gas_cost::EXTCODEHASH_COLD
-        } else {
-            gas_cost::EXTCODEHASH_WARM
-        };
+        let gas_cost = self.access_cost(
+            is_cold,
+            gas_cost::EXTCODEHASH_COLD,
+            gas_cost::EXTCODEHASH_WARM,
+            |gas| gas.extcodehash,
+        );
 
         let hash = match self.journal.get_account(&Address::from(address as &U256)) {
-            Some(account_info) => account_info.code_hash,
+            // Delegated accounts report the hash of their delegate's code (EIP-7702)
+            Some(account_info) => match self
+                .journal
+                .delegated_address(&Address::from(address as &U256))
+            {
+                Some(delegate) => self
+                    .journal
+                    .get_account(&delegate)
+                    .map(|delegate_info| delegate_info.code_hash)
+                    .unwrap_or_default(),
+                None => account_info.code_hash,
+            },
             _ => {
                 self.journal
                     .add_account_as_warm(Address::from(address as &U256));
@@ -930,8 +1762,15 @@ impl<'c> SyscallContext<'c> {
         let size = size as usize;
         let minimum_word_size = ((size + 31) / 32) as u64;
         let sender_address = self.env.tx.get_address();
+        // The init code is limited and charged per word since Shanghai (EIP-3860)
+        let limits_init_code = self.spec_id().is_enabled_in(SpecId::Shanghai);
+        let init_word_cost = if limits_init_code {
+            gas_cost::INIT_WORD_COST as u64
+        } else {
+            0
+        };
 
-        if size > MAX_CODE_SIZE * 2 {
+        if limits_init_code && size > MAX_CODE_SIZE * 2 {
             self.halt_reason = Some(HaltReason::CreateContractSizeLimit);
             return return_codes::HALT_RETURN_CODE;
         }
@@ -939,6 +1778,13 @@ impl<'c> SyscallContext<'c> {
         self.inner_context.resize_memory_if_necessary(offset, size);
 
         let initialization_bytecode = &self.inner_context.memory[offset..offset + size];
+
+        // Legacy creates can't deploy EOF containers (EIP-7620), failing as if the
+        // initcode halted
+        if self.spec_id().is_enabled_in(SpecId::Prague) && eof::is_eof(initialization_bytecode) {
+            *value = U256::zero();
+            return return_codes::SUCCESS_RETURN_CODE;
+        }
         let program = Program::from_bytecode(initialization_bytecode);
 
         // if we do a create from a program, and the created program would be the same, that means a recursive create
@@ -950,95 +1796,154 @@ impl<'c> SyscallContext<'c> {
             return return_codes::HALT_RETURN_CODE;
         }
 
+        // The create fails without running, pushing a zero address and keeping the gas
+        // that would have been sent
+        if self.call_frame.is_at_depth_limit() {
+            let hash_cost = match salt {
+                Some(_) => minimum_word_size * gas_cost::HASH_WORD_COST as u64,
+                None => 0,
+            };
+            *value = U256::zero();
+            *remaining_gas = minimum_word_size * init_word_cost + hash_cost;
+            return return_codes::SUCCESS_RETURN_CODE;
+        }
+
         let sender_account = self.journal.get_account(&sender_address).unwrap();
+        let init_code_cost = minimum_word_size * init_word_cost;
+        let hash_cost = match salt {
+            Some(_) => minimum_word_size * gas_cost::HASH_WORD_COST as u64,
+            None => 0,
+        };
 
-        let (dest_addr, hash_cost) = match salt {
-            Some(s) => (
-                compute_contract_address2(
-                    sender_address,
-                    s.to_primitive_u256(),
-                    initialization_bytecode,
-                ),
-                minimum_word_size * gas_cost::HASH_WORD_COST as u64,
-            ),
-            _ => {
-                if sender_account.nonce.checked_add(1).is_none() {
-                    self.halt_reason = Some(HaltReason::NonceOverflow);
-                    return return_codes::HALT_RETURN_CODE;
-                }
+        // The create fails without running if the value can't be sent, keeping the gas
+        if sender_account.balance < value_as_u256 {
+            *value = U256::zero();
+            *remaining_gas = init_code_cost + hash_cost;
+            return return_codes::SUCCESS_RETURN_CODE;
+        }
+        let Some(new_nonce) = sender_account.nonce.checked_add(1) else {
+            self.halt_reason = Some(HaltReason::NonceOverflow);
+            return return_codes::HALT_RETURN_CODE;
+        };
 
-                (
-                    compute_contract_address(sender_address, sender_account.nonce),
-                    0,
-                )
-            }
+        let dest_addr = match salt {
+            Some(s) => compute_contract_address2(
+                sender_address,
+                s.to_primitive_u256(),
+                initialization_bytecode,
+            ),
+            None => compute_contract_address(sender_address, sender_account.nonce),
         };
 
-        // Check if there is already a contract stored in dest_address
-        if self.journal.get_account(&dest_addr).is_some() {
+        if self.journal.account_collides(&dest_addr) {
             self.halt_reason = Some(HaltReason::CreateCollision);
             return return_codes::HALT_RETURN_CODE;
         }
-        self.journal.add_account_as_warm(dest_addr);
+        // The nonce is kept even if the initialization code fails
+        self.journal.set_nonce(&sender_address, new_nonce);
+
+        // All but one 64th of the gas left is sent since Tangerine (EIP-150)
+        let mut gas_limit = *remaining_gas;
+        if self.spec_id().is_enabled_in(SpecId::Tangerine) {
+            gas_limit -= gas_limit / call_opcode::GAS_CAP_DIVISION_FACTOR;
+        }
 
         // Create subcontext for the initialization code
-        // TODO: Add call depth check
         let mut new_env = self.env.clone();
         new_env.tx.transact_to = TransactTo::Call(dest_addr);
-        new_env.tx.gas_limit = *remaining_gas;
-        let call_frame = CallFrame::new(sender_address);
+        new_env.tx.gas_limit = gas_limit;
+        let call_frame = CallFrame {
+            depth: self.call_frame.depth + 1,
+            ..CallFrame::new(sender_address)
+        };
+
+        let inputs = CreateInputs {
+            caller: sender_address,
+            address: dest_addr,
+            value: value_as_u256,
+            init_code: Bytes::copy_from_slice(initialization_bytecode),
+            gas_limit,
+            salt: salt.map(U256::to_primitive_u256),
+        };
+        if let Some(inspector) = self.inspector.as_mut() {
+            inspector.create(&inputs);
+        }
 
         // Execute initialization code
-        let context = Context::new();
-        let module = context
-            .compile(&program, Default::default())
-            .expect("failed to compile program");
-
-        // NOTE: Here we are not taking into account what happens if the deployment code reverts
-        let ctx_journal = self.journal.eject_base();
-        let mut context =
-            SyscallContext::new(new_env.clone(), ctx_journal, call_frame, *remaining_gas);
-        context.journal.new_account(dest_addr, value_as_u256);
-        let executor = Executor::new(&module, &context, OptLevel::Aggressive);
+        let code_hash = cache::code_hash(initialization_bytecode);
+        let executor = ExecutorCache::checkout(
+            &self.executor_cache,
+            code_hash,
+            initialization_bytecode,
+            self.spec_id(),
+            self.steps_enabled(),
+        )
+        .expect("failed to compile program");
+
+        // The new account and the value sent to it are part of the nested frame, so
+        // they're discarded along with the rest of its changes if it fails
+        let mut ctx_journal = self.journal.eject_base();
+        ctx_journal.new_account(dest_addr, EU256::zero());
+        ctx_journal.set_nonce(&dest_addr, 1);
+        ctx_journal.transfer(&sender_address, &dest_addr, value_as_u256);
+        let mut context = SyscallContext::new(new_env, ctx_journal, call_frame, gas_limit)
+            .with_executor_cache(self.executor_cache.clone())
+            .with_precompiles(self.precompiles.clone());
         context.inner_context.program = program.to_bytecode();
+        context.inspector = self.inspector.take();
         let program_len = context.inner_context.program.len() as u32;
-        executor.execute(&mut context, new_env.tx.gas_limit);
+        with_stack_space(|| executor.execute(&mut context, gas_limit));
+        self.executor_cache
+            .lock()
+            .unwrap()
+            .release(code_hash, &executor);
+        self.inspector = context.inspector.take();
+
+        let result = match context.execution_result() {
+            Ok(result) => result,
+            Err(_) => {
+                // The database error is carried over to our journal, failing the transaction
+                self.journal.extend_from_reverted(context.journal);
+                return return_codes::HALT_RETURN_CODE;
+            }
+        };
+        if let Some(inspector) = self.inspector.as_mut() {
+            inspector.create_end(&inputs, &result);
+        }
 
-        let result = context.get_result().unwrap().result;
-        let bytecode = result.output().cloned().unwrap_or_default();
+        if !result.is_success() {
+            self.journal.extend_from_reverted(context.journal);
+            *value = U256::zero();
+            *remaining_gas = init_code_cost + hash_cost + result.gas_used();
+            // Only a revert returns data, there's no code deployed
+            self.call_frame.last_call_return_data =
+                result.into_output().unwrap_or_default().to_vec();
+            return return_codes::SUCCESS_RETURN_CODE;
+        }
 
+        // The deployed code is limited since Spurious Dragon (EIP-170), failing the
+        // create with all the gas sent
+        let bytecode = result.output().cloned().unwrap_or_default();
+        if self.spec_id().is_enabled_in(SpecId::SpuriousDragon) && bytecode.len() > MAX_CODE_SIZE {
+            self.journal.extend_from_reverted(context.journal);
+            *value = U256::zero();
+            *remaining_gas = init_code_cost + hash_cost + gas_limit;
+            self.call_frame.last_call_return_data.clear();
+            return return_codes::SUCCESS_RETURN_CODE;
+        }
         self.journal.extend_from_successful(context.journal);
+        self.inner_context
+            .logs
+            .append(&mut context.inner_context.logs);
 
         // Set the gas cost
-        let init_code_cost = minimum_word_size * gas_cost::INIT_WORD_COST as u64;
         let code_deposit_cost = (bytecode.len() as u64) * gas_cost::BYTE_DEPOSIT_COST as u64;
         let gas_cost = init_code_cost + code_deposit_cost + hash_cost + result.gas_used()
             - result.gas_refunded();
         *remaining_gas = gas_cost;
 
-        // Check if balance is enough
-        let Some(sender_balance) = sender_account.balance.checked_sub(value_as_u256) else {
-            *value = U256::zero();
-            self.write_result(
-                offset as u32,
-                program_len,
-                *remaining_gas,
-                return_codes::SUCCESS_RETURN_CODE,
-            );
-            return return_codes::SUCCESS_RETURN_CODE;
-        };
-
-        // Create new contract and update sender account
-        self.journal
-            .new_contract(dest_addr, bytecode, value_as_u256);
-
-        let Some(new_nonce) = sender_account.nonce.checked_add(1) else {
-            self.halt_reason = Some(HaltReason::NonceOverflow);
-            return return_codes::HALT_RETURN_CODE;
-        };
-
-        self.journal.set_nonce(&sender_address, new_nonce);
-        self.journal.set_balance(&sender_address, sender_balance);
+        self.journal.set_code(&dest_addr, bytecode);
+        self.call_frame.last_call_return_data.clear();
 
         value.copy_from(&dest_addr);
 
@@ -1073,6 +1978,165 @@ impl<'c> SyscallContext<'c> {
         self.create_aux(size, offset, value, remaining_gas, Some(salt))
     }
 
+    /// `EOFCREATE` (EIP-7620): creates an account running the container section
+    /// `initcontainer_index` with the memory at `input_offset` as calldata, at an address
+    /// derived from `salt` and the initcontainer. Like [`Self::create2`], `value` is
+    /// replaced by the address of the new account, or zero if the creation failed, and
+    /// `remaining_gas` by the cost of the creation.
+    pub extern "C" fn eof_create(
+        &mut self,
+        initcontainer_index: u8,
+        input_offset: u32,
+        input_size: u32,
+        value: &mut U256,
+        remaining_gas: &mut u64,
+        salt: &U256,
+    ) -> u8 {
+        let initcontainer = EofContainer::decode(&self.inner_context.program)
+            .ok()
+            .and_then(|container| {
+                container
+                    .container_sections
+                    .get(initcontainer_index as usize)
+                    .cloned()
+            });
+        let Some(initcontainer) = initcontainer else {
+            self.halt_reason = Some(HaltReason::InvalidEOFInitCode);
+            return return_codes::HALT_RETURN_CODE;
+        };
+
+        // The initcontainer is hashed to compute the address
+        let hash_cost = initcontainer.len().div_ceil(32) as u64 * gas_cost::HASH_WORD_COST as u64;
+        let Some(available_gas) = remaining_gas.checked_sub(hash_cost) else {
+            self.halt_reason = Some(HaltReason::OutOfGas(crate::result::OutOfGasError::Basic));
+            return return_codes::HALT_RETURN_CODE;
+        };
+        let gas_limit = available_gas - available_gas / call_opcode::GAS_CAP_DIVISION_FACTOR;
+
+        let value_as_u256 = value.to_primitive_u256();
+        *value = U256::zero();
+        *remaining_gas = hash_cost;
+        self.call_frame.last_call_return_data.clear();
+
+        // The create fails without running, keeping the gas that would have been sent
+        let sender_address = self.env.tx.get_address();
+        let sender = self
+            .journal
+            .get_account(&sender_address)
+            .unwrap_or_default();
+        let Some(new_nonce) = sender.nonce.checked_add(1) else {
+            return return_codes::SUCCESS_RETURN_CODE;
+        };
+        if self.call_frame.is_at_depth_limit() || sender.balance < value_as_u256 {
+            return return_codes::SUCCESS_RETURN_CODE;
+        }
+        self.journal.set_nonce(&sender_address, new_nonce);
+
+        let address =
+            compute_contract_address2(sender_address, salt.to_primitive_u256(), &initcontainer);
+        let input_offset = input_offset as usize;
+        let input = Bytes::copy_from_slice(
+            &self.inner_context.memory[input_offset..input_offset + input_size as usize],
+        );
+        let Some(result) = self.run_eof_initcode(
+            address,
+            initcontainer,
+            input,
+            value_as_u256,
+            gas_limit,
+            Some(salt.to_primitive_u256()),
+        ) else {
+            return return_codes::HALT_RETURN_CODE;
+        };
+
+        *remaining_gas += result.gas_used() - result.gas_refunded();
+        match result {
+            ExecutionResult::Success { .. } => value.copy_from(&address),
+            ExecutionResult::Revert { output, .. } => {
+                self.call_frame.last_call_return_data = output.to_vec();
+            }
+            ExecutionResult::Halt { .. } => {}
+        }
+        return_codes::SUCCESS_RETURN_CODE
+    }
+
+    /// `RETURNCONTRACT` (EIP-7620): ends the initcode, deploying its container section
+    /// `container_index` with the memory at `aux_data_offset` appended to its data.
+    pub extern "C" fn eof_return_contract(
+        &mut self,
+        container_index: u8,
+        aux_data_offset: u32,
+        aux_data_size: u32,
+    ) -> u8 {
+        let container = EofContainer::decode(&self.inner_context.program)
+            .ok()
+            .and_then(|container| {
+                let section = container.container_sections.get(container_index as usize)?;
+                EofContainer::decode_subcontainer(section).ok()
+            });
+        let Some(mut container) = container else {
+            self.halt_reason = Some(HaltReason::InvalidEOFInitCode);
+            return return_codes::HALT_RETURN_CODE;
+        };
+
+        let aux_data_offset = aux_data_offset as usize;
+        let aux_data =
+            &self.inner_context.memory[aux_data_offset..aux_data_offset + aux_data_size as usize];
+        if let Err(reason) = container.append_aux_data(aux_data) {
+            self.halt_reason = Some(reason);
+            return return_codes::HALT_RETURN_CODE;
+        }
+        self.inner_context.deployed_container = Some((container.encode(), None));
+        return_codes::SUCCESS_RETURN_CODE
+    }
+
+    /// Pushes the offset a `CALLF` returns to, failing if the return stack is full
+    pub extern "C" fn eof_push_return(&mut self, return_pc: u32) -> u8 {
+        if self.call_frame.return_stack.len() >= MAX_RETURN_STACK_HEIGHT {
+            self.halt_reason = Some(HaltReason::EOFFunctionStackOverflow);
+            return return_codes::HALT_RETURN_CODE;
+        }
+        self.call_frame.return_stack.push(return_pc);
+        return_codes::SUCCESS_RETURN_CODE
+    }
+
+    /// Pops the offset a `RETF` returns to. Validation ensures that returning code
+    /// sections can only be entered through a `CALLF`.
+    pub extern "C" fn eof_pop_return(&mut self) -> u32 {
+        self.call_frame
+            .return_stack
+            .pop()
+            .expect("RETF without a matching CALLF")
+    }
+
+    /// Replaces `offset` with the 32 bytes of the data section at it, padded with zeros
+    pub extern "C" fn eof_data_load(&mut self, offset: &mut U256) {
+        let data = eof::data_section(&self.inner_context.program);
+        let mut word = [0u8; 32];
+        if offset.hi == 0 && offset.lo < data.len() as u128 {
+            let start = offset.lo as usize;
+            let end = data.len().min(start + 32);
+            word[..end - start].copy_from_slice(&data[start..end]);
+        }
+        *offset = U256::from_fixed_be_bytes(word);
+    }
+
+    pub extern "C" fn copy_eof_data_to_memory(
+        &mut self,
+        data_offset: u32,
+        size: u32,
+        dest_offset: u32,
+    ) {
+        let data = eof::data_section(&self.inner_context.program).to_vec();
+        let size = size as usize;
+        let dest_offset = dest_offset as usize;
+
+        self.inner_context
+            .resize_memory_if_necessary(dest_offset, size);
+        self.inner_context
+            .set_value_to_memory(dest_offset, data_offset as usize, size, &data);
+    }
+
     pub extern "C" fn selfdestruct(&mut self, receiver_address: &U256) -> u64 {
         let sender_address = self.env.tx.get_address();
         let receiver_address = Address::from(receiver_address);
@@ -1083,27 +2147,47 @@ impl<'c> SyscallContext<'c> {
             .unwrap_or_default()
             .balance;
 
-        let receiver_is_empty = match self.journal.get_account(&receiver_address) {
-            Some(receiver) => {
-                let is_empty = receiver.is_empty();
-                self.journal
-                    .set_balance(&receiver_address, receiver.balance + sender_balance);
-                is_empty
-            }
+        if let Some(inspector) = self.inspector.as_mut() {
+            inspector.selfdestruct(sender_address, receiver_address, sender_balance);
+        }
+
+        let (receiver_exists, receiver_is_empty) = match self.journal.get_account(&receiver_address)
+        {
+            Some(receiver) => (true, receiver.is_empty()),
             None => {
-                self.journal.new_account(receiver_address, sender_balance);
-                true
+                self.journal.new_account(receiver_address, EU256::zero());
+                (false, true)
             }
         };
+        self.journal
+            .transfer(&sender_address, &receiver_address, sender_balance);
 
-        self.journal.set_balance(&sender_address, EU256::zero());
+        let spec_id = self.spec_id();
+        // Before London, the first selfdestruct of an account is refunded
+        if !self.journal.account_is_selfdestructed(&sender_address) {
+            self.inner_context.gas_refund += spec_id.gas_schedule().selfdestruct_refund;
+        }
 
-        if self.journal.get_account(&sender_address).is_some() {
+        // Since Cancun only accounts created in the same transaction are destroyed
+        // (EIP-6780), the rest just send their balance
+        let destroys_account = !spec_id.is_enabled_in(SpecId::Cancun)
+            || self.journal.account_is_created(&sender_address);
+        if destroys_account && self.journal.get_account(&sender_address).is_some() {
+            // The balance is burned if the account sends it to itself
+            self.journal.set_balance(&sender_address, EU256::zero());
             self.journal
                 .set_status(&sender_address, AccountStatus::SelfDestructed);
         }
 
-        if !sender_balance.is_zero() && receiver_is_empty {
+        // Sending funds to a new account costs extra since Tangerine. From Spurious
+        // Dragon on, it's only charged when there are funds to send, and empty
+        // accounts count as new.
+        let creates_account = if spec_id.is_enabled_in(SpecId::SpuriousDragon) {
+            !sender_balance.is_zero() && receiver_is_empty
+        } else {
+            spec_id.is_enabled_in(SpecId::Tangerine) && !receiver_exists
+        };
+        if creates_account {
             gas_cost::SELFDESTRUCT_DYNAMIC_GAS as u64
         } else {
             0
@@ -1132,12 +2216,47 @@ impl<'c> SyscallContext<'c> {
         let value = stg_value.to_primitive_u256();
         self.transient_storage.insert((address, key), value);
     }
+
+    /// Halts the execution on an operation introduced by a later hardfork
+    pub extern "C" fn not_activated(&mut self) {
+        self.halt_reason = Some(HaltReason::NotActivated);
+    }
+
+    /// Reports the operation about to run to the inspector
+    ///
+    /// # Safety
+    ///
+    /// The stack pointers must be the bounds of the program's stack, with every item
+    /// between them initialized.
+    pub unsafe extern "C" fn step(
+        &mut self,
+        pc: u64,
+        opcode: u8,
+        gas_remaining: u64,
+        stack_baseptr: *const U256,
+        stack_ptr: *const U256,
+    ) {
+        let address = self.env.tx.get_address();
+        let Some(inspector) = self.inspector.as_mut() else {
+            return;
+        };
+        let len = stack_ptr.offset_from(stack_baseptr) as usize;
+        let stack = std::slice::from_raw_parts(stack_baseptr, len);
+        inspector.step(&Step {
+            address,
+            pc: pc as usize,
+            opcode,
+            gas_remaining,
+            stack,
+            memory: &self.inner_context.memory,
+            return_data: &self.call_frame.last_call_return_data,
+            gas_refund: self.inner_context.gas_refund,
+        });
+    }
 }
 
 pub mod symbols {
-    // Global variables
     pub const CONTEXT_IS_STATIC: &str = "evm_mlir__context_is_static";
-    // Syscalls
     pub const WRITE_RESULT: &str = "evm_mlir__write_result";
     pub const EXTEND_MEMORY: &str = "evm_mlir__extend_memory";
     pub const KECCAK256_HASHER: &str = "evm_mlir__keccak256_hasher";
@@ -1174,264 +2293,332 @@ pub mod symbols {
     pub const CALL: &str = "evm_mlir__call";
     pub const CREATE: &str = "evm_mlir__create";
     pub const CREATE2: &str = "evm_mlir__create2";
+    pub const EOF_CREATE: &str = "evm_mlir__eof_create";
+    pub const EOF_RETURN_CONTRACT: &str = "evm_mlir__eof_return_contract";
+    pub const EOF_PUSH_RETURN: &str = "evm_mlir__eof_push_return";
+    pub const EOF_POP_RETURN: &str = "evm_mlir__eof_pop_return";
+    pub const EOF_DATA_LOAD: &str = "evm_mlir__eof_data_load";
+    pub const COPY_EOF_DATA_TO_MEMORY: &str = "evm_mlir__copy_eof_data_to_memory";
+    pub const EXT_CALL: &str = "evm_mlir__ext_call";
     pub const GET_RETURN_DATA_SIZE: &str = "evm_mlir__get_return_data_size";
     pub const COPY_RETURN_DATA_INTO_MEMORY: &str = "evm_mlir__copy_return_data_into_memory";
     pub const TRANSIENT_STORAGE_READ: &str = "evm_mlir__transient_storage_read";
     pub const TRANSIENT_STORAGE_WRITE: &str = "evm_mlir__transient_storage_write";
     pub const SELFDESTRUCT: &str = "evm_mlir__selfdestruct";
+    pub const STEP: &str = "evm_mlir__step";
+    pub const NOT_ACTIVATED: &str = "evm_mlir__not_activated";
 }
 
 impl<'c> SyscallContext<'c> {
     /// Registers all the syscalls as symbols in the execution engine
     ///
     /// This allows the generated code to call the syscalls by name.
-    pub fn register_symbols(&self, engine: &ExecutionEngine) {
-        unsafe {
-            // Global variables
-            engine.register_symbol(
-                symbols::CONTEXT_IS_STATIC,
-                &self.call_frame.ctx_is_static as *const bool as *mut (),
-            );
-            // Syscalls
-            engine.register_symbol(
-                symbols::WRITE_RESULT,
-                SyscallContext::write_result as *const fn(*mut c_void, u32, u32, u64, u8)
-                    as *mut (),
-            );
-            engine.register_symbol(
-                symbols::KECCAK256_HASHER,
-                SyscallContext::keccak256_hasher as *const fn(*mut c_void, u32, u32, *const U256)
-                    as *mut (),
-            );
-            engine.register_symbol(
-                symbols::EXTEND_MEMORY,
-                SyscallContext::extend_memory as *const fn(*mut c_void, u32) as *mut (),
-            );
-            engine.register_symbol(
-                symbols::STORAGE_READ,
-                SyscallContext::read_storage as *const fn(*mut c_void, *const U256, *mut U256)
-                    as *mut (),
-            );
-            engine.register_symbol(
-                symbols::STORAGE_WRITE,
-                SyscallContext::write_storage as *const fn(*mut c_void, *const U256, *const U256)
-                    as *mut (),
-            );
-            engine.register_symbol(
-                symbols::APPEND_LOG,
-                SyscallContext::append_log as *const fn(*mut c_void, u32, u32) as *mut (),
-            );
-            engine.register_symbol(
-                symbols::APPEND_LOG_ONE_TOPIC,
-                SyscallContext::append_log_with_one_topic
-                    as *const fn(*mut c_void, u32, u32, *const U256) as *mut (),
-            );
-            engine.register_symbol(
-                symbols::APPEND_LOG_TWO_TOPICS,
-                SyscallContext::append_log_with_two_topics
-                    as *const fn(*mut c_void, u32, u32, *const U256, *const U256)
-                    as *mut (),
-            );
-            engine.register_symbol(
-                symbols::APPEND_LOG_THREE_TOPICS,
-                SyscallContext::append_log_with_three_topics
-                    as *const fn(*mut c_void, u32, u32, *const U256, *const U256, *const U256)
-                    as *mut (),
-            );
-            engine.register_symbol(
-                symbols::APPEND_LOG_FOUR_TOPICS,
-                SyscallContext::append_log_with_four_topics
-                    as *const fn(
-                        *mut c_void,
-                        u32,
-                        u32,
-                        *const U256,
-                        *const U256,
-                        *const U256,
-                        *const U256,
-                    ) as *mut (),
-            );
-            engine.register_symbol(
-                symbols::CALL,
-                SyscallContext::call
-                    as *const fn(
-                        *mut c_void,
-                        u64,
-                        *const U256,
-                        *const U256,
-                        u32,
-                        u32,
-                        u32,
-                        u32,
-                        u64,
-                        *mut u64,
-                        u8,
-                    ) as *mut (),
-            );
-            engine.register_symbol(
-                symbols::GET_CALLDATA_PTR,
-                SyscallContext::get_calldata_ptr as *const fn(*mut c_void) as *mut (),
-            );
-            engine.register_symbol(
-                symbols::GET_CALLDATA_SIZE,
-                SyscallContext::get_calldata_size_syscall as *const fn(*mut c_void) as *mut (),
-            );
-            engine.register_symbol(
-                symbols::EXTEND_MEMORY,
-                SyscallContext::extend_memory as *const fn(*mut c_void, u32) as *mut (),
-            );
-            engine.register_symbol(
-                symbols::COPY_CODE_TO_MEMORY,
-                SyscallContext::copy_code_to_memory as *const fn(*mut c_void, u32, u32, u32)
-                    as *mut (),
-            );
-            engine.register_symbol(
-                symbols::GET_ORIGIN,
-                SyscallContext::get_origin as *const fn(*mut c_void, *mut U256) as *mut (),
-            );
-            engine.register_symbol(
-                symbols::GET_ADDRESS_PTR,
-                SyscallContext::get_address_ptr as *const fn(*mut c_void) as *mut (),
-            );
-            engine.register_symbol(
-                symbols::STORE_IN_CALLVALUE_PTR,
-                SyscallContext::store_in_callvalue_ptr as *const fn(*mut c_void, *mut U256)
-                    as *mut (),
-            );
-            engine.register_symbol(
-                symbols::STORE_IN_BLOBBASEFEE_PTR,
-                SyscallContext::store_in_blobbasefee_ptr
-                    as *const extern "C" fn(&SyscallContext, *mut u128) -> ()
-                    as *mut (),
-            );
-            engine.register_symbol(
-                symbols::GET_CODESIZE_FROM_ADDRESS,
-                SyscallContext::get_codesize_from_address
-                    as *const fn(*mut c_void, *mut U256, *mut u64) as *mut (),
-            );
-            engine.register_symbol(
-                symbols::GET_COINBASE_PTR,
-                SyscallContext::get_coinbase_ptr as *const fn(*mut c_void) as *mut (),
-            );
-            engine.register_symbol(
-                symbols::STORE_IN_TIMESTAMP_PTR,
-                SyscallContext::store_in_timestamp_ptr as *const fn(*mut c_void, *mut U256)
-                    as *mut (),
-            );
-            engine.register_symbol(
-                symbols::STORE_IN_BASEFEE_PTR,
-                SyscallContext::store_in_basefee_ptr as *const fn(*mut c_void, *mut U256)
-                    as *mut (),
-            );
-            engine.register_symbol(
-                symbols::STORE_IN_CALLER_PTR,
-                SyscallContext::store_in_caller_ptr as *const fn(*mut c_void, *mut U256) as *mut (),
-            );
-            engine.register_symbol(
-                symbols::GET_GASLIMIT,
-                SyscallContext::get_gaslimit as *const fn(*mut c_void) as *mut (),
-            );
-            engine.register_symbol(
-                symbols::STORE_IN_GASPRICE_PTR,
-                SyscallContext::store_in_gasprice_ptr as *const fn(*mut c_void, *mut U256)
-                    as *mut (),
-            );
-            engine.register_symbol(
-                symbols::GET_BLOCK_NUMBER,
-                SyscallContext::get_block_number as *const fn(*mut c_void, *mut U256) as *mut (),
-            );
-            engine.register_symbol(
-                symbols::GET_PREVRANDAO,
-                SyscallContext::get_prevrandao as *const fn(*mut c_void, *mut U256) as *mut (),
-            );
-            engine.register_symbol(
-                symbols::GET_BLOB_HASH_AT_INDEX,
-                SyscallContext::get_blob_hash_at_index
-                    as *const fn(*mut c_void, *mut U256, *mut U256) as *mut (),
-            );
-            engine.register_symbol(
-                symbols::GET_CHAINID,
-                SyscallContext::get_chainid as *const extern "C" fn(&SyscallContext) -> u64
-                    as *mut (),
-            );
-            engine.register_symbol(
-                symbols::STORE_IN_BALANCE,
-                SyscallContext::store_in_balance as *const fn(*mut c_void, *const U256, *mut U256)
-                    as *mut (),
-            );
-            engine.register_symbol(
-                symbols::STORE_IN_SELFBALANCE_PTR,
-                SyscallContext::store_in_selfbalance_ptr
-                    as *const extern "C" fn(&SyscallContext) -> u64 as *mut (),
-            );
-            engine.register_symbol(
-                symbols::COPY_EXT_CODE_TO_MEMORY,
-                SyscallContext::copy_ext_code_to_memory
-                    as *const extern "C" fn(*mut c_void, *mut U256, u32, u32, u32) -> u64
-                    as *mut (),
-            );
-            engine.register_symbol(
-                symbols::GET_BLOCK_HASH,
-                SyscallContext::get_block_hash as *const fn(*mut c_void, *mut U256) as *mut (),
-            );
+    pub fn register_symbols(engine: &ExecutionEngine) {
+        Self::for_each_symbol(|name, address| unsafe { engine.register_symbol(name, address) });
+    }
 
-            engine.register_symbol(
-                symbols::GET_CODE_HASH,
-                SyscallContext::get_code_hash as *const fn(*mut c_void, *mut U256) -> u64
-                    as *mut (),
-            );
+    /// Calls `register` with the name and address of every syscall
+    pub(crate) fn for_each_symbol(mut register: impl FnMut(&str, *mut ())) {
+        register(
+            symbols::CONTEXT_IS_STATIC,
+            SyscallContext::context_is_static as *const extern "C" fn(&SyscallContext) -> u8
+                as *mut (),
+        );
+        register(
+            symbols::WRITE_RESULT,
+            SyscallContext::write_result as *const fn(*mut c_void, u32, u32, u64, u8) as *mut (),
+        );
+        register(
+            symbols::KECCAK256_HASHER,
+            SyscallContext::keccak256_hasher as *const fn(*mut c_void, u32, u32, *const U256)
+                as *mut (),
+        );
+        register(
+            symbols::EXTEND_MEMORY,
+            SyscallContext::extend_memory as *const fn(*mut c_void, u32) as *mut (),
+        );
+        register(
+            symbols::STORAGE_READ,
+            SyscallContext::read_storage as *const fn(*mut c_void, *const U256, *mut U256)
+                as *mut (),
+        );
+        register(
+            symbols::STORAGE_WRITE,
+            SyscallContext::write_storage as *const fn(*mut c_void, *const U256, *const U256)
+                as *mut (),
+        );
+        register(
+            symbols::APPEND_LOG,
+            SyscallContext::append_log as *const fn(*mut c_void, u32, u32) as *mut (),
+        );
+        register(
+            symbols::APPEND_LOG_ONE_TOPIC,
+            SyscallContext::append_log_with_one_topic
+                as *const fn(*mut c_void, u32, u32, *const U256) as *mut (),
+        );
+        register(
+            symbols::APPEND_LOG_TWO_TOPICS,
+            SyscallContext::append_log_with_two_topics
+                as *const fn(*mut c_void, u32, u32, *const U256, *const U256)
+                as *mut (),
+        );
+        register(
+            symbols::APPEND_LOG_THREE_TOPICS,
+            SyscallContext::append_log_with_three_topics
+                as *const fn(*mut c_void, u32, u32, *const U256, *const U256, *const U256)
+                as *mut (),
+        );
+        register(
+            symbols::APPEND_LOG_FOUR_TOPICS,
+            SyscallContext::append_log_with_four_topics
+                as *const fn(
+                    *mut c_void,
+                    u32,
+                    u32,
+                    *const U256,
+                    *const U256,
+                    *const U256,
+                    *const U256,
+                ) as *mut (),
+        );
+        register(
+            symbols::CALL,
+            SyscallContext::call
+                as *const fn(
+                    *mut c_void,
+                    u64,
+                    *const U256,
+                    *const U256,
+                    u32,
+                    u32,
+                    u32,
+                    u32,
+                    u64,
+                    *mut u64,
+                    u8,
+                ) as *mut (),
+        );
+        register(
+            symbols::GET_CALLDATA_PTR,
+            SyscallContext::get_calldata_ptr as *const fn(*mut c_void) as *mut (),
+        );
+        register(
+            symbols::GET_CALLDATA_SIZE,
+            SyscallContext::get_calldata_size_syscall as *const fn(*mut c_void) as *mut (),
+        );
+        register(
+            symbols::EXTEND_MEMORY,
+            SyscallContext::extend_memory as *const fn(*mut c_void, u32) as *mut (),
+        );
+        register(
+            symbols::COPY_CODE_TO_MEMORY,
+            SyscallContext::copy_code_to_memory as *const fn(*mut c_void, u32, u32, u32) as *mut (),
+        );
+        register(
+            symbols::GET_ORIGIN,
+            SyscallContext::get_origin as *const fn(*mut c_void, *mut U256) as *mut (),
+        );
+        register(
+            symbols::GET_ADDRESS_PTR,
+            SyscallContext::get_address_ptr as *const fn(*mut c_void) as *mut (),
+        );
+        register(
+            symbols::STORE_IN_CALLVALUE_PTR,
+            SyscallContext::store_in_callvalue_ptr as *const fn(*mut c_void, *mut U256) as *mut (),
+        );
+        register(
+            symbols::STORE_IN_BLOBBASEFEE_PTR,
+            SyscallContext::store_in_blobbasefee_ptr
+                as *const extern "C" fn(&SyscallContext, *mut u128) -> () as *mut (),
+        );
+        register(
+            symbols::GET_CODESIZE_FROM_ADDRESS,
+            SyscallContext::get_codesize_from_address as *const fn(*mut c_void, *mut U256, *mut u64)
+                as *mut (),
+        );
+        register(
+            symbols::GET_COINBASE_PTR,
+            SyscallContext::get_coinbase_ptr as *const fn(*mut c_void) as *mut (),
+        );
+        register(
+            symbols::STORE_IN_TIMESTAMP_PTR,
+            SyscallContext::store_in_timestamp_ptr as *const fn(*mut c_void, *mut U256) as *mut (),
+        );
+        register(
+            symbols::STORE_IN_BASEFEE_PTR,
+            SyscallContext::store_in_basefee_ptr as *const fn(*mut c_void, *mut U256) as *mut (),
+        );
+        register(
+            symbols::STORE_IN_CALLER_PTR,
+            SyscallContext::store_in_caller_ptr as *const fn(*mut c_void, *mut U256) as *mut (),
+        );
+        register(
+            symbols::GET_GASLIMIT,
+            SyscallContext::get_gaslimit as *const fn(*mut c_void) as *mut (),
+        );
+        register(
+            symbols::STORE_IN_GASPRICE_PTR,
+            SyscallContext::store_in_gasprice_ptr as *const fn(*mut c_void, *mut U256) as *mut (),
+        );
+        register(
+            symbols::GET_BLOCK_NUMBER,
+            SyscallContext::get_block_number as *const fn(*mut c_void, *mut U256) as *mut (),
+        );
+        register(
+            symbols::GET_PREVRANDAO,
+            SyscallContext::get_prevrandao as *const fn(*mut c_void, *mut U256) as *mut (),
+        );
+        register(
+            symbols::GET_BLOB_HASH_AT_INDEX,
+            SyscallContext::get_blob_hash_at_index as *const fn(*mut c_void, *mut U256, *mut U256)
+                as *mut (),
+        );
+        register(
+            symbols::GET_CHAINID,
+            SyscallContext::get_chainid as *const extern "C" fn(&SyscallContext) -> u64 as *mut (),
+        );
+        register(
+            symbols::STORE_IN_BALANCE,
+            SyscallContext::store_in_balance as *const fn(*mut c_void, *const U256, *mut U256)
+                as *mut (),
+        );
+        register(
+            symbols::STORE_IN_SELFBALANCE_PTR,
+            SyscallContext::store_in_selfbalance_ptr as *const extern "C" fn(&SyscallContext) -> u64
+                as *mut (),
+        );
+        register(
+            symbols::COPY_EXT_CODE_TO_MEMORY,
+            SyscallContext::copy_ext_code_to_memory
+                as *const extern "C" fn(*mut c_void, *mut U256, u32, u32, u32) -> u64
+                as *mut (),
+        );
+        register(
+            symbols::GET_BLOCK_HASH,
+            SyscallContext::get_block_hash as *const fn(*mut c_void, *mut U256) as *mut (),
+        );
 
-            engine.register_symbol(
-                symbols::CREATE,
-                SyscallContext::create
-                    as *const extern "C" fn(*mut c_void, u32, u32, *mut U256, *mut u64)
-                    as *mut (),
-            );
+        register(
+            symbols::GET_CODE_HASH,
+            SyscallContext::get_code_hash as *const fn(*mut c_void, *mut U256) -> u64 as *mut (),
+        );
 
-            engine.register_symbol(
-                symbols::CREATE2,
-                SyscallContext::create2
-                    as *const extern "C" fn(*mut c_void, u32, u32, *mut U256, *mut u64, *mut U256)
-                    as *mut (),
-            );
+        register(
+            symbols::CREATE,
+            SyscallContext::create
+                as *const extern "C" fn(*mut c_void, u32, u32, *mut U256, *mut u64)
+                as *mut (),
+        );
 
-            engine.register_symbol(
-                symbols::GET_RETURN_DATA_SIZE,
-                SyscallContext::get_return_data_size as *const fn(*mut c_void) as *mut (),
-            );
-            engine.register_symbol(
-                symbols::COPY_RETURN_DATA_INTO_MEMORY,
-                SyscallContext::copy_return_data_into_memory
-                    as *const fn(*mut c_void, u32, u32, u32) as *mut (),
-            );
+        register(
+            symbols::CREATE2,
+            SyscallContext::create2
+                as *const extern "C" fn(*mut c_void, u32, u32, *mut U256, *mut u64, *mut U256)
+                as *mut (),
+        );
 
-            engine.register_symbol(
-                symbols::SELFDESTRUCT,
-                SyscallContext::selfdestruct as *const fn(*mut c_void, *mut U256) as *mut (),
-            );
+        register(
+            symbols::EOF_CREATE,
+            SyscallContext::eof_create
+                as *const extern "C" fn(
+                    *mut c_void,
+                    u8,
+                    u32,
+                    u32,
+                    *mut U256,
+                    *mut u64,
+                    *const U256,
+                ) -> u8 as *mut (),
+        );
 
-            engine.register_symbol(
-                symbols::TRANSIENT_STORAGE_READ,
-                SyscallContext::read_transient_storage
-                    as *const fn(*const c_void, *const U256, *mut U256) as *mut (),
-            );
+        register(
+            symbols::EOF_RETURN_CONTRACT,
+            SyscallContext::eof_return_contract
+                as *const extern "C" fn(*mut c_void, u8, u32, u32) -> u8 as *mut (),
+        );
 
-            engine.register_symbol(
-                symbols::TRANSIENT_STORAGE_WRITE,
-                SyscallContext::write_transient_storage
-                    as *const fn(*const c_void, *const U256, *mut U256) as *mut (),
-            );
-        }
+        register(
+            symbols::EOF_PUSH_RETURN,
+            SyscallContext::eof_push_return as *const extern "C" fn(*mut c_void, u32) -> u8
+                as *mut (),
+        );
+
+        register(
+            symbols::EOF_POP_RETURN,
+            SyscallContext::eof_pop_return as *const extern "C" fn(*mut c_void) -> u32 as *mut (),
+        );
+
+        register(
+            symbols::EOF_DATA_LOAD,
+            SyscallContext::eof_data_load as *const extern "C" fn(*mut c_void, *mut U256)
+                as *mut (),
+        );
+
+        register(
+            symbols::COPY_EOF_DATA_TO_MEMORY,
+            SyscallContext::copy_eof_data_to_memory
+                as *const extern "C" fn(*mut c_void, u32, u32, u32) as *mut (),
+        );
+
+        register(
+            symbols::EXT_CALL,
+            SyscallContext::ext_call
+                as *const extern "C" fn(
+                    *mut c_void,
+                    *mut U256,
+                    *const U256,
+                    u32,
+                    u32,
+                    u64,
+                    *mut u64,
+                    u8,
+                ) -> u8 as *mut (),
+        );
+
+        register(
+            symbols::GET_RETURN_DATA_SIZE,
+            SyscallContext::get_return_data_size as *const fn(*mut c_void) as *mut (),
+        );
+        register(
+            symbols::COPY_RETURN_DATA_INTO_MEMORY,
+            SyscallContext::copy_return_data_into_memory as *const fn(*mut c_void, u32, u32, u32)
+                as *mut (),
+        );
+
+        register(
+            symbols::SELFDESTRUCT,
+            SyscallContext::selfdestruct as *const fn(*mut c_void, *mut U256) as *mut (),
+        );
+
+        register(
+            symbols::TRANSIENT_STORAGE_READ,
+            SyscallContext::read_transient_storage
+                as *const fn(*const c_void, *const U256, *mut U256) as *mut (),
+        );
+
+        register(
+            symbols::TRANSIENT_STORAGE_WRITE,
+            SyscallContext::write_transient_storage
+                as *const fn(*const c_void, *const U256, *mut U256) as *mut (),
+        );
+
+        register(
+            symbols::STEP,
+            SyscallContext::step as *const fn(*mut c_void, u64, u8, u64, *const U256, *const U256)
+                as *mut (),
+        );
+
+        register(
+            symbols::NOT_ACTIVATED,
+            SyscallContext::not_activated as *const fn(*mut c_void) as *mut (),
+        );
     }
 }
 
 /// MLIR util for declaring syscalls
 pub(crate) mod mlir {
     use melior::{
-        dialect::{
-            func,
-            llvm::{attributes::Linkage, r#type::pointer},
-        },
+        dialect::{func, llvm::r#type::pointer},
         ir::{
             attribute::{FlatSymbolRefAttribute, StringAttribute, TypeAttribute},
             r#type::{FunctionType, IntegerType},
@@ -1440,7 +2627,7 @@ pub(crate) mod mlir {
         Context as MeliorContext,
     };
 
-    use crate::{errors::CodegenError, utils::llvm_mlir};
+    use crate::errors::CodegenError;
 
     use super::symbols;
 
@@ -1458,15 +2645,16 @@ pub(crate) mod mlir {
             StringAttribute::new(context, "private").into(),
         )];
 
-        // Globals declaration
-        module.body().append_operation(llvm_mlir::global(
+        // Syscall declarations
+        module.body().append_operation(func::func(
             context,
-            symbols::CONTEXT_IS_STATIC,
-            ptr_type,
-            Linkage::External,
+            StringAttribute::new(context, symbols::CONTEXT_IS_STATIC),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type], &[uint8]).into()),
+            Region::new(),
+            attributes,
             location,
         ));
-        // Syscall declarations
+
         module.body().append_operation(func::func(
             context,
             StringAttribute::new(context, symbols::WRITE_RESULT),
@@ -1734,7 +2922,82 @@ pub(crate) mod mlir {
 
         module.body().append_operation(func::func(
             context,
-            StringAttribute::new(context, symbols::STORE_IN_TIMESTAMP_PTR),
+            StringAttribute::new(context, symbols::STORE_IN_TIMESTAMP_PTR),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type, ptr_type], &[]).into()),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::STORE_IN_BASEFEE_PTR),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type, ptr_type], &[]).into()),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::CALL),
+            TypeAttribute::new(
+                FunctionType::new(
+                    context,
+                    &[
+                        ptr_type, uint64, ptr_type, ptr_type, uint32, uint32, uint32, uint32,
+                        uint64, ptr_type, uint8,
+                    ],
+                    &[uint8],
+                )
+                .into(),
+            ),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::STORE_IN_BALANCE),
+            TypeAttribute::new(
+                FunctionType::new(context, &[ptr_type, ptr_type, ptr_type], &[uint64]).into(),
+            ),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::COPY_EXT_CODE_TO_MEMORY),
+            TypeAttribute::new(
+                FunctionType::new(
+                    context,
+                    &[ptr_type, ptr_type, uint32, uint32, uint32],
+                    &[uint64],
+                )
+                .into(),
+            ),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::GET_BLOB_HASH_AT_INDEX),
+            TypeAttribute::new(
+                FunctionType::new(context, &[ptr_type, ptr_type, ptr_type], &[]).into(),
+            ),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::GET_BLOCK_HASH),
             TypeAttribute::new(FunctionType::new(context, &[ptr_type, ptr_type], &[]).into()),
             Region::new(),
             attributes,
@@ -1743,8 +3006,8 @@ pub(crate) mod mlir {
 
         module.body().append_operation(func::func(
             context,
-            StringAttribute::new(context, symbols::STORE_IN_BASEFEE_PTR),
-            TypeAttribute::new(FunctionType::new(context, &[ptr_type, ptr_type], &[]).into()),
+            StringAttribute::new(context, symbols::GET_CODE_HASH),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type, ptr_type], &[uint64]).into()),
             Region::new(),
             attributes,
             location,
@@ -1752,14 +3015,11 @@ pub(crate) mod mlir {
 
         module.body().append_operation(func::func(
             context,
-            StringAttribute::new(context, symbols::CALL),
+            StringAttribute::new(context, symbols::CREATE),
             TypeAttribute::new(
                 FunctionType::new(
                     context,
-                    &[
-                        ptr_type, uint64, ptr_type, ptr_type, uint32, uint32, uint32, uint32,
-                        uint64, ptr_type, uint8,
-                    ],
+                    &[ptr_type, uint32, uint32, ptr_type, ptr_type],
                     &[uint8],
                 )
                 .into(),
@@ -1771,9 +3031,14 @@ pub(crate) mod mlir {
 
         module.body().append_operation(func::func(
             context,
-            StringAttribute::new(context, symbols::STORE_IN_BALANCE),
+            StringAttribute::new(context, symbols::CREATE2),
             TypeAttribute::new(
-                FunctionType::new(context, &[ptr_type, ptr_type, ptr_type], &[uint64]).into(),
+                FunctionType::new(
+                    context,
+                    &[ptr_type, uint32, uint32, ptr_type, ptr_type, ptr_type],
+                    &[uint8],
+                )
+                .into(),
             ),
             Region::new(),
             attributes,
@@ -1782,12 +3047,14 @@ pub(crate) mod mlir {
 
         module.body().append_operation(func::func(
             context,
-            StringAttribute::new(context, symbols::COPY_EXT_CODE_TO_MEMORY),
+            StringAttribute::new(context, symbols::EOF_CREATE),
             TypeAttribute::new(
                 FunctionType::new(
                     context,
-                    &[ptr_type, ptr_type, uint32, uint32, uint32],
-                    &[uint64],
+                    &[
+                        ptr_type, uint8, uint32, uint32, ptr_type, ptr_type, ptr_type,
+                    ],
+                    &[uint8],
                 )
                 .into(),
             ),
@@ -1798,9 +3065,9 @@ pub(crate) mod mlir {
 
         module.body().append_operation(func::func(
             context,
-            StringAttribute::new(context, symbols::GET_BLOB_HASH_AT_INDEX),
+            StringAttribute::new(context, symbols::EOF_RETURN_CONTRACT),
             TypeAttribute::new(
-                FunctionType::new(context, &[ptr_type, ptr_type, ptr_type], &[]).into(),
+                FunctionType::new(context, &[ptr_type, uint8, uint32, uint32], &[uint8]).into(),
             ),
             Region::new(),
             attributes,
@@ -1809,8 +3076,8 @@ pub(crate) mod mlir {
 
         module.body().append_operation(func::func(
             context,
-            StringAttribute::new(context, symbols::GET_BLOCK_HASH),
-            TypeAttribute::new(FunctionType::new(context, &[ptr_type, ptr_type], &[]).into()),
+            StringAttribute::new(context, symbols::EOF_PUSH_RETURN),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type, uint32], &[uint8]).into()),
             Region::new(),
             attributes,
             location,
@@ -1818,8 +3085,8 @@ pub(crate) mod mlir {
 
         module.body().append_operation(func::func(
             context,
-            StringAttribute::new(context, symbols::GET_CODE_HASH),
-            TypeAttribute::new(FunctionType::new(context, &[ptr_type, ptr_type], &[uint64]).into()),
+            StringAttribute::new(context, symbols::EOF_POP_RETURN),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type], &[uint32]).into()),
             Region::new(),
             attributes,
             location,
@@ -1827,14 +3094,18 @@ pub(crate) mod mlir {
 
         module.body().append_operation(func::func(
             context,
-            StringAttribute::new(context, symbols::CREATE),
+            StringAttribute::new(context, symbols::EOF_DATA_LOAD),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type, ptr_type], &[]).into()),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::COPY_EOF_DATA_TO_MEMORY),
             TypeAttribute::new(
-                FunctionType::new(
-                    context,
-                    &[ptr_type, uint32, uint32, ptr_type, ptr_type],
-                    &[uint8],
-                )
-                .into(),
+                FunctionType::new(context, &[ptr_type, uint32, uint32, uint32], &[]).into(),
             ),
             Region::new(),
             attributes,
@@ -1843,11 +3114,13 @@ pub(crate) mod mlir {
 
         module.body().append_operation(func::func(
             context,
-            StringAttribute::new(context, symbols::CREATE2),
+            StringAttribute::new(context, symbols::EXT_CALL),
             TypeAttribute::new(
                 FunctionType::new(
                     context,
-                    &[ptr_type, uint32, uint32, ptr_type, ptr_type, ptr_type],
+                    &[
+                        ptr_type, ptr_type, ptr_type, uint32, uint32, uint64, ptr_type, uint8,
+                    ],
                     &[uint8],
                 )
                 .into(),
@@ -1907,6 +3180,31 @@ pub(crate) mod mlir {
             attributes,
             location,
         ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::STEP),
+            TypeAttribute::new(
+                FunctionType::new(
+                    context,
+                    &[ptr_type, uint64, uint8, uint64, ptr_type, ptr_type],
+                    &[],
+                )
+                .into(),
+            ),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::NOT_ACTIVATED),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type], &[]).into()),
+            Region::new(),
+            attributes,
+            location,
+        ));
     }
 
     /// Stores the return values in the syscall context
@@ -2006,6 +3304,25 @@ pub(crate) mod mlir {
         Ok(value.into())
     }
 
+    pub(crate) fn context_is_static_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        location: Location<'c>,
+    ) -> Result<Value<'c, 'c>, CodegenError> {
+        let uint8 = IntegerType::new(mlir_ctx, 8).into();
+        let value = block
+            .append_operation(func::call(
+                mlir_ctx,
+                FlatSymbolRefAttribute::new(mlir_ctx, symbols::CONTEXT_IS_STATIC),
+                &[syscall_ctx],
+                &[uint8],
+                location,
+            ))
+            .result(0)?;
+        Ok(value.into())
+    }
+
     pub(crate) fn get_chainid_syscall<'c>(
         mlir_ctx: &'c MeliorContext,
         syscall_ctx: Value<'c, 'c>,
@@ -2678,6 +3995,171 @@ pub(crate) mod mlir {
         Ok(result.into())
     }
 
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn eof_create_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        initcontainer_index: Value<'c, 'c>,
+        input_offset: Value<'c, 'c>,
+        input_size: Value<'c, 'c>,
+        value: Value<'c, 'c>,
+        remaining_gas: Value<'c, 'c>,
+        salt: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value<'c, 'c>, CodegenError> {
+        let uint8 = IntegerType::new(mlir_ctx, 8).into();
+        let result = block
+            .append_operation(func::call(
+                mlir_ctx,
+                FlatSymbolRefAttribute::new(mlir_ctx, symbols::EOF_CREATE),
+                &[
+                    syscall_ctx,
+                    initcontainer_index,
+                    input_offset,
+                    input_size,
+                    value,
+                    remaining_gas,
+                    salt,
+                ],
+                &[uint8],
+                location,
+            ))
+            .result(0)?;
+        Ok(result.into())
+    }
+
+    pub(crate) fn eof_return_contract_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        container_index: Value<'c, 'c>,
+        aux_data_offset: Value<'c, 'c>,
+        aux_data_size: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value<'c, 'c>, CodegenError> {
+        let uint8 = IntegerType::new(mlir_ctx, 8).into();
+        let result = block
+            .append_operation(func::call(
+                mlir_ctx,
+                FlatSymbolRefAttribute::new(mlir_ctx, symbols::EOF_RETURN_CONTRACT),
+                &[syscall_ctx, container_index, aux_data_offset, aux_data_size],
+                &[uint8],
+                location,
+            ))
+            .result(0)?;
+        Ok(result.into())
+    }
+
+    pub(crate) fn eof_push_return_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        return_pc: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value<'c, 'c>, CodegenError> {
+        let uint8 = IntegerType::new(mlir_ctx, 8).into();
+        let result = block
+            .append_operation(func::call(
+                mlir_ctx,
+                FlatSymbolRefAttribute::new(mlir_ctx, symbols::EOF_PUSH_RETURN),
+                &[syscall_ctx, return_pc],
+                &[uint8],
+                location,
+            ))
+            .result(0)?;
+        Ok(result.into())
+    }
+
+    pub(crate) fn eof_pop_return_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        location: Location<'c>,
+    ) -> Result<Value<'c, 'c>, CodegenError> {
+        let uint32 = IntegerType::new(mlir_ctx, 32).into();
+        let result = block
+            .append_operation(func::call(
+                mlir_ctx,
+                FlatSymbolRefAttribute::new(mlir_ctx, symbols::EOF_POP_RETURN),
+                &[syscall_ctx],
+                &[uint32],
+                location,
+            ))
+            .result(0)?;
+        Ok(result.into())
+    }
+
+    pub(crate) fn eof_data_load_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        offset: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        block.append_operation(func::call(
+            mlir_ctx,
+            FlatSymbolRefAttribute::new(mlir_ctx, symbols::EOF_DATA_LOAD),
+            &[syscall_ctx, offset],
+            &[],
+            location,
+        ));
+    }
+
+    pub(crate) fn copy_eof_data_to_memory_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        data_offset: Value<'c, 'c>,
+        size: Value<'c, 'c>,
+        dest_offset: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        block.append_operation(func::call(
+            mlir_ctx,
+            FlatSymbolRefAttribute::new(mlir_ctx, symbols::COPY_EOF_DATA_TO_MEMORY),
+            &[syscall_ctx, data_offset, size, dest_offset],
+            &[],
+            location,
+        ));
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn ext_call_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        location: Location<'c>,
+        address_ptr: Value<'c, 'c>,
+        value_ptr: Value<'c, 'c>,
+        args_offset: Value<'c, 'c>,
+        args_size: Value<'c, 'c>,
+        available_gas: Value<'c, 'c>,
+        consumed_gas_ptr: Value<'c, 'c>,
+        call_type: Value<'c, 'c>,
+    ) -> Result<Value<'c, 'c>, CodegenError> {
+        let uint8 = IntegerType::new(mlir_ctx, 8).into();
+        let result = block
+            .append_operation(func::call(
+                mlir_ctx,
+                FlatSymbolRefAttribute::new(mlir_ctx, symbols::EXT_CALL),
+                &[
+                    syscall_ctx,
+                    address_ptr,
+                    value_ptr,
+                    args_offset,
+                    args_size,
+                    available_gas,
+                    consumed_gas_ptr,
+                    call_type,
+                ],
+                &[uint8],
+                location,
+            ))
+            .result(0)?;
+        Ok(result.into())
+    }
+
     pub(crate) fn get_return_data_size<'c>(
         mlir_ctx: &'c MeliorContext,
         syscall_ctx: Value<'c, 'c>,
@@ -2737,4 +4219,49 @@ pub(crate) mod mlir {
 
         Ok(result.into())
     }
+
+    /// Reports the operation about to run to the inspector
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn step_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        pc: Value<'c, 'c>,
+        opcode: Value<'c, 'c>,
+        gas_remaining: Value<'c, 'c>,
+        stack_baseptr: Value<'c, 'c>,
+        stack_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        block.append_operation(func::call(
+            mlir_ctx,
+            FlatSymbolRefAttribute::new(mlir_ctx, symbols::STEP),
+            &[
+                syscall_ctx,
+                pc,
+                opcode,
+                gas_remaining,
+                stack_baseptr,
+                stack_ptr,
+            ],
+            &[],
+            location,
+        ));
+    }
+
+    /// Sets the halt reason for operations introduced by a later hardfork
+    pub(crate) fn not_activated_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        location: Location<'c>,
+    ) {
+        block.append_operation(func::call(
+            mlir_ctx,
+            FlatSymbolRefAttribute::new(mlir_ctx, symbols::NOT_ACTIVATED),
+            &[syscall_ctx],
+            &[],
+            location,
+        ));
+    }
 }
@@ -0,0 +1,76 @@
+//! Helpers shared by the unit tests
+use std::cell::Cell;
+
+use crate::{
+    db::{AccountInfo, Bytecode, DatabaseRef},
+    fork::{ProviderError, StateProvider},
+    primitives::{Address, B256, U256},
+};
+
+/// Wraps a database or a state provider, counting the reads made to it
+pub(crate) struct CountingReads<T> {
+    pub inner: T,
+    reads: Cell<usize>,
+}
+
+impl<T> CountingReads<T> {
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            reads: Cell::new(0),
+        }
+    }
+
+    /// Reads made so far
+    pub fn reads(&self) -> usize {
+        self.reads.get()
+    }
+
+    fn count(&self) -> &T {
+        self.reads.set(self.reads.get() + 1);
+        &self.inner
+    }
+}
+
+impl<T: DatabaseRef> DatabaseRef for CountingReads<T> {
+    type Error = T::Error;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        self.count().basic_ref(address)
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        self.count().code_by_hash_ref(code_hash)
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        self.count().storage_ref(address, index)
+    }
+
+    fn block_hash_ref(&self, number: U256) -> Result<B256, Self::Error> {
+        self.count().block_hash_ref(number)
+    }
+}
+
+impl<T: StateProvider> StateProvider for CountingReads<T> {
+    fn account(
+        &self,
+        address: Address,
+        block_number: u64,
+    ) -> Result<Option<AccountInfo>, ProviderError> {
+        self.count().account(address, block_number)
+    }
+
+    fn storage(
+        &self,
+        address: Address,
+        index: U256,
+        block_number: u64,
+    ) -> Result<U256, ProviderError> {
+        self.count().storage(address, index, block_number)
+    }
+
+    fn block_hash(&self, number: u64) -> Result<B256, ProviderError> {
+        self.count().block_hash(number)
+    }
+}
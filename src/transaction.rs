@@ -0,0 +1,521 @@
+//! # Signed transactions
+//!
+//! Decodes raw signed transactions into a [`TxEnv`], recovering their sender from
+//! the signature. Besides legacy transactions, the typed envelopes of EIP-2718 are
+//! supported: access list (EIP-2930), fee market (EIP-1559), blob (EIP-4844) and
+//! set code (EIP-7702) transactions.
+use rlp::{Decodable, DecoderError, Rlp, RlpStream};
+use sha3::{Digest, Keccak256};
+use thiserror::Error;
+
+use crate::{
+    constants::SECP256K1N_HALF,
+    env::{AccessList, AuthorizationList, SignedAuthorization, TransactTo, TxEnv},
+    precompiles::recover_address,
+    primitives::{Address, Bytes, B256, U256},
+};
+
+/// Type of a transaction, the first byte of its envelope (EIP-2718)
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum TransactionType {
+    /// Transactions before EIP-2718, whose envelope is just an RLP list
+    Legacy = 0x00,
+    AccessList = 0x01,
+    FeeMarket = 0x02,
+    Blob = 0x03,
+    SetCode = 0x04,
+}
+
+impl TransactionType {
+    /// Amount of fields of the signed transaction, including the signature
+    fn field_count(self) -> usize {
+        match self {
+            TransactionType::Legacy => 9,
+            TransactionType::AccessList => 11,
+            TransactionType::FeeMarket => 12,
+            TransactionType::Blob => 14,
+            TransactionType::SetCode => 13,
+        }
+    }
+}
+
+impl TryFrom<u8> for TransactionType {
+    type Error = TransactionDecodingError;
+
+    fn try_from(tx_type: u8) -> Result<Self, Self::Error> {
+        let tx_type = match tx_type {
+            0x01 => TransactionType::AccessList,
+            0x02 => TransactionType::FeeMarket,
+            0x03 => TransactionType::Blob,
+            0x04 => TransactionType::SetCode,
+            _ => return Err(TransactionDecodingError::UnsupportedType(tx_type)),
+        };
+        Ok(tx_type)
+    }
+}
+
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum TransactionDecodingError {
+    #[error("empty transaction")]
+    Empty,
+    #[error("unsupported transaction type: {0:#04x}")]
+    UnsupportedType(u8),
+    #[error("invalid RLP: {0}")]
+    Rlp(#[from] DecoderError),
+    #[error("transaction for chain {0} doesn't match the chain id")]
+    InvalidChainId(u64),
+    #[error("malleable signature, its s is in the upper half of the curve order")]
+    MalleableSignature,
+    #[error("invalid signature")]
+    InvalidSignature,
+}
+
+/// Decodes a raw signed transaction for the chain `chain_id`, recovering its sender.
+///
+/// Legacy transactions signed before EIP-155 aren't bound to any chain, so their
+/// [chain id](TxEnv::chain_id) is `None`. Blob transactions can also be given in
+/// their network form, wrapped with their blobs, commitments and proofs.
+pub fn decode_signed_transaction(
+    raw: &[u8],
+    chain_id: u64,
+) -> Result<TxEnv, TransactionDecodingError> {
+    let (&first_byte, typed_payload) = raw.split_first().ok_or(TransactionDecodingError::Empty)?;
+    // Legacy transactions start with an RLP list prefix, which is never a valid type
+    let (tx_type, payload) = if first_byte >= 0xc0 {
+        (TransactionType::Legacy, raw)
+    } else {
+        (TransactionType::try_from(first_byte)?, typed_payload)
+    };
+    let mut rlp = Rlp::new(payload);
+    if rlp.payload_info()?.total() != payload.len() {
+        return Err(DecoderError::RlpIsTooBig.into());
+    }
+    if tx_type == TransactionType::Blob && rlp.at(0)?.is_list() {
+        rlp = rlp.at(0)?;
+    }
+    if rlp.item_count()? != tx_type.field_count() {
+        return Err(DecoderError::RlpIncorrectListLen.into());
+    }
+
+    match tx_type {
+        TransactionType::Legacy => decode_legacy(&rlp, chain_id),
+        _ => decode_typed(tx_type, &rlp, chain_id),
+    }
+}
+
+fn decode_legacy(rlp: &Rlp, chain_id: u64) -> Result<TxEnv, TransactionDecodingError> {
+    let mut fields = Fields::new(rlp);
+    let mut tx = TxEnv {
+        nonce: Some(fields.next()?),
+        gas_price: fields.next()?,
+        gas_limit: fields.next()?,
+        transact_to: decode_destination(&fields.next_item()?)?,
+        value: fields.next()?,
+        data: Bytes::copy_from_slice(fields.next_item()?.data()?),
+        ..Default::default()
+    };
+    let v: u64 = fields.next()?;
+    let (y_parity, tx_chain_id) = match v {
+        27 | 28 => (v - 27, None),
+        // Replay protected transactions encode the chain id in `v` (EIP-155)
+        _ if v >= 35 => ((v - 35) % 2, Some((v - 35) / 2)),
+        _ => return Err(TransactionDecodingError::InvalidSignature),
+    };
+    if let Some(tx_chain_id) = tx_chain_id.filter(|&tx_chain_id| tx_chain_id != chain_id) {
+        return Err(TransactionDecodingError::InvalidChainId(tx_chain_id));
+    }
+    let (r, s) = (fields.next()?, fields.next()?);
+
+    // The signed payload is the list of the first 6 fields, followed by the chain id
+    // and two empty fields for replay protected transactions
+    let mut payload = RlpStream::new_list(if tx_chain_id.is_some() { 9 } else { 6 });
+    for index in 0..6 {
+        payload.append_raw(rlp.at(index)?.as_raw(), 1);
+    }
+    if let Some(tx_chain_id) = tx_chain_id {
+        payload.append(&tx_chain_id);
+        payload.append_empty_data();
+        payload.append_empty_data();
+    }
+    tx.chain_id = tx_chain_id;
+    tx.caller = recover_signer(&keccak(&payload.out()), y_parity as u8, r, s)?;
+    Ok(tx)
+}
+
+fn decode_typed(
+    tx_type: TransactionType,
+    rlp: &Rlp,
+    chain_id: u64,
+) -> Result<TxEnv, TransactionDecodingError> {
+    let mut fields = Fields::new(rlp);
+    let tx_chain_id: u64 = fields.next()?;
+    if tx_chain_id != chain_id {
+        return Err(TransactionDecodingError::InvalidChainId(tx_chain_id));
+    }
+    let mut tx = TxEnv {
+        chain_id: Some(tx_chain_id),
+        nonce: Some(fields.next()?),
+        ..Default::default()
+    };
+    if tx_type == TransactionType::AccessList {
+        tx.gas_price = fields.next()?;
+    } else {
+        tx.gas_priority_fee = Some(fields.next()?);
+        tx.gas_price = fields.next()?;
+    }
+    tx.gas_limit = fields.next()?;
+    tx.transact_to = decode_destination(&fields.next_item()?)?;
+    tx.value = fields.next()?;
+    tx.data = Bytes::copy_from_slice(fields.next_item()?.data()?);
+    tx.access_list = decode_access_list(&fields.next_item()?)?;
+    if tx_type == TransactionType::Blob {
+        tx.max_fee_per_blob_gas = Some(fields.next()?);
+        tx.blob_hashes = fields.next_item()?.as_list()?;
+    }
+    if tx_type == TransactionType::SetCode {
+        tx.authorization_list = Some(decode_authorization_list(&fields.next_item()?)?);
+    }
+    let (y_parity, r, s) = (fields.next()?, fields.next()?, fields.next()?);
+
+    // The signed payload is the type followed by the list of the fields but the signature
+    let field_count = tx_type.field_count() - 3;
+    let mut payload = RlpStream::new_list(field_count);
+    for index in 0..field_count {
+        payload.append_raw(rlp.at(index)?.as_raw(), 1);
+    }
+    let mut signed = vec![tx_type as u8];
+    signed.extend_from_slice(&payload.out());
+    tx.caller = recover_signer(&keccak(&signed), y_parity, r, s)?;
+    Ok(tx)
+}
+
+/// Transactions without a destination create a contract
+fn decode_destination(rlp: &Rlp) -> Result<TransactTo, DecoderError> {
+    if rlp.is_empty() {
+        Ok(TransactTo::Create)
+    } else {
+        Ok(TransactTo::Call(rlp.as_val()?))
+    }
+}
+
+fn decode_access_list(rlp: &Rlp) -> Result<AccessList, DecoderError> {
+    rlp.iter()
+        .map(|item| {
+            if item.item_count()? != 2 {
+                return Err(DecoderError::RlpIncorrectListLen);
+            }
+            let address = item.val_at(0)?;
+            // Storage keys are 32 bytes long, so they can't be decoded as integers
+            let keys: Vec<B256> = item.list_at(1)?;
+            let keys = keys
+                .iter()
+                .map(|key| U256::from_big_endian(key.as_bytes()))
+                .collect();
+            Ok((address, keys))
+        })
+        .collect()
+}
+
+fn decode_authorization_list(rlp: &Rlp) -> Result<AuthorizationList, DecoderError> {
+    rlp.iter()
+        .map(|item| {
+            if item.item_count()? != 6 {
+                return Err(DecoderError::RlpIncorrectListLen);
+            }
+            Ok(SignedAuthorization {
+                chain_id: item.val_at(0)?,
+                address: item.val_at(1)?,
+                nonce: item.val_at(2)?,
+                y_parity: item.val_at(3)?,
+                r: item.val_at(4)?,
+                s: item.val_at(5)?,
+            })
+        })
+        .collect()
+}
+
+/// Recovers the address that signed `hash`, rejecting malleable signatures (EIP-2)
+fn recover_signer(
+    hash: &B256,
+    y_parity: u8,
+    r: U256,
+    s: U256,
+) -> Result<Address, TransactionDecodingError> {
+    if s > U256::from_big_endian(&SECP256K1N_HALF) {
+        return Err(TransactionDecodingError::MalleableSignature);
+    }
+    if y_parity > 1 {
+        return Err(TransactionDecodingError::InvalidSignature);
+    }
+    let mut signature = [0u8; 64];
+    r.to_big_endian(&mut signature[..32]);
+    s.to_big_endian(&mut signature[32..]);
+    recover_address(hash.as_bytes(), y_parity as i32, &signature)
+        .map_err(|_| TransactionDecodingError::InvalidSignature)
+}
+
+fn keccak(data: &[u8]) -> B256 {
+    B256::from_slice(&Keccak256::digest(data))
+}
+
+/// Reads the fields of a transaction in order
+struct Fields<'a> {
+    rlp: &'a Rlp<'a>,
+    index: usize,
+}
+
+impl<'a> Fields<'a> {
+    fn new(rlp: &'a Rlp<'a>) -> Self {
+        Self { rlp, index: 0 }
+    }
+
+    fn next_item(&mut self) -> Result<Rlp<'a>, DecoderError> {
+        let item = self.rlp.at(self.index)?;
+        self.index += 1;
+        Ok(item)
+    }
+
+    fn next<T: Decodable>(&mut self) -> Result<T, DecoderError> {
+        self.next_item()?.as_val()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
+
+    use super::*;
+
+    const CHAIN_ID: u64 = 1;
+
+    fn secret_key() -> SecretKey {
+        SecretKey::from_slice(&[0x46; 32]).unwrap()
+    }
+
+    fn signer_address() -> Address {
+        let public_key = PublicKey::from_secret_key(&Secp256k1::new(), &secret_key());
+        let hash = Keccak256::digest(&public_key.serialize_uncompressed()[1..]);
+        Address::from_slice(&hash[12..])
+    }
+
+    /// Signs a typed transaction whose unsigned fields are appended by `append_fields`
+    fn sign_typed(tx_type: TransactionType, append_fields: impl Fn(&mut RlpStream)) -> Vec<u8> {
+        let field_count = tx_type.field_count() - 3;
+        let mut payload = RlpStream::new_list(field_count);
+        append_fields(&mut payload);
+        let mut signed = vec![tx_type as u8];
+        signed.extend_from_slice(&payload.out());
+        let message = Message::from_digest(keccak(&signed).to_fixed_bytes());
+        let (recovery_id, signature) = Secp256k1::new()
+            .sign_ecdsa_recoverable(&message, &secret_key())
+            .serialize_compact();
+
+        let mut stream = RlpStream::new_list(field_count + 3);
+        append_fields(&mut stream);
+        stream.append(&(recovery_id.to_i32() as u8));
+        stream.append(&U256::from_big_endian(&signature[..32]));
+        stream.append(&U256::from_big_endian(&signature[32..]));
+        let mut raw = vec![tx_type as u8];
+        raw.extend_from_slice(&stream.out());
+        raw
+    }
+
+    /// Appends the fields shared by fee market, blob and set code transactions
+    fn append_fee_market_fields(stream: &mut RlpStream, chain_id: u64) {
+        stream.append(&chain_id);
+        stream.append(&7_u64); // nonce
+        stream.append(&U256::from(2)); // max priority fee per gas
+        stream.append(&U256::from(30)); // max fee per gas
+        stream.append(&50_000_u64); // gas limit
+        stream.append(&Address::from_low_u64_be(0x42)); // destination
+        stream.append(&U256::from(1000)); // value
+        stream.append(&vec![0xca_u8, 0xfe]); // data
+        stream.begin_list(1); // access list
+        stream.begin_list(2);
+        stream.append(&Address::from_low_u64_be(0x43));
+        stream.begin_list(1);
+        stream.append(&B256::from_low_u64_be(5));
+    }
+
+    #[test]
+    fn decodes_replay_protected_legacy_transactions() {
+        // Example of EIP-155
+        let raw = hex::decode(
+            "f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83",
+        )
+        .unwrap();
+
+        let tx = decode_signed_transaction(&raw, CHAIN_ID).unwrap();
+
+        assert_eq!(tx.caller, signer_address());
+        assert_eq!(
+            tx.caller,
+            Address::from_str("0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f").unwrap()
+        );
+        assert_eq!(tx.nonce, Some(9));
+        assert_eq!(tx.chain_id, Some(CHAIN_ID));
+        assert_eq!(tx.gas_price, U256::from(20_000_000_000_u64));
+        assert_eq!(tx.gas_limit, 21_000);
+        assert!(matches!(tx.transact_to, TransactTo::Call(to) if to == Address::from([0x35; 20])));
+        assert_eq!(tx.value, U256::from(1_000_000_000_000_000_000_u64));
+        assert!(tx.data.is_empty());
+        assert_eq!(tx.gas_priority_fee, None);
+
+        assert_eq!(
+            decode_signed_transaction(&raw, 5).unwrap_err(),
+            TransactionDecodingError::InvalidChainId(CHAIN_ID)
+        );
+    }
+
+    #[test]
+    fn decodes_fee_market_transactions() {
+        let raw = sign_typed(TransactionType::FeeMarket, |stream| {
+            append_fee_market_fields(stream, CHAIN_ID)
+        });
+
+        let tx = decode_signed_transaction(&raw, CHAIN_ID).unwrap();
+
+        assert_eq!(tx.caller, signer_address());
+        assert_eq!(tx.nonce, Some(7));
+        assert_eq!(tx.gas_priority_fee, Some(U256::from(2)));
+        assert_eq!(tx.gas_price, U256::from(30));
+        assert_eq!(tx.gas_limit, 50_000);
+        assert_eq!(tx.value, U256::from(1000));
+        assert_eq!(tx.data, Bytes::from_static(&[0xca, 0xfe]));
+        assert_eq!(
+            tx.access_list,
+            vec![(Address::from_low_u64_be(0x43), vec![U256::from(5)])]
+        );
+        assert!(tx.authorization_list.is_none());
+    }
+
+    #[test]
+    fn decodes_access_list_transactions() {
+        let raw = sign_typed(TransactionType::AccessList, |stream| {
+            stream.append(&CHAIN_ID);
+            stream.append(&0_u64); // nonce
+            stream.append(&U256::from(10)); // gas price
+            stream.append(&60_000_u64); // gas limit
+            stream.append_empty_data(); // creation
+            stream.append_empty_data(); // value
+            stream.append(&vec![0x60_u8, 0x00]); // init code
+            stream.begin_list(0); // access list
+        });
+
+        let tx = decode_signed_transaction(&raw, CHAIN_ID).unwrap();
+
+        assert_eq!(tx.caller, signer_address());
+        assert_eq!(tx.gas_price, U256::from(10));
+        assert_eq!(tx.gas_priority_fee, None);
+        assert!(matches!(tx.transact_to, TransactTo::Create));
+        assert!(tx.access_list.is_empty());
+    }
+
+    #[test]
+    fn decodes_blob_transactions() {
+        let blob_hash = B256::from_low_u64_be(0x0100);
+        let append_fields = |stream: &mut RlpStream| {
+            append_fee_market_fields(stream, CHAIN_ID);
+            stream.append(&U256::from(3)); // max fee per blob gas
+            stream.begin_list(1);
+            stream.append(&blob_hash);
+        };
+        let raw = sign_typed(TransactionType::Blob, append_fields);
+
+        let tx = decode_signed_transaction(&raw, CHAIN_ID).unwrap();
+
+        assert_eq!(tx.caller, signer_address());
+        assert_eq!(tx.max_fee_per_blob_gas, Some(U256::from(3)));
+        assert_eq!(tx.blob_hashes, vec![blob_hash]);
+
+        // The network form wraps the transaction with its blobs, commitments and proofs
+        let mut wrapped = RlpStream::new_list(4);
+        wrapped.append_raw(&raw[1..], 1);
+        wrapped.begin_list(0);
+        wrapped.begin_list(0);
+        wrapped.begin_list(0);
+        let mut network_raw = vec![TransactionType::Blob as u8];
+        network_raw.extend_from_slice(&wrapped.out());
+        let network_tx = decode_signed_transaction(&network_raw, CHAIN_ID).unwrap();
+        assert_eq!(network_tx.caller, signer_address());
+        assert_eq!(network_tx.blob_hashes, vec![blob_hash]);
+    }
+
+    #[test]
+    fn decodes_set_code_transactions() {
+        let raw = sign_typed(TransactionType::SetCode, |stream| {
+            append_fee_market_fields(stream, CHAIN_ID);
+            stream.begin_list(1);
+            stream.begin_list(6);
+            stream.append(&U256::zero()); // any chain
+            stream.append(&Address::from_low_u64_be(0x44));
+            stream.append(&3_u64);
+            stream.append(&1_u8);
+            stream.append(&U256::from(11));
+            stream.append(&U256::from(12));
+        });
+
+        let tx = decode_signed_transaction(&raw, CHAIN_ID).unwrap();
+
+        assert_eq!(tx.caller, signer_address());
+        assert_eq!(
+            tx.authorization_list,
+            Some(vec![SignedAuthorization {
+                chain_id: U256::zero(),
+                address: Address::from_low_u64_be(0x44),
+                nonce: 3,
+                y_parity: 1,
+                r: U256::from(11),
+                s: U256::from(12),
+            }])
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_transactions() {
+        let other_chain = sign_typed(TransactionType::FeeMarket, |stream| {
+            append_fee_market_fields(stream, 5)
+        });
+        assert_eq!(
+            decode_signed_transaction(&other_chain, CHAIN_ID).unwrap_err(),
+            TransactionDecodingError::InvalidChainId(5)
+        );
+
+        let mut trailing_bytes = sign_typed(TransactionType::FeeMarket, |stream| {
+            append_fee_market_fields(stream, CHAIN_ID)
+        });
+        trailing_bytes.push(0);
+        assert_eq!(
+            decode_signed_transaction(&trailing_bytes, CHAIN_ID).unwrap_err(),
+            TransactionDecodingError::Rlp(DecoderError::RlpIsTooBig)
+        );
+
+        assert_eq!(
+            decode_signed_transaction(&[0x05, 0xc0], CHAIN_ID).unwrap_err(),
+            TransactionDecodingError::UnsupportedType(0x05)
+        );
+        assert_eq!(
+            decode_signed_transaction(&[], CHAIN_ID).unwrap_err(),
+            TransactionDecodingError::Empty
+        );
+    }
+
+    #[test]
+    fn rejects_malleable_signatures() {
+        let mut stream = RlpStream::new_list(12);
+        append_fee_market_fields(&mut stream, CHAIN_ID);
+        stream.append(&0_u8);
+        stream.append(&U256::one());
+        stream.append(&(U256::from_big_endian(&SECP256K1N_HALF) + 1));
+        let mut raw = vec![TransactionType::FeeMarket as u8];
+        raw.extend_from_slice(&stream.out());
+
+        assert_eq!(
+            decode_signed_transaction(&raw, CHAIN_ID).unwrap_err(),
+            TransactionDecodingError::MalleableSignature
+        );
+    }
+}
@@ -0,0 +1,249 @@
+//! # Merkle Patricia Trie
+//!
+//! Roots of the tries Ethereum commits to in its block headers. The tries are built
+//! from all of their entries at once and their nodes aren't kept, so only the root
+//! is computed.
+//!
+//! The world state is a trie of the RLP encoded accounts, keyed by the hash of their
+//! address. Each account commits to its storage with the root of another trie, keyed
+//! by the hash of the slots.
+use std::{collections::BTreeMap, str::FromStr};
+
+use rlp::RlpStream;
+use sha3::{Digest, Keccak256};
+
+use crate::{
+    constants::EMPTY_TRIE_ROOT_STR,
+    db::AccountInfo,
+    primitives::{B256, U256},
+};
+
+/// Root of a trie without entries, `keccak256(rlp(""))`
+pub fn empty_trie_root() -> B256 {
+    B256::from_str(EMPTY_TRIE_ROOT_STR).unwrap()
+}
+
+/// Root of the trie holding `entries`. If a key is repeated, its last value is kept.
+pub fn trie_root<K, V>(entries: impl IntoIterator<Item = (K, V)>) -> B256
+where
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+{
+    // Sorting the keys keeps the entries of every subtrie next to each other
+    let entries: BTreeMap<Vec<u8>, Vec<u8>> = entries
+        .into_iter()
+        .map(|(key, value)| (to_nibbles(key.as_ref()), value.as_ref().to_vec()))
+        .collect();
+    let entries: Vec<_> = entries.into_iter().collect();
+    keccak(&encode_node(&entries, 0))
+}
+
+/// Root of the trie holding `entries` keyed by the hash of their keys, like the state
+/// and storage tries.
+pub fn secure_trie_root<K, V>(entries: impl IntoIterator<Item = (K, V)>) -> B256
+where
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+{
+    trie_root(
+        entries
+            .into_iter()
+            .map(|(key, value)| (keccak(key.as_ref()), value)),
+    )
+}
+
+/// Root of the storage trie of an account. Slots holding zero aren't part of it.
+pub fn storage_root(storage: impl IntoIterator<Item = (U256, U256)>) -> B256 {
+    secure_trie_root(
+        storage
+            .into_iter()
+            .filter(|(_, value)| !value.is_zero())
+            .map(|(key, value)| {
+                let mut slot = [0u8; 32];
+                key.to_big_endian(&mut slot);
+                (slot, rlp::encode(&value))
+            }),
+    )
+}
+
+/// Encodes an account as stored in the state trie: `rlp([nonce, balance, storage_root, code_hash])`
+pub fn encode_account(account: &AccountInfo, storage_root: B256) -> Vec<u8> {
+    let mut stream = RlpStream::new_list(4);
+    stream.append(&account.nonce);
+    stream.append(&account.balance);
+    stream.append(&storage_root);
+    stream.append(&account.code_hash);
+    stream.out().to_vec()
+}
+
+fn keccak(data: &[u8]) -> B256 {
+    let mut hasher = Keccak256::new();
+    hasher.update(data);
+    B256::from_slice(&hasher.finalize())
+}
+
+fn to_nibbles(key: &[u8]) -> Vec<u8> {
+    key.iter()
+        .flat_map(|byte| [byte >> 4, byte & 0x0f])
+        .collect()
+}
+
+/// Packs the nibbles of a path into bytes, with a first nibble flagging whether it
+/// belongs to a leaf and whether its length is odd.
+fn hex_prefix(nibbles: &[u8], is_leaf: bool) -> Vec<u8> {
+    let flag = if is_leaf { 2 } else { 0 };
+    let mut encoded = Vec::with_capacity(nibbles.len() / 2 + 1);
+    let rest = if nibbles.len() % 2 == 1 {
+        encoded.push(((flag + 1) << 4) | nibbles[0]);
+        &nibbles[1..]
+    } else {
+        encoded.push(flag << 4);
+        nibbles
+    };
+    encoded.extend(rest.chunks(2).map(|pair| (pair[0] << 4) | pair[1]));
+    encoded
+}
+
+/// RLP encoding of the node holding the sorted `entries`, which share the first
+/// `depth` nibbles of their keys.
+fn encode_node(entries: &[(Vec<u8>, Vec<u8>)], depth: usize) -> Vec<u8> {
+    match entries {
+        [] => rlp::NULL_RLP.to_vec(),
+        [(key, value)] => {
+            let mut stream = RlpStream::new_list(2);
+            stream.append(&hex_prefix(&key[depth..], true));
+            stream.append(value);
+            stream.out().to_vec()
+        }
+        _ => {
+            let prefix_len = common_prefix_len(entries, depth);
+            if prefix_len > 0 {
+                let (first_key, _) = &entries[0];
+                let mut stream = RlpStream::new_list(2);
+                stream.append(&hex_prefix(&first_key[depth..depth + prefix_len], false));
+                append_child(&mut stream, &encode_node(entries, depth + prefix_len));
+                return stream.out().to_vec();
+            }
+            encode_branch(entries, depth)
+        }
+    }
+}
+
+fn encode_branch(entries: &[(Vec<u8>, Vec<u8>)], depth: usize) -> Vec<u8> {
+    let mut stream = RlpStream::new_list(17);
+    // A key ending here sorts first, and its value is kept in the branch itself
+    let (value, mut rest) = match entries.split_first() {
+        Some(((key, value), rest)) if key.len() == depth => (Some(value), rest),
+        _ => (None, entries),
+    };
+    for nibble in 0..16 {
+        let children_len = rest
+            .iter()
+            .take_while(|(key, _)| key[depth] == nibble)
+            .count();
+        let (children, next) = rest.split_at(children_len);
+        if children.is_empty() {
+            stream.append_empty_data();
+        } else {
+            append_child(&mut stream, &encode_node(children, depth + 1));
+        }
+        rest = next;
+    }
+    match value {
+        Some(value) => stream.append(value),
+        None => stream.append_empty_data(),
+    };
+    stream.out().to_vec()
+}
+
+/// Length of the path shared by all the sorted `entries` after the first `depth` nibbles
+fn common_prefix_len(entries: &[(Vec<u8>, Vec<u8>)], depth: usize) -> usize {
+    let (first, _) = &entries[0];
+    let (last, _) = &entries[entries.len() - 1];
+    first[depth..]
+        .iter()
+        .zip(&last[depth..])
+        .take_while(|(a, b)| a == b)
+        .count()
+}
+
+/// Nodes shorter than a hash are embedded in their parent, the rest are referenced
+/// by their hash.
+fn append_child(stream: &mut RlpStream, node: &[u8]) {
+    if node.len() < 32 {
+        stream.append_raw(node, 1);
+    } else {
+        stream.append(&keccak(node));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::EMPTY_CODE_HASH_STR;
+
+    #[test]
+    fn empty_trie_root_is_the_hash_of_an_empty_string() {
+        assert_eq!(
+            trie_root(Vec::<(Vec<u8>, Vec<u8>)>::new()),
+            empty_trie_root()
+        );
+        assert_eq!(
+            storage_root([(U256::one(), U256::zero())]),
+            empty_trie_root()
+        );
+    }
+
+    #[test]
+    fn trie_root_matches_known_roots() {
+        let entries = [
+            ("doe", "reindeer"),
+            ("dog", "puppy"),
+            ("dogglesworth", "cat"),
+        ];
+        assert_eq!(
+            trie_root(entries),
+            B256::from_str("0x8aad789dff2f538bca5d8ea56e8abe10f4c7ba3a5dea95fea4cd6e7c3a1168d3")
+                .unwrap()
+        );
+
+        let entries = [
+            ("do", "verb"),
+            ("dog", "puppy"),
+            ("doge", "coin"),
+            ("horse", "stallion"),
+        ];
+        assert_eq!(
+            trie_root(entries),
+            B256::from_str("0x5991bb8c6514148a29db676a14ac506cd2cd5775ace63c30a4fe457715e9ac84")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn trie_root_is_independent_of_the_entries_order() {
+        let entries = [("a", "1"), ("ab", "2"), ("b", "3"), ("abc", "4")];
+        let mut reversed = entries;
+        reversed.reverse();
+        assert_eq!(trie_root(entries), trie_root(reversed));
+    }
+
+    #[test]
+    fn accounts_are_encoded_as_rlp_lists() {
+        let account = AccountInfo {
+            nonce: 1,
+            balance: U256::from(0x0a),
+            code_hash: B256::from_str(EMPTY_CODE_HASH_STR).unwrap(),
+            code: None,
+        };
+
+        let encoded = encode_account(&account, empty_trie_root());
+
+        // A list of 68 bytes: two single byte integers and two 32 bytes strings
+        assert_eq!(encoded[..4], [0xf8, 0x44, 0x01, 0x0a]);
+        assert_eq!(encoded[4], 0xa0);
+        assert_eq!(encoded[5..37], *empty_trie_root().as_bytes());
+        assert_eq!(encoded[37], 0xa0);
+        assert_eq!(encoded[38..], *account.code_hash.as_bytes());
+    }
+}
@@ -19,19 +19,21 @@ use crate::{
     codegen::context::OperationCtx,
     constants::{
         gas_cost::{self, TX_ACCESS_LIST_ADDRESS_COST, TX_ACCESS_LIST_STORAGE_KEY_COST},
-        precompiles::{
-            BLAKE2F_ADDRESS, ECADD_ADDRESS, ECMUL_ADDRESS, ECPAIRING_ADDRESS, ECRECOVER_ADDRESS,
-            IDENTITY_ADDRESS, MODEXP_ADDRESS, RIPEMD_160_ADDRESS, SHA2_256_ADDRESS,
-        },
-        CALLDATA_PTR_GLOBAL, CALLDATA_SIZE_GLOBAL, GAS_COUNTER_GLOBAL,
+        CALLDATA_PTR_GLOBAL, CALLDATA_SIZE_GLOBAL, DELEGATION_DESIGNATOR_PREFIX,
+        DELEGATION_DESIGNATOR_SIZE, GAS_COUNTER_GLOBAL,
     },
     env::AccessList,
     errors::CodegenError,
     primitives::{Address, H160, U256},
-    syscall::{symbols::CONTEXT_IS_STATIC, ExitStatusCode},
+    syscall::{self, ExitStatusCode},
 };
 
-use super::{extend_memory, gas::get_remaining_gas, llvm_mlir, stack::stack_pop};
+use super::{
+    extend_memory,
+    gas::get_remaining_gas,
+    llvm_mlir,
+    stack::{get_stack_base_pointer, get_stack_pointer, stack_pop},
+};
 
 pub(crate) fn check_context_is_not_static<'c>(
     op_ctx: &'c OperationCtx,
@@ -66,24 +68,10 @@ pub(crate) fn context_is_static<'c>(
     let context = &op_ctx.mlir_context;
     let location = Location::unknown(context);
     let uint1 = IntegerType::new(context, 1);
-    let ptr_type = pointer(context, 0);
-    let static_flag_ptr = block
-        .append_operation(llvm_mlir::addressof(
-            context,
-            CONTEXT_IS_STATIC,
-            ptr_type,
-            location,
-        ))
-        .result(0)?
-        .into();
+    let static_flag =
+        syscall::mlir::context_is_static_syscall(context, op_ctx.syscall_ctx, block, location)?;
     let is_static = block
-        .append_operation(llvm::load(
-            context,
-            static_flag_ptr,
-            uint1.into(),
-            location,
-            LoadStoreOptions::default(),
-        ))
+        .append_operation(arith::trunci(static_flag, uint1.into(), location))
         .result(0)?
         .into();
 
@@ -299,6 +287,54 @@ pub(crate) fn return_empty_result(
     Ok(())
 }
 
+/// Reports the operation about to run at `pc` to the inspector, along with the
+/// remaining gas and the stack.
+pub(crate) fn inspect_step<'c>(
+    op_ctx: &'c OperationCtx,
+    block: &'c Block,
+    pc: usize,
+    opcode: u8,
+    location: Location<'c>,
+) -> Result<(), CodegenError> {
+    let context = op_ctx.mlir_context;
+    let uint8 = IntegerType::new(context, 8).into();
+    let uint64 = IntegerType::new(context, 64).into();
+
+    let pc = block
+        .append_operation(arith::constant(
+            context,
+            IntegerAttribute::new(uint64, pc as i64).into(),
+            location,
+        ))
+        .result(0)?
+        .into();
+    let opcode = block
+        .append_operation(arith::constant(
+            context,
+            IntegerAttribute::new(uint8, opcode.into()).into(),
+            location,
+        ))
+        .result(0)?
+        .into();
+    let gas_remaining = get_remaining_gas(context, block)?;
+    let stack_baseptr = get_stack_base_pointer(context, block)?;
+    let stack_ptr = get_stack_pointer(context, block)?;
+
+    syscall::mlir::step_syscall(
+        context,
+        op_ctx.syscall_ctx,
+        block,
+        pc,
+        opcode,
+        gas_remaining,
+        stack_baseptr,
+        stack_ptr,
+        location,
+    );
+
+    Ok(())
+}
+
 pub(crate) fn return_result_from_stack(
     op_ctx: &OperationCtx,
     region: &Region<'_>,
@@ -360,6 +396,7 @@ pub(crate) fn return_result_with_offset_and_size(
 
     op_ctx.write_result_syscall(block, offset, size, remaining_gas, reason, location);
 
+    op_ctx.restore_globals(block)?;
     block.append_operation(func::r#return(&[reason], location));
     Ok(())
 }
@@ -627,20 +664,23 @@ pub fn access_list_cost(access_list: &AccessList) -> u64 {
     })
 }
 
-pub fn precompiled_addresses() -> AccessList {
-    let access_list = vec![
-        (H160::from_low_u64_be(ECRECOVER_ADDRESS), Vec::new()),
-        (H160::from_low_u64_be(SHA2_256_ADDRESS), Vec::new()),
-        (H160::from_low_u64_be(RIPEMD_160_ADDRESS), Vec::new()),
-        (H160::from_low_u64_be(IDENTITY_ADDRESS), Vec::new()),
-        (H160::from_low_u64_be(MODEXP_ADDRESS), Vec::new()),
-        (H160::from_low_u64_be(ECADD_ADDRESS), Vec::new()),
-        (H160::from_low_u64_be(ECMUL_ADDRESS), Vec::new()),
-        (H160::from_low_u64_be(ECPAIRING_ADDRESS), Vec::new()),
-        (H160::from_low_u64_be(BLAKE2F_ADDRESS), Vec::new()),
-    ];
-
-    access_list
+/// Code of an account delegating its execution to `address` (EIP-7702)
+pub fn delegation_designator(address: Address) -> Bytes {
+    let mut code = Vec::with_capacity(DELEGATION_DESIGNATOR_SIZE);
+    code.extend_from_slice(&DELEGATION_DESIGNATOR_PREFIX);
+    code.extend_from_slice(address.as_bytes());
+    code.into()
+}
+
+/// Address the code delegates to, if it is a delegation designator
+pub fn delegated_address(code: &[u8]) -> Option<Address> {
+    if code.len() == DELEGATION_DESIGNATOR_SIZE && code.starts_with(&DELEGATION_DESIGNATOR_PREFIX) {
+        Some(Address::from_slice(
+            &code[DELEGATION_DESIGNATOR_PREFIX.len()..],
+        ))
+    } else {
+        None
+    }
 }
 
 pub fn allocate_gas_counter_ptr<'c>(
@@ -51,6 +51,38 @@ pub fn get_stack_pointer<'ctx>(
     Ok(stack_ptr)
 }
 
+pub fn get_stack_base_pointer<'ctx>(
+    context: &'ctx MeliorContext,
+    block: &'ctx Block,
+) -> Result<Value<'ctx, 'ctx>, CodegenError> {
+    let location = Location::unknown(context);
+    let ptr_type = pointer(context, 0);
+
+    // Get address of stack base pointer global
+    let stack_baseptr_ptr = block
+        .append_operation(llvm_mlir::addressof(
+            context,
+            STACK_BASEPTR_GLOBAL,
+            ptr_type,
+            location,
+        ))
+        .result(0)?;
+
+    // Load stack base pointer
+    let stack_baseptr = block
+        .append_operation(llvm::load(
+            context,
+            stack_baseptr_ptr.into(),
+            ptr_type,
+            location,
+            LoadStoreOptions::default(),
+        ))
+        .result(0)?
+        .into();
+
+    Ok(stack_baseptr)
+}
+
 pub fn inc_stack_pointer<'ctx>(
     context: &'ctx MeliorContext,
     block: &'ctx Block,
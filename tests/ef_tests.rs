@@ -37,7 +37,6 @@ fn get_ignored_groups() -> HashSet<String> {
         "stStackTests".into(),
         "eip5656_mcopy".into(),
         "eip6780_selfdestruct".into(),
-        "stCallCreateCallCodeTest".into(),
         "stZeroKnowledge2".into(),
         "stDelegatecallTestHomestead".into(),
         "stEIP150singleCodeGasPrices".into(),
@@ -47,7 +46,6 @@ fn get_ignored_groups() -> HashSet<String> {
         "stMemoryStressTest".into(),
         "vmTests".into(),
         "stZeroKnowledge".into(),
-        "stLogTests".into(),
         "stBugs".into(),
         "stEIP1559".into(),
         "stMemExpandingEIP150Calls".into(),
@@ -59,14 +57,12 @@ fn get_ignored_groups() -> HashSet<String> {
         "eip1153_tstore".into(),
         "stEIP3607".into(),
         "stZeroCallsTest".into(),
-        "stAttackTest".into(),
         "stExample".into(),
         "vmArithmeticTest".into(),
         "stQuadraticComplexityTest".into(),
         "stSelfBalance".into(),
         "stEIP3855-push0".into(),
         "stWalletTest".into(),
-        "vmLogTest".into(),
     ])
 }
 
@@ -4,8 +4,9 @@ use bytes::Bytes;
 use evm_mlir::{
     db::Db,
     env::{AccessList, TransactTo},
+    precompiles::Precompiles,
     result::{EVMError, ExecutionResult, ResultAndState},
-    utils::precompiled_addresses,
+    spec::SpecId,
     Env, Evm,
 };
 
@@ -29,16 +30,23 @@ fn decode_hex(bytes_in_hex: Bytes) -> Option<Bytes> {
     Some(Bytes::from(opcodes))
 }
 
-fn setup_evm(test: &Test, unit: &TestUnit) -> Evm<Db> {
+fn setup_evm(test: &Test, unit: &TestUnit, spec_id: SpecId) -> Evm<Db> {
     let to = match unit.transaction.to {
         Some(to) => TransactTo::Call(to),
         None => TransactTo::Create,
     };
     let sender = unit.transaction.sender.unwrap_or_default();
-    let gas_price = unit.transaction.gas_price.unwrap_or_default();
+    // EIP-1559 transactions have a max fee instead of a gas price
+    let gas_price = unit
+        .transaction
+        .gas_price
+        .or(unit.transaction.max_fee_per_gas)
+        .unwrap_or_default();
     let mut env = Env::default();
+    env.cfg.spec_id = spec_id;
     env.tx.transact_to = to.clone();
     env.tx.gas_price = gas_price;
+    env.tx.gas_priority_fee = unit.transaction.max_priority_fee_per_gas;
     env.tx.caller = sender;
     env.tx.gas_limit = unit.transaction.gas_limit[test.indexes.gas].as_u64();
     env.tx.value = unit.transaction.value[test.indexes.value];
@@ -60,13 +68,18 @@ fn setup_evm(test: &Test, unit: &TestUnit) -> Evm<Db> {
 
         access_list.push((access_list_item.address, storage_keys));
     }
-    access_list.push((env.block.coinbase, Vec::new())); // after Shanghai, coinbase address is added to access list
+    if spec_id.is_enabled_in(SpecId::Shanghai) {
+        access_list.push((env.block.coinbase, Vec::new())); // after Shanghai, coinbase address is added to access list
+    }
     access_list.push((env.tx.caller, Vec::new())); // after Berlin, tx.sender is added to access list
-    access_list.append(&mut precompiled_addresses()); // precompiled address are always warm
+    access_list.append(&mut Precompiles::new(spec_id).warm_addresses()); // precompiled address are always warm
 
     env.block.number = unit.env.current_number;
     env.block.coinbase = unit.env.current_coinbase;
+    env.block.gas_limit = unit.env.current_gas_limit;
     env.block.timestamp = unit.env.current_timestamp;
+    env.block.parent_beacon_block_root = unit.env.current_beacon_root;
+    env.block.parent_hash = unit.env.previous_hash;
     let excess_blob_gas = unit
         .env
         .current_excess_blob_gas
@@ -153,24 +166,59 @@ fn verify_storage(
     assert_eq!(*post_state, result_state);
 }
 
+/// Test the hash of the emitted logs is the expected one
+fn verify_logs(test: &Test, result: &ExecutionResult) -> Result<(), String> {
+    let logs_hash = result.logs_hash();
+    if logs_hash != test.logs {
+        return Err(format!(
+            "Wrong logs hash: expected {:?}, got {:?}",
+            test.logs, logs_hash
+        ));
+    }
+    Ok(())
+}
+
+/// Test the root of the resulting state is the expected one
+fn verify_state_root(test: &Test, db: &Db) -> Result<(), String> {
+    let state_root = db.state_root();
+    if state_root != test.hash {
+        return Err(format!(
+            "Wrong state root: expected {:?}, got {:?}",
+            test.hash, state_root
+        ));
+    }
+    Ok(())
+}
+
+/// Forks whose post states are checked. Enabling another one needs the groups failing in
+/// it to be fixed or ignored first.
+const CHECKED_FORKS: &[SpecId] = &[SpecId::Cancun];
+
 pub fn run_test(path: &Path, contents: String) -> datatest_stable::Result<()> {
     let test_suite: TestSuite = serde_json::from_reader(contents.as_bytes())
         .unwrap_or_else(|_| panic!("Failed to parse JSON test {}", path.display()));
 
     for (_name, unit) in test_suite.0 {
-        // NOTE: currently we only support Cancun spec
-        let Some(tests) = unit.post.get("Cancun") else {
-            continue;
-        };
-
-        for test in tests {
-            let mut evm = setup_evm(test, &unit);
-            let res = evm.transact();
-            verify_result(test, unit.out.as_ref(), &res)?;
-            // TODO: use rlp and hash to check logs
-
-            if let Ok(res) = res {
-                verify_storage(&test.post_state, res.state);
+        for (fork, tests) in &unit.post {
+            // Forks we don't know about or don't check yet are skipped
+            let Ok(spec_id) = fork.parse::<SpecId>() else {
+                continue;
+            };
+            if !CHECKED_FORKS.contains(&spec_id) {
+                continue;
+            }
+
+            for test in tests {
+                let mut evm = setup_evm(test, &unit, spec_id);
+                let res = evm.transact();
+                verify_result(test, unit.out.as_ref(), &res)?;
+
+                if let Ok(res) = res {
+                    verify_logs(test, &res.result)?;
+                    verify_storage(&test.post_state, res.state.clone());
+                    evm.db.commit(res.state);
+                }
+                verify_state_root(test, &evm.db)?;
             }
         }
     }
@@ -1,21 +1,43 @@
 use rstest::rstest;
 use sha3::{Digest, Keccak256};
-use std::{collections::HashMap, str::FromStr};
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
 
 use evm_mlir::{
+    block::{BlockExecutionError, BlockExecutor, Withdrawal},
+    cache::{CacheConfig, EvictionPolicy, ExecutorCache},
+    cache_db::CacheDB,
     constants::{
-        call_opcode,
+        call_opcode, eof,
         gas_cost::{self, exp_dynamic_cost, init_code_cost, MAX_CODE_SIZE, TX_BASE_COST},
         precompiles::BLAKE2F_ADDRESS,
         return_codes::{REVERT_RETURN_CODE, SUCCESS_RETURN_CODE},
-        EMPTY_CODE_HASH_STR,
+        CallType, BEACON_ROOTS_ADDRESS, CALL_STACK_LIMIT, GAS_PER_BLOB, HISTORY_STORAGE_ADDRESS,
+        SYSTEM_ADDRESS, VERSIONED_HASH_VERSION_KZG,
     },
-    db::{Bytecode, Database, Db},
-    env::{AccessList, TransactTo},
+    db::{AccountInfo, Bytecode, Database, DatabaseCommit, DatabaseError, DatabaseRef, Db},
+    disk_db::DiskDb,
+    env::{AccessList, BlockEnv, SignedAuthorization, TransactTo, TxEnv},
+    eof::{CodeType, EofContainer},
+    fork::{FixtureProvider, ForkDb, ForkState},
+    inspector::{CallInputs, CreateInputs, Inspector, Step, TracerEip3155},
+    overrides::AccountOverride,
     primitives::{Address, Bytes, B256, U256 as EU256},
     program::{Operation, Program},
-    syscall::{LogData, GAS_REFUND_DENOMINATOR, U256},
-    utils::{access_list_cost, compute_contract_address2},
+    result::{
+        EVMError, ExecutionResult, HaltReason, InvalidTransaction, ResultAndState, SuccessReason,
+    },
+    simulation::EstimateGasError,
+    spec::SpecId,
+    state::{Account, AccountStatus},
+    syscall::{Log, LogData, GAS_REFUND_DENOMINATOR, U256},
+    utils::{
+        access_list_cost, compute_contract_address, compute_contract_address2,
+        delegation_designator,
+    },
     Env, Evm,
 };
 
@@ -88,8 +110,12 @@ fn run_program_assert_gas_exact_with_db(mut env: Env, db: Db, needed_gas: u64) {
     assert!(result.is_halt());
 }
 
-fn run_program_assert_gas_exact(operations: Vec<Operation>, env: Env, needed_gas: u64) {
+fn run_program_assert_gas_exact(operations: Vec<Operation>, mut env: Env, needed_gas: u64) {
     let address = env.tx.get_address();
+    // Accounts with code can't send transactions (EIP-3607)
+    if env.tx.caller == address {
+        env.tx.caller = Address::from_low_u64_be(79);
+    }
 
     //Ok run
     let program = Program::from(operations.clone());
@@ -619,7 +645,10 @@ fn log1() {
     let mut env = Env::default();
     env.tx.gas_limit = 999_999;
 
-    let (address, bytecode) = (Address::zero(), Bytecode::from(program.to_bytecode()));
+    let (address, bytecode) = (
+        Address::from_low_u64_be(40),
+        Bytecode::from(program.to_bytecode()),
+    );
     env.tx.transact_to = TransactTo::Call(address);
     let db = Db::new().with_contract(address, bytecode);
     let mut evm = Evm::new(env, db);
@@ -635,6 +664,68 @@ fn log1() {
     assert_eq!(logs, expected_logs);
 }
 
+#[rstest]
+#[case(vec![Operation::Stop], true)]
+#[case(vec![Operation::Push0, Operation::Push0, Operation::Revert], false)]
+fn logs_of_nested_calls_are_kept_if_they_succeed(
+    #[case] callee_end: Vec<Operation>,
+    #[case] is_kept: bool,
+) {
+    let callee_address = Address::from_low_u64_be(8080);
+    let callee_ops = [
+        vec![
+            Operation::Push((1_u8, BigUint::from(1_u8))), //Topic
+            Operation::Push0,                             //Size
+            Operation::Push0,                             //Offset
+            Operation::Log(1),
+        ],
+        callee_end,
+    ]
+    .concat();
+    let caller_ops = vec![
+        Operation::Push0, //Ret size
+        Operation::Push0, //Ret offset
+        Operation::Push0, //Args size
+        Operation::Push0, //Args offset
+        Operation::Push0, //Value
+        Operation::Push((20_u8, BigUint::from_bytes_be(callee_address.as_bytes()))), //Address
+        Operation::Push((3_u8, BigUint::from(100_000_u32))), //Gas
+        Operation::Call,
+        Operation::Pop,
+        Operation::Push0, //Size
+        Operation::Push0, //Offset
+        Operation::Log(0),
+    ];
+    let (env, db) = default_env_and_db_setup(caller_ops);
+    let caller_address = env.tx.get_address();
+    let db = db.with_contract(
+        callee_address,
+        Bytecode::from(Program::from(callee_ops).to_bytecode()),
+    );
+    let mut evm = Evm::new(env, db);
+
+    let result = evm.transact_commit().unwrap();
+
+    assert!(result.is_success());
+    let callee_log = Log {
+        address: callee_address,
+        data: LogData {
+            data: vec![],
+            topics: vec![U256 { lo: 1, hi: 0 }],
+        },
+    };
+    let caller_log = Log {
+        address: caller_address,
+        data: LogData::default(),
+    };
+    let expected_logs = if is_kept {
+        vec![callee_log, caller_log]
+    } else {
+        vec![caller_log]
+    };
+    assert_eq!(result.into_logs(), expected_logs);
+}
+
 #[test]
 fn log2() {
     let data: [u8; 32] = [0xff; 32];
@@ -862,12 +953,9 @@ fn callvalue_happy_path() {
     let callvalue: u32 = 1500;
     let mut operations = vec![Operation::Callvalue];
     append_return_result_operations(&mut operations);
-    let mut env = Env::default();
-    env.tx.gas_limit = 999_999;
+    let (mut env, mut db) = default_env_and_db_setup(operations);
     env.tx.value = EU256::from(callvalue);
-    let program = Program::from(operations);
-    let bytecode = Bytecode::from(program.to_bytecode());
-    let db = Db::new().with_contract(Address::zero(), bytecode);
+    db.set_balance(env.tx.caller, env.tx.value);
     let expected_result = BigUint::from(callvalue);
     run_program_assert_num_result(env, db, expected_result);
 }
@@ -952,8 +1040,11 @@ fn basefee() {
     let basefee = 10_u8;
     let mut operations = vec![Operation::Basefee];
     append_return_result_operations(&mut operations);
-    let (mut env, db) = default_env_and_db_setup(operations);
+    let (mut env, mut db) = default_env_and_db_setup(operations);
     env.block.basefee = EU256::from(basefee);
+    // The gas price can't be lower than the base fee
+    env.tx.gas_price = env.block.basefee;
+    db.set_balance(env.tx.caller, env.tx.gas_price * env.tx.gas_limit);
     let expected_result = BigUint::from(basefee);
     run_program_assert_num_result(env, db, expected_result);
 }
@@ -1053,8 +1144,9 @@ fn gasprice_happy_path() {
     let gas_price: u32 = 33192;
     let mut operations = vec![Operation::Gasprice];
     append_return_result_operations(&mut operations);
-    let (mut env, db) = default_env_and_db_setup(operations);
+    let (mut env, mut db) = default_env_and_db_setup(operations);
     env.tx.gas_price = EU256::from(gas_price);
+    db.set_balance(env.tx.caller, env.tx.gas_price * env.tx.gas_limit);
     let expected_result = BigUint::from(gas_price);
     run_program_assert_num_result(env, db, expected_result);
 }
@@ -1259,7 +1351,6 @@ fn balance_with_invalid_address() {
         Address::from_slice(&a.to_bytes_be()[0..20]),
         Bytecode::from(program.to_bytecode()),
     );
-    env.tx.caller = address;
     env.tx.transact_to = TransactTo::Call(address);
     let mut db = Db::new().with_contract(address, bytecode);
 
@@ -1307,7 +1398,6 @@ fn balance_with_existing_account() {
         Address::from_str("0x9bbfed6889322e016e0a02ee459d306fc19545d8").unwrap(),
         Bytecode::from(program.to_bytecode()),
     );
-    env.tx.caller = address;
     env.tx.transact_to = TransactTo::Call(address);
     let mut db = Db::new().with_contract(address, bytecode);
 
@@ -1979,7 +2069,6 @@ fn call_simple_callee_call(#[case] call_type: Operation) {
     let mut env = Env::default();
     env.tx.gas_limit = 999_999;
     env.tx.transact_to = TransactTo::Call(caller_address);
-    env.tx.caller = caller_address;
     let db = db.with_contract(caller_address, bytecode);
 
     let mut evm = Evm::new(env, db);
@@ -2070,7 +2159,6 @@ fn call_addition_with_value_transfer(#[case] call_type: Operation) {
     let mut env = Env::default();
     env.tx.gas_limit = 999_999;
     env.tx.transact_to = TransactTo::Call(caller_address);
-    env.tx.caller = caller_address;
     let caller_balance = 100_u8;
     let mut db = db.with_contract(caller_address, bytecode);
     db.set_account(caller_address, 0, caller_balance.into(), Default::default());
@@ -2082,8 +2170,14 @@ fn call_addition_with_value_transfer(#[case] call_type: Operation) {
     let res_bytes: &[u8] = result.output().unwrap();
 
     let expected_contract_data_result = a + b;
-    let expected_caller_balance_result = (caller_balance - value).into();
-    let expected_callee_balance_result = (callee_balance + value).into();
+    // CALLCODE runs the callee's code in the caller's account, so the value stays there
+    let (expected_caller_balance_result, expected_callee_balance_result) = match call_type {
+        Operation::CallCode => (caller_balance.into(), callee_balance.into()),
+        _ => (
+            (caller_balance - value).into(),
+            (callee_balance + value).into(),
+        ),
+    };
     let expected_contract_status_result = SUCCESS_RETURN_CODE.into();
 
     let contract_data_result = BigUint::from_bytes_be(&res_bytes[..32]);
@@ -2107,6 +2201,92 @@ fn call_addition_with_value_transfer(#[case] call_type: Operation) {
     assert_eq!(final_callee_balance, expected_callee_balance_result);
 }
 
+#[rstest]
+#[case(Some(vec![Operation::Stop]), 10)]
+#[case(Some(vec![Operation::Push0, Operation::Push0, Operation::Revert]), 0)]
+#[case(None, 10)]
+fn transaction_value_is_sent_to_the_callee_if_it_succeeds(
+    #[case] callee_ops: Option<Vec<Operation>>,
+    #[case] received: u8,
+) {
+    let (caller_address, callee_address) =
+        (Address::from_low_u64_be(79), Address::from_low_u64_be(8080));
+    let caller_balance = 100_u8;
+    let mut db = Db::new();
+    // Without code the callee is an account that doesn't exist yet
+    if let Some(callee_ops) = callee_ops {
+        db = db.with_contract(
+            callee_address,
+            Bytecode::from(Program::from(callee_ops).to_bytecode()),
+        );
+    }
+    db.set_balance(caller_address, caller_balance.into());
+    let mut env = Env::default();
+    env.tx.gas_limit = 999_999;
+    env.tx.caller = caller_address;
+    env.tx.transact_to = TransactTo::Call(callee_address);
+    env.tx.value = EU256::from(10);
+
+    let mut evm = Evm::new(env, db);
+    evm.transact_commit().unwrap();
+
+    let final_caller_balance = evm.db.basic(caller_address).unwrap().unwrap().balance;
+    let final_callee_balance = evm
+        .db
+        .basic(callee_address)
+        .unwrap()
+        .unwrap_or_default()
+        .balance;
+    assert_eq!(final_caller_balance, (caller_balance - received).into());
+    assert_eq!(final_callee_balance, received.into());
+}
+
+#[test]
+fn call_with_value_to_reverting_callee_keeps_the_balances() {
+    let callee_balance = 0_u8;
+    let callee_ops = vec![Operation::Push0, Operation::Push0, Operation::Revert];
+    let callee_address = Address::from_low_u64_be(8080);
+    let mut db = Db::new().with_contract(
+        callee_address,
+        Bytecode::from(Program::from(callee_ops).to_bytecode()),
+    );
+    db.set_account(callee_address, 0, callee_balance.into(), Default::default());
+
+    let value = 1_u8;
+    let caller_address = Address::from_low_u64_be(4040);
+    let mut caller_ops = vec![
+        Operation::Push0,                              //Ret size
+        Operation::Push0,                              //Ret offset
+        Operation::Push0,                              //Args size
+        Operation::Push0,                              //Args offset
+        Operation::Push((1_u8, BigUint::from(value))), //Value
+        Operation::Push((16_u8, BigUint::from_bytes_be(callee_address.as_bytes()))), //Address
+        Operation::Push((1_u8, BigUint::from(100_u8))), //Gas
+        Operation::Call,
+    ];
+    append_return_result_operations(&mut caller_ops);
+    let caller_balance = 100_u8;
+    let mut db = db.with_contract(
+        caller_address,
+        Bytecode::from(Program::from(caller_ops).to_bytecode()),
+    );
+    db.set_account(caller_address, 0, caller_balance.into(), Default::default());
+    let mut env = Env::default();
+    env.tx.gas_limit = 999_999;
+    env.tx.transact_to = TransactTo::Call(caller_address);
+
+    let mut evm = Evm::new(env, db);
+    let result = evm.transact_commit().unwrap();
+
+    assert!(result.is_success());
+    let call_result = BigUint::from_bytes_be(result.output().unwrap_or(&Bytes::new()));
+    assert_eq!(call_result, REVERT_RETURN_CODE.into());
+    let final_caller_balance = evm.db.basic(caller_address).unwrap().unwrap().balance;
+    let final_callee_balance = evm.db.basic(callee_address).unwrap().unwrap().balance;
+    assert_eq!(final_caller_balance, caller_balance.into());
+    assert_eq!(final_callee_balance, callee_balance.into());
+}
+
 #[rstest]
 #[case(Operation::Call)]
 #[case(Operation::CallCode)]
@@ -2160,7 +2340,6 @@ fn call_without_enough_balance(#[case] call_type: Operation) {
     let mut env = Env::default();
     env.tx.gas_limit = 999_999;
     env.tx.transact_to = TransactTo::Call(caller_address);
-    env.tx.caller = caller_address;
     let mut db = db.with_contract(caller_address, bytecode);
     db.set_account(caller_address, 0, caller_balance.into(), Default::default());
 
@@ -2292,7 +2471,6 @@ fn call_gas_check_with_value_zero_args_return_and_non_empty_callee(#[case] call_
     let bytecode = Bytecode::from(program.to_bytecode());
     let mut env = Env::default();
     env.tx.transact_to = TransactTo::Call(caller_address);
-    env.tx.caller = caller_address;
     let mut db = db.with_contract(caller_address, bytecode);
     db.set_account(caller_address, 0, caller_balance.into(), Default::default());
 
@@ -2392,7 +2570,6 @@ fn call_return_with_offset_and_size(
     let bytecode = Bytecode::from(program.to_bytecode());
     let mut env = Env::default();
     env.tx.transact_to = TransactTo::Call(caller_address);
-    env.tx.caller = caller_address;
     let db = db.with_contract(caller_address, bytecode);
 
     run_program_assert_bytes_result(env, db, expected_result);
@@ -2459,9 +2636,13 @@ fn call_gas_check_with_value_and_empty_account(#[case] call_type: Operation) {
     .concat();
 
     //address_access_cost + positive_value_cost + value_to_empty_account_cost
-    let caller_call_cost = gas_cost::CALL_WARM as u64
-        + call_opcode::NOT_ZERO_VALUE_COST
-        + call_opcode::EMPTY_CALLEE_COST;
+    //CALLCODE keeps the value in the caller, so it doesn't pay for the empty account
+    let empty_callee_cost = match call_type {
+        Operation::Call => call_opcode::EMPTY_CALLEE_COST,
+        _ => 0,
+    };
+    let caller_call_cost =
+        gas_cost::CALL_WARM as u64 + call_opcode::NOT_ZERO_VALUE_COST + empty_callee_cost;
     let needed_gas = gas_cost::PUSHN * 7 + caller_call_cost as i64;
 
     let caller_balance: u8 = 5;
@@ -2469,7 +2650,6 @@ fn call_gas_check_with_value_and_empty_account(#[case] call_type: Operation) {
     let bytecode = Bytecode::from(program.to_bytecode());
     let mut env = Env::default();
     env.tx.transact_to = TransactTo::Call(caller_address);
-    env.tx.caller = caller_address;
     let mut db = db.with_contract(caller_address, bytecode);
     db.set_account(caller_address, 0, caller_balance.into(), Default::default());
 
@@ -2542,6 +2722,7 @@ fn call_callee_returns_new_value(#[case] call_type: Operation) {
     let mut env = Env::default();
     let mut db = db.with_contract(caller_address, caller_bytecode);
     db.set_account(caller_address, 0, caller_balance.into(), Default::default());
+    db.set_balance(origin, origin_value.into());
     env.tx.transact_to = TransactTo::Call(caller_address);
     env.tx.caller = origin;
     env.tx.value = origin_value.into();
@@ -2672,7 +2853,8 @@ fn call_callee_storage_modified() {
     let program = Program::from(caller_ops);
     let caller_bytecode = Bytecode::from(program.to_bytecode());
     let mut env = Env::default();
-    let db = db.with_contract(caller_address, caller_bytecode);
+    let mut db = db.with_contract(caller_address, caller_bytecode);
+    db.set_balance(origin, origin_value.into());
     env.tx.transact_to = TransactTo::Call(caller_address);
     env.tx.caller = origin;
     env.tx.value = origin_value.into();
@@ -2808,7 +2990,6 @@ fn returndatasize_happy_path() {
     let bytecode = Bytecode::from(program.to_bytecode());
     let mut env = Env::default();
     env.tx.transact_to = TransactTo::Call(caller_address);
-    env.tx.caller = caller_address;
     let db = db.with_contract(caller_address, bytecode);
 
     let expected_result = 32_u8.into();
@@ -2827,7 +3008,6 @@ fn returndatasize_no_return_data() {
     let bytecode = Bytecode::from(program.to_bytecode());
     let mut env = Env::default();
     env.tx.transact_to = TransactTo::Call(caller_address);
-    env.tx.caller = caller_address;
     let db = Db::default().with_contract(caller_address, bytecode);
 
     let expected_result = 0_u8.into();
@@ -2895,7 +3075,6 @@ fn returndatacopy_happy_path() {
     let bytecode = Bytecode::from(program.to_bytecode());
     let mut env = Env::default();
     env.tx.transact_to = TransactTo::Call(caller_address);
-    env.tx.caller = caller_address;
     let db = db.with_contract(caller_address, bytecode);
 
     let expected_result = return_value.into();
@@ -2933,7 +3112,6 @@ fn returndatacopy_no_return_data() {
     let bytecode = Bytecode::from(program.to_bytecode());
     let mut env = Env::default();
     env.tx.transact_to = TransactTo::Call(caller_address);
-    env.tx.caller = caller_address;
     let db = Db::default().with_contract(caller_address, bytecode);
 
     // There was no return data, so memory stays the same
@@ -3008,7 +3186,6 @@ fn returndatacopy_size_smaller_than_data() {
     let bytecode = Bytecode::from(program.to_bytecode());
     let mut env = Env::default();
     env.tx.transact_to = TransactTo::Call(caller_address);
-    env.tx.caller = caller_address;
     let db = db.with_contract(caller_address, bytecode);
 
     let expected_result = &[
@@ -3068,7 +3245,6 @@ fn returndatacopy_with_offset_and_size_bigger_than_data() {
     let bytecode = Bytecode::from(program.to_bytecode());
     let mut env = Env::default();
     env.tx.transact_to = TransactTo::Call(caller_address);
-    env.tx.caller = caller_address;
     let db = db.with_contract(caller_address, bytecode);
 
     run_program_assert_halt(env, db);
@@ -3135,7 +3311,6 @@ fn returndatacopy_gas_check() {
     let bytecode = Bytecode::from(program.to_bytecode());
     let mut env = Env::default();
     env.tx.transact_to = TransactTo::Call(caller_address);
-    env.tx.caller = caller_address;
     let db = db.with_contract(caller_address, bytecode);
 
     let callee_gas_cost = gas_cost::PUSHN * 2
@@ -3190,6 +3365,7 @@ fn create_happy_path() {
         Default::default(),
     );
     env.tx.value = EU256::from(value);
+    db.set_balance(env.tx.caller, env.tx.value);
     let mut evm = Evm::new(env, db);
     let result = evm.transact_commit().unwrap();
     assert!(result.is_success());
@@ -3203,10 +3379,11 @@ fn create_happy_path() {
     let new_account_code = evm.db.code_by_hash(new_account.code_hash).unwrap();
     assert_ne!(new_account_code, Bytecode::default());
 
-    // Check that the sender account is updated
+    // Check that the sender account is updated, the value of the transaction making up
+    // for the one sent
     let sender_account = evm.db.basic(sender_addr).unwrap().unwrap();
     assert_eq!(sender_account.nonce, sender_nonce + 1);
-    assert_eq!(sender_account.balance, sender_balance - value);
+    assert_eq!(sender_account.balance, sender_balance);
 }
 
 #[test]
@@ -3241,14 +3418,13 @@ fn create_with_balance_underflow() {
         Operation::Create,
     ];
     append_return_result_operations(&mut operations);
-    let (mut env, mut db) = default_env_and_db_setup(operations);
+    let (env, mut db) = default_env_and_db_setup(operations);
     db.set_account(
         sender_addr,
         sender_nonce,
         sender_balance,
         Default::default(),
     );
-    env.tx.value = EU256::from(value);
     let mut evm = Evm::new(env, db);
     let result = evm.transact_commit().unwrap();
 
@@ -3267,10 +3443,10 @@ fn create_with_invalid_initialization_code() {
     let value: u8 = 0;
     let offset: u8 = 19;
     let size: u8 = 13;
+    let sender_addr = Address::from_low_u64_be(40);
 
     // Code that halts
     let initialization_code = hex::decode("63ffffffff526004601cf3").unwrap();
-    let initialization_code_hash = B256::from_str(EMPTY_CODE_HASH_STR).unwrap();
 
     let mut operations = vec![
         // Store initialization code in memory
@@ -3284,17 +3460,118 @@ fn create_with_invalid_initialization_code() {
         Operation::Create,
     ];
     append_return_result_operations(&mut operations);
-    let (mut env, db) = default_env_and_db_setup(operations);
+    let (mut env, mut db) = default_env_and_db_setup(operations);
     env.tx.value = EU256::from(value);
+    db.set_balance(env.tx.caller, env.tx.value);
+    let sender_nonce = db.basic(sender_addr).unwrap().unwrap().nonce;
     let mut evm = Evm::new(env, db);
     let result = evm.transact_commit().unwrap();
 
-    // Check that contract is created in the returned address with empty bytecode
-    let returned_addr = Address::from_slice(&result.output().unwrap()[12..]);
-    let new_account = evm.db.basic(returned_addr).unwrap().unwrap();
-    assert_eq!(new_account.balance, EU256::from(value));
-    assert_eq!(new_account.nonce, 1);
-    assert_eq!(new_account.code_hash, initialization_code_hash);
+    // Check that the create failed without leaving a contract behind
+    assert!(result.is_success());
+    assert_eq!(result.output().unwrap().to_vec(), [0_u8; 32].to_vec());
+    let new_address = compute_contract_address(sender_addr, sender_nonce);
+    assert_eq!(evm.db.basic(new_address).unwrap(), None);
+}
+
+#[test]
+fn create_with_reverting_initialization_code_keeps_no_state() {
+    let value: u8 = 10;
+    let sender_nonce = 1;
+    let sender_balance = EU256::from(25);
+    let sender_addr = Address::from_low_u64_be(40);
+
+    // Code that stores 1 at slot 0 and reverts with 0xff..ff as output
+    let initialization_code = hex::decode("600160005560001960005260206000fd").unwrap();
+    let size = initialization_code.len() as u8;
+    let offset = 32 - size;
+
+    let mut operations = vec![
+        // Store initialization code in memory
+        Operation::Push((size, BigUint::from_bytes_be(&initialization_code))),
+        Operation::Push((1, BigUint::ZERO)),
+        Operation::Mstore,
+        // Create
+        Operation::Push((1, BigUint::from(size))),
+        Operation::Push((1, BigUint::from(offset))),
+        Operation::Push((1, BigUint::from(value))),
+        Operation::Create,
+    ];
+    append_return_result_operations(&mut operations);
+    let (env, mut db) = default_env_and_db_setup(operations);
+    db.set_account(
+        sender_addr,
+        sender_nonce,
+        sender_balance,
+        Default::default(),
+    );
+    let mut evm = Evm::new(env, db);
+    let result = evm.transact_commit().unwrap();
+
+    // The create pushes a zero address
+    assert!(result.is_success());
+    assert_eq!(result.output().unwrap().to_vec(), [0_u8; 32].to_vec());
+    // Only the nonce of the sender is kept, the value isn't sent
+    let sender_account = evm.db.basic(sender_addr).unwrap().unwrap();
+    assert_eq!(sender_account.nonce, sender_nonce + 1);
+    assert_eq!(sender_account.balance, sender_balance);
+    let new_address = compute_contract_address(sender_addr, sender_nonce);
+    assert_eq!(evm.db.basic(new_address).unwrap(), None);
+}
+
+#[rstest]
+#[case(SpecId::Tangerine, true)]
+#[case(SpecId::SpuriousDragon, false)]
+fn create_deploys_code_over_the_size_limit_before_spurious_dragon(
+    #[case] spec_id: SpecId,
+    #[case] is_deployed: bool,
+) {
+    let sender_addr = Address::from_low_u64_be(40);
+    // Code that returns MAX_CODE_SIZE + 1 zeros
+    let initialization_code = hex::decode("6160016000f3").unwrap();
+    let size = initialization_code.len() as u8;
+
+    // PUSH0 isn't activated in these forks
+    let operations = vec![
+        // Store initialization code in memory
+        Operation::Push((size, BigUint::from_bytes_be(&initialization_code))),
+        Operation::Push((1, BigUint::ZERO)),
+        Operation::Mstore,
+        // Create
+        Operation::Push((1, BigUint::from(size))),
+        Operation::Push((1, BigUint::from(32 - size))),
+        Operation::Push((1, BigUint::ZERO)),
+        Operation::Create,
+        // Return the address
+        Operation::Push((1, BigUint::ZERO)),
+        Operation::Mstore,
+        Operation::Push((1, BigUint::from(32_u8))),
+        Operation::Push((1, BigUint::ZERO)),
+        Operation::Return,
+    ];
+    let (mut env, mut db) = default_env_and_db_setup(operations);
+    env.cfg.spec_id = spec_id;
+    env.tx.gas_limit = 6_000_000;
+    let sender_nonce = db.basic(sender_addr).unwrap().unwrap().nonce;
+    let new_address = compute_contract_address(sender_addr, sender_nonce);
+    let mut evm = Evm::new(env, db);
+
+    let result = evm.transact_commit().unwrap();
+
+    assert!(result.is_success());
+    let returned_address = Address::from_slice(&result.output().unwrap()[12..]);
+    let deployed_code = evm
+        .db
+        .basic(new_address)
+        .unwrap()
+        .and_then(|info| info.code);
+    if is_deployed {
+        assert_eq!(returned_address, new_address);
+        assert_eq!(deployed_code.unwrap().len(), MAX_CODE_SIZE + 1);
+    } else {
+        assert_eq!(returned_address, Address::zero());
+        assert_eq!(deployed_code, None);
+    }
 }
 
 #[test]
@@ -3329,8 +3606,9 @@ fn create_gas_cost() {
         Operation::Push((1, BigUint::from(value))),
         Operation::Create,
     ];
-    let (mut env, db) = default_env_and_db_setup(operations);
+    let (mut env, mut db) = default_env_and_db_setup(operations);
     env.tx.value = EU256::from(value);
+    db.set_balance(env.tx.caller, env.tx.value);
 
     run_program_assert_gas_exact_with_db(env, db, needed_gas as _);
 }
@@ -3376,6 +3654,7 @@ fn create2_happy_path() {
         Default::default(),
     );
     env.tx.value = EU256::from(value);
+    db.set_balance(env.tx.caller, env.tx.value);
     let mut evm = Evm::new(env, db);
     let result = evm.transact_commit().unwrap();
     assert!(result.is_success());
@@ -3390,10 +3669,11 @@ fn create2_happy_path() {
     assert_eq!(new_account.nonce, 1);
     assert_eq!(new_account.code_hash, initialization_code_hash);
 
-    // Check that the sender account is updated
+    // Check that the sender account is updated, the value of the transaction making up
+    // for the one sent
     let sender_account = evm.db.basic(sender_addr).unwrap().unwrap();
     assert_eq!(sender_account.nonce, sender_nonce + 1);
-    assert_eq!(sender_account.balance, sender_balance - value);
+    assert_eq!(sender_account.balance, sender_balance);
 }
 
 #[test]
@@ -3438,7 +3718,6 @@ fn staticcall_state_modifying_revert_with_callee_ops(callee_ops: Vec<Operation>)
     let bytecode = Bytecode::from(program.to_bytecode());
     let mut env = Env::default();
     env.tx.transact_to = TransactTo::Call(caller_address);
-    env.tx.caller = caller_address;
     let caller_balance = 100_u8;
     let mut db = db.with_contract(caller_address, bytecode);
     db.set_account(caller_address, 0, caller_balance.into(), Default::default());
@@ -3467,6 +3746,82 @@ fn staticcall_with_sstore_reverts() {
     staticcall_state_modifying_revert_with_callee_ops(operations);
 }
 
+#[test]
+fn call_reverted_by_callee_discards_its_storage_writes() {
+    let key = 1_u8;
+    let callee_ops = vec![
+        Operation::Push((1_u8, 42_u8.into())),
+        Operation::Push((1_u8, key.into())),
+        Operation::Sstore,
+        Operation::Push0,
+        Operation::Push0,
+        Operation::Revert,
+    ];
+    let callee_address = Address::from_low_u64_be(8080);
+    let bytecode = Bytecode::from(Program::from(callee_ops).to_bytecode());
+    let mut db = Db::new().with_contract(callee_address, bytecode);
+    db.write_storage(callee_address, key.into(), 10_u8.into());
+
+    let mut caller_ops = vec![
+        Operation::Push0, //Ret size
+        Operation::Push0, //Ret offset
+        Operation::Push0, //Args size
+        Operation::Push0, //Args offset
+        Operation::Push0, //Value
+        Operation::Push((16_u8, BigUint::from_bytes_be(callee_address.as_bytes()))), //Address
+        Operation::Push((3_u8, BigUint::from(100_000_u32))), //Gas
+        Operation::Call,
+    ];
+    append_return_result_operations(&mut caller_ops);
+    let caller_address = Address::from_low_u64_be(4040);
+    let bytecode = Bytecode::from(Program::from(caller_ops).to_bytecode());
+    let db = db.with_contract(caller_address, bytecode);
+    let mut env = Env::default();
+    env.tx.transact_to = TransactTo::Call(caller_address);
+
+    let mut evm = Evm::new(env, db);
+    let result = evm.transact_commit().unwrap();
+
+    assert!(result.is_success());
+    let call_result = BigUint::from_bytes_be(result.output().unwrap());
+    assert_eq!(call_result, REVERT_RETURN_CODE.into());
+    assert_eq!(
+        evm.db.read_storage(callee_address, key.into()),
+        10_u8.into()
+    );
+}
+
+#[test]
+fn recursive_calls_stop_at_the_call_depth_limit() {
+    // Calls itself and returns the value returned by the call plus one
+    let operations = vec![
+        Operation::Push((1_u8, 32_u8.into())), //Ret size
+        Operation::Push0,                      //Ret offset
+        Operation::Push0,                      //Args size
+        Operation::Push0,                      //Args offset
+        Operation::Push0,                      //Value
+        Operation::Address,
+        Operation::Gas,
+        Operation::Call,
+        Operation::Pop,
+        Operation::Push0,
+        Operation::Mload,
+        Operation::Push((1_u8, 1_u8.into())),
+        Operation::Add,
+        Operation::Push0,
+        Operation::Mstore,
+        Operation::Push((1_u8, 32_u8.into())),
+        Operation::Push0,
+        Operation::Return,
+    ];
+    let (mut env, db) = default_env_and_db_setup(operations);
+    // Each frame keeps 1/64 of the gas, so the innermost one needs most of it
+    env.tx.gas_limit = 1 << 40;
+
+    // The innermost frame, at depth 1024, fails to call and returns 1
+    run_program_assert_num_result(env, db, (CALL_STACK_LIMIT + 1).into());
+}
+
 #[test]
 fn staticcall_with_call_with_value_not_zero_reverts() {
     let operations = vec![
@@ -3586,7 +3941,8 @@ fn staticcall_callee_returns_value() {
     let program = Program::from(caller_ops);
     let caller_bytecode = Bytecode::from(program.to_bytecode());
     let mut env = Env::default();
-    let db = db.with_contract(caller_address, caller_bytecode);
+    let mut db = db.with_contract(caller_address, caller_bytecode);
+    db.set_balance(origin, origin_value.into());
     env.tx.transact_to = TransactTo::Call(caller_address);
     env.tx.caller = origin;
     env.tx.value = origin_value.into();
@@ -3746,21 +4102,66 @@ fn selfdestruct_on_already_existing_account() {
 }
 
 #[test]
-fn selfdestruct_on_newly_created_account() {
-    let origin = Address::from_low_u64_be(123);
-    let caller_address = Address::from_low_u64_be(4040);
-    let caller_init_balance = 50_u8;
-    let receiver_acc_address = Address::from_low_u64_be(3030);
+fn selfdestruct_removes_the_account_before_cancun() {
+    let receiver_address = Address::from_low_u64_be(123);
+    let balance = EU256::from(50);
 
-    // Call args
-    let gas = 100_000_u32;
-    let value = 0_u8;
-    let args_offset = 0_u8;
-    let args_size = 0_u8;
-    let ret_offset = 0_u8;
-    let ret_size = 0_u8;
+    let operations = vec![
+        Operation::Push((20, BigUint::from_bytes_be(receiver_address.as_bytes()))),
+        Operation::SelfDestruct,
+    ];
+    let (mut env, mut db) = default_env_and_db_setup(operations);
+    env.cfg.spec_id = SpecId::Shanghai;
+    let contract_address = env.tx.get_address();
+    db.set_account(
+        contract_address,
+        1,
+        balance,
+        HashMap::from([(EU256::one(), EU256::one())]),
+    );
 
-    // Create args
+    let mut evm = Evm::new(env, db);
+    let result = evm.transact_commit().unwrap();
+    assert!(result.is_success());
+
+    assert_eq!(evm.db.basic(contract_address).unwrap(), None);
+    assert_eq!(
+        evm.db.read_storage(contract_address, EU256::one()),
+        EU256::zero()
+    );
+    let receiver = evm.db.basic(receiver_address).unwrap().unwrap();
+    assert_eq!(receiver.balance, balance);
+}
+
+#[test]
+fn touched_empty_accounts_are_removed() {
+    let empty_address = Address::from_low_u64_be(1000);
+    let (env, mut db) = default_env_and_db_setup(call_with_gas(empty_address, 0));
+    db.set_account(empty_address, 0, EU256::zero(), Default::default());
+
+    let mut evm = Evm::new(env, db);
+    let result = evm.transact_commit().unwrap();
+    assert!(result.is_success());
+
+    assert_eq!(evm.db.basic(empty_address).unwrap(), None);
+}
+
+#[test]
+fn selfdestruct_on_newly_created_account() {
+    let origin = Address::from_low_u64_be(123);
+    let caller_address = Address::from_low_u64_be(4040);
+    let caller_init_balance = 50_u8;
+    let receiver_acc_address = Address::from_low_u64_be(3030);
+
+    // Call args
+    let gas = 100_000_u32;
+    let value = 0_u8;
+    let args_offset = 0_u8;
+    let args_size = 0_u8;
+    let ret_offset = 0_u8;
+    let ret_size = 0_u8;
+
+    // Create args
     let salt: u8 = 52;
     let init_code_size = 13_u8;
     let init_code_offset = 32_u8 - init_code_size;
@@ -3803,6 +4204,7 @@ fn selfdestruct_on_newly_created_account() {
     env.tx.caller = origin;
     env.tx.value = transfer_value.into();
     let mut db = Db::new().with_contract(caller_address, bytecode);
+    db.set_balance(origin, transfer_value.into());
     db.set_account(
         caller_address,
         1,
@@ -3816,7 +4218,8 @@ fn selfdestruct_on_newly_created_account() {
     let expected_return_code = SUCCESS_RETURN_CODE.into();
     assert_eq!(call_return_code, expected_return_code);
 
-    let expected_caller_balance = (caller_init_balance - transfer_value).into();
+    // The value of the transaction makes up for the one sent to the new contract
+    let expected_caller_balance = caller_init_balance.into();
     let expected_receiver_balance = transfer_value.into();
 
     assert!(evm.db.basic(caller_address).unwrap().is_some());
@@ -4143,7 +4546,6 @@ fn coinbase_address_is_warm() {
     let bytecode = Bytecode::from(program.to_bytecode());
     let mut env = Env::default();
     env.tx.transact_to = TransactTo::Call(caller_address);
-    env.tx.caller = caller_address;
     let access_list = vec![(coinbase_addr, Vec::new())];
     env.tx.access_list = access_list;
     env.block.coinbase = coinbase_addr;
@@ -4220,7 +4622,6 @@ fn addresses_in_access_list_are_warm() {
     let bytecode = Bytecode::from(program.to_bytecode());
     let mut env = Env::default();
     env.tx.transact_to = TransactTo::Call(caller_address);
-    env.tx.caller = caller_address;
     env.tx.access_list = access_list;
     let mut db = Db::new().with_contract(caller_address, bytecode);
     db.set_account(caller_address, 0, caller_balance.into(), Default::default());
@@ -4488,3 +4889,1707 @@ fn transact_to_create_max_init_code_len() {
     let result = evm.transact_commit();
     assert!(result.is_err());
 }
+
+fn static_call_twice_env_and_db_setup() -> (Env, Db) {
+    let mut callee_ops = vec![Operation::Push((1_u8, 7_u8.into()))];
+    append_return_result_operations(&mut callee_ops);
+    let program = Program::from(callee_ops);
+    let (callee_address, callee_bytecode) = (
+        Address::from_low_u64_be(8080),
+        Bytecode::from(program.to_bytecode()),
+    );
+
+    let static_call = vec![
+        Operation::Push((1_u8, 32_u8.into())), //Ret size
+        Operation::Push0,                      //Ret offset
+        Operation::Push0,                      //Args size
+        Operation::Push0,                      //Args offset
+        Operation::Push((20_u8, BigUint::from_bytes_be(callee_address.as_bytes()))), //Address
+        Operation::Push((32_u8, 100_000_u32.into())), //Gas
+        Operation::StaticCall,
+    ];
+    let mut caller_ops = [static_call.clone(), static_call, vec![Operation::Add]].concat();
+    append_return_result_operations(&mut caller_ops);
+    let program = Program::from(caller_ops);
+    let caller_address = Address::from_low_u64_be(4040);
+    let caller_bytecode = Bytecode::from(program.to_bytecode());
+
+    let db = Db::new()
+        .with_contract(callee_address, callee_bytecode)
+        .with_contract(caller_address, caller_bytecode);
+    let mut env = Env::default();
+    env.tx.gas_limit = 999_999;
+    env.tx.transact_to = TransactTo::Call(caller_address);
+    (env, db)
+}
+
+#[test]
+fn executor_cache_reuses_compiled_program_between_transactions() {
+    let mut operations = vec![
+        Operation::Push((1_u8, 10_u8.into())),
+        Operation::Push((1_u8, 10_u8.into())),
+        Operation::Add,
+    ];
+    append_return_result_operations(&mut operations);
+    let (env, db) = default_env_and_db_setup(operations);
+    let mut evm = Evm::new(env, db);
+
+    for _ in 0..2 {
+        let result = evm.transact_commit().unwrap();
+        assert!(result.is_success());
+        assert_eq!(
+            BigUint::from_bytes_be(result.output().unwrap()),
+            BigUint::from(20_u8)
+        );
+    }
+
+    let stats = evm.cache_stats();
+    assert_eq!(stats.misses, 1);
+    assert_eq!(stats.hits, 1);
+}
+
+#[test]
+fn executor_cache_reuses_compiled_callee() {
+    let (env, db) = static_call_twice_env_and_db_setup();
+    let mut evm = Evm::new(env, db);
+    let result = evm.transact_commit().unwrap();
+
+    // both calls succeed, so the caller returns 1 + 1
+    assert!(result.is_success());
+    assert_eq!(
+        BigUint::from_bytes_be(result.output().unwrap()),
+        BigUint::from(2_u8)
+    );
+    let stats = evm.cache_stats();
+    assert_eq!(stats.misses, 2);
+    assert_eq!(stats.hits, 1);
+    assert_eq!(evm.executor_cache.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn executor_cache_shares_the_executor_with_reentrant_calls() {
+    let return_pc = 26;
+    let operations = vec![
+        // The inner call gets some calldata and returns 1
+        Operation::CallDataSize,
+        Operation::Push((1_u8, return_pc.into())),
+        Operation::Jumpi,
+        // The outer one calls itself and returns the result plus one
+        Operation::Push((1_u8, 32_u8.into())), //Ret size
+        Operation::Push0,                      //Ret offset
+        Operation::Push((1_u8, 1_u8.into())),  //Args size
+        Operation::Push0,                      //Args offset
+        Operation::Push0,                      //Value
+        Operation::Address,
+        Operation::Gas,
+        Operation::Call,
+        Operation::Pop,
+        Operation::Push0,
+        Operation::Mload,
+        Operation::Push((1_u8, 1_u8.into())),
+        Operation::Add,
+        Operation::Push0,
+        Operation::Mstore,
+        Operation::Push((1_u8, 32_u8.into())),
+        Operation::Push0,
+        Operation::Return,
+        Operation::Jumpdest { pc: return_pc },
+        Operation::Push((1_u8, 1_u8.into())),
+        Operation::Push0,
+        Operation::Mstore,
+        Operation::Push((1_u8, 32_u8.into())),
+        Operation::Push0,
+        Operation::Return,
+    ];
+    let (env, db) = default_env_and_db_setup(operations);
+    let mut evm = Evm::new(env, db);
+
+    let result = evm.transact_commit().unwrap();
+
+    assert!(result.is_success());
+    assert_eq!(
+        BigUint::from_bytes_be(result.output().unwrap()),
+        BigUint::from(2_u8)
+    );
+    // The inner frame runs the executor of the outer one
+    let stats = evm.cache_stats();
+    assert_eq!(stats.misses, 1);
+    assert_eq!(stats.hits, 1);
+}
+
+#[test]
+fn executor_cache_evicts_when_full() {
+    let (env, db) = static_call_twice_env_and_db_setup();
+    let mut evm = Evm::<Db>::builder()
+        .with_db(db)
+        .with_cache_config(CacheConfig {
+            capacity: 1,
+            eviction_policy: EvictionPolicy::Lru,
+            ..Default::default()
+        })
+        .build();
+    evm.env = env;
+
+    let result = evm.transact_commit().unwrap();
+    assert!(result.is_success());
+
+    // the caller is evicted to make room for the callee
+    let stats = evm.cache_stats();
+    assert_eq!(stats.evictions, 1);
+    assert_eq!(evm.executor_cache.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn executor_cache_with_zero_capacity_always_compiles() {
+    let (env, db) = static_call_twice_env_and_db_setup();
+    let mut evm = Evm::<Db>::builder()
+        .with_db(db)
+        .with_cache_config(CacheConfig {
+            capacity: 0,
+            ..Default::default()
+        })
+        .build();
+    evm.env = env;
+
+    let result = evm.transact_commit().unwrap();
+    assert!(result.is_success());
+
+    let stats = evm.cache_stats();
+    assert_eq!(stats.misses, 3);
+    assert_eq!(stats.hits, 0);
+    assert!(evm.executor_cache.lock().unwrap().is_empty());
+}
+
+#[test]
+fn executor_cache_can_be_shared_between_evms() {
+    let executor_cache = ExecutorCache::shared(Default::default());
+    for _ in 0..2 {
+        let (env, db) = static_call_twice_env_and_db_setup();
+        let mut evm = Evm::<Db>::builder()
+            .with_db(db)
+            .with_executor_cache(executor_cache.clone())
+            .build();
+        evm.env = env;
+        assert!(evm.transact_commit().unwrap().is_success());
+    }
+
+    let stats = executor_cache.lock().unwrap().stats();
+    assert_eq!(stats.misses, 2);
+    assert_eq!(stats.hits, 4);
+}
+
+#[test]
+fn executor_cache_loads_aot_compiled_programs_from_disk() {
+    let aot_dir = tempfile::tempdir().unwrap();
+    let config = CacheConfig {
+        aot_dir: Some(aot_dir.path().to_path_buf()),
+        ..Default::default()
+    };
+
+    let (env, db) = static_call_twice_env_and_db_setup();
+    let mut evm = Evm::<Db>::builder()
+        .with_db(db)
+        .with_cache_config(config.clone())
+        .build();
+    evm.env = env;
+    let result = evm.transact_commit().unwrap();
+    assert!(result.is_success());
+    assert_eq!(evm.cache_stats().aot_loads, 0);
+    assert_eq!(std::fs::read_dir(aot_dir.path()).unwrap().count(), 2);
+
+    // a new cache, like the one of a restarted node, reuses the compiled libraries
+    let (env, db) = static_call_twice_env_and_db_setup();
+    let mut evm = Evm::<Db>::builder()
+        .with_db(db)
+        .with_cache_config(config)
+        .build();
+    evm.env = env;
+    let result = evm.transact_commit().unwrap();
+    assert!(result.is_success());
+    assert_eq!(
+        BigUint::from_bytes_be(result.output().unwrap()),
+        BigUint::from(2_u8)
+    );
+    let stats = evm.cache_stats();
+    assert_eq!(stats.misses, 2);
+    assert_eq!(stats.aot_loads, 2);
+}
+
+/// Database backed by [`Db`] whose storage reads fail for a given address
+#[derive(Debug)]
+struct FailingStorageDb {
+    db: Db,
+    failing_address: Address,
+}
+
+impl Database for FailingStorageDb {
+    type Error = String;
+
+    fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        Ok(self.db.basic(address).unwrap())
+    }
+
+    fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        Ok(self.db.code_by_hash(code_hash).unwrap())
+    }
+
+    fn storage(&mut self, address: Address, index: EU256) -> Result<EU256, Self::Error> {
+        if address == self.failing_address {
+            return Err("storage unavailable".to_string());
+        }
+        Ok(self.db.storage(address, index).unwrap())
+    }
+
+    fn block_hash(&mut self, number: EU256) -> Result<B256, Self::Error> {
+        Ok(self.db.block_hash(number).unwrap())
+    }
+}
+
+impl DatabaseCommit for FailingStorageDb {
+    fn commit(&mut self, changes: HashMap<Address, Account>) {
+        self.db.commit(changes)
+    }
+}
+
+#[test]
+fn transact_commit_with_custom_database() {
+    let mut operations = vec![
+        Operation::Push((1_u8, 10_u8.into())),
+        Operation::Push0,
+        Operation::Sstore,
+        Operation::Push0,
+        Operation::Sload,
+    ];
+    append_return_result_operations(&mut operations);
+    let (env, db) = default_env_and_db_setup(operations);
+    let address = env.tx.get_address();
+    let db = FailingStorageDb {
+        db,
+        failing_address: Address::zero(),
+    };
+    let mut evm = Evm::new(env, db);
+
+    let result = evm.transact_commit().unwrap();
+
+    assert!(result.is_success());
+    assert_eq!(
+        BigUint::from_bytes_be(result.output().unwrap()),
+        BigUint::from(10_u8)
+    );
+    assert_eq!(evm.db.db.read_storage(address, EU256::zero()), 10.into());
+}
+
+#[test]
+fn database_errors_are_returned_from_transact() {
+    let mut operations = vec![Operation::Push0, Operation::Sload];
+    append_return_result_operations(&mut operations);
+    let (env, db) = default_env_and_db_setup(operations);
+    let failing_address = env.tx.get_address();
+    let db = FailingStorageDb {
+        db,
+        failing_address,
+    };
+    let mut evm = Evm::new(env, db);
+
+    let result = evm.transact();
+
+    assert_eq!(
+        result.unwrap_err(),
+        EVMError::Database(DatabaseError::new("storage unavailable"))
+    );
+}
+
+#[test]
+fn database_errors_in_nested_calls_are_returned_from_transact() {
+    let (env, db) = static_call_twice_env_and_db_setup();
+    let callee_address = Address::from_low_u64_be(8080);
+    let mut callee_ops = vec![Operation::Push0, Operation::Sload];
+    append_return_result_operations(&mut callee_ops);
+    let mut db = db;
+    db.insert_contract(
+        callee_address,
+        Bytecode::from(Program::from(callee_ops).to_bytecode()),
+        EU256::zero(),
+    );
+    let db = FailingStorageDb {
+        db,
+        failing_address: callee_address,
+    };
+    let mut evm = Evm::new(env, db);
+
+    let result = evm.transact();
+
+    assert!(matches!(result, Err(EVMError::Database(_))));
+}
+
+#[derive(Debug, Default)]
+struct RecordingInspector {
+    record_steps: bool,
+    calls: Vec<CallInputs>,
+    call_results: Vec<ExecutionResult>,
+    creates: Vec<CreateInputs>,
+    logs: Vec<Log>,
+    selfdestructs: Vec<(Address, Address, EU256)>,
+    steps: Vec<(usize, u8, Vec<EU256>)>,
+}
+
+impl Inspector for RecordingInspector {
+    fn call(&mut self, inputs: &CallInputs) {
+        self.calls.push(inputs.clone());
+    }
+
+    fn call_end(&mut self, _inputs: &CallInputs, result: &ExecutionResult) {
+        self.call_results.push(result.clone());
+    }
+
+    fn create(&mut self, inputs: &CreateInputs) {
+        self.creates.push(inputs.clone());
+    }
+
+    fn log(&mut self, log: &Log) {
+        self.logs.push(log.clone());
+    }
+
+    fn selfdestruct(&mut self, contract: Address, target: Address, value: EU256) {
+        self.selfdestructs.push((contract, target, value));
+    }
+
+    fn step(&mut self, step: &Step) {
+        let stack = step.stack.iter().map(U256::to_primitive_u256).collect();
+        self.steps.push((step.pc, step.opcode, stack));
+    }
+
+    fn step_enabled(&self) -> bool {
+        self.record_steps
+    }
+}
+
+fn inspected_evm(env: Env, db: Db, inspector: Arc<Mutex<RecordingInspector>>) -> Evm<Db> {
+    let mut evm = Evm::builder().with_db(db).with_inspector(inspector).build();
+    evm.env = env;
+    evm
+}
+
+#[test]
+fn inspector_is_notified_of_nested_calls() {
+    let (env, db) = static_call_twice_env_and_db_setup();
+    let caller_address = env.tx.get_address();
+    let callee_address = Address::from_low_u64_be(8080);
+    let inspector = Arc::new(Mutex::new(RecordingInspector::default()));
+    let mut evm = inspected_evm(env, db, inspector.clone());
+
+    let result = evm.transact_commit().unwrap();
+    assert!(result.is_success());
+
+    let inspector = inspector.lock().unwrap();
+    assert_eq!(inspector.calls.len(), 3);
+    assert_eq!(inspector.calls[0].target_address, caller_address);
+    assert_eq!(inspector.calls[0].call_type, CallType::Call);
+    for call in &inspector.calls[1..] {
+        assert_eq!(call.caller, caller_address);
+        assert_eq!(call.target_address, callee_address);
+        assert_eq!(call.call_type, CallType::StaticCall);
+        assert!(call.is_static);
+    }
+    // Nested calls finish before the transaction does
+    assert_eq!(inspector.call_results.len(), 3);
+    assert_eq!(
+        inspector.call_results[0].output().unwrap().as_ref(),
+        &[[0; 31].as_slice(), &[7]].concat()
+    );
+    assert_eq!(inspector.call_results[2], result);
+}
+
+#[test]
+fn inspector_is_notified_of_logs() {
+    let operations = vec![
+        Operation::Push((1_u8, 0xff_u8.into())),
+        Operation::Push0,
+        Operation::Mstore8,
+        Operation::Push((1_u8, 1_u8.into())),
+        Operation::Push0,
+        Operation::Log(0),
+    ];
+    let (env, db) = default_env_and_db_setup(operations);
+    let address = env.tx.get_address();
+    let inspector = Arc::new(Mutex::new(RecordingInspector::default()));
+    let mut evm = inspected_evm(env, db, inspector.clone());
+
+    let result = evm.transact_commit().unwrap();
+    assert!(result.is_success());
+
+    let expected_log = Log {
+        address,
+        data: LogData {
+            topics: vec![],
+            data: vec![0xff],
+        },
+    };
+    assert_eq!(inspector.lock().unwrap().logs, vec![expected_log]);
+}
+
+#[test]
+fn inspector_is_notified_of_selfdestruct() {
+    let receiver_address = 100;
+    let balance = EU256::from(231);
+    let operations = vec![
+        Operation::Push((20, BigUint::from(receiver_address))),
+        Operation::SelfDestruct,
+    ];
+    let (env, mut db) = default_env_and_db_setup(operations);
+    let address = env.tx.get_address();
+    db.set_account(address, 1, balance, Default::default());
+    let inspector = Arc::new(Mutex::new(RecordingInspector::default()));
+    let mut evm = inspected_evm(env, db, inspector.clone());
+
+    let result = evm.transact_commit().unwrap();
+    assert!(result.is_success());
+
+    assert_eq!(
+        inspector.lock().unwrap().selfdestructs,
+        vec![(address, Address::from_low_u64_be(receiver_address), balance)]
+    );
+}
+
+#[test]
+fn inspector_is_notified_of_create() {
+    let initialization_code = [0x5f, 0x5f, 0xf3]; // PUSH0 PUSH0 RETURN
+    let mut env = Env::default();
+    env.tx.gas_limit = 999_999;
+    env.tx.transact_to = TransactTo::Create;
+    env.tx.data = Bytes::copy_from_slice(&initialization_code);
+    let inspector = Arc::new(Mutex::new(RecordingInspector::default()));
+    let mut evm = inspected_evm(env.clone(), Db::new(), inspector.clone());
+
+    let result = evm.transact_commit().unwrap();
+    assert!(result.is_success());
+
+    let inspector = inspector.lock().unwrap();
+    assert_eq!(inspector.creates.len(), 1);
+    assert_eq!(inspector.creates[0].caller, env.tx.caller);
+    assert_eq!(
+        inspector.creates[0].init_code.as_ref(),
+        &initialization_code
+    );
+    assert_eq!(inspector.creates[0].salt, None);
+}
+
+#[test]
+fn inspector_steps_are_only_reported_when_enabled() {
+    let operations = vec![
+        Operation::Push((1_u8, 1_u8.into())),
+        Operation::Push((1_u8, 2_u8.into())),
+        Operation::Add,
+    ];
+    let (env, db) = default_env_and_db_setup(operations);
+
+    let inspector = Arc::new(Mutex::new(RecordingInspector::default()));
+    let mut evm = inspected_evm(env.clone(), db.clone(), inspector.clone());
+    assert!(evm.transact_commit().unwrap().is_success());
+    assert!(inspector.lock().unwrap().steps.is_empty());
+
+    let inspector = Arc::new(Mutex::new(RecordingInspector {
+        record_steps: true,
+        ..Default::default()
+    }));
+    let mut evm = inspected_evm(env, db, inspector.clone());
+    assert!(evm.transact_commit().unwrap().is_success());
+
+    let expected_steps = vec![
+        (0, 0x60, vec![]),
+        (2, 0x60, vec![1.into()]),
+        (4, 0x01, vec![1.into(), 2.into()]),
+    ];
+    assert_eq!(inspector.lock().unwrap().steps, expected_steps);
+}
+
+#[test]
+fn inspector_steps_are_reported_on_jump_destinations() {
+    let operations = vec![
+        Operation::Push((1_u8, 4_u8.into())),
+        Operation::Jump,
+        Operation::Invalid,
+        Operation::Jumpdest { pc: 4 },
+    ];
+    let (env, db) = default_env_and_db_setup(operations);
+    let inspector = Arc::new(Mutex::new(RecordingInspector {
+        record_steps: true,
+        ..Default::default()
+    }));
+    let mut evm = inspected_evm(env, db, inspector.clone());
+
+    assert!(evm.transact_commit().unwrap().is_success());
+
+    let pcs: Vec<usize> = inspector
+        .lock()
+        .unwrap()
+        .steps
+        .iter()
+        .map(|(pc, _, _)| *pc)
+        .collect();
+    assert_eq!(pcs, vec![0, 2, 4]);
+}
+
+#[test]
+fn eip3155_tracer_writes_a_line_per_operation() {
+    let operations = vec![
+        Operation::Push((1_u8, 1_u8.into())),
+        Operation::Push((1_u8, 2_u8.into())),
+        Operation::Add,
+    ];
+    let (env, db) = default_env_and_db_setup(operations);
+    let tracer = Arc::new(Mutex::new(TracerEip3155::new(Vec::new())));
+    let mut evm = Evm::builder()
+        .with_db(db)
+        .with_inspector(tracer.clone())
+        .build();
+    evm.env = env;
+
+    let result = evm.transact_commit().unwrap();
+    assert!(result.is_success());
+
+    let tracer = tracer.lock().unwrap();
+    let trace = String::from_utf8(tracer.get_ref().clone()).unwrap();
+    let lines: Vec<&str> = trace.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert!(lines[0].starts_with(r#"{"pc":0,"op":96,"#));
+    assert!(lines[0].contains(r#""gasCost":"0x3","#));
+    assert!(lines[1].contains(r#""stack":["0x1"],"depth":1,"#));
+    assert!(lines[2].contains(r#""stack":["0x1","0x2"],"#));
+    assert!(lines[2].ends_with(r#""opName":"ADD"}"#));
+    assert_eq!(
+        lines[3],
+        format!(
+            r#"{{"output":"0x","gasUsed":"0x{:x}","pass":true}}"#,
+            result.gas_used()
+        )
+    );
+}
+
+#[test]
+fn push0_is_not_activated_before_shanghai() {
+    let (mut env, db) = default_env_and_db_setup(vec![Operation::Push0]);
+    env.cfg.spec_id = SpecId::Merge;
+    let mut evm = Evm::new(env, db);
+
+    let result = evm.transact_commit().unwrap();
+
+    assert!(matches!(
+        result,
+        ExecutionResult::Halt {
+            reason: HaltReason::NotActivated,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn sload_gas_consumption_before_berlin() {
+    let program = vec![
+        Operation::Push((1_u8, BigUint::from(1_u8))),
+        Operation::Sload,
+    ];
+    let result = gas_cost::PUSHN + SpecId::Istanbul.gas_schedule().sload;
+    let mut env = Env::default();
+    env.cfg.spec_id = SpecId::Istanbul;
+
+    run_program_assert_gas_exact(program, env, result as _);
+}
+
+/// Operations calling `address` with no value, arguments nor return data, sending `gas`.
+/// PUSH0 isn't used, so they can run in any fork.
+fn call_with_gas(address: Address, gas: u8) -> Vec<Operation> {
+    let mut operations = vec![Operation::Push((1, BigUint::ZERO)); 5];
+    operations.extend([
+        Operation::Push((20, BigUint::from_bytes_be(address.as_bytes()))),
+        Operation::Push((1, BigUint::from(gas))),
+        Operation::Call,
+    ]);
+    operations
+}
+
+#[test]
+fn calls_to_missing_accounts_create_them_before_spurious_dragon() {
+    let gas = 100;
+    let program = call_with_gas(Address::from_low_u64_be(1000), gas);
+    // The requested gas must be available before Tangerine
+    let needed_gas = gas_cost::PUSHN * 7
+        + SpecId::Homestead.gas_schedule().call
+        + call_opcode::EMPTY_CALLEE_COST as i64
+        + gas as i64;
+    let mut env = Env::default();
+    env.cfg.spec_id = SpecId::Homestead;
+
+    run_program_assert_gas_exact(program, env, needed_gas as _);
+}
+
+#[test]
+fn calls_without_value_to_missing_accounts_are_not_charged_for_them() {
+    // The gas sent is capped to what is left (EIP-150)
+    let program = call_with_gas(Address::from_low_u64_be(1000), 100);
+    let needed_gas = gas_cost::PUSHN * 7 + gas_cost::CALL_COLD;
+
+    run_program_assert_gas_exact(program, Env::default(), needed_gas as _);
+}
+
+#[test]
+fn delegate_calls_to_missing_accounts_dont_create_them() {
+    let missing = Address::from_low_u64_be(1000);
+    let mut operations = vec![Operation::Push((1, BigUint::ZERO)); 4];
+    operations.extend([
+        Operation::Push((20, BigUint::from_bytes_be(missing.as_bytes()))),
+        Operation::Push((1, BigUint::from(100_u8))),
+        Operation::DelegateCall,
+    ]);
+    let (mut env, db) = default_env_and_db_setup(operations);
+    // Empty accounts are kept before Spurious Dragon
+    env.cfg.spec_id = SpecId::Homestead;
+    let mut evm = Evm::new(env, db);
+
+    assert!(evm.transact_commit().unwrap().is_success());
+    assert_eq!(evm.db.basic(missing).unwrap(), None);
+}
+
+#[test]
+fn contracts_can_be_deployed_to_addresses_called_before() {
+    let salt = 7_u8;
+    // Deploys code that self-destructs, sending the balance to the zero address
+    let initialization_code = hex::decode("626000ff6000526003601df3").unwrap();
+    let size = initialization_code.len() as u8;
+    let factory = Address::from_low_u64_be(40);
+    let contract = compute_contract_address2(factory, EU256::from(salt), &initialization_code);
+    let mut operations = call_with_gas(contract, 100);
+    operations.extend([
+        Operation::Pop,
+        // Store initialization code in memory
+        Operation::Push((size, BigUint::from_bytes_be(&initialization_code))),
+        Operation::Push0,
+        Operation::Mstore,
+        // Create2
+        Operation::Push((1, BigUint::from(salt))),
+        Operation::Push((1, BigUint::from(size))),
+        Operation::Push((1, BigUint::from(32 - size))),
+        Operation::Push0,
+        Operation::Create2,
+    ]);
+    append_return_result_operations(&mut operations);
+    let (env, db) = default_env_and_db_setup(operations);
+    let mut evm = Evm::new(env, db);
+
+    let result = evm.transact_commit().unwrap();
+
+    assert!(result.is_success());
+    assert_eq!(
+        Address::from_slice(&result.output().unwrap()[12..]),
+        contract
+    );
+}
+
+fn sign_authorization(
+    secret_key: &secp256k1::SecretKey,
+    mut authorization: SignedAuthorization,
+) -> SignedAuthorization {
+    let secp = secp256k1::Secp256k1::new();
+    let message = secp256k1::Message::from_digest(authorization.signature_hash().0);
+    let (recovery_id, signature) = secp
+        .sign_ecdsa_recoverable(&message, secret_key)
+        .serialize_compact();
+    authorization.y_parity = recovery_id.to_i32() as u8;
+    authorization.r = EU256::from_big_endian(&signature[..32]);
+    authorization.s = EU256::from_big_endian(&signature[32..]);
+    authorization
+}
+
+fn address_of(secret_key: &secp256k1::SecretKey) -> Address {
+    let public_key = secret_key.public_key(&secp256k1::Secp256k1::new());
+    let mut hasher = Keccak256::new();
+    hasher.update(&public_key.serialize_uncompressed()[1..]);
+    Address::from_slice(&hasher.finalize()[12..])
+}
+
+#[test]
+fn set_code_transaction_delegates_the_authority() {
+    let secret_key = secp256k1::SecretKey::from_slice(&[0x11; 32]).unwrap();
+    let authority = address_of(&secret_key);
+    let delegate = Address::from_low_u64_be(40);
+    let mut operations = vec![Operation::Push((1_u8, BigUint::from(42_u8)))];
+    append_return_result_operations(&mut operations);
+    let db = Db::new().with_contract(
+        delegate,
+        Bytecode::from(Program::from(operations).to_bytecode()),
+    );
+
+    let mut env = Env::default();
+    env.cfg.spec_id = SpecId::Prague;
+    env.tx.gas_limit = 999_999;
+    env.tx.transact_to = TransactTo::Call(authority);
+    let authorization = SignedAuthorization {
+        address: delegate,
+        ..Default::default()
+    };
+    env.tx.authorization_list = Some(vec![sign_authorization(&secret_key, authorization)]);
+    let mut evm = Evm::new(env, db);
+
+    let ResultAndState { result, state, .. } = evm.transact().unwrap();
+
+    assert!(result.is_success());
+    assert_eq!(
+        BigUint::from_bytes_be(result.output().unwrap()),
+        BigUint::from(42_u8)
+    );
+    let authority_info = &state.get(&authority).unwrap().info;
+    assert_eq!(authority_info.nonce, 1);
+    assert_eq!(authority_info.code, Some(delegation_designator(delegate)));
+}
+
+#[test]
+fn authorizations_signed_for_another_chain_are_skipped() {
+    let secret_key = secp256k1::SecretKey::from_slice(&[0x11; 32]).unwrap();
+    let authority = address_of(&secret_key);
+    let mut env = Env::default();
+    env.cfg.spec_id = SpecId::Prague;
+    env.cfg.chain_id = 1;
+    env.tx.gas_limit = 999_999;
+    env.tx.transact_to = TransactTo::Call(authority);
+    let authorization = SignedAuthorization {
+        chain_id: EU256::from(2),
+        address: Address::from_low_u64_be(40),
+        ..Default::default()
+    };
+    env.tx.authorization_list = Some(vec![sign_authorization(&secret_key, authorization)]);
+    let mut evm = Evm::new(env, Db::new());
+
+    let ResultAndState { result, state, .. } = evm.transact().unwrap();
+
+    assert!(result.is_success());
+    assert!(state
+        .get(&authority)
+        .map_or(true, |account| account.info.code.is_none()));
+}
+
+fn eof_code_type(inputs: u8, outputs: u8, max_stack_height: u16) -> CodeType {
+    CodeType {
+        inputs,
+        outputs,
+        max_stack_height,
+    }
+}
+
+fn eof_container(
+    code_sections: Vec<(CodeType, Vec<u8>)>,
+    container_sections: Vec<Bytes>,
+    data: &[u8],
+) -> EofContainer {
+    let (types, code_sections) = code_sections
+        .into_iter()
+        .map(|(code_type, code)| (code_type, Bytes::from(code)))
+        .unzip();
+    EofContainer {
+        types,
+        code_sections,
+        container_sections,
+        data: Bytes::copy_from_slice(data),
+        data_size: data.len() as u16,
+    }
+}
+
+/// Container returning a word of its data section, loaded by a function called after
+/// a relative jump
+fn eof_data_returning_container() -> EofContainer {
+    let main = vec![
+        0x60, 0x01, // PUSH1 1
+        0xe1, 0x00, 0x01, // RJUMPI +1
+        0xfe, // INVALID
+        0xe3, 0x00, 0x01, // CALLF 1
+        0x5f, // PUSH0
+        0x52, // MSTORE
+        0x60, 0x20, // PUSH1 32
+        0x5f, // PUSH0
+        0xf3, // RETURN
+    ];
+    let load_data = vec![
+        0xd1, 0x00, 0x00, // DATALOADN 0
+        0xe4, // RETF
+    ];
+    let mut data = [0; 32];
+    data[31] = 42;
+    eof_container(
+        vec![
+            (eof_code_type(0, eof::NON_RETURNING, 2), main),
+            (eof_code_type(0, 1, 1), load_data),
+        ],
+        vec![],
+        &data,
+    )
+}
+
+#[test]
+fn eof_contract_calls_functions_and_loads_data() {
+    let code = eof_data_returning_container().encode();
+    let address = Address::from_low_u64_be(40);
+    let mut env = Env::default();
+    env.cfg.spec_id = SpecId::Prague;
+    env.tx.gas_limit = 999_999;
+    env.tx.transact_to = TransactTo::Call(address);
+    let db = Db::new().with_contract(address, code);
+
+    run_program_assert_num_result(env, db, BigUint::from(42_u8));
+}
+
+#[test]
+fn eof_contract_is_invalid_code_before_prague() {
+    let code = eof_data_returning_container().encode();
+    let address = Address::from_low_u64_be(40);
+    let mut env = Env::default();
+    env.cfg.spec_id = SpecId::Cancun;
+    env.tx.gas_limit = 999_999;
+    env.tx.transact_to = TransactTo::Call(address);
+    let db = Db::new().with_contract(address, code);
+
+    run_program_assert_halt(env, db);
+}
+
+#[test]
+fn eof_creation_transaction_deploys_the_returned_container() {
+    let runtime = eof_container(
+        vec![(eof_code_type(0, eof::NON_RETURNING, 0), vec![0x00])],
+        vec![],
+        &[],
+    )
+    .encode();
+    let initcode = vec![
+        0x5f, // PUSH0
+        0x5f, // PUSH0
+        0xee, 0x00, // RETURNCONTRACT 0
+    ];
+    let initcontainer = eof_container(
+        vec![(eof_code_type(0, eof::NON_RETURNING, 2), initcode)],
+        vec![runtime.clone()],
+        &[],
+    );
+
+    let mut env = Env::default();
+    env.cfg.spec_id = SpecId::Prague;
+    env.tx.gas_limit = 999_999;
+    env.tx.transact_to = TransactTo::Create;
+    env.tx.data = initcontainer.encode();
+    let sender = env.tx.caller;
+    let mut evm = Evm::new(env, Db::new());
+
+    let ResultAndState { result, state, .. } = evm.transact().unwrap();
+
+    let address = compute_contract_address(sender, 0);
+    assert!(matches!(
+        result,
+        ExecutionResult::Success {
+            reason: SuccessReason::EofReturnContract,
+            ..
+        }
+    ));
+    assert_eq!(state.get(&address).unwrap().info.code, Some(runtime));
+}
+
+#[test]
+fn eof_creation_transaction_with_invalid_initcode_halts() {
+    // The initcode must end with RETURNCONTRACT, not RETURN
+    let initcontainer = eof_container(
+        vec![(
+            eof_code_type(0, eof::NON_RETURNING, 2),
+            vec![0x5f, 0x5f, 0xf3],
+        )],
+        vec![],
+        &[],
+    );
+
+    let mut env = Env::default();
+    env.cfg.spec_id = SpecId::Prague;
+    env.tx.gas_limit = 999_999;
+    env.tx.transact_to = TransactTo::Create;
+    env.tx.data = initcontainer.encode();
+    let mut evm = Evm::new(env, Db::new());
+
+    let result = evm.transact().unwrap().result;
+
+    assert!(matches!(
+        result,
+        ExecutionResult::Halt {
+            reason: HaltReason::InvalidEOFInitCode,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn transaction_fees_are_charged_to_the_caller_and_paid_to_the_coinbase() {
+    let caller = Address::from_low_u64_be(79);
+    let coinbase = Address::from_low_u64_be(80);
+    let caller_balance = EU256::from(10_000_000);
+    let (gas_price, basefee) = (10_u64, 7_u64);
+    let (mut env, mut db) = default_env_and_db_setup(vec![Operation::Stop]);
+    env.tx.caller = caller;
+    env.tx.gas_price = EU256::from(gas_price);
+    env.block.basefee = EU256::from(basefee);
+    env.block.coinbase = coinbase;
+    db.set_balance(caller, caller_balance);
+    let mut evm = Evm::new(env, db);
+
+    let ResultAndState {
+        result,
+        state,
+        fees,
+    } = evm.transact().unwrap();
+
+    assert!(result.is_success());
+    let gas_used = result.gas_used();
+    assert_eq!(gas_used, TX_BASE_COST);
+    assert_eq!(fees.burned, EU256::from(gas_used * basefee));
+    assert_eq!(fees.tip, EU256::from(gas_used * (gas_price - basefee)));
+    let caller_info = &state.get(&caller).unwrap().info;
+    assert_eq!(caller_info.nonce, 1);
+    assert_eq!(
+        caller_info.balance,
+        caller_balance - EU256::from(gas_used * gas_price)
+    );
+    // The base fee is burned, so the coinbase only gets the priority fee
+    let coinbase_info = &state.get(&coinbase).unwrap().info;
+    assert_eq!(
+        coinbase_info.balance,
+        EU256::from(gas_used * (gas_price - basefee))
+    );
+}
+
+#[test]
+fn reverted_transactions_keep_the_fees_and_nonce_bump() {
+    let caller = Address::from_low_u64_be(79);
+    let caller_balance = EU256::from(10_000_000);
+    let gas_price = 10_u64;
+    let operations = vec![
+        Operation::Push((1, BigUint::from(1_u8))),
+        Operation::Push0,
+        Operation::Sstore,
+        Operation::Push0,
+        Operation::Push0,
+        Operation::Revert,
+    ];
+    let (mut env, mut db) = default_env_and_db_setup(operations);
+    let callee = env.tx.get_address();
+    env.tx.caller = caller;
+    env.tx.gas_price = EU256::from(gas_price);
+    db.set_balance(caller, caller_balance);
+    let mut evm = Evm::new(env, db);
+
+    let result = evm.transact_commit().unwrap();
+
+    assert!(result.is_revert());
+    let caller_info = evm.db.basic(caller).unwrap().unwrap();
+    assert_eq!(caller_info.nonce, 1);
+    assert_eq!(
+        caller_info.balance,
+        caller_balance - EU256::from(result.gas_used() * gas_price)
+    );
+    assert_eq!(evm.db.read_storage(callee, EU256::zero()), EU256::zero());
+}
+
+#[test]
+fn transactions_with_a_wrong_nonce_are_rejected() {
+    let caller = Address::from_low_u64_be(79);
+    let (mut env, mut db) = default_env_and_db_setup(vec![Operation::Stop]);
+    env.tx.caller = caller;
+    env.tx.nonce = Some(0);
+    db.set_account(caller, 1, EU256::zero(), Default::default());
+    let mut evm = Evm::new(env, db);
+
+    let result = evm.transact();
+
+    assert_eq!(
+        result.unwrap_err(),
+        EVMError::Transaction(InvalidTransaction::NonceTooLow { tx: 0, state: 1 })
+    );
+}
+
+#[test]
+fn blob_fees_are_charged_to_the_caller_and_burned() {
+    let caller = Address::from_low_u64_be(79);
+    let caller_balance = EU256::from(10_000_000);
+    let (mut env, mut db) = default_env_and_db_setup(vec![Operation::Stop]);
+    env.tx.caller = caller;
+    env.tx.gas_limit = 100_000;
+    env.tx.blob_hashes = vec![B256::from_low_u64_be(1); 2];
+    env.tx.blob_hashes[0].0[0] = VERSIONED_HASH_VERSION_KZG;
+    env.tx.blob_hashes[1].0[0] = VERSIONED_HASH_VERSION_KZG;
+    env.tx.max_fee_per_blob_gas = Some(EU256::from(5));
+    env.block.set_blob_base_fee(0);
+    let blob_gasprice = env.block.blob_gasprice.unwrap();
+    db.set_balance(caller, caller_balance);
+    let mut evm = Evm::new(env, db);
+
+    let ResultAndState { state, fees, .. } = evm.transact().unwrap();
+
+    let blob_fee = EU256::from(blob_gasprice) * EU256::from(2 * GAS_PER_BLOB);
+    assert!(!blob_fee.is_zero());
+    // The gas price is zero, so only the blob fee is paid
+    assert_eq!(fees.burned, blob_fee);
+    let caller_info = &state.get(&caller).unwrap().info;
+    assert_eq!(caller_info.balance, caller_balance - blob_fee);
+}
+
+#[test]
+fn callers_that_cannot_afford_the_gas_limit_are_rejected() {
+    let caller = Address::from_low_u64_be(79);
+    let (mut env, mut db) = default_env_and_db_setup(vec![Operation::Stop]);
+    env.tx.caller = caller;
+    env.tx.gas_price = EU256::from(10);
+    db.set_balance(caller, EU256::from(100));
+    let mut evm = Evm::new(env, db);
+
+    let result = evm.transact();
+
+    assert!(matches!(
+        result,
+        Err(EVMError::Transaction(
+            InvalidTransaction::LackOfFundForMaxFee { .. }
+        ))
+    ));
+}
+
+#[test]
+fn contracts_cannot_send_transactions() {
+    let (mut env, db) = default_env_and_db_setup(vec![Operation::Stop]);
+    env.tx.caller = env.tx.get_address();
+    let mut evm = Evm::new(env, db);
+
+    let result = evm.transact();
+
+    assert_eq!(
+        result.unwrap_err(),
+        EVMError::Transaction(InvalidTransaction::RejectCallerWithCode)
+    );
+}
+
+#[test]
+fn fee_market_transactions_pay_the_effective_gas_price() {
+    let caller = Address::from_low_u64_be(79);
+    let caller_balance = EU256::from(10_000_000);
+    let (max_fee, priority_fee, basefee) = (10_u64, 2_u64, 7_u64);
+    let mut operations = vec![Operation::Gasprice];
+    append_return_result_operations(&mut operations);
+    let (mut env, mut db) = default_env_and_db_setup(operations);
+    env.tx.caller = caller;
+    env.tx.gas_price = EU256::from(max_fee);
+    env.tx.gas_priority_fee = Some(EU256::from(priority_fee));
+    env.block.basefee = EU256::from(basefee);
+    db.set_balance(caller, caller_balance);
+    let mut evm = Evm::new(env, db);
+
+    let ResultAndState {
+        result,
+        state,
+        fees,
+    } = evm.transact().unwrap();
+
+    let effective_gas_price = basefee + priority_fee;
+    assert_eq!(
+        BigUint::from_bytes_be(result.output().unwrap()),
+        BigUint::from(effective_gas_price)
+    );
+    let gas_used = result.gas_used();
+    assert_eq!(fees.effective_gas_price, EU256::from(effective_gas_price));
+    assert_eq!(fees.burned, EU256::from(gas_used * basefee));
+    assert_eq!(fees.tip, EU256::from(gas_used * priority_fee));
+    assert_eq!(
+        state.get(&caller).unwrap().info.balance,
+        caller_balance - EU256::from(gas_used * effective_gas_price)
+    );
+}
+
+#[test]
+fn transactions_paying_less_than_the_basefee_are_rejected() {
+    let (mut env, db) = default_env_and_db_setup(vec![Operation::Stop]);
+    env.tx.gas_price = EU256::from(6);
+    env.block.basefee = EU256::from(7);
+    let mut evm = Evm::new(env, db);
+
+    let result = evm.transact();
+
+    assert_eq!(
+        result.unwrap_err(),
+        EVMError::Transaction(InvalidTransaction::GasPriceLessThanBasefee)
+    );
+}
+
+fn block_transaction(caller: Address, to: Address, nonce: u64) -> TxEnv {
+    TxEnv {
+        caller,
+        transact_to: TransactTo::Call(to),
+        gas_limit: 100_000,
+        nonce: Some(nonce),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn block_executor_commits_transactions_and_withdrawals() {
+    let caller = Address::from_low_u64_be(79);
+    let withdrawal_address = Address::from_low_u64_be(90);
+    let key = 1_u8;
+    let operations = vec![
+        // Increment the stored counter and log it
+        Operation::Push((1, BigUint::from(key))),
+        Operation::Sload,
+        Operation::Push((1, BigUint::from(1_u8))),
+        Operation::Add,
+        Operation::Dup(1),
+        Operation::Push((1, BigUint::from(key))),
+        Operation::Sstore,
+        Operation::Push0,
+        Operation::Push0,
+        Operation::Log(1),
+    ];
+    let (env, db) = default_env_and_db_setup(operations);
+    let contract = env.tx.get_address();
+    let mut executor = BlockExecutor::new(Evm::new(env, db));
+    let transactions = vec![
+        block_transaction(caller, contract, 0),
+        block_transaction(caller, contract, 1),
+    ];
+    let withdrawals = [Withdrawal {
+        address: withdrawal_address,
+        amount: 2,
+        ..Default::default()
+    }];
+
+    let block_result = executor
+        .execute_block(BlockEnv::default(), transactions, &withdrawals)
+        .unwrap();
+
+    let receipts = &block_result.receipts;
+    assert_eq!(receipts.len(), 2);
+    assert!(receipts.iter().all(|receipt| receipt.success));
+    assert_eq!(receipts[1].cumulative_gas_used, block_result.gas_used);
+    assert!(receipts[0].cumulative_gas_used < receipts[1].cumulative_gas_used);
+    assert_eq!(receipts[1].logs[0].data.topics, vec![U256 { lo: 2, hi: 0 }]);
+    assert_eq!(block_result.blob_gas_used, 0);
+
+    let mut db = executor.into_db();
+    assert_eq!(db.read_storage(contract, EU256::from(key)), EU256::from(2));
+    assert_eq!(db.basic(caller).unwrap().unwrap().nonce, 2);
+    assert_eq!(
+        db.get_balance(withdrawal_address),
+        Some(EU256::from(2_000_000_000_u64))
+    );
+}
+
+#[test]
+fn block_receipts_include_the_logs_of_nested_calls() {
+    let caller = Address::from_low_u64_be(79);
+    let emitter = Address::from_low_u64_be(8080);
+    let topic = U256 { lo: 7, hi: 0 };
+    let emitter_ops = vec![
+        Operation::Push((1, BigUint::from(7_u8))), //Topic
+        Operation::Push0,                          //Size
+        Operation::Push0,                          //Offset
+        Operation::Log(1),
+    ];
+    let mut proxy_ops = vec![Operation::Push0; 5];
+    proxy_ops.extend([
+        Operation::Push((20, BigUint::from_bytes_be(emitter.as_bytes()))),
+        Operation::Gas,
+        Operation::Call,
+    ]);
+    let (env, db) = default_env_and_db_setup(proxy_ops);
+    let proxy = env.tx.get_address();
+    let db = db.with_contract(
+        emitter,
+        Bytecode::from(Program::from(emitter_ops).to_bytecode()),
+    );
+    let mut executor = BlockExecutor::new(Evm::new(env, db));
+
+    let block_result = executor
+        .execute_block(
+            BlockEnv::default(),
+            vec![block_transaction(caller, proxy, 0)],
+            &[],
+        )
+        .unwrap();
+
+    let receipt = &block_result.receipts[0];
+    assert!(receipt.success);
+    assert_eq!(
+        receipt.logs,
+        vec![Log {
+            address: emitter,
+            data: LogData {
+                topics: vec![topic],
+                data: vec![],
+            },
+        }]
+    );
+    assert!(receipt
+        .logs_bloom
+        .contains_input(ethereum_types::BloomInput::Raw(emitter.as_bytes())));
+    assert!(receipt
+        .logs_bloom
+        .contains_input(ethereum_types::BloomInput::Raw(&topic.to_fixed_be_bytes())));
+    assert!(!receipt
+        .logs_bloom
+        .contains_input(ethereum_types::BloomInput::Raw(proxy.as_bytes())));
+}
+
+#[test]
+fn block_executor_enforces_the_block_gas_limit() {
+    let caller = Address::from_low_u64_be(79);
+    let (env, db) = default_env_and_db_setup(vec![Operation::Stop]);
+    let contract = env.tx.get_address();
+    let mut executor = BlockExecutor::new(Evm::new(env, db));
+    let block = BlockEnv {
+        gas_limit: EU256::from(110_000),
+        ..Default::default()
+    };
+    let transactions = vec![
+        block_transaction(caller, contract, 0),
+        block_transaction(caller, contract, 1),
+    ];
+
+    let result = executor.execute_block(block, transactions, &[]);
+
+    // The first transaction only uses the base cost, but the gas limit of the second
+    // one doesn't fit in what's left
+    assert_eq!(
+        result.unwrap_err(),
+        BlockExecutionError::Transaction {
+            index: 1,
+            error: EVMError::Transaction(InvalidTransaction::CallerGasLimitMoreThanBlock),
+        }
+    );
+    assert_eq!(
+        executor.db().clone().basic(caller).unwrap().unwrap().nonce,
+        1
+    );
+}
+
+#[test]
+fn block_executor_stores_the_parent_roots_with_system_calls() {
+    let beacon_roots = Address::from(BEACON_ROOTS_ADDRESS);
+    let history_storage = Address::from(HISTORY_STORAGE_ADDRESS);
+    let system_address = Address::from(SYSTEM_ADDRESS);
+    let operations = vec![
+        // Store the input at the timestamp and the caller at the zero slot
+        Operation::Push0,
+        Operation::CalldataLoad,
+        Operation::Timestamp,
+        Operation::Sstore,
+        Operation::Caller,
+        Operation::Push0,
+        Operation::Sstore,
+    ];
+    let bytecode = Bytecode::from(Program::from(operations).to_bytecode());
+    let db = Db::new()
+        .with_contract(beacon_roots, bytecode.clone())
+        .with_contract(history_storage, bytecode);
+    let mut env = Env::default();
+    env.cfg.spec_id = SpecId::Cancun;
+    let mut executor = BlockExecutor::new(Evm::new(env, db));
+    let root = B256::from_low_u64_be(0xbeac);
+    let block = BlockEnv {
+        number: EU256::from(1),
+        timestamp: EU256::from(12),
+        parent_beacon_block_root: Some(root),
+        parent_hash: Some(B256::from_low_u64_be(0x2935)),
+        ..Default::default()
+    };
+
+    let block_result = executor.execute_block(block, vec![], &[]).unwrap();
+
+    // System calls don't use the gas of the block
+    assert_eq!(block_result.gas_used, 0);
+    let mut db = executor.into_db();
+    assert_eq!(
+        db.read_storage(beacon_roots, EU256::from(12)),
+        EU256::from_big_endian(root.as_bytes())
+    );
+    assert_eq!(
+        db.read_storage(beacon_roots, EU256::zero()),
+        EU256::from_big_endian(system_address.as_bytes())
+    );
+    // The history storage contract is only called from Prague on
+    assert_eq!(
+        db.read_storage(history_storage, EU256::zero()),
+        EU256::zero()
+    );
+    assert_eq!(db.basic(system_address).unwrap(), None);
+}
+
+#[test]
+fn system_calls_to_contracts_without_code_do_nothing() {
+    let mut evm = Evm::new(Env::default(), Db::new());
+    let contract = Address::from(HISTORY_STORAGE_ADDRESS);
+
+    let ResultAndState { result, state, .. } = evm
+        .system_call(contract, Bytes::from_static(&[0xff; 32]))
+        .unwrap();
+
+    assert!(result.is_success());
+    assert_eq!(result.gas_used(), 0);
+    assert!(state.is_empty());
+}
+
+fn simulation_evm() -> (Evm<Db>, Address, Address) {
+    let callee = Address::from_low_u64_be(8080);
+    let caller = Address::from_low_u64_be(4040);
+    let callee_ops = vec![
+        Operation::Push((1, BigUint::from(1_u8))),
+        Operation::Push0,
+        Operation::Sstore,
+    ];
+    // Calls the callee with all the gas left, and reverts if the call fails
+    let caller_ops = vec![
+        Operation::Push0,                                                // 0
+        Operation::Push0,                                                // 1
+        Operation::Push0,                                                // 2
+        Operation::Push0,                                                // 3
+        Operation::Push0,                                                // 4
+        Operation::Push((2, BigUint::from_bytes_be(callee.as_bytes()))), // 5-7
+        Operation::Gas,                                                  // 8
+        Operation::Call,                                                 // 9
+        Operation::Push((1, BigUint::from(16_u8))),                      // 10-11
+        Operation::Jumpi,                                                // 12
+        Operation::Push0,                                                // 13
+        Operation::Push0,                                                // 14
+        Operation::Revert,                                               // 15
+        Operation::Jumpdest { pc: 16 },                                  // 16
+        Operation::Stop,
+    ];
+    let db = Db::new()
+        .with_contract(
+            callee,
+            Bytecode::from(Program::from(callee_ops).to_bytecode()),
+        )
+        .with_contract(
+            caller,
+            Bytecode::from(Program::from(caller_ops).to_bytecode()),
+        );
+    let mut env = Env::default();
+    env.tx.caller = Address::from_low_u64_be(79);
+    env.tx.transact_to = TransactTo::Call(caller);
+    env.tx.gas_limit = 1_000_000;
+    (Evm::new(env, db), caller, callee)
+}
+
+#[test]
+fn simulations_do_not_change_the_state_nor_the_configured_transaction() {
+    let (mut evm, _, callee) = simulation_evm();
+    let tx = TxEnv {
+        gas_price: EU256::from(10),
+        nonce: Some(3),
+        ..evm.env.tx.clone()
+    };
+
+    // The caller has no funds and its nonce is zero
+    assert!(matches!(
+        evm.simulate(tx.clone()),
+        Err(EVMError::Transaction(
+            InvalidTransaction::NonceTooHigh { .. }
+        ))
+    ));
+    evm.env.cfg.disable_nonce_check = true;
+    evm.env.cfg.disable_balance_check = true;
+    let ResultAndState { result, state, .. } = evm.simulate(tx).unwrap();
+
+    assert!(result.is_success());
+    assert_eq!(
+        state[&callee].storage[&EU256::zero()].present_value,
+        EU256::one()
+    );
+    assert_eq!(evm.env.tx.gas_limit, 1_000_000);
+    assert_eq!(evm.env.tx.nonce, None);
+    assert_eq!(evm.db.read_storage(callee, EU256::zero()), EU256::zero());
+}
+
+#[test]
+fn estimated_gas_covers_the_gas_held_back_by_calls() {
+    let (mut evm, _, callee) = simulation_evm();
+    let tx = evm.env.tx.clone();
+    let gas_used = evm.simulate(tx.clone()).unwrap().result.gas_used();
+
+    let estimate = evm.estimate_gas(tx.clone()).unwrap();
+
+    // Calls get 63/64 of the gas left, so using all of it isn't enough
+    assert!(estimate > gas_used);
+    let with_estimate = TxEnv {
+        gas_limit: estimate,
+        ..tx.clone()
+    };
+    assert!(evm.simulate(with_estimate).unwrap().result.is_success());
+    let below_estimate = TxEnv {
+        gas_limit: estimate - 1,
+        ..tx.clone()
+    };
+    assert!(!evm.simulate(below_estimate).unwrap().result.is_success());
+    assert_eq!(evm.db.read_storage(callee, EU256::zero()), EU256::zero());
+
+    // Transactions failing with the highest gas limit can't be estimated
+    let low_limit = TxEnv {
+        gas_limit: gas_used - 1,
+        ..tx
+    };
+    assert!(matches!(
+        evm.estimate_gas(low_limit),
+        Err(EstimateGasError::ExecutionFailed(
+            ExecutionResult::Revert { .. }
+        ))
+    ));
+}
+
+#[test]
+fn simulations_read_the_state_through_the_overrides() {
+    let address = Address::from_low_u64_be(8080);
+    // Returns the sum of the storage slots 0 and 1
+    let operations = vec![
+        Operation::Push0,
+        Operation::Sload,
+        Operation::Push((1, BigUint::from(1_u8))),
+        Operation::Sload,
+        Operation::Add,
+        Operation::Push0,
+        Operation::Mstore,
+        Operation::Push((1, BigUint::from(32_u8))),
+        Operation::Push0,
+        Operation::Return,
+    ];
+    let program = Program::from(operations).to_bytecode();
+    let mut db = Db::new().with_contract(address, Bytecode::from(program.clone()));
+    db.write_storage(address, EU256::zero(), EU256::from(1));
+    db.write_storage(address, EU256::one(), EU256::from(2));
+    let mut env = Env::default();
+    env.tx.caller = Address::from_low_u64_be(79);
+    env.tx.transact_to = TransactTo::Call(address);
+    env.tx.gas_limit = 1_000_000;
+    let mut evm = Evm::new(env, db);
+    let tx = evm.env.tx.clone();
+    let returned_sum = |result: &ExecutionResult| EU256::from_big_endian(result.output().unwrap());
+
+    let state_diff = HashMap::from([(
+        address,
+        AccountOverride {
+            state_diff: Some(HashMap::from([(EU256::one(), EU256::from(5))])),
+            ..Default::default()
+        },
+    )]);
+    let result = evm
+        .simulate_with_overrides(tx.clone(), &state_diff)
+        .unwrap()
+        .result;
+    assert_eq!(returned_sum(&result), EU256::from(6));
+
+    // Slots missing from a full storage override hold zero
+    let state = HashMap::from([(
+        address,
+        AccountOverride {
+            state: Some(HashMap::from([(EU256::one(), EU256::from(5))])),
+            ..Default::default()
+        },
+    )]);
+    let result = evm
+        .simulate_with_overrides(tx.clone(), &state)
+        .unwrap()
+        .result;
+    assert_eq!(returned_sum(&result), EU256::from(5));
+
+    // Overridden code runs over the storage of the account
+    let other_address = Address::from_low_u64_be(9090);
+    let code = HashMap::from([(
+        other_address,
+        AccountOverride {
+            code: Some(Bytecode::from(program)),
+            state_diff: Some(HashMap::from([(EU256::zero(), EU256::from(7))])),
+            ..Default::default()
+        },
+    )]);
+    let tx = TxEnv {
+        transact_to: TransactTo::Call(other_address),
+        ..tx
+    };
+    let result = evm.simulate_with_overrides(tx, &code).unwrap().result;
+    assert_eq!(returned_sum(&result), EU256::from(7));
+
+    assert_eq!(evm.db.read_storage(address, EU256::one()), EU256::from(2));
+    assert!(evm.db.basic(other_address).unwrap().is_none());
+}
+
+#[test]
+fn cache_db_keeps_committed_changes_off_the_wrapped_database() {
+    let (evm, _, callee) = simulation_evm();
+    let Evm { env, db, .. } = evm;
+    let mut evm = Evm::new(env, CacheDB::new(&db));
+
+    let result = evm.transact_commit().unwrap();
+
+    assert!(result.is_success());
+    assert_eq!(
+        evm.db.storage_ref(callee, EU256::zero()).unwrap(),
+        EU256::one()
+    );
+    assert_eq!(db.read_storage(callee, EU256::zero()), EU256::zero());
+}
+
+#[test]
+fn cache_db_accounts_recreated_after_selfdestruct_start_with_empty_storage() {
+    let salt = 7_u8;
+    // Deploys code that self-destructs, sending the balance to the zero address
+    let initialization_code = hex::decode("626000ff6000526003601df3").unwrap();
+    let size = initialization_code.len() as u8;
+    let mut operations = vec![
+        // Store initialization code in memory
+        Operation::Push((size, BigUint::from_bytes_be(&initialization_code))),
+        Operation::Push0,
+        Operation::Mstore,
+        // Create2
+        Operation::Push((1, BigUint::from(salt))),
+        Operation::Push((1, BigUint::from(size))),
+        Operation::Push((1, BigUint::from(32 - size))),
+        Operation::Push0,
+        Operation::Create2,
+    ];
+    append_return_result_operations(&mut operations);
+    let (mut env, mut db) = default_env_and_db_setup(operations);
+    // Self-destructed accounts are only removed if they were just created since Cancun
+    env.cfg.spec_id = SpecId::Shanghai;
+    let factory = env.tx.get_address();
+    let contract = compute_contract_address2(factory, EU256::from(salt), &initialization_code);
+    db.insert_contract(
+        contract,
+        Bytecode::from_static(&[0x60, 0x00, 0xff]),
+        EU256::zero(),
+    );
+    db.write_storage(contract, EU256::one(), EU256::from(5));
+    let mut evm = Evm::new(env, CacheDB::new(&db));
+
+    evm.env.tx.transact_to = TransactTo::Call(contract);
+    assert!(evm.transact_commit().unwrap().is_success());
+    assert_eq!(evm.db.basic(contract).unwrap(), None);
+
+    evm.env.tx.transact_to = TransactTo::Call(factory);
+    let result = evm.transact_commit().unwrap();
+    assert!(result.is_success());
+    assert_eq!(
+        Address::from_slice(&result.output().unwrap()[12..]),
+        contract
+    );
+    assert!(evm.db.basic(contract).unwrap().is_some());
+    assert_eq!(
+        evm.db.storage(contract, EU256::one()).unwrap(),
+        EU256::zero()
+    );
+    assert_eq!(db.read_storage(contract, EU256::one()), EU256::from(5));
+}
+
+#[test]
+fn forked_state_is_read_from_the_fixture_and_changes_stay_in_the_cache() {
+    let caller = Address::from_low_u64_be(79);
+    let contract = Address::from_low_u64_be(8080);
+    // Copies the storage slot 1 to the slot 0
+    let program = Program::from(vec![
+        Operation::Push((1, BigUint::from(1_u8))),
+        Operation::Sload,
+        Operation::Push0,
+        Operation::Sstore,
+    ])
+    .to_bytecode();
+    let code = Bytecode::from(program);
+    let mut state = ForkState::new(20_000_000);
+    state.insert_account(caller, None);
+    state.insert_account(
+        contract,
+        Some(AccountInfo {
+            balance: EU256::zero(),
+            nonce: 1,
+            code_hash: B256::from_slice(&Keccak256::digest(&code)),
+            code: Some(code),
+        }),
+    );
+    state.storage.insert(
+        contract,
+        HashMap::from([
+            (EU256::zero(), EU256::zero()),
+            (EU256::one(), EU256::from(7)),
+        ]),
+    );
+    let fork_db = ForkDb::new(FixtureProvider::new(state), 20_000_000);
+    let mut env = Env::default();
+    env.tx.caller = caller;
+    env.tx.transact_to = TransactTo::Call(contract);
+    env.tx.gas_limit = 100_000;
+    let mut evm = Evm::new(env, CacheDB::new(&fork_db));
+
+    let result = evm.transact_commit().unwrap();
+
+    assert!(result.is_success());
+    assert_eq!(
+        evm.db.storage_ref(contract, EU256::zero()).unwrap(),
+        EU256::from(7)
+    );
+    assert_eq!(
+        fork_db.storage_ref(contract, EU256::zero()).unwrap(),
+        EU256::zero()
+    );
+}
+
+#[test]
+fn disk_db_keeps_the_blocks_executed_across_restarts() {
+    let caller = Address::from_low_u64_be(79);
+    let contract = Address::from_low_u64_be(40);
+    let dir = tempfile::tempdir().unwrap();
+    // Increments the counter at slot 0
+    let operations = vec![
+        Operation::Push0,
+        Operation::Sload,
+        Operation::Push((1, BigUint::from(1_u8))),
+        Operation::Add,
+        Operation::Push0,
+        Operation::Sstore,
+    ];
+    let code = Bytecode::from(Program::from(operations).to_bytecode());
+    let genesis = HashMap::from([(
+        contract,
+        Account {
+            info: AccountInfo {
+                nonce: 1,
+                code_hash: B256::from_slice(&Keccak256::digest(&code)),
+                code: Some(code),
+                ..Default::default()
+            },
+            storage: HashMap::new(),
+            status: AccountStatus::Created | AccountStatus::Touched,
+        },
+    )]);
+    let mut disk_db = DiskDb::open(dir.path()).unwrap();
+    disk_db.commit(genesis);
+    disk_db
+        .commit_block(EU256::zero(), B256::from_low_u64_be(100))
+        .unwrap();
+
+    let mut executor = BlockExecutor::new(Evm::new(Env::default(), disk_db));
+    let transactions = vec![
+        block_transaction(caller, contract, 0),
+        block_transaction(caller, contract, 1),
+    ];
+    executor
+        .execute_block(BlockEnv::default(), transactions, &[])
+        .unwrap();
+    executor
+        .db_mut()
+        .commit_block(EU256::one(), B256::from_low_u64_be(101))
+        .unwrap();
+    drop(executor);
+
+    let mut disk_db = DiskDb::open(dir.path()).unwrap();
+    assert_eq!(
+        disk_db.storage(contract, EU256::zero()).unwrap(),
+        EU256::from(2)
+    );
+    assert_eq!(disk_db.basic(caller).unwrap().unwrap().nonce, 2);
+    assert_eq!(
+        disk_db.block_hash(EU256::one()).unwrap(),
+        B256::from_low_u64_be(101)
+    );
+}
@@ -35,7 +35,7 @@ fn run_program_get_result_with_gas(
     let mut db = Db::default();
     let journal = Journal::new(&mut db).with_prefetch(&env.tx.access_list);
     let mut context = SyscallContext::new(env, journal, Default::default(), initial_gas);
-    let executor = Executor::new(&module, &context, Default::default());
+    let executor = Executor::new(&module, Default::default());
 
     let _result = executor.execute(&mut context, initial_gas);
 
@@ -7,9 +7,11 @@ use evm_mlir::{
     },
     db::{Bytecode, Db},
     env::TransactTo,
+    precompiles::Precompiles,
     primitives::{Address, Bytes},
     program::{Operation, Program},
-    Env,
+    result::PrecompileError,
+    Env, Evm,
 };
 
 use crate::evm::{
@@ -1665,3 +1667,76 @@ fn staticcall_on_precompile_blake2f_happy_path() {
 
     run_program_assert_bytes_result(env, db, &expected_result);
 }
+
+/// Program that static calls `callee_address` with the byte `data` and returns one byte of output
+fn staticcall_one_byte_program(callee_address: Address, data: u8) -> Vec<Operation> {
+    vec![
+        // Place the parameter in memory
+        Operation::Push((1_u8, BigUint::from(data))),
+        Operation::Push((1_u8, BigUint::ZERO)),
+        Operation::Mstore,
+        // Do the call
+        Operation::Push((1_u8, BigUint::from(1_u8))), //Ret size
+        Operation::Push((1_u8, BigUint::from(63_u8))), //Ret offset
+        Operation::Push((1_u8, BigUint::from(1_u8))), //Args size
+        Operation::Push((1_u8, BigUint::from(31_u8))), //Args offset
+        Operation::Push((20_u8, BigUint::from_bytes_be(callee_address.as_bytes()))), //Address
+        Operation::Push((32_u8, BigUint::from(100_000_000_u32))), //Gas
+        Operation::StaticCall,
+        // Return
+        Operation::Push((1_u8, BigUint::from(1_u8))),
+        Operation::Push((1_u8, BigUint::from(63_u8))),
+        Operation::Return,
+    ]
+}
+
+fn negate(
+    calldata: &Bytes,
+    gas_limit: u64,
+    consumed_gas: &mut u64,
+) -> Result<Bytes, PrecompileError> {
+    if gas_limit < 10 {
+        return Err(PrecompileError::NotEnoughGas);
+    }
+    *consumed_gas += 10;
+    Ok(calldata.iter().map(|byte| !byte).collect())
+}
+
+#[test]
+fn staticcall_on_custom_precompile() {
+    let callee_address = Address::from_low_u64_be(0x100);
+    let caller_address = Address::from_low_u64_be(4040);
+    let program = Program::from(staticcall_one_byte_program(callee_address, 0x0f));
+    let db = Db::new().with_contract(caller_address, Bytecode::from(program.to_bytecode()));
+    let precompiles = Precompiles::default().with_precompile(callee_address, negate);
+    let mut evm = Evm::builder()
+        .with_db(db)
+        .with_precompiles(precompiles)
+        .build();
+    evm.env.tx.transact_to = TransactTo::Call(caller_address);
+
+    let result = evm.transact_commit().unwrap();
+
+    assert!(result.is_success());
+    assert_eq!(result.output().unwrap().as_ref(), &[0xf0]);
+}
+
+#[test]
+fn staticcall_on_disabled_precompile() {
+    let callee_address = Address::from_low_u64_be(IDENTITY_ADDRESS);
+    let caller_address = Address::from_low_u64_be(4040);
+    let program = Program::from(staticcall_one_byte_program(callee_address, 0xff));
+    let db = Db::new().with_contract(caller_address, Bytecode::from(program.to_bytecode()));
+    let precompiles = Precompiles::default().without_precompile(&callee_address);
+    let mut evm = Evm::builder()
+        .with_db(db)
+        .with_precompiles(precompiles)
+        .build();
+    evm.env.tx.transact_to = TransactTo::Call(caller_address);
+
+    let result = evm.transact_commit().unwrap();
+
+    // The address has no code, so nothing is copied into the return buffer
+    assert!(result.is_success());
+    assert_eq!(result.output().unwrap().as_ref(), &[0x00]);
+}